@@ -1,12 +1,12 @@
 use winit::{
-    event::{ElementState, Event, KeyboardInput, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
+    event::{ElementState, Event, KeyEvent, WindowEvent},
+    event_loop::EventLoop,
     window::WindowBuilder,
 };
 use winit_blit::{PixelBufferTyped, BGRA};
 
 fn main() {
-    let event_loop = EventLoop::new();
+    let event_loop = EventLoop::new().unwrap();
 
     let window = WindowBuilder::new()
         .with_title("Software rendering example")
@@ -19,15 +19,15 @@ fn main() {
     let alpha = BGRA::new(0, 0, 0, 255);
     let mut blend_mode = BlendMode::Approx;
     println!("blend mode = {:?}", blend_mode);
-    event_loop.run(move |event, _, control_flow| {
+    let result = event_loop.run(move |event, elwt| {
         // println!("{:?}", event);
 
         match event {
             Event::WindowEvent {
                 event:
                     WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInput {
+                        event:
+                            KeyEvent {
                                 state: ElementState::Pressed,
                                 ..
                             },
@@ -46,61 +46,62 @@ fn main() {
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 window_id,
-            } if window_id == window.id() => *control_flow = ControlFlow::Exit,
-            Event::RedrawRequested(window_id) => {
-                if window_id == window.id() {
-                    let (width, height): (u32, u32) = window.inner_size().into();
-                    let mut buffer =
-                        PixelBufferTyped::<BGRA>::new_supported(width, height, &window);
-                    let start = std::time::Instant::now();
-
-                    let blend_fn = match blend_mode {
-                        BlendMode::Approx => blend_approx,
-                        BlendMode::Exact => blend_exact,
-                        BlendMode::Naive => blend_naive,
-                    };
-
-                    #[cfg(feature = "rayon")]
-                    {
-                        use rayon::prelude::*;
-
-                        buffer
-                            .par_rows_mut()
-                            .enumerate()
-                            .flat_map(|(i, row)| {
-                                let y = ((i as f32 / height as f32) * 255.0).round() as u8;
-                                let t_blend = blend_fn(y, red, green);
-                                let b_blend = blend_fn(y, alpha, blue);
-                                row.par_iter_mut()
-                                    .enumerate()
-                                    .map(move |(j, pixel)| (j, t_blend, b_blend, pixel))
-                            })
-                            .for_each(|(j, t_blend, b_blend, pixel)| {
-                                let x = ((j as f32 / width as f32) * 255.0).round() as u8;
-                                *pixel = blend_fn(x, t_blend, b_blend);
-                            })
-                    }
-                    #[cfg(not(feature = "rayon"))]
-                    {
-                        for (i, row) in buffer.rows_mut().enumerate() {
+            } if window_id == window.id() => elwt.exit(),
+            Event::WindowEvent {
+                event: WindowEvent::RedrawRequested,
+                window_id,
+            } if window_id == window.id() => {
+                let (width, height): (u32, u32) = window.inner_size().into();
+                let mut buffer = PixelBufferTyped::<BGRA>::new_supported(width, height, &window);
+                let start = std::time::Instant::now();
+
+                let blend_fn = match blend_mode {
+                    BlendMode::Approx => blend_approx,
+                    BlendMode::Exact => blend_exact,
+                    BlendMode::Naive => blend_naive,
+                };
+
+                #[cfg(feature = "rayon")]
+                {
+                    use rayon::prelude::*;
+
+                    buffer
+                        .par_rows_mut()
+                        .enumerate()
+                        .flat_map(|(i, row)| {
                             let y = ((i as f32 / height as f32) * 255.0).round() as u8;
                             let t_blend = blend_fn(y, red, green);
                             let b_blend = blend_fn(y, alpha, blue);
-                            for (j, pixel) in row.into_iter().enumerate() {
-                                let x = ((j as f32 / width as f32) * 255.0).round() as u8;
-                                *pixel = blend_fn(x, t_blend, b_blend);
-                            }
+                            row.par_iter_mut()
+                                .enumerate()
+                                .map(move |(j, pixel)| (j, t_blend, b_blend, pixel))
+                        })
+                        .for_each(|(j, t_blend, b_blend, pixel)| {
+                            let x = ((j as f32 / width as f32) * 255.0).round() as u8;
+                            *pixel = blend_fn(x, t_blend, b_blend);
+                        })
+                }
+                #[cfg(not(feature = "rayon"))]
+                {
+                    for (i, row) in buffer.rows_mut().enumerate() {
+                        let y = ((i as f32 / height as f32) * 255.0).round() as u8;
+                        let t_blend = blend_fn(y, red, green);
+                        let b_blend = blend_fn(y, alpha, blue);
+                        for (j, pixel) in row.into_iter().enumerate() {
+                            let x = ((j as f32 / width as f32) * 255.0).round() as u8;
+                            *pixel = blend_fn(x, t_blend, b_blend);
                         }
                     }
-                    let end = std::time::Instant::now();
-                    println!("{:?}", end - start);
-
-                    buffer.blit(&window).unwrap();
                 }
+                let end = std::time::Instant::now();
+                println!("{:?}", end - start);
+
+                buffer.blit(&window).unwrap();
             }
-            _ => *control_flow = ControlFlow::Wait,
+            _ => (),
         }
     });
+    result.unwrap();
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
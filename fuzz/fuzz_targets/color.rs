@@ -0,0 +1,30 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use winit_blit::{Color, PixelBufferFormatType, BGR, BGRA};
+
+fuzz_target!(|input: (&str, [u8; 4], [f32; 3], f32)| {
+    let (hex, [r, g, b, a], [hue, saturation, value], t) = input;
+    let _ = Color::from_hex_str(hex);
+
+    let color = Color::rgba(r, g, b, a);
+    let _ = Color::from_hsv(hue, saturation, value).to_hsv();
+    let _ = Color::from_hsl(hue, saturation, value).to_hsl();
+    let _ = color.to_hsv();
+    let _ = color.lerp(Color::from_hsv(hue, saturation, value), t);
+    assert_eq!(Color::from_pixel(color.to_pixel::<BGRA>()), color);
+    assert_eq!(
+        Color::from_pixel(color.to_pixel::<BGR>()),
+        color.with_alpha(255)
+    );
+
+    let mut bytes = [0; 4];
+    for &format in &[
+        PixelBufferFormatType::BGR,
+        PixelBufferFormatType::BGRA,
+        PixelBufferFormatType::RGB,
+        PixelBufferFormatType::RGBA,
+    ] {
+        let _ = color.write_bytes(format, &mut bytes);
+    }
+});
@@ -0,0 +1,56 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use winit_blit::{draw, Color, LayerStack, BGRA};
+
+#[derive(Debug, Arbitrary)]
+enum Op {
+    Pixel((i32, i32)),
+    Line((i32, i32), (i32, i32)),
+    ThickLine((i32, i32), (i32, i32), f32),
+    Rect((i32, i32), (u32, u32)),
+    FillRect((i32, i32), (u32, u32)),
+    FillRectBlend((i32, i32), (u32, u32), u8),
+    Ellipse((i32, i32), (u32, u32)),
+    FillEllipse((i32, i32), (u32, u32)),
+    FillConvexPolygon(Vec<(i32, i32)>),
+    BlitSprite(Vec<[u8; 4]>, u8, (i32, i32)),
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    width: u8,
+    height: u8,
+    ops: Vec<Op>,
+}
+
+fuzz_target!(|input: Input| {
+    let mut layers = LayerStack::<BGRA>::new(input.width as u32, input.height as u32, 1);
+    let mut target = layers.layer_mut(0);
+    let color = BGRA::from(Color::RED);
+    for op in input.ops {
+        let target = target.reborrow();
+        match op {
+            Op::Pixel(pos) => draw::pixel(target, pos, color),
+            Op::Line(from, to) => draw::line(target, from, to, color),
+            Op::ThickLine(from, to, width) => draw::thick_line(target, from, to, width, color),
+            Op::Rect(pos, size) => draw::rect(target, pos, size, color),
+            Op::FillRect(pos, size) => draw::fill_rect(target, pos, size, color),
+            Op::FillRectBlend(pos, size, alpha) => {
+                draw::fill_rect_blend(target, pos, size, Color::BLUE.with_alpha(alpha))
+            }
+            Op::Ellipse(center, radii) => draw::ellipse(target, center, radii, color),
+            Op::FillEllipse(center, radii) => draw::fill_ellipse(target, center, radii, color),
+            Op::FillConvexPolygon(points) => draw::fill_convex_polygon(target, &points, color),
+            Op::BlitSprite(sprite, width, pos) => {
+                let width = (width as usize).max(1);
+                let sprite: Vec<BGRA> = sprite[..sprite.len() / width * width]
+                    .iter()
+                    .map(|&[b, g, r, a]| BGRA::new(b, g, r, a))
+                    .collect();
+                draw::blit_sprite(target, &sprite, width as u32, pos, Some(color))
+            }
+        }
+    }
+});
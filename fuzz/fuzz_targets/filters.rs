@@ -0,0 +1,47 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use winit_blit::{filters, ChannelDepth, ConversionOptions, DitherMode, LayerStack, BGRA};
+
+#[derive(Debug, Arbitrary)]
+enum Op {
+    BoxBlur(u32),
+    GaussianBlur(f32),
+    Quantize([u8; 3], u8),
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    width: u8,
+    height: u8,
+    pixels: Vec<[u8; 4]>,
+    ops: Vec<Op>,
+}
+
+fuzz_target!(|input: Input| {
+    let mut layers = LayerStack::<BGRA>::new(input.width as u32, input.height as u32, 1);
+    let mut target = layers.layer_mut(0);
+    let mut pixels = input.pixels.iter().cycle();
+    for row in target.rows_mut() {
+        for (pixel, &[b, g, r, a]) in row.iter_mut().zip(&mut pixels) {
+            *pixel = BGRA::new(b, g, r, a);
+        }
+    }
+    for op in input.ops {
+        let target = target.reborrow();
+        match op {
+            Op::BoxBlur(radius) => filters::box_blur(target, radius),
+            Op::GaussianBlur(sigma) => filters::gaussian_blur(target, sigma),
+            Op::Quantize([r, g, b], dither) => {
+                let dither = match dither % 3 {
+                    0 => DitherMode::None,
+                    1 => DitherMode::Ordered,
+                    _ => DitherMode::FloydSteinberg,
+                };
+                let options = ConversionOptions { dither };
+                filters::quantize(target, ChannelDepth { r, g, b }, options)
+            }
+        }
+    }
+});
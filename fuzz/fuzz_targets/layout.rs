@@ -0,0 +1,75 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use winit_blit::{BufferOptions, Converter, ImageDesc, PixelBuffer, PixelBufferFormatType};
+
+/// Images are only allocated if they're no larger than this, so the fuzzer doesn't spend its time
+/// zeroing memory. Larger sizes still go through the size checks that reject them.
+const MAX_ALLOC: u64 = 1 << 22;
+
+#[derive(Debug, Arbitrary)]
+struct Image {
+    format: u8,
+    width: u32,
+    height: u32,
+    stride: usize,
+}
+
+impl Image {
+    fn desc(&self) -> ImageDesc {
+        ImageDesc::new(format(self.format), self.width, self.height).with_stride(self.stride)
+    }
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input {
+    format: u8,
+    width: u32,
+    height: u32,
+    alignment_shift: u8,
+    src: Image,
+    dst: Image,
+}
+
+fn format(n: u8) -> PixelBufferFormatType {
+    match n % 4 {
+        0 => PixelBufferFormatType::BGR,
+        1 => PixelBufferFormatType::BGRA,
+        2 => PixelBufferFormatType::RGB,
+        _ => PixelBufferFormatType::RGBA,
+    }
+}
+
+/// Whether `desc` is a valid image small enough to allocate.
+fn fits(desc: &ImageDesc) -> bool {
+    let row = desc.width as usize * desc.format.bytes_per_pixel();
+    (desc.height <= 1 || desc.stride >= row) && desc.min_len() as u64 <= MAX_ALLOC
+}
+
+fuzz_target!(|input: Input| {
+    let format = format(input.format);
+    let alignment = 1 << (input.alignment_shift % 16);
+    let too_large = input.width.max(input.height) > i32::MAX as u32;
+    let padded_len = (input.width as u64 * 4 + alignment as u64) * input.height as u64;
+    if too_large || padded_len <= MAX_ALLOC {
+        let options = BufferOptions::new().row_alignment(alignment);
+        let buffer =
+            PixelBuffer::new_offscreen_with_options(input.width, input.height, format, options);
+        if let Ok(mut buffer) = buffer {
+            let row_bytes = input.width as usize * format.bytes_per_pixel();
+            assert!(buffer.row_len() >= row_bytes);
+            assert_eq!(buffer.row_len() % alignment.max(4), 0);
+            assert_eq!(buffer.rows().len(), input.height as usize);
+            assert!(buffer.rows_mut().all(|row| row.len() == row_bytes));
+            assert!(buffer.row(input.height).is_none());
+        }
+    }
+
+    let (src_desc, dst_desc) = (input.src.desc(), input.dst.desc());
+    if fits(&src_desc) && fits(&dst_desc) {
+        let src = vec![0x5a; src_desc.min_len()];
+        let mut dst = vec![0; dst_desc.min_len()];
+        Converter::new().convert(&src, src_desc, &mut dst, dst_desc);
+    }
+});
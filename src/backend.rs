@@ -0,0 +1,100 @@
+use crate::{platform_impl, Rect};
+use std::io;
+
+/// A user-provided presentation target, such as an in-house compositor, an RDP server or a
+/// display driven over a custom link, for
+/// [`PixelBuffer::present_to`](crate::PixelBuffer::present_to).
+///
+/// Backends receive the buffer's pixels in its own format, along with the regions that changed
+/// since the last present, so they get the crate's formats, damage tracking and drawing helpers
+/// without needing a window.
+pub trait CustomBackend {
+    /// Presents the buffer's `rows`.
+    ///
+    /// Only the `rects`, measured from the top-left corner, changed since the last successful
+    /// present. If an error is returned, they're presented again next
+    /// time.
+    fn present(&mut self, rows: BackendRows<'_>, rects: &[Rect]) -> io::Result<()>;
+}
+
+/// The rows of a pixel buffer being presented to a [`CustomBackend`].
+///
+/// The rows are borrowed straight from the buffer's memory, so backends that only read the
+/// changed regions don't pay for copying the rest.
+#[derive(Clone, Copy)]
+pub struct BackendRows<'a> {
+    buffer: &'a platform_impl::PixelBuffer,
+}
+
+impl<'a> BackendRows<'a> {
+    pub(crate) fn new(buffer: &'a platform_impl::PixelBuffer) -> BackendRows<'a> {
+        BackendRows { buffer }
+    }
+
+    /// The width, in pixels, of the pixel buffer.
+    pub fn width(&self) -> u32 {
+        self.buffer.width()
+    }
+
+    /// The height, in pixels, of the pixel buffer.
+    pub fn height(&self) -> u32 {
+        self.buffer.height()
+    }
+
+    /// Gets the row at the particular height, without any padding.
+    pub fn row(&self, row: u32) -> Option<&'a [u8]> {
+        self.buffer.row(row)
+    }
+
+    /// Iterate through all rows, top-down.
+    pub fn rows(&self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a [u8]> {
+        self.buffer.rows()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PixelBuffer, PixelBufferFormatType};
+
+    #[test]
+    /// The purpose of this test is to verify that `present_to` hands custom backends the buffer's
+    /// own rows top-down, along with the regions that changed.
+    fn present_to_custom_backend() {
+        struct Frame {
+            rows: Vec<Vec<u8>>,
+            first_row: *const u8,
+            rects: Vec<Rect>,
+        }
+        #[derive(Default)]
+        struct Frames(Vec<Frame>);
+        impl CustomBackend for Frames {
+            fn present(&mut self, rows: BackendRows<'_>, rects: &[Rect]) -> io::Result<()> {
+                assert_eq!(rows.rows().len(), rows.height() as usize);
+                self.0.push(Frame {
+                    rows: rows.rows().map(<[u8]>::to_vec).collect(),
+                    first_row: rows.row(0).unwrap().as_ptr(),
+                    rects: rects.to_vec(),
+                });
+                Ok(())
+            }
+        }
+
+        let mut pb = PixelBuffer::new_offscreen(5, 4, PixelBufferFormatType::BGR).unwrap();
+        let mut frames = Frames::default();
+        pb.present_to(&mut frames).unwrap();
+        pb.row_mut(1).unwrap()[..3].copy_from_slice(&[1, 2, 3]);
+        pb.present_to(&mut frames).unwrap();
+        pb.present_to(&mut frames).unwrap();
+
+        assert_eq!(frames.0.len(), 2);
+        assert_eq!(frames.0[0].rects, [Rect::new((0, 0), (5, 4))]);
+        let frame = &frames.0[1];
+        // The rows are the buffer's own memory, not a copy.
+        assert_eq!(frame.first_row, pb.row(0).unwrap().as_ptr());
+        assert_eq!(frame.rows.len(), 4);
+        assert_eq!(frame.rows[1].len(), 15);
+        assert_eq!(&frame.rows[1][..3], &[1, 2, 3]);
+        assert_eq!(frame.rects, [Rect::new((0, 1), (5, 1))]);
+    }
+}
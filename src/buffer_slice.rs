@@ -0,0 +1,221 @@
+use crate::{BlitError, PixelBufferFormat, PixelBufferTyped, Point, Size};
+use raw_window_handle::HasWindowHandle;
+
+/// A rectangle of a larger pixel buffer, which can be presented on its own.
+///
+/// Returned by [`PixelBufferTyped::slice`]. Nothing is copied, so sprite atlases and similar
+/// workflows can keep everything in one big buffer, and present different slices of it onto
+/// different windows, or different parts of one window.
+pub struct BufferSlice<'a, P: PixelBufferFormat> {
+    buffer: &'a PixelBufferTyped<P>,
+    pos: Point,
+    size: Size,
+}
+
+impl<P: PixelBufferFormat> Clone for BufferSlice<'_, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P: PixelBufferFormat> Copy for BufferSlice<'_, P> {}
+
+impl<P: PixelBufferFormat> PixelBufferTyped<P> {
+    /// Gets the `size` rectangle with its top-left corner at `pos`, as a slice that can be
+    /// presented on its own.
+    ///
+    /// Returns `None` if the requested rectangle doesn't fit inside the pixel buffer.
+    pub fn slice(
+        &self,
+        pos: impl Into<Point>,
+        size: impl Into<Size>,
+    ) -> Option<BufferSlice<'_, P>> {
+        let (pos, size): (Point, Size) = (pos.into(), size.into());
+        let fits = |pos: u32, size: u32, len: u32| matches!(pos.checked_add(size), Some(end) if end <= len);
+        if !fits(pos.x, size.width, self.width()) || !fits(pos.y, size.height, self.height()) {
+            return None;
+        }
+        Some(BufferSlice {
+            buffer: self,
+            pos,
+            size,
+        })
+    }
+}
+
+impl<'a, P: PixelBufferFormat> BufferSlice<'a, P> {
+    /// The pixel buffer the slice is part of.
+    pub fn buffer(&self) -> &'a PixelBufferTyped<P> {
+        self.buffer
+    }
+
+    /// The position of the slice's top-left corner in the pixel buffer.
+    pub fn pos(&self) -> Point {
+        self.pos
+    }
+
+    /// The width, in pixels, of the slice.
+    pub fn width(&self) -> u32 {
+        self.size.width
+    }
+
+    /// The height, in pixels, of the slice.
+    pub fn height(&self) -> u32 {
+        self.size.height
+    }
+
+    /// Gets the slice's row at the particular height.
+    pub fn row(&self, row: u32) -> Option<&'a [P]> {
+        if row >= self.size.height {
+            return None;
+        }
+        let start = self.pos.x as usize;
+        let row = self.buffer.row(self.pos.y + row)?;
+        Some(&row[start..start + self.size.width as usize])
+    }
+
+    /// Iterate through all of the slice's rows.
+    pub fn rows(&self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a [P]> {
+        let slice = *self;
+        (0..self.size.height).map(move |row| slice.row(row).unwrap())
+    }
+
+    /// Gets a smaller slice, with its top-left corner at `pos` in this one.
+    ///
+    /// Returns `None` if the requested rectangle doesn't fit inside this slice.
+    pub fn slice(
+        &self,
+        pos: impl Into<Point>,
+        size: impl Into<Size>,
+    ) -> Option<BufferSlice<'a, P>> {
+        let (pos, size): (Point, Size) = (pos.into(), size.into());
+        let fits = |pos: u32, size: u32, len: u32| matches!(pos.checked_add(size), Some(end) if end <= len);
+        if !fits(pos.x, size.width, self.size.width) || !fits(pos.y, size.height, self.size.height)
+        {
+            return None;
+        }
+        Some(BufferSlice {
+            buffer: self.buffer,
+            pos: Point::new(self.pos.x + pos.x, self.pos.y + pos.y),
+            size,
+        })
+    }
+
+    /// Blits the slice onto `window`, with its top-left corner at `dst_pos`.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to the buffer's `new`, or
+    /// one added with `add_window`. Failing to do so will result in a panic.
+    pub fn blit<H: HasWindowHandle>(
+        &self,
+        dst_pos: impl Into<Point>,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        self.buffer.blit_rect(self.pos, dst_pos, self.size, window)
+    }
+
+    /// Blits a subsection of the slice onto `window`.
+    ///
+    /// `src_pos` is measured from the slice's top-left corner. The part of the rectangle that lies
+    /// outside the slice isn't blitted.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to the buffer's `new`, or
+    /// one added with `add_window`. Failing to do so will result in a panic.
+    pub fn blit_rect<H: HasWindowHandle>(
+        &self,
+        src_pos: impl Into<Point>,
+        dst_pos: impl Into<Point>,
+        blit_size: impl Into<Size>,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        let (src_pos, blit_size) = self.clip(src_pos.into(), blit_size.into());
+        self.buffer.blit_rect(src_pos, dst_pos, blit_size, window)
+    }
+
+    /// Clips the `size` rectangle at `pos` in the slice to the slice's bounds, and returns it
+    /// measured from the buffer's top-left corner.
+    fn clip(&self, pos: Point, size: Size) -> (Point, Size) {
+        let size = Size::new(
+            size.width.min(self.size.width.saturating_sub(pos.x)),
+            size.height.min(self.size.height.saturating_sub(pos.y)),
+        );
+        let pos = Point::new(
+            self.pos.x.saturating_add(pos.x.min(self.size.width)),
+            self.pos.y.saturating_add(pos.y.min(self.size.height)),
+        );
+        (pos, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BGRA;
+
+    /// A buffer whose pixels record their own position.
+    fn numbered(width: u32, height: u32) -> PixelBufferTyped<BGRA> {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(width, height).unwrap();
+        pb.generate(|x, y| BGRA::from_rgb(x as u8 + 1, y as u8 + 1, 0));
+        pb
+    }
+
+    /// The pixel `numbered` put at `(x, y)`.
+    fn at(x: u32, y: u32) -> BGRA {
+        BGRA::from_rgb(x as u8 + 1, y as u8 + 1, 0)
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that slices, and slices of slices, read the pixels
+    /// of their own rectangle, and see what was written to the buffer.
+    fn slice_reads_its_rectangle() {
+        let mut pb = numbered(5, 4);
+        let slice = pb.slice((1, 1), (3, 2)).unwrap();
+        assert_eq!(
+            (slice.pos(), slice.width(), slice.height()),
+            (Point::new(1, 1), 3, 2)
+        );
+        assert_eq!(slice.row(0).unwrap(), [at(1, 1), at(2, 1), at(3, 1)]);
+        assert_eq!(slice.rows().len(), 2);
+        assert_eq!(slice.rows().last().unwrap(), [at(1, 2), at(2, 2), at(3, 2)]);
+        assert_eq!(slice.row(2), None);
+
+        let inner = slice.slice((2, 1), (1, 1)).unwrap();
+        assert_eq!(inner.pos(), Point::new(3, 2));
+        assert_eq!(inner.row(0).unwrap(), [at(3, 2)]);
+
+        pb.rows_mut().nth(2).unwrap()[3] = BGRA::from_rgb(9, 9, 9);
+        let inner = pb
+            .slice((1, 1), (3, 2))
+            .unwrap()
+            .slice((2, 1), (1, 1))
+            .unwrap();
+        assert_eq!(inner.row(0).unwrap(), [BGRA::from_rgb(9, 9, 9)]);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that slices reaching past the buffer's or the outer
+    /// slice's edges are refused, and that blitting part of a slice is clipped to it.
+    fn slice_clips_at_the_edges() {
+        let pb = numbered(5, 4);
+        assert!(pb.slice((0, 0), (5, 4)).is_some());
+        assert!(pb.slice((5, 4), (0, 0)).is_some());
+        assert!(pb.slice((1, 0), (5, 4)).is_none());
+        assert!(pb.slice((0, 1), (5, 4)).is_none());
+        assert!(pb.slice((u32::MAX, 0), (2, 1)).is_none());
+
+        let slice = pb.slice((1, 1), (3, 2)).unwrap();
+        assert!(slice.slice((1, 1), (2, 1)).is_some());
+        assert!(slice.slice((2, 0), (2, 1)).is_none());
+        assert!(slice.slice((0, 2), (1, 1)).is_none());
+
+        let clip = |pos: (u32, u32), size: (u32, u32)| slice.clip(pos.into(), size.into());
+        assert_eq!(clip((1, 0), (1, 1)), (Point::new(2, 1), Size::new(1, 1)));
+        assert_eq!(clip((2, 1), (5, 5)), (Point::new(3, 2), Size::new(1, 1)));
+        assert_eq!(clip((3, 2), (1, 1)), (Point::new(4, 3), Size::new(0, 0)));
+        assert_eq!(
+            clip((u32::MAX, u32::MAX), (u32::MAX, u32::MAX)),
+            (Point::new(4, 3), Size::new(0, 0))
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A flag that aborts presenting a stale frame, set from any thread.
+///
+/// Presenting a buffer that needs [converting](crate::PixelBuffer::set_present_conversion) can
+/// take a while for large windows. Once a token is
+/// [attached](crate::PixelBuffer::set_cancel_token) to a buffer, cancelling it from another thread,
+/// e.g. the event loop on a resize or while shutting down, makes the blit in progress stop
+/// between rows and fail with an [`Interrupted`](std::io::ErrorKind::Interrupted) error, instead
+/// of finishing a frame nobody will see.
+///
+/// Clones share the same flag. It stays set, failing every later blit, until it's
+/// [reset](Self::reset).
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Creates a token that isn't cancelled.
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Cancels the blits of every buffer the token is attached to.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the token has been cancelled since it was last reset.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clears the flag, so buffers can be presented again.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
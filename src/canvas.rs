@@ -0,0 +1,104 @@
+use crate::{
+    BlitError, Color, NativeFormat, PixelBufferCreationError, PixelBufferFormatSupported,
+    PixelBufferTyped,
+};
+use raw_window_handle::HasWindowHandle;
+
+/// A pixel buffer that's larger than the window it's presented onto, of which only a viewport is
+/// visible at a time.
+///
+/// Document viewers, maps and similar applications can render a large area once, and then scroll
+/// through it with [`present_viewport`](Self::present_viewport), which only changes which part of
+/// the canvas is blitted instead of re-rendering every frame.
+pub struct Canvas<P: PixelBufferFormatSupported = NativeFormat> {
+    buffer: PixelBufferTyped<P>,
+    background: Color,
+}
+
+impl<P: PixelBufferFormatSupported> Canvas<P> {
+    /// Creates a `width` by `height` canvas that can be presented onto `window`.
+    ///
+    /// The canvas may be larger than the window in either direction.
+    pub fn new<H: HasWindowHandle>(
+        width: u32,
+        height: u32,
+        window: &H,
+    ) -> Result<Canvas<P>, PixelBufferCreationError> {
+        Ok(Canvas {
+            buffer: PixelBufferTyped::new(width, height, window)?,
+            background: Color::BLACK,
+        })
+    }
+
+    /// The pixel buffer holding the whole canvas.
+    pub fn buffer(&self) -> &PixelBufferTyped<P> {
+        &self.buffer
+    }
+
+    /// The pixel buffer holding the whole canvas, allowing modification.
+    pub fn buffer_mut(&mut self) -> &mut PixelBufferTyped<P> {
+        &mut self.buffer
+    }
+
+    /// The width, in pixels, of the canvas.
+    pub fn width(&self) -> u32 {
+        self.buffer.width()
+    }
+
+    /// The height, in pixels, of the canvas.
+    pub fn height(&self) -> u32 {
+        self.buffer.height()
+    }
+
+    /// The color shown in the parts of the viewport that lie outside the canvas. Defaults to
+    /// black.
+    pub fn background(&self) -> Color {
+        self.background
+    }
+
+    /// Sets the color shown in the parts of the viewport that lie outside the canvas.
+    pub fn set_background(&mut self, background: impl Into<Color>) {
+        self.background = background.into();
+    }
+
+    /// Moves `offset` as little as possible so that a viewport of `viewport_size` at that offset
+    /// lies inside the canvas, e.g. to keep scrolling from overshooting the canvas' edges.
+    ///
+    /// Along directions where the viewport is larger than the canvas, the offset becomes `0`.
+    pub fn clamp_offset(&self, offset: (u32, u32), viewport_size: (u32, u32)) -> (u32, u32) {
+        (
+            offset.0.min(self.width().saturating_sub(viewport_size.0)),
+            offset.1.min(self.height().saturating_sub(viewport_size.1)),
+        )
+    }
+
+    /// Blits the part of the canvas with its top-left corner at `offset` onto the top-left corner
+    /// of `window`. `viewport_size` is the size of the window's area showing the canvas, usually
+    /// its inner size.
+    ///
+    /// The offset is [clamped](Self::clamp_offset) to the canvas first. Where the viewport is
+    /// larger than the canvas, the rest of it is filled with the
+    /// [background](Self::set_background).
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn present_viewport<H: HasWindowHandle>(
+        &self,
+        offset: (u32, u32),
+        viewport_size: (u32, u32),
+        window: &H,
+    ) -> Result<(), BlitError> {
+        let offset = self.clamp_offset(offset, viewport_size);
+        let size = (
+            viewport_size.0.min(self.width()),
+            viewport_size.1.min(self.height()),
+        );
+        if size == viewport_size {
+            self.buffer.blit_rect(offset, (0, 0), size, window)
+        } else {
+            self.buffer
+                .blit_rect_with_background(offset, (0, 0), size, self.background, window)
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{PixelBufferFormat, PixelBufferFormatType};
+
+/// An sRGB color with straight alpha, independent of any pixel format.
+///
+/// Colors convert into every pixel format with [`Into`] or [`to_pixel`](Self::to_pixel), so the
+/// order of a format's channels never has to be spelled out by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+    pub const RED: Color = Color::rgb(255, 0, 0);
+    pub const GREEN: Color = Color::rgb(0, 255, 0);
+    pub const BLUE: Color = Color::rgb(0, 0, 255);
+
+    /// Creates an opaque color.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color::rgba(r, g, b, 255)
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+
+    /// Creates an opaque color from a `0xRRGGBB` value.
+    pub const fn hex(rgb: u32) -> Color {
+        Color::rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+    }
+
+    /// Parses a CSS-style hex color: `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`, with the leading
+    /// `#` being optional.
+    pub fn from_hex_str(hex: &str) -> Option<Color> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        // `from_str_radix` also accepts a leading sign, so the digits are checked up front.
+        if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        match hex.len() {
+            3 | 4 => {
+                let mut channels = [255; 4];
+                for (i, channel) in channels.iter_mut().enumerate().take(hex.len()) {
+                    *channel = digit(i)? * 0x11;
+                }
+                let [r, g, b, a] = channels;
+                Some(Color::rgba(r, g, b, a))
+            }
+            6 | 8 => {
+                let mut channels = [255; 4];
+                for (i, channel) in channels.iter_mut().enumerate().take(hex.len() / 2) {
+                    *channel = byte(i * 2)?;
+                }
+                let [r, g, b, a] = channels;
+                Some(Color::rgba(r, g, b, a))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the color with its alpha channel replaced.
+    pub const fn with_alpha(self, a: u8) -> Color {
+        Color { a, ..self }
+    }
+
+    /// Creates an opaque color from hue (in degrees), saturation and value (both from `0.0` to
+    /// `1.0`).
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Color {
+        let (saturation, value) = (saturation.clamp(0.0, 1.0), value.clamp(0.0, 1.0));
+        let chroma = value * saturation;
+        Color::from_hue_chroma(hue, chroma, value - chroma)
+    }
+
+    /// The color's hue (in degrees, from `0.0` to `360.0`), saturation and value (both from `0.0`
+    /// to `1.0`). Alpha is ignored.
+    pub fn to_hsv(self) -> (f32, f32, f32) {
+        let (hue, max, min) = self.hue_max_min();
+        let saturation = if max == 0.0 { 0.0 } else { (max - min) / max };
+        (hue, saturation, max)
+    }
+
+    /// Creates an opaque color from hue (in degrees), saturation and lightness (both from `0.0` to
+    /// `1.0`).
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Color {
+        let (saturation, lightness) = (saturation.clamp(0.0, 1.0), lightness.clamp(0.0, 1.0));
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        Color::from_hue_chroma(hue, chroma, lightness - chroma / 2.0)
+    }
+
+    /// The color's hue (in degrees, from `0.0` to `360.0`), saturation and lightness (both from
+    /// `0.0` to `1.0`). Alpha is ignored.
+    pub fn to_hsl(self) -> (f32, f32, f32) {
+        let (hue, max, min) = self.hue_max_min();
+        let lightness = (max + min) / 2.0;
+        let saturation = match lightness {
+            l if l <= 0.0 || l >= 1.0 => 0.0,
+            l => (max - min) / (1.0 - (2.0 * l - 1.0).abs()),
+        };
+        (hue, saturation, lightness)
+    }
+
+    /// Builds a color from a hue, its chroma and the amount added to every channel.
+    fn from_hue_chroma(hue: f32, chroma: f32, offset: f32) -> Color {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let channel = |c: f32| ((c + offset) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Color::rgb(channel(r), channel(g), channel(b))
+    }
+
+    /// The color's hue in degrees, and its largest and smallest channels from `0.0` to `1.0`.
+    fn hue_max_min(self) -> (f32, f32, f32) {
+        let (r, g, b) = (
+            self.r as f32 / 255.0,
+            self.g as f32 / 255.0,
+            self.b as f32 / 255.0,
+        );
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let delta = max - min;
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        (hue, max, min)
+    }
+
+    /// Linearly interpolates between `self` (at `t = 0.0`) and `other` (at `t = 1.0`), including
+    /// alpha.
+    ///
+    /// The interpolation happens directly on the sRGB values, which is fast but slightly darker in
+    /// the middle than blending in linear light.
+    pub fn lerp(self, other: Color, t: f32) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Color::rgba(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
+
+    /// Converts the color into a pixel of format `P`. Formats without an alpha channel drop it.
+    pub fn to_pixel<P: PixelBufferFormat>(self) -> P {
+        P::from_rgba(self.r, self.g, self.b, self.a)
+    }
+
+    /// Converts a pixel of any format into a color. Formats without an alpha channel produce
+    /// opaque colors.
+    pub fn from_pixel<P: PixelBufferFormat>(pixel: P) -> Color {
+        let [r, g, b, a] = pixel.to_rgba();
+        Color::rgba(r, g, b, a)
+    }
+
+    /// Writes the color's bytes in the layout of `format` to the start of `bytes`, returning how
+    /// many bytes were written.
+    ///
+    /// # Panics
+    /// Panics if `bytes` is shorter than one pixel of `format`.
+    pub fn write_bytes(self, format: PixelBufferFormatType, bytes: &mut [u8]) -> usize {
+        let Color { r, g, b, a } = self;
+        let pixel: &[u8] = match format {
+            PixelBufferFormatType::BGR => &[b, g, r],
+            PixelBufferFormatType::BGRA => &[b, g, r, a],
+            PixelBufferFormatType::RGB => &[r, g, b],
+            PixelBufferFormatType::RGBA => &[r, g, b, a],
+        };
+        bytes[..pixel.len()].copy_from_slice(pixel);
+        pixel.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// The purpose of this test is to verify that every supported hex notation parses, with or
+    /// without the leading `#`, and that malformed strings are rejected.
+    fn from_hex_str_parses_css_notations() {
+        assert_eq!(
+            Color::from_hex_str("#f80"),
+            Some(Color::rgb(0xff, 0x88, 0x00))
+        );
+        assert_eq!(
+            Color::from_hex_str("f80"),
+            Some(Color::rgb(0xff, 0x88, 0x00))
+        );
+        assert_eq!(
+            Color::from_hex_str("#f808"),
+            Some(Color::rgba(0xff, 0x88, 0x00, 0x88))
+        );
+        assert_eq!(
+            Color::from_hex_str("#12aBcD"),
+            Some(Color::rgb(0x12, 0xab, 0xcd))
+        );
+        assert_eq!(
+            Color::from_hex_str("#12abcd7f"),
+            Some(Color::rgba(0x12, 0xab, 0xcd, 0x7f))
+        );
+        for invalid in [
+            "",
+            "#",
+            "#12",
+            "#12345",
+            "#1234567",
+            "#123456789",
+            "#ggg",
+            "#+f+f+f",
+            "#-1",
+            "#é00",
+        ] {
+            assert_eq!(Color::from_hex_str(invalid), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that hues land on the right primary and secondary
+    /// colors at the sector boundaries, and wrap around outside of `0.0..360.0`.
+    fn from_hsv_hue_boundaries() {
+        let cases = [
+            (0.0, Color::RED),
+            (60.0, Color::rgb(255, 255, 0)),
+            (120.0, Color::GREEN),
+            (180.0, Color::rgb(0, 255, 255)),
+            (240.0, Color::BLUE),
+            (300.0, Color::rgb(255, 0, 255)),
+            (360.0, Color::RED),
+            (720.0, Color::RED),
+            (-60.0, Color::rgb(255, 0, 255)),
+            (-f32::EPSILON, Color::RED),
+        ];
+        for &(hue, color) in &cases {
+            assert_eq!(Color::from_hsv(hue, 1.0, 1.0), color, "hue {}", hue);
+        }
+        assert_eq!(Color::from_hsv(359.0, 1.0, 1.0), Color::rgb(255, 0, 4));
+        assert_eq!(Color::from_hsv(123.0, 0.0, 1.0), Color::WHITE);
+        assert_eq!(Color::from_hsv(123.0, 1.0, 0.0), Color::BLACK);
+    }
+}
@@ -0,0 +1,180 @@
+/// How the error introduced by reducing a pixel's bit depth is distributed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DitherMode {
+    /// Round every pixel to the nearest representable color. Fastest, but smooth gradients turn
+    /// into visible bands.
+    #[default]
+    None,
+    /// Offset every pixel by a threshold from a 4x4 Bayer matrix before rounding. Cheap, and
+    /// stable between frames.
+    Ordered,
+    /// Diffuse each pixel's rounding error into its unprocessed neighbors. Gives the smoothest
+    /// result, but processes the image sequentially.
+    FloydSteinberg,
+}
+
+/// Options controlling how pixels are converted between formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ConversionOptions {
+    /// How to dither when converting to a format with fewer bits per channel.
+    pub dither: DitherMode,
+}
+
+/// The number of bits stored for each color channel of a pixel format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChannelDepth {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl ChannelDepth {
+    /// 16-bit color with 5 bits of red, 6 bits of green and 5 bits of blue.
+    pub const RGB565: ChannelDepth = ChannelDepth { r: 5, g: 6, b: 5 };
+    /// 15-bit color with 5 bits per channel.
+    pub const RGB555: ChannelDepth = ChannelDepth { r: 5, g: 5, b: 5 };
+    /// 8-bit color with 3 bits of red, 3 bits of green and 2 bits of blue.
+    pub const RGB332: ChannelDepth = ChannelDepth { r: 3, g: 3, b: 2 };
+}
+
+/// Brightness, contrast and gamma adjustments applied while presenting a pixel buffer.
+///
+/// The adjustments are baked into a lookup table, and applied to the color channels as the pixels
+/// are copied for presentation, leaving the buffer's own contents untouched. Useful for screen
+/// dimming and night modes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorAdjustment {
+    /// Multiplies every channel after the other adjustments. `1.0` leaves colors unchanged.
+    pub brightness: f32,
+    /// Scales every channel's distance from middle gray. `1.0` leaves colors unchanged.
+    pub contrast: f32,
+    /// The gamma correction exponent. Values above `1.0` brighten midtones, and `1.0` leaves
+    /// colors unchanged.
+    pub gamma: f32,
+}
+
+impl ColorAdjustment {
+    /// The adjustment that leaves every color unchanged.
+    pub const IDENTITY: ColorAdjustment = ColorAdjustment {
+        brightness: 1.0,
+        contrast: 1.0,
+        gamma: 1.0,
+    };
+
+    /// Builds the table mapping every channel value to its adjusted value.
+    pub(crate) fn lut(&self) -> Box<[u8; 256]> {
+        let mut lut = Box::new([0; 256]);
+        for (i, out) in lut.iter_mut().enumerate() {
+            let mut v = (i as f32 / 255.0).powf(1.0 / self.gamma);
+            v = (v - 0.5) * self.contrast + 0.5;
+            v *= self.brightness;
+            *out = (v * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+        lut
+    }
+}
+
+impl Default for ColorAdjustment {
+    fn default() -> ColorAdjustment {
+        ColorAdjustment::IDENTITY
+    }
+}
+
+/// How the alpha channel of a pixel buffer's pixels is interpreted.
+///
+/// This is tracked per buffer and used by the crate's blending and conversion routines. Presenting
+/// doesn't depend on it: on Windows, buffers are copied with `BitBlt`, which ignores alpha.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AlphaMode {
+    /// Color channels are independent of the alpha channel.
+    #[default]
+    Straight,
+    /// Color channels have already been multiplied by the alpha channel.
+    Premultiplied,
+    /// The alpha channel carries no meaning, and every pixel is treated as opaque.
+    Ignored,
+}
+
+/// Which color space the crate's drawing helpers blend translucent colors in.
+///
+/// Colors are always given, and pixels always stored, gamma-encoded as sRGB. Only blending is
+/// affected: it's what decides how anti-aliased or translucent edges look, so matching the other
+/// toolkits in an application keeps its UI consistent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RenderingIntent {
+    /// Blend the gamma-encoded values directly. This is fast, and matches most 2D toolkits and
+    /// browsers, but translucent colors come out slightly darker than they physically would.
+    #[default]
+    Gamma,
+    /// Convert to linear light, blend, and convert back, the way GPU pipelines with sRGB render
+    /// targets do. Slower, but blends look physically correct.
+    Linear,
+}
+
+/// What a pixel buffer's present path does with the alpha channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlphaBehavior {
+    /// The alpha channel is discarded, and every pixel is shown as opaque.
+    Ignored,
+    /// The pixels are expected to be premultiplied, and are composited with whatever is behind the
+    /// window.
+    Premultiplied,
+    /// The pixels are composited with whatever is behind the window using straight alpha.
+    Composited,
+}
+
+/// Converts an RGBA color from one alpha mode to another.
+pub(crate) fn convert_alpha(rgba: [u8; 4], from: AlphaMode, to: AlphaMode) -> [u8; 4] {
+    let [r, g, b, a] = rgba;
+    match (from, to) {
+        (AlphaMode::Straight, AlphaMode::Premultiplied) => {
+            let premultiply = |c: u8| ((c as u32 * a as u32 + 127) / 255) as u8;
+            [premultiply(r), premultiply(g), premultiply(b), a]
+        }
+        (AlphaMode::Premultiplied, AlphaMode::Straight) => {
+            if a == 0 {
+                [0, 0, 0, 0]
+            } else {
+                let unpremultiply =
+                    |c: u8| ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8;
+                [unpremultiply(r), unpremultiply(g), unpremultiply(b), a]
+            }
+        }
+        (AlphaMode::Premultiplied, AlphaMode::Ignored) => [r, g, b, 255],
+        (AlphaMode::Straight, AlphaMode::Ignored) => {
+            // Composite over black, which is how a premultiplied pixel would look.
+            let [r, g, b, _] = convert_alpha(rgba, from, AlphaMode::Premultiplied);
+            [r, g, b, 255]
+        }
+        (AlphaMode::Ignored, _) => [r, g, b, 255],
+        _ => rgba,
+    }
+}
+
+/// Decodes an sRGB channel value into linear light, from `0.0` to `1.0`.
+pub(crate) fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    match c <= 0.04045 {
+        true => c / 12.92,
+        false => ((c + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+/// Encodes a linear light value, from `0.0` to `1.0`, into an sRGB channel value.
+pub(crate) fn linear_to_srgb(c: f32) -> u8 {
+    let c = match c <= 0.0031308 {
+        true => c * 12.92,
+        false => 1.055 * c.powf(1.0 / 2.4) - 0.055,
+    };
+    (c * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// Converts a pixel to limited-range BT.601 Y'CbCr, with the integer approximation used by
+/// most video software.
+pub(crate) fn to_ycbcr([r, g, b, _]: [u8; 4]) -> (u8, u8, u8) {
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    (y as u8, u as u8, v as u8)
+}
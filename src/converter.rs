@@ -0,0 +1,397 @@
+use crate::{
+    filters, ChannelDepth, ConversionOptions, PixelBufferFormat, PixelBufferFormatType, RegionMut,
+    BGR, BGRA, RGB, RGBA,
+};
+
+/// Converts a row of pixels from `src` to `dst`, which hold the same number of pixels.
+type RowFn = fn(&[u8], &mut [u8]);
+
+/// Describes how an image's pixels are laid out in a byte slice, for a [`Converter`].
+///
+/// Rows are stored top-down, `stride` bytes apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageDesc {
+    pub format: PixelBufferFormatType,
+    pub width: u32,
+    pub height: u32,
+    /// The number of bytes between the start of one row and the next.
+    pub stride: usize,
+}
+
+impl ImageDesc {
+    /// Describes a `width` by `height` image of tightly-packed pixels.
+    pub fn new(format: PixelBufferFormatType, width: u32, height: u32) -> ImageDesc {
+        ImageDesc {
+            format,
+            width,
+            height,
+            stride: width as usize * format.bytes_per_pixel(),
+        }
+    }
+
+    /// The same image, with `stride` bytes between the start of one row and the next.
+    pub fn with_stride(self, stride: usize) -> ImageDesc {
+        ImageDesc { stride, ..self }
+    }
+
+    /// The fewest bytes a slice holding the image can have. The last row doesn't need padding.
+    pub fn min_len(&self) -> usize {
+        match self.height {
+            0 => 0,
+            height => {
+                // Saturates, so descriptions too large to address are simply too long for any
+                // slice instead of overflowing.
+                (height as usize - 1)
+                    .saturating_mul(self.stride)
+                    .saturating_add(self.width as usize * self.format.bytes_per_pixel())
+            }
+        }
+    }
+}
+
+/// Converts images between pixel formats and row strides, outside of presenting, e.g. to make
+/// thumbnails or save a buffer's contents to a file.
+///
+/// This is the conversion [`copy_from_buffer`](crate::PixelBufferTyped::copy_from_buffer) uses.
+/// Picking the routine for a pair of formats is cached, so converting many images of the same
+/// formats only does it once.
+#[derive(Debug, Clone, Default)]
+pub struct Converter {
+    cached: Option<(PixelBufferFormatType, PixelBufferFormatType, RowFn)>,
+}
+
+impl Converter {
+    /// Creates a converter.
+    pub fn new() -> Converter {
+        Converter::default()
+    }
+
+    /// Converts the image in `src`, described by `src_desc`, to the format and stride of
+    /// `dst_desc`, writing it to `dst`.
+    ///
+    /// If the images differ in size, only their overlapping top-left part is converted. Padding
+    /// bytes at the end of `dst`'s rows are left untouched. Alpha is treated as straight, and
+    /// set to `255` when the source has none.
+    ///
+    /// # Panics
+    /// Panics if either slice is shorter than the [`min_len`](ImageDesc::min_len) of its
+    /// description, or if either stride is shorter than a row of pixels.
+    pub fn convert(
+        &mut self,
+        src: &[u8],
+        src_desc: ImageDesc,
+        dst: &mut [u8],
+        dst_desc: ImageDesc,
+    ) {
+        for desc in [&src_desc, &dst_desc] {
+            assert!(
+                desc.height <= 1
+                    || desc.stride >= desc.width as usize * desc.format.bytes_per_pixel(),
+                "stride is shorter than a row of pixels"
+            );
+        }
+        assert!(src.len() >= src_desc.min_len(), "source is too short");
+        assert!(dst.len() >= dst_desc.min_len(), "destination is too short");
+
+        let convert_row = self.row_fn(src_desc.format, dst_desc.format);
+        let width = src_desc.width.min(dst_desc.width) as usize;
+        let height = src_desc.height.min(dst_desc.height) as usize;
+        let src_len = width * src_desc.format.bytes_per_pixel();
+        let dst_len = width * dst_desc.format.bytes_per_pixel();
+        for y in 0..height {
+            let src = &src[y * src_desc.stride..][..src_len];
+            let dst = &mut dst[y * dst_desc.stride..][..dst_len];
+            convert_row(src, dst);
+        }
+    }
+
+    fn row_fn(&mut self, src: PixelBufferFormatType, dst: PixelBufferFormatType) -> RowFn {
+        match self.cached {
+            Some((cached_src, cached_dst, row_fn)) if (cached_src, cached_dst) == (src, dst) => {
+                row_fn
+            }
+            _ => {
+                let row_fn = select(src, dst);
+                self.cached = Some((src, dst, row_fn));
+                row_fn
+            }
+        }
+    }
+}
+
+/// The conversion a buffer's rows go through while it's presented, after any user-provided
+/// [`PresentConversion`](crate::PresentConversion) has run: converting them to the format the
+/// platform presents, mapping their color channels through a LUT, and reducing their color depth.
+pub(crate) struct PresentStage<'a> {
+    src_format: PixelBufferFormatType,
+    dst_format: PixelBufferFormatType,
+    row_fn: RowFn,
+    lut: Option<&'a [u8; 256]>,
+    depth: Option<(ChannelDepth, ConversionOptions)>,
+}
+
+impl<'a> PresentStage<'a> {
+    /// Creates a stage converting rows from `src_format` to `dst_format`, which must store as
+    /// many bytes per pixel.
+    pub(crate) fn new(
+        src_format: PixelBufferFormatType,
+        dst_format: PixelBufferFormatType,
+        lut: Option<&'a [u8; 256]>,
+        depth: Option<(ChannelDepth, ConversionOptions)>,
+    ) -> PresentStage<'a> {
+        assert_eq!(src_format.bytes_per_pixel(), dst_format.bytes_per_pixel());
+        PresentStage {
+            src_format,
+            dst_format,
+            row_fn: select(src_format, dst_format),
+            lut,
+            depth,
+        }
+    }
+
+    /// Converts the first `width` pixels of every row in `src` into the matching row of `dst`.
+    /// Rows are `row_len` bytes apart in both, and their padding is left untouched.
+    pub(crate) fn convert_rows(&self, src: &[u8], dst: &mut [u8], width: u32, row_len: usize) {
+        let len = width as usize * self.dst_format.bytes_per_pixel();
+        for (src, dst) in src.chunks_exact(row_len).zip(dst.chunks_exact_mut(row_len)) {
+            let dst = &mut dst[..len];
+            (self.row_fn)(&src[..len], dst);
+            self.map_colors(dst);
+        }
+    }
+
+    /// Converts the first `width` pixels of every row in `rows`, `row_len` bytes apart, where they
+    /// are, e.g. after a [`PresentConversion`](crate::PresentConversion) wrote them.
+    pub(crate) fn convert_rows_in_place(&self, rows: &mut [u8], width: u32, row_len: usize) {
+        let bytes_per_pixel = self.dst_format.bytes_per_pixel();
+        let len = width as usize * bytes_per_pixel;
+        for row in rows.chunks_exact_mut(row_len) {
+            let row = &mut row[..len];
+            if self.src_format != self.dst_format {
+                for pixel in row.chunks_exact_mut(bytes_per_pixel) {
+                    let mut src = [0; 4];
+                    src[..bytes_per_pixel].copy_from_slice(pixel);
+                    (self.row_fn)(&src[..bytes_per_pixel], pixel);
+                }
+            }
+            self.map_colors(row);
+        }
+    }
+
+    /// Maps the color channels of `pixels` through the LUT, if there is one. Alpha is left alone.
+    fn map_colors(&self, pixels: &mut [u8]) {
+        if let Some(lut) = self.lut {
+            for pixel in pixels.chunks_exact_mut(self.dst_format.bytes_per_pixel()) {
+                for channel in &mut pixel[..3] {
+                    *channel = lut[*channel as usize];
+                }
+            }
+        }
+    }
+
+    /// Reduces the color depth of the first `width` pixels of every row in `rows`, `row_len` bytes
+    /// apart, once they've been converted. Dithering carries over from one row to the next, so
+    /// this has to see all the rows at once, in the order given by `bottom_up`.
+    pub(crate) fn reduce_depth(
+        &self,
+        rows: &mut [u8],
+        width: u32,
+        row_len: usize,
+        bottom_up: bool,
+    ) {
+        let (depth, options) = match self.depth {
+            Some(depth) => depth,
+            None => return,
+        };
+        let height = rows.len().checked_div(row_len).unwrap_or(0);
+        if height == 0 {
+            return;
+        }
+        let (offset, pitch) = match bottom_up {
+            true => ((height - 1) * row_len, -(row_len as isize)),
+            false => (0, row_len as isize),
+        };
+        let height = height as u32;
+        unsafe {
+            // The top row's pointer is derived from all of `rows`, since a negative pitch reaches
+            // the rows before it.
+            let top = rows.as_mut_ptr().add(offset);
+            match self.dst_format {
+                PixelBufferFormatType::BGR => filters::quantize(
+                    RegionMut::<BGR>::from_raw_parts(top, pitch, width, height),
+                    depth,
+                    options,
+                ),
+                PixelBufferFormatType::BGRA => filters::quantize(
+                    RegionMut::<BGRA>::from_raw_parts(top, pitch, width, height),
+                    depth,
+                    options,
+                ),
+                PixelBufferFormatType::RGB => filters::quantize(
+                    RegionMut::<RGB>::from_raw_parts(top, pitch, width, height),
+                    depth,
+                    options,
+                ),
+                PixelBufferFormatType::RGBA => filters::quantize(
+                    RegionMut::<RGBA>::from_raw_parts(top, pitch, width, height),
+                    depth,
+                    options,
+                ),
+            }
+        }
+    }
+}
+
+/// Picks the routine converting rows from `src` to `dst`.
+fn select(src: PixelBufferFormatType, dst: PixelBufferFormatType) -> RowFn {
+    fn to<P: PixelBufferFormat>(dst: PixelBufferFormatType) -> RowFn {
+        match dst {
+            PixelBufferFormatType::BGR => convert_row_raw::<P, BGR>,
+            PixelBufferFormatType::BGRA => convert_row_raw::<P, BGRA>,
+            PixelBufferFormatType::RGB => convert_row_raw::<P, RGB>,
+            PixelBufferFormatType::RGBA => convert_row_raw::<P, RGBA>,
+        }
+    }
+    match src {
+        PixelBufferFormatType::BGR => to::<BGR>(dst),
+        PixelBufferFormatType::BGRA => to::<BGRA>(dst),
+        PixelBufferFormatType::RGB => to::<RGB>(dst),
+        PixelBufferFormatType::RGBA => to::<RGBA>(dst),
+    }
+}
+
+fn convert_row_raw<P: PixelBufferFormat, Q: PixelBufferFormat>(src: &[u8], dst: &mut [u8]) {
+    convert_row(P::from_raw_slice(src), Q::from_raw_slice_mut(dst));
+}
+
+/// Converts the pixels of `src` into `dst`, which must be as long.
+pub(crate) fn convert_row<P: PixelBufferFormat, Q: PixelBufferFormat>(src: &[P], dst: &mut [Q]) {
+    if P::FORMAT_TYPE == Q::FORMAT_TYPE {
+        Q::to_raw_slice_mut(dst).copy_from_slice(P::to_raw_slice(src));
+    } else {
+        for (dst, src) in dst.iter_mut().zip(src) {
+            let [r, g, b, a] = src.to_rgba();
+            *dst = Q::from_rgba(r, g, b, a);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DitherMode;
+
+    /// Inverts every channel.
+    fn inverting_lut() -> [u8; 256] {
+        let mut lut = [0; 256];
+        for (i, v) in lut.iter_mut().enumerate() {
+            *v = 255 - i as u8;
+        }
+        lut
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that the converter swaps channels between formats,
+    /// honors both strides, and only converts the part of the images that overlaps.
+    fn converter_converts_formats_and_strides() {
+        // A 2x2 RGB image, with 2 bytes of padding per row.
+        let src = [1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12];
+        let src_desc = ImageDesc::new(PixelBufferFormatType::RGB, 2, 2).with_stride(8);
+        assert_eq!(src_desc.min_len(), src.len());
+        // A 3x1 BGRA image, so only the top row of the source fits.
+        let mut dst = [0xaa; 12];
+        let dst_desc = ImageDesc::new(PixelBufferFormatType::BGRA, 3, 1);
+
+        let mut converter = Converter::new();
+        converter.convert(&src, src_desc, &mut dst, dst_desc);
+        assert_eq!(dst, [3, 2, 1, 255, 6, 5, 4, 255, 0xaa, 0xaa, 0xaa, 0xaa]);
+
+        // Converting back picks a new routine, for the reversed pair of formats.
+        let mut back = [0; 14];
+        converter.convert(&dst, dst_desc, &mut back, src_desc);
+        assert_eq!(back, [1, 2, 3, 4, 5, 6, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that presented rows are swapped into the platform's
+    /// format and mapped through the LUT, leaving alpha and the rows' padding alone.
+    fn present_stage_converts_padded_rows() {
+        let lut = inverting_lut();
+        let stage = PresentStage::new(
+            PixelBufferFormatType::RGB,
+            PixelBufferFormatType::BGR,
+            Some(&lut),
+            None,
+        );
+        // Two rows of two pixels, each padded to eight bytes.
+        let src = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+        let mut dst = [0; 16];
+        stage.convert_rows(&src, &mut dst, 2, 8);
+        assert_eq!(
+            dst,
+            [252, 253, 254, 249, 250, 251, 0, 0, 244, 245, 246, 241, 242, 243, 0, 0]
+        );
+
+        let stage = PresentStage::new(
+            PixelBufferFormatType::RGBA,
+            PixelBufferFormatType::BGRA,
+            Some(&lut),
+            None,
+        );
+        let mut dst = [0; 8];
+        stage.convert_rows(&src[..8], &mut dst, 2, 8);
+        assert_eq!(dst, [252, 253, 254, 4, 248, 249, 250, 8]);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that rows written by a present conversion are
+    /// converted where they are, the same way as when they're copied.
+    fn present_stage_converts_rows_in_place() {
+        let lut = inverting_lut();
+        let stage = PresentStage::new(
+            PixelBufferFormatType::RGBA,
+            PixelBufferFormatType::BGRA,
+            None,
+            None,
+        );
+        let mut rows = [1, 2, 3, 4, 5, 6, 7, 8];
+        stage.convert_rows_in_place(&mut rows, 1, 4);
+        assert_eq!(rows, [3, 2, 1, 4, 7, 6, 5, 8]);
+
+        let stage = PresentStage::new(
+            PixelBufferFormatType::BGR,
+            PixelBufferFormatType::BGR,
+            Some(&lut),
+            None,
+        );
+        let mut rows = [1, 2, 3, 4];
+        stage.convert_rows_in_place(&mut rows, 1, 4);
+        assert_eq!(rows, [254, 253, 252, 4]);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that reducing the color depth of bottom-up rows
+    /// starts at the top row, i.e. the last one in memory, and diffuses its error downwards.
+    fn present_stage_reduces_depth_top_down() {
+        let depth = ChannelDepth { r: 1, g: 1, b: 1 };
+        let options = ConversionOptions {
+            dither: DitherMode::FloydSteinberg,
+        };
+        let stage = PresentStage::new(
+            PixelBufferFormatType::BGR,
+            PixelBufferFormatType::BGR,
+            None,
+            Some((depth, options)),
+        );
+        // The top row rounds down, and its error pushes the row below it up.
+        let mut rows = [100, 100, 100, 9, 100, 100, 100, 9];
+        stage.reduce_depth(&mut rows, 1, 4, true);
+        assert_eq!(rows, [255, 255, 255, 9, 0, 0, 0, 9]);
+
+        let mut rows = [100, 100, 100, 9, 100, 100, 100, 9];
+        stage.reduce_depth(&mut rows, 1, 4, false);
+        assert_eq!(rows, [0, 0, 0, 9, 255, 255, 255, 9]);
+
+        stage.reduce_depth(&mut [], 1, 4, true);
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{converter::convert_row, PixelBufferFormat, PixelBufferTyped, Point, Size};
+
+impl<P: PixelBufferFormat> PixelBufferTyped<P> {
+    /// Copies the `size` pixels at `src_pos` in `other` to `dst_pos` in this pixel buffer,
+    /// converting them from `other`'s format as needed.
+    ///
+    /// The buffers may differ in format, size and row pitch, so this can move content between
+    /// windows with different native formats, e.g. when a tab is dragged onto another monitor.
+    /// The part of the rectangle that lies outside either buffer isn't copied.
+    pub fn copy_from_buffer<Q: PixelBufferFormat>(
+        &mut self,
+        other: &PixelBufferTyped<Q>,
+        src_pos: impl Into<Point>,
+        size: impl Into<Size>,
+        dst_pos: impl Into<Point>,
+    ) {
+        let (src_pos, size, dst_pos): (Point, Size, Point) =
+            (src_pos.into(), size.into(), dst_pos.into());
+        let clip = |size: u32, src: u32, src_len: u32, dst: u32, dst_len: u32| {
+            size.min(src_len.saturating_sub(src))
+                .min(dst_len.saturating_sub(dst))
+        };
+        let width = clip(
+            size.width,
+            src_pos.x,
+            other.width(),
+            dst_pos.x,
+            self.width(),
+        );
+        let height = clip(
+            size.height,
+            src_pos.y,
+            other.height(),
+            dst_pos.y,
+            self.height(),
+        );
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let (src_x, dst_x) = (src_pos.x as usize, dst_pos.x as usize);
+        for y in 0..height {
+            let src = &other.row(src_pos.y + y).unwrap()[src_x..src_x + width as usize];
+            let dst = self.p.p.row_mut(dst_pos.y + y).unwrap();
+            let dst = &mut P::from_raw_slice_mut(dst)[dst_x..dst_x + width as usize];
+            convert_row(src, dst);
+        }
+        self.mark_dirty(dst_pos, (width, height));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{PixelBufferFormatSupported, PixelBufferTyped};
+use std::slice;
+
+/// A user-defined pixel format, such as BGRA with 2-bit alpha, or a bitfield layout used by
+/// custom hardware.
+///
+/// Platforms can only present their native formats, so pixels of a custom format are kept in a
+/// [`PixelBufferCustom`] and converted with [`to_native`](Self::to_native) before presenting.
+/// Implementing this trait is all that's needed to use a format; nothing has to be registered.
+///
+/// Every [supported](PixelBufferFormatSupported) format is a custom format as well, which converts
+/// to itself, so code that's generic over custom formats also works with the built-in ones.
+pub trait CustomFormat: Copy {
+    /// The supported format the pixels are converted to for presenting.
+    type Native: PixelBufferFormatSupported;
+
+    /// Converts the pixel into the native format.
+    fn to_native(self) -> Self::Native;
+}
+
+impl<P: PixelBufferFormatSupported> CustomFormat for P {
+    type Native = P;
+
+    fn to_native(self) -> P {
+        self
+    }
+}
+
+/// A heap-allocated buffer of pixels in a [`CustomFormat`].
+///
+/// Fill it through its [rows](Self::rows_mut), and copy it into a window's [`PixelBufferTyped`]
+/// with [`convert_to`](Self::convert_to) once per frame, converting every pixel along the way.
+/// The [`draw`](crate::draw) functions only work on the built-in formats, so draw into the
+/// native buffer after converting instead, e.g. to overlay a UI.
+pub struct PixelBufferCustom<F: CustomFormat> {
+    pixels: Vec<F>,
+    width: u32,
+    height: u32,
+}
+
+impl<F: CustomFormat> PixelBufferCustom<F> {
+    /// Creates a `width` by `height` buffer filled with `fill`.
+    pub fn new(width: u32, height: u32, fill: F) -> PixelBufferCustom<F> {
+        PixelBufferCustom {
+            pixels: vec![fill; width as usize * height as usize],
+            width,
+            height,
+        }
+    }
+
+    /// The width, in pixels, of the buffer.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height, in pixels, of the buffer.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Gets the row at the particular height.
+    pub fn row(&self, row: u32) -> Option<&[F]> {
+        self.rows().nth(row as usize)
+    }
+
+    /// Mutably gets the row at the particular height.
+    pub fn row_mut(&mut self, row: u32) -> Option<&mut [F]> {
+        self.rows_mut().nth(row as usize)
+    }
+
+    /// Iterate through all rows in the buffer.
+    pub fn rows(&self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &[F]> {
+        let (pixels, width) = (&self.pixels, self.width as usize);
+        (0..self.height as usize).map(move |y| &pixels[y * width..][..width])
+    }
+
+    /// Mutably iterate through all rows in the buffer.
+    pub fn rows_mut(&mut self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &mut [F]> {
+        let (pixels, width) = (self.pixels.as_mut_ptr(), self.width as usize);
+        (0..self.height as usize).map(move |y| unsafe {
+            // Every row is handed out exactly once, so none of these slices alias. Rows of an
+            // empty buffer are empty slices, which are fine to create from any pointer.
+            slice::from_raw_parts_mut(pixels.add(y * width), width)
+        })
+    }
+
+    /// Converts the buffer into the top-left corner of `target`. Pixels that don't fit are cut
+    /// off.
+    pub fn convert_to(&self, target: &mut PixelBufferTyped<F::Native>) {
+        let width = self.width.min(target.width()) as usize;
+        for (src, dst) in self.rows().zip(target.rows_mut()) {
+            for (dst, &src) in dst[..width].iter_mut().zip(src) {
+                *dst = src.to_native();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BGRA;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Rgb565(u16);
+
+    impl CustomFormat for Rgb565 {
+        type Native = BGRA;
+        fn to_native(self) -> BGRA {
+            let expand = |value: u16, bits: u32| ((value as u32 * 255) / ((1 << bits) - 1)) as u8;
+            BGRA::from_rgb(
+                expand(self.0 >> 11, 5),
+                expand((self.0 >> 5) & 0x3f, 6),
+                expand(self.0 & 0x1f, 5),
+            )
+        }
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that pixels of a user-defined format are converted
+    /// into a native buffer, and cut off where they don't fit.
+    fn convert_to_converts_and_crops() {
+        let mut custom = PixelBufferCustom::new(8, 2, Rgb565(0xf800));
+        custom.row_mut(1).unwrap()[0] = Rgb565(0x07ff);
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(4, 4).unwrap();
+        pb.clear(BGRA::from_rgb(0, 0, 0));
+        custom.convert_to(&mut pb);
+        assert_eq!(pb.row(0).unwrap(), &[BGRA::from_rgb(255, 0, 0); 4]);
+        assert_eq!(pb.row(1).unwrap()[0], BGRA::from_rgb(0, 255, 255));
+        assert_eq!(pb.row(2).unwrap(), &[BGRA::from_rgb(0, 0, 0); 4]);
+
+        // Built-in formats convert to themselves.
+        let mut native = PixelBufferCustom::new(2, 2, BGRA::from_rgb(1, 2, 3));
+        native.row_mut(0).unwrap()[1] = BGRA::from_rgb(4, 5, 6);
+        native.convert_to(&mut pb);
+        assert_eq!(
+            pb.row(0).unwrap()[..3],
+            [
+                BGRA::from_rgb(1, 2, 3),
+                BGRA::from_rgb(4, 5, 6),
+                BGRA::from_rgb(255, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that buffers without any columns still have all of
+    /// their rows.
+    fn rows_of_empty_buffers() {
+        let mut custom = PixelBufferCustom::new(0, 3, Rgb565(0));
+        assert_eq!(custom.rows().len(), 3);
+        assert!(custom.rows().all(|row| row.is_empty()));
+        assert_eq!(custom.rows_mut().rev().len(), 3);
+        assert_eq!(custom.row(2), Some(&[][..]));
+        assert_eq!(custom.row(3), None);
+
+        let mut custom = PixelBufferCustom::new(3, 2, Rgb565(0));
+        for (y, row) in custom.rows_mut().enumerate() {
+            row[2] = Rgb565(y as u16);
+        }
+        assert_eq!(custom.row(1).unwrap(), &[Rgb565(0), Rgb565(0), Rgb565(1)]);
+        assert_eq!(custom.rows().next_back().unwrap()[2], Rgb565(1));
+    }
+}
@@ -0,0 +1,1060 @@
+//! Simple rasterization primitives.
+//!
+//! Every function takes anything that can be turned into a [`RegionMut`], so shapes can be drawn
+//! into a whole [`PixelBufferTyped`](crate::PixelBufferTyped) or into one region of it. Shapes
+//! may lie partially or entirely outside the target; anything outside of it is clipped.
+//!
+//! Coordinates are in pixels, with the origin in the top-left corner of the target. Pixel
+//! `(x, y)` covers the area from `(x, y)` to `(x + 1, y + 1)`. Positions are signed `(x, y)`
+//! tuples, so shapes can start above or left of the target, while sizes and radii accept
+//! anything that converts into a [`Size`].
+
+use crate::{
+    fill::{blend_src_over, fill_row},
+    transform::{Affine2, Source},
+    Color, Filter, PixelBufferFormat, Point, RegionMut, Size,
+};
+use std::convert::TryFrom;
+
+/// Sets a single pixel, if it lies inside the target.
+pub fn pixel<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    pos: (i32, i32),
+    color: impl Into<P>,
+) {
+    let color = color.into();
+    set_pixel(&mut target.into(), pos, color);
+}
+
+/// Draws a one pixel wide line from `from` to `to`, including both end points.
+pub fn line<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    from: (i32, i32),
+    to: (i32, i32),
+    color: impl Into<P>,
+) {
+    let color = color.into();
+    let mut target = target.into();
+    // Walks along the axis the line is longer in, and rounds the offset along the other one,
+    // which sets the same pixels as Bresenham's algorithm. Unlike Bresenham's, it can start and
+    // stop at the target's edges, so lines reaching far outside of it cost no more than ones
+    // inside it.
+    let (x0, y0, x1, y1) = (from.0 as i128, from.1 as i128, to.0 as i128, to.1 as i128);
+    let (width, height) = (target.width() as i128, target.height() as i128);
+    let x_major = (x1 - x0).abs() >= (y1 - y0).abs();
+    let ((a0, a1, a_len), (b0, b1, b_len)) = match x_major {
+        true => ((x0, x1, width), (y0, y1, height)),
+        false => ((y0, y1, height), (x0, x1, width)),
+    };
+    let (major, minor) = ((a1 - a0).abs(), (b1 - b0).abs());
+    let (step_a, step_b) = ((a1 - a0).signum(), (b1 - b0).signum());
+    // The offset along the minor axis after `k` steps along the major axis.
+    let offset = |k: i128| match major {
+        0 => 0,
+        _ => (2 * k * minor + major).div_euclid(2 * major),
+    };
+
+    let (mut first, mut last) = (0, major);
+    match inside(a0, step_a, a_len) {
+        Some((lo, hi)) => (first, last) = (first.max(lo), last.min(hi)),
+        None => return,
+    }
+    match (inside(b0, step_b, b_len), minor) {
+        (None, _) => return,
+        (Some(_), 0) => (),
+        (Some((lo, hi)), _) => {
+            // The steps whose offset lies in `lo..=hi`.
+            let ceil_div = |n: i128, d: i128| -(-n).div_euclid(d);
+            first = first.max(ceil_div(2 * major * lo - major, 2 * minor));
+            last = last.min((2 * major * (hi + 1) - major - 1).div_euclid(2 * minor));
+        }
+    }
+    for k in first..=last {
+        let (a, b) = (a0 + step_a * k, b0 + step_b * offset(k));
+        let (x, y) = if x_major { (a, b) } else { (b, a) };
+        set_pixel(&mut target, (x as i32, y as i32), color);
+    }
+}
+
+/// Draws a line from `from` to `to` that's `width` pixels wide.
+///
+/// The line has square ends that stop at its end points.
+pub fn thick_line<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    from: (i32, i32),
+    to: (i32, i32),
+    width: f32,
+    color: impl Into<P>,
+) {
+    let color = color.into();
+    let mut target = target.into();
+    // Pixel centers are offset by half a pixel from pixel coordinates.
+    let from = (from.0 as f32 + 0.5, from.1 as f32 + 0.5);
+    let to = (to.0 as f32 + 0.5, to.1 as f32 + 0.5);
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let len = (dx * dx + dy * dy).sqrt();
+    if len == 0.0 {
+        let half = width / 2.0;
+        let corners = [
+            (from.0 - half, from.1 - half),
+            (from.0 + half, from.1 - half),
+            (from.0 + half, from.1 + half),
+            (from.0 - half, from.1 + half),
+        ];
+        fill_convex_polygon_f32(&mut target, &corners, color);
+        return;
+    }
+    let (nx, ny) = (-dy / len * width / 2.0, dx / len * width / 2.0);
+    let corners = [
+        (from.0 + nx, from.1 + ny),
+        (to.0 + nx, to.1 + ny),
+        (to.0 - nx, to.1 - ny),
+        (from.0 - nx, from.1 - ny),
+    ];
+    fill_convex_polygon_f32(&mut target, &corners, color);
+}
+
+/// Draws the outline of an axis-aligned rectangle with its top-left corner at `pos`.
+pub fn rect<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    pos: (i32, i32),
+    size: impl Into<Size>,
+    color: impl Into<P>,
+) {
+    let color = color.into();
+    let mut target = target.into();
+    let size: Size = size.into();
+    if size.is_empty() {
+        return;
+    }
+    let (x0, y0) = (pos.0 as i64, pos.1 as i64);
+    let (x1, y1) = (x0 + size.width as i64, y0 + size.height as i64);
+    span(&mut target, y0, x0, x1, color);
+    span(&mut target, y1 - 1, x0, x1, color);
+    // Only the rows inside the target can be drawn, however tall the rectangle is.
+    for y in (y0 + 1).max(0)..(y1 - 1).min(target.height() as i64) {
+        span(&mut target, y, x0, x0 + 1, color);
+        span(&mut target, y, x1 - 1, x1, color);
+    }
+}
+
+/// Fills an axis-aligned rectangle with its top-left corner at `pos`.
+pub fn fill_rect<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    pos: (i32, i32),
+    size: impl Into<Size>,
+    color: impl Into<P>,
+) {
+    let color = color.into();
+    let mut target = target.into();
+    let size: Size = size.into();
+    let (x0, y0) = (pos.0 as i64, pos.1 as i64);
+    for y in y0.max(0)..(y0 + size.height as i64).min(target.height() as i64) {
+        span(&mut target, y, x0, x0 + size.width as i64, color);
+    }
+}
+
+/// Blends `color` over an axis-aligned rectangle with its top-left corner at `pos`.
+///
+/// `color` may be a [`Color`] or a pixel in any format; its alpha channel controls how much of it covers the
+/// existing pixels (source-over compositing). This is what dimming overlays, selection
+/// rectangles and translucent HUD backgrounds need. `color` is always taken to have straight
+/// alpha, while the existing pixels are interpreted according to the target's
+/// [`alpha_mode`](RegionMut::alpha_mode). The colors are blended in the color space chosen by
+/// the target's [`rendering_intent`](RegionMut::rendering_intent).
+pub fn fill_rect_blend<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    pos: (i32, i32),
+    size: impl Into<Size>,
+    color: impl Into<Color>,
+) {
+    let mut target = target.into();
+    let mode = target.alpha_mode();
+    let intent = target.rendering_intent();
+    let color: Color = color.into();
+    let size: Size = size.into();
+    match color.a {
+        0 => return,
+        255 => return fill_rect(target, pos, size, color.to_pixel::<P>()),
+        _ => (),
+    }
+    let rgba = [color.r, color.g, color.b, color.a];
+    let (x0, y0) = (pos.0 as i64, pos.1 as i64);
+    let width = target.width() as i64;
+    let (first_column, last_column) = (
+        x0.clamp(0, width) as usize,
+        (x0 + size.width as i64).clamp(0, width) as usize,
+    );
+    for y in y0.max(0)..(y0 + size.height as i64).min(target.height() as i64) {
+        let row = target.row_mut(y as u32).unwrap();
+        for pixel in &mut row[first_column..last_column] {
+            *pixel = blend_src_over(*pixel, rgba, mode, intent);
+        }
+    }
+}
+
+/// Draws the outline of a circle centered on pixel `center`.
+pub fn circle<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    center: (i32, i32),
+    radius: u32,
+    color: impl Into<P>,
+) {
+    let color = color.into();
+    ellipse(target, center, (radius, radius), color)
+}
+
+/// Fills a circle centered on pixel `center`.
+pub fn fill_circle<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    center: (i32, i32),
+    radius: u32,
+    color: impl Into<P>,
+) {
+    let color = color.into();
+    fill_ellipse(target, center, (radius, radius), color)
+}
+
+/// Draws the outline of an axis-aligned ellipse centered on pixel `center`, with horizontal and
+/// vertical radii `radii`.
+///
+/// An ellipse with a radius of zero is a line through its center.
+pub fn ellipse<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    center: (i32, i32),
+    radii: impl Into<Size>,
+    color: impl Into<P>,
+) {
+    let color = color.into();
+    let mut target = target.into();
+    let radii: Size = radii.into();
+    let (cx, cy) = (center.0 as i64, center.1 as i64);
+    let (a, b) = (radii.width as i64, radii.height as i64);
+    if b == 0 {
+        return span(&mut target, cy, cx - a, cx + a + 1, color);
+    }
+    let (width, height) = (target.width() as i64, target.height() as i64);
+    if a == 0 {
+        if (0..width).contains(&cx) {
+            for y in (cy - b).max(0)..=(cy + b).min(height - 1) {
+                set_pixel(&mut target, (cx as i32, y as i32), color);
+            }
+        }
+        return;
+    }
+
+    // The midpoint ellipse algorithm walks along x while the curve is shallow, and along y once it
+    // becomes steep. Walking the whole curve takes billions of steps for the largest radii, so
+    // only the steps that land inside the target are taken, each computing its offset along the
+    // other axis directly.
+    for &(major, minor, along_y) in &[(a, b, false), (b, a, true)] {
+        let (center, len) = if along_y { (cy, height) } else { (cx, width) };
+        let last = arc_end(major, minor);
+        // The steps for which `center + step` or `center - step` lies inside the target.
+        let ahead = (-center).max(0)..=(len - 1 - center).min(last);
+        let behind = (center - len + 1).max(0)..=center.min(last);
+        for step in ahead
+            .clone()
+            .chain(behind.filter(|step| !ahead.contains(step)))
+        {
+            let offset = arc_offset(major, minor, step);
+            for &(step, offset) in &[
+                (step, offset),
+                (-step, offset),
+                (step, -offset),
+                (-step, -offset),
+            ] {
+                let (x, y) = match along_y {
+                    false => (cx + step, cy + offset),
+                    true => (cx + offset, cy + step),
+                };
+                if let (Ok(x), Ok(y)) = (i32::try_from(x), i32::try_from(y)) {
+                    set_pixel(&mut target, (x, y), color);
+                }
+            }
+        }
+    }
+}
+
+/// Fills an axis-aligned ellipse centered on pixel `center`, with horizontal and vertical radii
+/// `radii`.
+pub fn fill_ellipse<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    center: (i32, i32),
+    radii: impl Into<Size>,
+    color: impl Into<P>,
+) {
+    let color = color.into();
+    let mut target = target.into();
+    let radii: Size = radii.into();
+    let (cx, cy) = (center.0 as i64, center.1 as i64);
+    let (a, b) = (radii.width as f64, radii.height as i64);
+    let height = target.height() as i64;
+    for dy in (-b).max(-cy)..=b.min(height - 1 - cy) {
+        let t = if b == 0 { 0.0 } else { dy as f64 / b as f64 };
+        let dx = (a * (1.0 - t * t).sqrt()).round() as i64;
+        span(&mut target, cy + dy, cx - dx, cx + dx + 1, color);
+    }
+}
+
+/// Fills a convex polygon with corners at `points`.
+///
+/// Pixels are filled when their centers lie inside the polygon, so the polygon
+/// `[(0, 0), (4, 0), (4, 4), (0, 4)]` covers exactly the pixels from `(0, 0)` to `(3, 3)`. The
+/// result is unspecified if the polygon isn't convex.
+pub fn fill_convex_polygon<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    points: &[(i32, i32)],
+    color: impl Into<P>,
+) {
+    let color = color.into();
+    let points = points
+        .iter()
+        .map(|&(x, y)| (x as f32, y as f32))
+        .collect::<Vec<_>>();
+    fill_convex_polygon_f32(&mut target.into(), &points, color);
+}
+
+/// Copies a sprite into the target, with its top-left corner at `pos`.
+///
+/// `sprite` holds the sprite's pixels row by row, with each row `sprite_width` pixels long. When
+/// `color_key` is set, pixels matching it are treated as transparent and left untouched. Opaque
+/// pixels are copied in runs rather than one at a time, which is much faster than branching on
+/// every pixel.
+///
+/// # Panics
+/// Panics if the length of `sprite` isn't a multiple of `sprite_width`.
+pub fn blit_sprite<'a, P: PixelBufferFormat + PartialEq>(
+    target: impl Into<RegionMut<'a, P>>,
+    sprite: &[P],
+    sprite_width: u32,
+    pos: (i32, i32),
+    color_key: Option<P>,
+) {
+    let mut target = target.into();
+    if sprite_width == 0 {
+        return;
+    }
+    let sprite_width = sprite_width as usize;
+    assert_eq!(
+        0,
+        sprite.len() % sprite_width,
+        "sprite length not multiple of {}",
+        sprite_width
+    );
+    // The part of each sprite row that lands inside the target.
+    let first_column = (-(pos.0 as i64)).clamp(0, sprite_width as i64) as usize;
+    let last_column = (target.width() as i64 - pos.0 as i64)
+        .clamp(first_column as i64, sprite_width as i64) as usize;
+    if first_column == last_column {
+        return;
+    }
+    let x = (pos.0 as i64 + first_column as i64) as usize;
+
+    for (sprite_y, sprite_row) in sprite.chunks(sprite_width).enumerate() {
+        let y = pos.1 as i64 + sprite_y as i64;
+        if y < 0 {
+            continue;
+        }
+        let row = match target.row_mut(y as u32) {
+            Some(row) => row,
+            None => break,
+        };
+        let src = &sprite_row[first_column..last_column];
+        let dst = &mut row[x..x + src.len()];
+        match color_key {
+            None => dst.copy_from_slice(src),
+            Some(key) => {
+                let mut start = 0;
+                while start < src.len() {
+                    // Skip transparent pixels, then copy the following opaque run in one go.
+                    match src[start..].iter().position(|pixel| *pixel != key) {
+                        Some(skip) => start += skip,
+                        None => break,
+                    }
+                    let run = src[start..]
+                        .iter()
+                        .position(|pixel| *pixel == key)
+                        .unwrap_or(src.len() - start);
+                    dst[start..start + run].copy_from_slice(&src[start..start + run]);
+                    start += run;
+                }
+            }
+        }
+    }
+}
+
+/// Composites `color` through an 8-bit coverage mask, such as a glyph produced by a font
+/// rasterizer, with the mask's top-left corner at `pos`.
+///
+/// `mask` holds `size` coverage values, row by row, with `mask_stride` values between the start
+/// of one row and the next, so a glyph can be drawn straight out of an atlas by slicing
+/// it from the glyph's top-left corner. Each value scales `color`'s alpha, and the result is
+/// blended over the target the same way as in [`fill_rect_blend`].
+///
+/// # Panics
+/// Panics if `mask_stride` is less than the mask's width, or if `mask` is too short to hold as
+/// many rows as its height.
+pub fn blit_alpha_mask<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    mask: &[u8],
+    mask_stride: usize,
+    size: impl Into<Size>,
+    pos: (i32, i32),
+    color: impl Into<Color>,
+) {
+    let mut target = target.into();
+    let (mode, intent) = (target.alpha_mode(), target.rendering_intent());
+    let color: Color = color.into();
+    let size: Size = size.into();
+    let (width, height) = (size.width as usize, size.height as usize);
+    if width == 0 || height == 0 {
+        return;
+    }
+    assert!(mask_stride >= width, "mask stride less than its width");
+    assert!(
+        mask.len() >= (height - 1) * mask_stride + width,
+        "mask too short for its size"
+    );
+    let opaque = color.to_pixel::<P>();
+    let first_column = (-(pos.0 as i64)).clamp(0, width as i64) as usize;
+    let last_column =
+        (target.width() as i64 - pos.0 as i64).clamp(first_column as i64, width as i64) as usize;
+    if first_column == last_column {
+        // The mask lies entirely to the left or right of the target.
+        return;
+    }
+    for mask_y in 0..height {
+        let y = pos.1 as i64 + mask_y as i64;
+        if y < 0 {
+            continue;
+        }
+        let row = match target.row_mut(y as u32) {
+            Some(row) => row,
+            None => break,
+        };
+        let start = mask_y * mask_stride;
+        let coverage = &mask[start + first_column..start + last_column];
+        let x = (pos.0 as i64 + first_column as i64) as usize;
+        for (pixel, &coverage) in row[x..x + coverage.len()].iter_mut().zip(coverage) {
+            let alpha = (color.a as u32 * coverage as u32 + 127) / 255;
+            *pixel = match alpha {
+                0 => continue,
+                255 => opaque,
+                _ => blend_src_over(
+                    *pixel,
+                    [color.r, color.g, color.b, alpha as u8],
+                    mode,
+                    intent,
+                ),
+            };
+        }
+    }
+}
+
+/// Copies a rotated and/or scaled part of a sprite into the target.
+///
+/// `sprite` holds the sprite's pixels row by row, with each row `sprite_width` pixels long. The
+/// part of the sprite with its top-left corner at `src_pos` and dimensions `src_size` is
+/// transformed by `transform`, which maps that part's own coordinates (with `(0, 0)` at its
+/// top-left corner) onto the target's coordinates. Pixels are sampled with `filter`.
+///
+/// Every covered target pixel is mapped back into the sprite, one row at a time, so the result
+/// has no holes regardless of the transformation.
+///
+/// # Panics
+/// Panics if the length of `sprite` isn't a multiple of `sprite_width`, or if the source
+/// rectangle doesn't fit inside the sprite.
+pub fn blit_transformed<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    sprite: &[P],
+    sprite_width: u32,
+    src_pos: impl Into<Point>,
+    src_size: impl Into<Size>,
+    transform: Affine2,
+    filter: Filter,
+) {
+    let mut target = target.into();
+    let (src_pos, src_size): (Point, Size) = (src_pos.into(), src_size.into());
+    if sprite_width == 0 || src_size.is_empty() {
+        return;
+    }
+    assert_eq!(
+        0,
+        sprite.len() % sprite_width as usize,
+        "sprite length not multiple of {}",
+        sprite_width
+    );
+    let sprite_height = (sprite.len() / sprite_width as usize) as u64;
+    assert!(
+        src_pos.x as u64 + src_size.width as u64 <= sprite_width as u64
+            && src_pos.y as u64 + src_size.height as u64 <= sprite_height,
+        "source rectangle doesn't fit inside the sprite"
+    );
+    let inverse = match transform.inverse() {
+        Some(inverse) => inverse,
+        None => return,
+    };
+    let source = Source {
+        pixels: sprite,
+        offset: src_pos.y as usize * sprite_width as usize + src_pos.x as usize,
+        stride: sprite_width as usize,
+        width: src_size.width,
+        height: src_size.height,
+    };
+
+    // Only visit the target pixels inside the transformed source rectangle's bounding box.
+    let (w, h) = (src_size.width as f32, src_size.height as f32);
+    let corners = [(0.0, 0.0), (w, 0.0), (0.0, h), (w, h)];
+    let corners = corners.iter().map(|&corner| transform.apply(corner));
+    let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+    let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for (x, y) in corners {
+        min_x = min_x.min(x);
+        min_y = min_y.min(y);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+    let first_column = (min_x.floor().max(0.0) as u32).min(target.width());
+    let last_column = (max_x.ceil().max(0.0) as u32).min(target.width());
+    let first_row = (min_y.floor().max(0.0) as u32).min(target.height());
+    let last_row = (max_y.ceil().max(0.0) as u32).min(target.height());
+
+    for y in first_row..last_row {
+        let row = target.row_mut(y).unwrap();
+        let (mut src_x, mut src_y) = inverse.apply((first_column as f32 + 0.5, y as f32 + 0.5));
+        for pixel in &mut row[first_column as usize..last_column as usize] {
+            if let Some(sample) = source.sample((src_x, src_y), filter) {
+                *pixel = sample;
+            }
+            src_x += inverse.xx;
+            src_y += inverse.yx;
+        }
+    }
+}
+
+fn fill_convex_polygon_f32<P: PixelBufferFormat>(
+    target: &mut RegionMut<'_, P>,
+    points: &[(f32, f32)],
+    color: P,
+) {
+    if points.len() < 3 {
+        return;
+    }
+    let min_y = points.iter().map(|p| p.1).fold(f32::INFINITY, f32::min);
+    let max_y = points.iter().map(|p| p.1).fold(f32::NEG_INFINITY, f32::max);
+    // Only visit rows that are both inside the polygon and inside the target.
+    let first_row = ((min_y - 0.5).ceil() as i64).max(0);
+    let last_row = ((max_y - 0.5).ceil() as i64).min(target.height() as i64);
+    for y in first_row..last_row {
+        let center_y = y as f32 + 0.5;
+        let mut left = f32::INFINITY;
+        let mut right = f32::NEG_INFINITY;
+        for (i, &(x0, y0)) in points.iter().enumerate() {
+            let (x1, y1) = points[(i + 1) % points.len()];
+            if (y0 <= center_y && center_y < y1) || (y1 <= center_y && center_y < y0) {
+                let x = x0 + (center_y - y0) / (y1 - y0) * (x1 - x0);
+                left = left.min(x);
+                right = right.max(x);
+            }
+        }
+        if left < right {
+            let x0 = (left - 0.5).ceil() as i64;
+            let x1 = (right - 0.5).ceil() as i64;
+            span(target, y, x0, x1, color);
+        }
+    }
+}
+
+/// The range of steps `n` for which `start + step * n` lies inside `0..len`, if there are any.
+/// Every step is inside when `step` is `0` and `start` is.
+fn inside(start: i128, step: i128, len: i128) -> Option<(i128, i128)> {
+    match step {
+        0 if (0..len).contains(&start) => Some((i128::MIN / 4, i128::MAX / 4)),
+        0 => None,
+        1 => Some((-start, len - 1 - start)),
+        _ => Some((start - len + 1, start)),
+    }
+}
+
+/// The offset along the other axis of the pixel the midpoint ellipse algorithm sets `step` pixels
+/// along an axis with radius `major`, where the other axis has radius `minor`.
+///
+/// That's the smallest offset `y` for which the midpoint `(step, y + 1/2)` isn't inside the
+/// ellipse. Both radii must be at least 1, and `step` at most `major`.
+fn arc_offset(major: i64, minor: i64, step: i64) -> i64 {
+    let (a2, b2) = ((major as u128).pow(2), (minor as u128).pow(2));
+    // `b²x² + a²(y + 1/2)² >= a²b²`, multiplied out so every term is an integer that fits.
+    let outside = |y: i64| {
+        let (x, y) = (step as u128, y as u128);
+        (b2 * x * x).saturating_add(a2 * y * (y + 1)) >= a2 * b2 - a2 / 4
+    };
+    let estimate = minor as f64 * (((major - step) as f64) * ((major + step) as f64)).sqrt()
+        / major as f64
+        - 0.5;
+    let mut y = (estimate.round() as i64).clamp(0, minor);
+    while y > 0 && outside(y - 1) {
+        y -= 1;
+    }
+    while !outside(y) {
+        y += 1;
+    }
+    y
+}
+
+/// The last step the midpoint ellipse algorithm takes along an axis with radius `major`, before
+/// the curve becomes too steep and it switches to walking along the other one.
+fn arc_end(major: i64, minor: i64) -> i64 {
+    let shallow = |step: i64| {
+        (minor as u128).pow(2) * step as u128
+            <= (major as u128).pow(2) * arc_offset(major, minor, step) as u128
+    };
+    // The curve is shallow at the first step, and steep at the last.
+    let (mut lo, mut hi) = (0, major);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        if shallow(mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+fn set_pixel<P: PixelBufferFormat>(target: &mut RegionMut<'_, P>, pos: (i32, i32), color: P) {
+    if pos.0 >= 0 && pos.1 >= 0 {
+        if let Some(pixel) = target
+            .row_mut(pos.1 as u32)
+            .and_then(|row| row.get_mut(pos.0 as usize))
+        {
+            *pixel = color;
+        }
+    }
+}
+
+/// Fills the pixels from `x0` up to, but not including, `x1` on row `y`, clipped to the target.
+fn span<P: PixelBufferFormat>(target: &mut RegionMut<'_, P>, y: i64, x0: i64, x1: i64, color: P) {
+    if y < 0 || y >= target.height() as i64 {
+        return;
+    }
+    let width = target.width() as i64;
+    let (x0, x1) = (x0.clamp(0, width) as usize, x1.clamp(0, width) as usize);
+    if x0 < x1 {
+        fill_row(&mut target.row_mut(y as u32).unwrap()[x0..x1], color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PixelBufferTyped, RenderingIntent, BGR, BGRA};
+
+    const ON: BGRA = BGRA::from_rgb(255, 255, 255);
+    const ON_RED: BGRA = BGRA::from_rgb(255, 0, 0);
+
+    /// Bresenham's line algorithm, walking every pixel of the line.
+    fn reference_line(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+        let (mut x, mut y) = from;
+        let (dx, dy) = ((to.0 - x).abs(), -(to.1 - y).abs());
+        let (step_x, step_y) = ((to.0 - x).signum(), (to.1 - y).signum());
+        let mut error = dx + dy;
+        let mut pixels = vec![];
+        loop {
+            pixels.push((x, y));
+            if (x, y) == to {
+                return pixels;
+            }
+            let error2 = 2 * error;
+            if error2 >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if error2 <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// The midpoint ellipse algorithm, walking every pixel of the outline.
+    fn reference_ellipse(center: (i32, i32), (a, b): (i64, i64)) -> Vec<(i32, i32)> {
+        let mut pixels = vec![];
+        let mut plot = |x: i64, y: i64| {
+            for &(px, py) in &[(x, y), (-x, y), (x, -y), (-x, -y)] {
+                pixels.push((center.0 + px as i32, center.1 + py as i32));
+            }
+        };
+        let (a2, b2) = (a * a, b * b);
+        let (mut x, mut y, mut d) = (0, b, 4 * b2 - 4 * a2 * b + a2);
+        while b2 * x <= a2 * y {
+            plot(x, y);
+            if d >= 0 {
+                y -= 1;
+                d -= 8 * a2 * y;
+            }
+            x += 1;
+            d += 4 * b2 * (2 * x + 1);
+        }
+        let (mut x, mut y, mut d) = (a, 0, 4 * a2 - 4 * b2 * a + b2);
+        while a2 * y <= b2 * x {
+            plot(x, y);
+            if d >= 0 {
+                x -= 1;
+                d -= 8 * b2 * x;
+            }
+            y += 1;
+            d += 4 * a2 * (2 * y + 1);
+        }
+        pixels
+    }
+
+    /// The pixels of a `size` buffer set by `draw`.
+    fn drawn(size: u32, draw: impl FnOnce(&mut PixelBufferTyped<BGRA>)) -> Vec<(i32, i32)> {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(size, size).unwrap();
+        draw(&mut pb);
+        let mut pixels = vec![];
+        for (y, row) in pb.rows().enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                if pixel == ON {
+                    pixels.push((x as i32, y as i32));
+                }
+            }
+        }
+        pixels
+    }
+
+    /// The pixels of `pixels` that lie inside a `size` buffer, in the order `drawn` returns them.
+    fn clipped(size: u32, mut pixels: Vec<(i32, i32)>) -> Vec<(i32, i32)> {
+        pixels.retain(|&(x, y)| (0..size as i32).contains(&x) && (0..size as i32).contains(&y));
+        pixels.sort_by_key(|&(x, y)| (y, x));
+        pixels.dedup();
+        pixels
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that clipping lines to the target doesn't change
+    /// which pixels they set, including for lines that start or end outside of it.
+    fn line_matches_bresenham() {
+        let coords = -3..12;
+        for (x0, y0) in coords
+            .clone()
+            .flat_map(|x| coords.clone().map(move |y| (x, y)))
+        {
+            for &(x1, y1) in &[(9, -2), (-3, 4), (4, 11), (0, 0), (11, 8), (x0, 3), (5, y0)] {
+                let expected = clipped(8, reference_line((x0, y0), (x1, y1)));
+                let actual = drawn(8, |pb| line(pb, (x0, y0), (x1, y1), ON));
+                assert_eq!(actual, expected, "from {:?} to {:?}", (x0, y0), (x1, y1));
+            }
+        }
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that lines reaching far outside the target only
+    /// walk the part inside it, instead of hanging.
+    fn line_clips_huge_coordinates() {
+        let actual = drawn(8, |pb| line(pb, (i32::MIN, 3), (i32::MAX, 3), ON));
+        assert_eq!(actual, (0..8).map(|x| (x, 3)).collect::<Vec<_>>());
+        let actual = drawn(8, |pb| {
+            line(pb, (i32::MIN, i32::MIN), (i32::MAX, i32::MAX), ON)
+        });
+        assert_eq!(actual, (0..8).map(|i| (i, i)).collect::<Vec<_>>());
+        assert!(drawn(8, |pb| line(pb, (i32::MIN, 9), (i32::MAX, i32::MIN), ON)).len() <= 8);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that only walking the visible part of an ellipse's
+    /// outline sets the same pixels as walking all of it.
+    fn ellipse_matches_midpoint() {
+        for a in 1..20 {
+            for b in 1..20 {
+                for &center in &[(7, 7), (-5, 3), (20, 12), (3, -9)] {
+                    let expected = clipped(16, reference_ellipse(center, (a, b)));
+                    let actual = drawn(16, |pb| ellipse(pb, center, (a as u32, b as u32), ON));
+                    assert_eq!(actual, expected, "radii {:?} at {:?}", (a, b), center);
+                }
+            }
+        }
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that ellipses with huge or zero radii return
+    /// promptly, and that flat ones are drawn as lines.
+    fn ellipse_clips_huge_radii() {
+        let _ = drawn(8, |pb| ellipse(pb, (3, 3), (u32::MAX, u32::MAX), ON));
+        let _ = drawn(8, |pb| ellipse(pb, (i32::MIN, i32::MAX), (u32::MAX, 1), ON));
+        let actual = drawn(8, |pb| fill_ellipse(pb, (3, 3), (u32::MAX, u32::MAX), ON));
+        assert_eq!(actual.len(), 64);
+        assert_eq!(drawn(8, |pb| ellipse(pb, (3, 3), (0, 0), ON)), [(3, 3)]);
+        assert_eq!(
+            drawn(8, |pb| ellipse(pb, (3, 3), (0, 1), ON)),
+            [(3, 2), (3, 3), (3, 4)]
+        );
+        assert_eq!(
+            drawn(8, |pb| ellipse(pb, (3, 3), (u32::MAX, 0), ON)),
+            (0..8).map(|x| (x, 3)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that rectangles reaching far outside the target only
+    /// walk the rows inside it, instead of hanging.
+    fn rect_clips_huge_sizes() {
+        let actual = drawn(8, |pb| fill_rect(pb, (2, -5), (3, u32::MAX), ON));
+        let expected: Vec<_> = (0..8).flat_map(|y| (2..5).map(move |x| (x, y))).collect();
+        assert_eq!(actual, expected);
+        let actual = drawn(8, |pb| {
+            fill_rect(pb, (i32::MIN, i32::MIN), (u32::MAX, u32::MAX), ON)
+        });
+        assert_eq!(actual.len(), 64);
+        assert!(drawn(8, |pb| fill_rect(pb, (0, 9), (8, u32::MAX), ON)).is_empty());
+
+        let actual = drawn(8, |pb| rect(pb, (1, 1), (3, u32::MAX), ON));
+        let mut expected = vec![(1, 1), (2, 1), (3, 1)];
+        expected.extend((2..8).flat_map(|y| vec![(1, y), (3, y)]));
+        assert_eq!(actual, expected);
+        let actual = drawn(8, |pb| {
+            rect(pb, (i32::MIN, i32::MIN), (u32::MAX, u32::MAX), ON)
+        });
+        assert!(actual.is_empty());
+    }
+
+    /// A `width` by `height` sprite whose pixels record their own position.
+    fn numbered_sprite(width: u32, height: u32) -> Vec<BGRA> {
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| BGRA::from_rgb(x as u8 + 1, y as u8 + 1, 0)))
+            .collect()
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that sprites hanging over any edge of the target
+    /// only copy the part inside it, and that sprites entirely outside of it are skipped.
+    fn blit_sprite_clips_at_each_edge() {
+        const BLANK: BGRA = BGRA::new(0, 0, 0, 0);
+        let sprite = numbered_sprite(3, 2);
+        let at = |x: u32, y: u32| sprite[(y * 3 + x) as usize];
+        let blit = |pos: (i32, i32)| {
+            let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(4, 4).unwrap();
+            blit_sprite(&mut pb, &sprite, 3, pos, None);
+            pb.rows().map(<[BGRA]>::to_vec).collect::<Vec<_>>()
+        };
+
+        let left = blit((-1, 0));
+        assert_eq!(left[0], [at(1, 0), at(2, 0), BLANK, BLANK]);
+        assert_eq!(left[1], [at(1, 1), at(2, 1), BLANK, BLANK]);
+        let right = blit((2, 0));
+        assert_eq!(right[0], [BLANK, BLANK, at(0, 0), at(1, 0)]);
+        assert_eq!(right[1], [BLANK, BLANK, at(0, 1), at(1, 1)]);
+        let top = blit((0, -1));
+        assert_eq!(top[0], [at(0, 1), at(1, 1), at(2, 1), BLANK]);
+        assert!(top[1].iter().all(|&pixel| pixel == BLANK));
+        let bottom = blit((1, 3));
+        assert_eq!(bottom[3], [BLANK, at(0, 0), at(1, 0), at(2, 0)]);
+        assert!(bottom[..3].iter().flatten().all(|&pixel| pixel == BLANK));
+
+        for &pos in &[(-3, 0), (4, 0), (0, -2), (0, 4), (i32::MIN, i32::MAX)] {
+            assert!(blit(pos).iter().flatten().all(|&pixel| pixel == BLANK));
+        }
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that a sprite bigger than the target is clipped on
+    /// every side at once, and that the color key still applies to the visible part.
+    fn blit_sprite_larger_than_target() {
+        let sprite = numbered_sprite(6, 6);
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(4, 4).unwrap();
+        blit_sprite(&mut pb, &sprite, 6, (-1, -1), None);
+        for (y, row) in pb.rows().enumerate() {
+            assert_eq!(row, &sprite[(y + 1) * 6 + 1..(y + 1) * 6 + 5]);
+        }
+
+        let key = sprite[2 * 6 + 2];
+        pb.clear(ON);
+        blit_sprite(&mut pb, &sprite, 6, (-1, -1), Some(key));
+        assert_eq!(pb.row(1).unwrap()[1], ON);
+        assert_eq!(pb.row(1).unwrap()[0], sprite[2 * 6 + 1]);
+        assert_eq!(pb.row(1).unwrap()[2], sprite[2 * 6 + 3]);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that the identity, translations and scales map
+    /// sprite pixels onto the expected target pixels, and that singular transformations draw
+    /// nothing.
+    fn blit_transformed_maps_pixels() {
+        const BLANK: BGRA = BGRA::new(0, 0, 0, 0);
+        let sprite = numbered_sprite(3, 2);
+        let at = |x: u32, y: u32| sprite[(y * 3 + x) as usize];
+        let blit = |transform: Affine2| {
+            let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(4, 4).unwrap();
+            blit_transformed(
+                &mut pb,
+                &sprite,
+                3,
+                (0, 0),
+                (3, 2),
+                transform,
+                Filter::Nearest,
+            );
+            pb.rows().map(<[BGRA]>::to_vec).collect::<Vec<_>>()
+        };
+
+        let identity = blit(Affine2::IDENTITY);
+        assert_eq!(identity[0], [at(0, 0), at(1, 0), at(2, 0), BLANK]);
+        assert_eq!(identity[1], [at(0, 1), at(1, 1), at(2, 1), BLANK]);
+        assert!(identity[2..].iter().flatten().all(|&pixel| pixel == BLANK));
+
+        let translated = blit(Affine2::translation(1.0, 2.0));
+        assert!(translated[..2]
+            .iter()
+            .flatten()
+            .all(|&pixel| pixel == BLANK));
+        assert_eq!(translated[2], [BLANK, at(0, 0), at(1, 0), at(2, 0)]);
+        assert_eq!(translated[3], [BLANK, at(0, 1), at(1, 1), at(2, 1)]);
+
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(4, 4).unwrap();
+        let scale = Affine2::scale(2.0, 2.0);
+        blit_transformed(&mut pb, &sprite, 3, (1, 0), (2, 2), scale, Filter::Nearest);
+        for (y, row) in pb.rows().enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                assert_eq!(pixel, at(1 + x as u32 / 2, y as u32 / 2), "at {:?}", (x, y));
+            }
+        }
+
+        for &singular in &[Affine2::scale(0.0, 1.0), Affine2::scale(f32::INFINITY, 1.0)] {
+            assert!(blit(singular).iter().flatten().all(|&pixel| pixel == BLANK));
+        }
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that transformed sprites hanging over the edges of
+    /// the target only draw the part inside it, and that ones entirely outside of it are skipped.
+    fn blit_transformed_clips_at_the_edges() {
+        const BLANK: BGRA = BGRA::new(0, 0, 0, 0);
+        let sprite = numbered_sprite(3, 2);
+        let at = |x: u32, y: u32| sprite[(y * 3 + x) as usize];
+        let blit = |x: f32, y: f32| {
+            let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(4, 4).unwrap();
+            let transform = Affine2::translation(x, y);
+            blit_transformed(
+                &mut pb,
+                &sprite,
+                3,
+                (0, 0),
+                (3, 2),
+                transform,
+                Filter::Nearest,
+            );
+            pb.rows().map(<[BGRA]>::to_vec).collect::<Vec<_>>()
+        };
+
+        let top_left = blit(-1.0, -1.0);
+        assert_eq!(top_left[0], [at(1, 1), at(2, 1), BLANK, BLANK]);
+        assert!(top_left[1..].iter().flatten().all(|&pixel| pixel == BLANK));
+        let bottom_right = blit(3.0, 3.0);
+        assert_eq!(bottom_right[3], [BLANK, BLANK, BLANK, at(0, 0)]);
+        assert!(bottom_right[..3]
+            .iter()
+            .flatten()
+            .all(|&pixel| pixel == BLANK));
+
+        for &(x, y) in &[
+            (-3.0, 0.0),
+            (4.0, 0.0),
+            (0.0, -2.0),
+            (0.0, 4.0),
+            (-1e30, 1e30),
+        ] {
+            assert!(blit(x, y).iter().flatten().all(|&pixel| pixel == BLANK));
+        }
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that coverage masks are composited at their
+    /// position, read with their stride, and clipped against the target.
+    fn blit_alpha_mask_composites_coverage() {
+        use crate::BGR;
+
+        let mut pb = PixelBufferTyped::<BGR>::new_offscreen(3, 2).unwrap();
+        // A 2x2 glyph in the top-right corner of a 3-wide atlas.
+        let atlas = [9, 255, 128, 9, 0, 9];
+        let red = Color::rgb(255, 0, 0);
+        blit_alpha_mask(&mut pb, &atlas[1..], 3, (2, 2), (2, 0), red);
+        let rgb = |pb: &PixelBufferTyped<BGR>, x: usize, y: u32| {
+            let [r, g, b, _] = pb.row(y).unwrap()[x].to_rgba();
+            [r, g, b]
+        };
+        assert_eq!(rgb(&pb, 2, 0), [255, 0, 0]);
+        assert_eq!(rgb(&pb, 2, 1), [0, 0, 0]);
+        assert_eq!(rgb(&pb, 1, 0), [0, 0, 0]);
+
+        blit_alpha_mask(&mut pb, &atlas[1..], 3, (2, 2), (-1, 0), red);
+        assert_eq!(rgb(&pb, 0, 0), [128, 0, 0]);
+        assert_eq!(rgb(&pb, 0, 1), [9, 0, 0]);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that masks lying entirely to the left or right of
+    /// the target, or above or below it, are skipped instead of panicking.
+    fn blit_alpha_mask_skips_masks_outside_target() {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(4, 3).unwrap();
+        pb.clear(BGRA::from_rgb(0, 0, 0));
+        let mask = [255; 6];
+        for &pos in &[
+            (-3, 0),
+            (-100, 1),
+            (i32::MIN, 0),
+            (4, 0),
+            (100, 1),
+            (i32::MAX, 0),
+            (0, -2),
+            (0, 3),
+            (i32::MIN, i32::MIN),
+        ] {
+            blit_alpha_mask(&mut pb, &mask, 3, (3, 2), pos, Color::rgb(255, 0, 0));
+        }
+        assert!(pb
+            .rows()
+            .flatten()
+            .all(|&pixel| pixel == BGRA::from_rgb(0, 0, 0)));
+
+        // Masks sticking out by a column still draw that column.
+        blit_alpha_mask(&mut pb, &mask, 3, (3, 2), (-2, 0), Color::rgb(255, 0, 0));
+        blit_alpha_mask(&mut pb, &mask, 3, (3, 2), (3, 1), Color::rgb(255, 0, 0));
+        assert_eq!(pb.row(0).unwrap()[..2], [ON_RED, BGRA::from_rgb(0, 0, 0)]);
+        assert_eq!(pb.row(2).unwrap()[2..], [BGRA::from_rgb(0, 0, 0), ON_RED]);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that blending reads and writes the target's pixels
+    /// according to the buffer's alpha mode.
+    fn alpha_mode_blending() {
+        use crate::AlphaMode;
+
+        let half_white = Color::rgba(255, 255, 255, 128);
+        let blend = |mode: AlphaMode| {
+            let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(1, 1).unwrap();
+            pb.clear(BGRA::from_rgba(200, 0, 0, 128));
+            pb.set_alpha_mode(mode);
+            fill_rect_blend(&mut pb, (0, 0), (1, 1), half_white);
+            pb.row(0).unwrap()[0].to_rgba()
+        };
+        let premultiplied = blend(AlphaMode::Premultiplied);
+        assert_eq!(premultiplied, [228, 128, 128, 192]);
+        assert_ne!(blend(AlphaMode::Straight), premultiplied);
+        assert_eq!(blend(AlphaMode::Ignored)[3], 255);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that translucent colors are blended in the color
+    /// space chosen by the buffer's rendering intent.
+    fn rendering_intent_blending() {
+        let mut pb = PixelBufferTyped::<BGR>::new_offscreen(2, 1).unwrap();
+        let half_white = Color::rgba(255, 255, 255, 128);
+        fill_rect_blend(&mut pb, (0, 0), (1, 1), half_white);
+        pb.set_rendering_intent(RenderingIntent::Linear);
+        fill_rect_blend(&mut pb, (1, 0), (1, 1), half_white);
+        let row = pb.row(0).unwrap();
+        assert_eq!(row[0].to_rgba(), [128, 128, 128, 255]);
+        assert_eq!(row[1].to_rgba(), [188, 188, 188, 255]);
+    }
+}
@@ -0,0 +1,376 @@
+//! Image filters that operate on a pixel buffer in place.
+//!
+//! Like the [`draw`](crate::draw) functions, every filter takes anything that can be turned into a
+//! [`RegionMut`], so a filter can be applied to a whole
+//! [`PixelBufferTyped`](crate::PixelBufferTyped), or to just one part of it by passing the result
+//! of [`region_mut`](crate::PixelBufferTyped::region_mut). Pixels outside the region are never
+//! read; the region's edge pixels are extended outwards instead.
+
+use crate::{ChannelDepth, ConversionOptions, DitherMode, PixelBufferFormat, RegionMut};
+
+#[cfg(feature = "rayon")]
+use crate::threads;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Kernel weights are fixed-point fractions out of `1 << KERNEL_SHIFT`.
+const KERNEL_SHIFT: u32 = 16;
+
+/// Blurs the target by averaging each pixel with its neighbors up to `radius` pixels away.
+///
+/// Radii larger than the target are clamped to its size.
+pub fn box_blur<'a, P: PixelBufferFormat>(target: impl Into<RegionMut<'a, P>>, radius: u32) {
+    let mut target = target.into();
+    let kernel = box_kernel(radius, max_radius(&target));
+    convolve(&mut target, &kernel);
+}
+
+/// Blurs the target with a Gaussian kernel with a standard deviation of `sigma` pixels.
+///
+/// Kernels larger than the target are cut off at its size.
+pub fn gaussian_blur<'a, P: PixelBufferFormat>(target: impl Into<RegionMut<'a, P>>, sigma: f32) {
+    let mut target = target.into();
+    let kernel = gaussian_kernel(sigma, max_radius(&target));
+    convolve(&mut target, &kernel);
+}
+
+/// Like [`box_blur`], but distributes rows across rayon's thread pool, or the one set with
+/// [`set_thread_pool`](crate::set_thread_pool).
+#[cfg(feature = "rayon")]
+pub fn par_box_blur<'a, P: PixelBufferFormat + Send + Sync>(
+    target: impl Into<RegionMut<'a, P>>,
+    radius: u32,
+) {
+    let mut target = target.into();
+    let kernel = box_kernel(radius, max_radius(&target));
+    par_convolve(&mut target, &kernel);
+}
+
+/// Like [`gaussian_blur`], but distributes rows across rayon's thread pool, or the one set with
+/// [`set_thread_pool`](crate::set_thread_pool).
+#[cfg(feature = "rayon")]
+pub fn par_gaussian_blur<'a, P: PixelBufferFormat + Send + Sync>(
+    target: impl Into<RegionMut<'a, P>>,
+    sigma: f32,
+) {
+    let mut target = target.into();
+    let kernel = gaussian_kernel(sigma, max_radius(&target));
+    par_convolve(&mut target, &kernel);
+}
+
+/// Reduces the color channels of every pixel in the target to `depth` bits, dithering according
+/// to `options`.
+///
+/// The pixels keep their format; each channel is just rounded to the closest value representable
+/// with fewer bits. Applying this before content is converted to a low bit depth format (such as
+/// 16-bit 565 color) avoids the banding that plain truncation causes in smooth gradients. The
+/// alpha channel is left untouched, and channel depths are clamped to between 1 and 8 bits.
+pub fn quantize<'a, P: PixelBufferFormat>(
+    target: impl Into<RegionMut<'a, P>>,
+    depth: ChannelDepth,
+    options: ConversionOptions,
+) {
+    let mut target = target.into();
+    let levels = [depth.r, depth.g, depth.b].map(|bits| (1u32 << bits.clamp(1, 8)) - 1);
+    // Rounds `value`, scaled by 16, to the closest representable value of `channel`.
+    let round = |value: i32, channel: usize| -> u8 {
+        let levels = levels[channel] as i32;
+        let value = value.clamp(0, 255 * 16);
+        let level = (value * levels + 255 * 8) / (255 * 16);
+        ((level * 255 + levels / 2) / levels) as u8
+    };
+
+    match options.dither {
+        DitherMode::None | DitherMode::Ordered => {
+            const BAYER: [[i32; 4]; 4] =
+                [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+            let ordered = options.dither == DitherMode::Ordered;
+            for (y, row) in target.rows_mut().enumerate() {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let mut rgba = pixel.to_rgba();
+                    for (channel, value) in rgba[..3].iter_mut().enumerate() {
+                        let mut scaled = *value as i32 * 16;
+                        if ordered {
+                            // Offset by up to half a quantization step in either direction.
+                            let step = 255 / levels[channel] as i32;
+                            scaled += (2 * BAYER[y % 4][x % 4] - 15) * step / 2;
+                        }
+                        *value = round(scaled, channel);
+                    }
+                    let [r, g, b, a] = rgba;
+                    *pixel = P::from_rgba(r, g, b, a);
+                }
+            }
+        }
+        DitherMode::FloydSteinberg => {
+            // Accumulated error for the current and next rows, in sixteenths. Each row has a pixel
+            // of padding on either side so the kernel never needs bounds checks.
+            let width = target.width() as usize;
+            let mut error = vec![[0i32; 3]; width + 2];
+            let mut next_error = vec![[0i32; 3]; width + 2];
+            for row in target.rows_mut() {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let mut rgba = pixel.to_rgba();
+                    for (channel, value) in rgba[..3].iter_mut().enumerate() {
+                        let wanted = *value as i32 * 16 + error[x + 1][channel];
+                        *value = round(wanted, channel);
+                        let diff = wanted - *value as i32 * 16;
+                        error[x + 2][channel] += diff * 7 / 16;
+                        next_error[x][channel] += diff * 3 / 16;
+                        next_error[x + 1][channel] += diff * 5 / 16;
+                        next_error[x + 2][channel] += diff / 16;
+                    }
+                    let [r, g, b, a] = rgba;
+                    *pixel = P::from_rgba(r, g, b, a);
+                }
+                std::mem::swap(&mut error, &mut next_error);
+                next_error.iter_mut().for_each(|error| *error = [0; 3]);
+            }
+        }
+    }
+}
+
+/// The largest kernel radius that still affects the target; every pixel further away is a copy
+/// of one of its edges.
+fn max_radius<P: PixelBufferFormat>(target: &RegionMut<'_, P>) -> u32 {
+    target.width().max(target.height())
+}
+
+fn box_kernel(radius: u32, max_radius: u32) -> Vec<u32> {
+    let len = 2 * radius.min(max_radius) as usize + 1;
+    normalize(vec![1.0; len])
+}
+
+fn gaussian_kernel(sigma: f32, max_radius: u32) -> Vec<u32> {
+    if sigma.is_nan() || sigma <= 0.0 {
+        return vec![1 << KERNEL_SHIFT];
+    }
+    // Three standard deviations cover over 99.7% of the curve.
+    let radius = ((sigma * 3.0).ceil() as i64).min(max_radius as i64);
+    normalize(
+        (-radius..=radius)
+            .map(|x| (-(x * x) as f32 / (2.0 * sigma * sigma)).exp())
+            .collect(),
+    )
+}
+
+/// Converts a kernel into fixed-point weights that sum to exactly `1 << KERNEL_SHIFT`.
+fn normalize(kernel: Vec<f32>) -> Vec<u32> {
+    let sum: f32 = kernel.iter().sum();
+    let mut kernel: Vec<u32> = kernel
+        .iter()
+        .map(|weight| (weight / sum * (1 << KERNEL_SHIFT) as f32) as u32)
+        .collect();
+    // Rounding may leave the weights a little short of (or, for huge kernels, past) the total.
+    let total: u32 = kernel.iter().sum();
+    let center = kernel.len() / 2;
+    kernel[center] = (kernel[center] + (1 << KERNEL_SHIFT)).saturating_sub(total);
+    kernel
+}
+
+/// Convolves the target with `kernel` horizontally, then vertically.
+fn convolve<P: PixelBufferFormat>(target: &mut RegionMut<'_, P>, kernel: &[u32]) {
+    let mut scratch = Vec::new();
+    for &pass in &[Pass::Horizontal, Pass::Vertical] {
+        if !fill_scratch(target, kernel, &mut scratch) {
+            return;
+        }
+        let (width, height) = (target.width() as usize, target.height() as usize);
+        for (y, row) in target.rows_mut().enumerate() {
+            convolve_row(row, y, pass, kernel, &scratch, (width, height));
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+fn par_convolve<P: PixelBufferFormat + Send + Sync>(target: &mut RegionMut<'_, P>, kernel: &[u32]) {
+    let mut scratch = Vec::new();
+    for &pass in &[Pass::Horizontal, Pass::Vertical] {
+        if !fill_scratch(target, kernel, &mut scratch) {
+            return;
+        }
+        let (width, height) = (target.width() as usize, target.height() as usize);
+        let scratch = &scratch;
+        let rows = target.rows_mut().collect::<Vec<_>>();
+        threads::install(|| {
+            rows.into_par_iter().enumerate().for_each(|(y, row)| {
+                convolve_row(row, y, pass, kernel, scratch, (width, height));
+            })
+        });
+    }
+}
+
+/// Copies the target's pixels into `scratch`, returning `false` if there's nothing to convolve.
+fn fill_scratch<P: PixelBufferFormat>(
+    target: &RegionMut<'_, P>,
+    kernel: &[u32],
+    scratch: &mut Vec<[u8; 4]>,
+) -> bool {
+    if target.width() == 0 || target.height() == 0 || kernel.len() == 1 {
+        return false;
+    }
+    scratch.clear();
+    scratch.extend(target.rows().flatten().map(|pixel| pixel.to_rgba()));
+    true
+}
+
+/// Computes row `y` of one convolution pass, reading the pass's input from `scratch`.
+fn convolve_row<P: PixelBufferFormat>(
+    row: &mut [P],
+    y: usize,
+    pass: Pass,
+    kernel: &[u32],
+    scratch: &[[u8; 4]],
+    (width, height): (usize, usize),
+) {
+    let radius = (kernel.len() / 2) as isize;
+    for (x, pixel) in row.iter_mut().enumerate() {
+        let mut sum = [0u32; 4];
+        for (offset, &weight) in (-radius..=radius).zip(kernel) {
+            let index = match pass {
+                Pass::Horizontal => y * width + clamp_offset(x, offset, width),
+                Pass::Vertical => clamp_offset(y, offset, height) * width + x,
+            };
+            for (sum, &channel) in sum.iter_mut().zip(&scratch[index]) {
+                *sum += channel as u32 * weight;
+            }
+        }
+        let [r, g, b, a] = sum.map(|sum| ((sum + (1 << (KERNEL_SHIFT - 1))) >> KERNEL_SHIFT) as u8);
+        *pixel = P::from_rgba(r, g, b, a);
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Pass {
+    Horizontal,
+    Vertical,
+}
+
+/// Offsets `index` by `offset`, clamping the result to `0..len`.
+fn clamp_offset(index: usize, offset: isize, len: usize) -> usize {
+    (index as isize + offset).clamp(0, len as isize - 1) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PixelBufferTyped, BGRA};
+
+    fn gray(value: u8) -> BGRA {
+        BGRA::from_rgb(value, value, value)
+    }
+
+    fn row_values(pb: &PixelBufferTyped<BGRA>, row: u32) -> Vec<u8> {
+        let row = pb.row(row).unwrap();
+        row.iter().map(|pixel| pixel.to_rgba()[0]).collect()
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that kernels are clamped to the target's size, and
+    /// that their weights always add up to exactly one.
+    fn kernels_are_normalized() {
+        for kernel in [
+            box_kernel(2, 10),
+            box_kernel(100, 3),
+            gaussian_kernel(1.5, 10),
+            gaussian_kernel(100.0, 4),
+        ] {
+            assert_eq!(kernel.iter().sum::<u32>(), 1 << KERNEL_SHIFT);
+            assert!(kernel.iter().eq(kernel.iter().rev()));
+        }
+        assert_eq!(box_kernel(2, 10).len(), 5);
+        assert_eq!(box_kernel(100, 3).len(), 7);
+        assert_eq!(gaussian_kernel(1.5, 10).len(), 11);
+        assert_eq!(gaussian_kernel(100.0, 4).len(), 9);
+        assert_eq!(gaussian_kernel(0.0, 10), [1 << KERNEL_SHIFT]);
+        assert_eq!(gaussian_kernel(f32::NAN, 10), [1 << KERNEL_SHIFT]);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that blurring spreads a pixel evenly over its
+    /// neighbors, extends the region's edges outwards, and leaves pixels outside the region
+    /// alone.
+    fn box_blur_spreads_pixels() {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(5, 1).unwrap();
+        pb.row_mut(0).unwrap()[2] = gray(255);
+        box_blur(&mut pb, 1);
+        assert_eq!(row_values(&pb, 0), [0, 85, 85, 85, 0]);
+
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(5, 1).unwrap();
+        pb.row_mut(0).unwrap()[1] = gray(255);
+        pb.row_mut(0).unwrap()[4] = gray(255);
+        box_blur(pb.region_mut((1, 0), (3, 1)).unwrap(), 1);
+        // The region's left edge is counted twice, and the pixel after the region isn't read.
+        assert_eq!(row_values(&pb, 0), [0, 170, 85, 0, 255]);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that blurring an even color, or with an empty
+    /// kernel, changes nothing.
+    fn blur_keeps_even_colors() {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(4, 3).unwrap();
+        pb.clear(gray(77));
+        box_blur(&mut pb, 3);
+        gaussian_blur(&mut pb, 2.0);
+        assert!(pb.rows().flatten().all(|&pixel| pixel == gray(77)));
+
+        pb.row_mut(1).unwrap()[2] = gray(200);
+        gaussian_blur(&mut pb, 0.0);
+        box_blur(&mut pb, 0);
+        assert_eq!(pb.row(1).unwrap()[2], gray(200));
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    /// The purpose of this test is to verify that the parallel blurs match the sequential ones.
+    fn par_blur_matches_blur() {
+        let mut a = PixelBufferTyped::<BGRA>::new_offscreen(9, 7).unwrap();
+        a.generate(|x, y| BGRA::from_rgba((x * 30) as u8, (y * 40) as u8, 90, 200));
+        let mut b = PixelBufferTyped::<BGRA>::new_offscreen(9, 7).unwrap();
+        b.generate(|x, y| BGRA::from_rgba((x * 30) as u8, (y * 40) as u8, 90, 200));
+        gaussian_blur(&mut a, 1.2);
+        par_gaussian_blur(&mut b, 1.2);
+        box_blur(&mut a, 2);
+        par_box_blur(&mut b, 2);
+        assert!(a.rows().flatten().eq(b.rows().flatten()));
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that quantizing rounds every color channel to the
+    /// closest representable value, leaves alpha alone, and dithers mid-tones into a pattern.
+    fn quantize_rounds_channels() {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(4, 4).unwrap();
+        pb.row_mut(0).unwrap()[..3].copy_from_slice(&[
+            BGRA::from_rgba(100, 200, 0, 7),
+            BGRA::from_rgba(255, 127, 128, 7),
+            BGRA::from_rgba(0, 255, 10, 7),
+        ]);
+        let one_bit = ChannelDepth { r: 1, g: 1, b: 1 };
+        quantize(
+            pb.region_mut((0, 0), (3, 1)).unwrap(),
+            one_bit,
+            Default::default(),
+        );
+        assert_eq!(
+            pb.row(0).unwrap()[..3],
+            [
+                BGRA::from_rgba(0, 255, 0, 7),
+                BGRA::from_rgba(255, 0, 255, 7),
+                BGRA::from_rgba(0, 255, 0, 7),
+            ]
+        );
+
+        pb.clear(gray(128));
+        let options = ConversionOptions {
+            dither: DitherMode::Ordered,
+        };
+        quantize(&mut pb, one_bit, options);
+        let on = pb
+            .rows()
+            .flatten()
+            .filter(|&&pixel| pixel == gray(255))
+            .count();
+        assert!(pb.rows().flatten().all(|&p| p == gray(0) || p == gray(255)));
+        assert_eq!(on, 8);
+    }
+}
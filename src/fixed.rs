@@ -0,0 +1,120 @@
+use crate::{PixelBufferFormat, PixelBufferTyped, RegionMut};
+use std::{convert::TryInto, mem};
+
+/// A heap-allocated pixel buffer whose size is fixed at compile time.
+///
+/// Pixels are stored as a boxed `[[P; W]; H]`, so every row has a statically-known length and
+/// indexing with in-range constants or loop counters needs no bounds checks. This suits
+/// fixed-resolution renderers like emulators, which draw into a `PixelBufferFixed` and copy it
+/// into a window's [`PixelBufferTyped`] with [`copy_to`](Self::copy_to) once per frame.
+///
+/// `W` and `H` are `usize`s, since array lengths can't be computed from other types' const
+/// parameters on stable Rust.
+pub struct PixelBufferFixed<const W: usize, const H: usize, P: PixelBufferFormat> {
+    pixels: Box<[[P; W]; H]>,
+}
+
+impl<const W: usize, const H: usize, P: PixelBufferFormat> PixelBufferFixed<W, H, P> {
+    /// The width, in pixels, of the buffer.
+    pub const WIDTH: u32 = W as u32;
+    /// The height, in pixels, of the buffer.
+    pub const HEIGHT: u32 = H as u32;
+
+    /// Creates a buffer filled with opaque black.
+    pub fn new() -> PixelBufferFixed<W, H, P> {
+        let black = P::from_rgba(0, 0, 0, 255);
+        // Building the rows on the heap keeps large buffers from overflowing the stack.
+        let pixels = vec![[black; W]; H].into_boxed_slice().try_into();
+        PixelBufferFixed {
+            pixels: pixels.unwrap_or_else(|_| unreachable!()),
+        }
+    }
+
+    /// The buffer's pixels, row by row.
+    pub fn pixels(&self) -> &[[P; W]; H] {
+        &self.pixels
+    }
+
+    /// The buffer's pixels, row by row, allowing modification.
+    pub fn pixels_mut(&mut self) -> &mut [[P; W]; H] {
+        &mut self.pixels
+    }
+
+    /// Gets a mutable view into the whole buffer, for use with the [`draw`](crate::draw) and
+    /// [`filters`](crate::filters) functions.
+    pub fn as_region_mut(&mut self) -> RegionMut<'_, P> {
+        let row_pitch = mem::size_of::<[P; W]>() as isize;
+        unsafe {
+            RegionMut::from_raw_parts(
+                self.pixels.as_mut_ptr() as *mut u8,
+                row_pitch,
+                Self::WIDTH,
+                Self::HEIGHT,
+            )
+        }
+    }
+
+    /// Copies the buffer into the top-left corner of `target`. Pixels that don't fit are cut off.
+    pub fn copy_to(&self, target: &mut PixelBufferTyped<P>) {
+        let width = W.min(target.width() as usize);
+        for (src, dst) in self.pixels.iter().zip(target.rows_mut()) {
+            dst[..width].copy_from_slice(&src[..width]);
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, P: PixelBufferFormat> Default for PixelBufferFixed<W, H, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, const W: usize, const H: usize, P: PixelBufferFormat>
+    From<&'a mut PixelBufferFixed<W, H, P>> for RegionMut<'a, P>
+{
+    fn from(buffer: &'a mut PixelBufferFixed<W, H, P>) -> RegionMut<'a, P> {
+        buffer.as_region_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{draw, BGRA};
+
+    const BLACK: BGRA = BGRA::from_rgb(0, 0, 0);
+    const ON: BGRA = BGRA::from_rgb(255, 255, 255);
+    /// The zeroed pixels of a new offscreen buffer.
+    const CLEAR: BGRA = BGRA::new(0, 0, 0, 0);
+
+    #[test]
+    /// The purpose of this test is to verify that drawing through the region writes the buffer's
+    /// pixels, and that copying it into larger and smaller buffers only fills their overlap.
+    fn region_draws_and_copies() {
+        let mut fixed = PixelBufferFixed::<4, 3, BGRA>::new();
+        let mut region = fixed.as_region_mut();
+        assert_eq!((region.width(), region.height()), (4, 3));
+        draw::fill_rect(region.reborrow(), (1, 1), (3, 5), ON);
+        draw::pixel(region, (0, 2), ON);
+        let expected = [
+            [BLACK, BLACK, BLACK, BLACK],
+            [BLACK, ON, ON, ON],
+            [ON, ON, ON, ON],
+        ];
+        assert_eq!(fixed.pixels(), &expected);
+
+        let mut larger = PixelBufferTyped::<BGRA>::new_offscreen(6, 5).unwrap();
+        fixed.copy_to(&mut larger);
+        for (y, row) in larger.rows().enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                let expected = expected.get(y).and_then(|row| row.get(x));
+                assert_eq!(pixel, *expected.unwrap_or(&CLEAR), "at {:?}", (x, y));
+            }
+        }
+
+        let mut smaller = PixelBufferTyped::<BGRA>::new_offscreen(2, 2).unwrap();
+        fixed.copy_to(&mut smaller);
+        let rows: Vec<_> = smaller.rows().map(<[BGRA]>::to_vec).collect();
+        assert_eq!(rows, [[BLACK, BLACK], [BLACK, ON]]);
+    }
+}
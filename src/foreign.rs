@@ -0,0 +1,87 @@
+//! Support for windows created by libraries other than winit.
+//!
+//! Pixel buffers only need a window's raw handle, so any windowing library works as long as it
+//! implements [`HasWindowHandle`]. Libraries that don't, or that only implement older versions of
+//! `raw-window-handle`, can have their windows wrapped in a [`ForeignWindow`] instead.
+//!
+//! With the `glfw` crate:
+//!
+//! ```ignore
+//! let window = unsafe { ForeignWindow::from_hwnd(glfw_window.get_win32_window()) }.unwrap();
+//! let buffer = PixelBufferTyped::<NativeFormat>::new(width, height, &window)?;
+//! ```
+//!
+//! With the `sdl2` crate, which implements `raw-window-handle` 0.5:
+//!
+//! ```ignore
+//! let handle = match sdl_window.raw_window_handle() {
+//!     rwh_05::RawWindowHandle::Win32(handle) => handle.hwnd,
+//!     _ => unreachable!(),
+//! };
+//! let window = unsafe { ForeignWindow::from_hwnd(handle) }.unwrap();
+//! ```
+
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, RawWindowHandle, WindowHandle,
+};
+
+#[cfg(target_os = "windows")]
+use raw_window_handle::Win32WindowHandle;
+#[cfg(target_os = "windows")]
+use std::{ffi::c_void, num::NonZeroIsize};
+
+/// A window created by another windowing library, identified only by its raw handle.
+///
+/// Implements [`HasWindowHandle`], so it can be passed anywhere a window is expected. It also
+/// implements [`HasDisplayHandle`] for handle types that don't need a connection to a display
+/// server, since some libraries don't provide one; this makes it usable with
+/// [`Context`](crate::Context) as well.
+#[derive(Debug, Clone, Copy)]
+pub struct ForeignWindow {
+    handle: RawWindowHandle,
+}
+
+impl ForeignWindow {
+    /// Wraps a raw window handle.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must refer to a valid window for as long as the `ForeignWindow` is used.
+    pub unsafe fn from_raw(handle: RawWindowHandle) -> ForeignWindow {
+        ForeignWindow { handle }
+    }
+
+    /// Wraps a Win32 `HWND`. Returns `None` if `hwnd` is null.
+    ///
+    /// # Safety
+    ///
+    /// `hwnd` must refer to a valid window for as long as the `ForeignWindow` is used.
+    #[cfg(target_os = "windows")]
+    pub unsafe fn from_hwnd(hwnd: *mut c_void) -> Option<ForeignWindow> {
+        let hwnd = NonZeroIsize::new(hwnd as isize)?;
+        Some(ForeignWindow::from_raw(RawWindowHandle::Win32(
+            Win32WindowHandle::new(hwnd),
+        )))
+    }
+
+    /// The wrapped raw window handle.
+    pub fn raw_handle(&self) -> RawWindowHandle {
+        self.handle
+    }
+}
+
+impl HasWindowHandle for ForeignWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        // Validity for as long as `self` is alive is guaranteed by the constructors' contracts.
+        Ok(unsafe { WindowHandle::borrow_raw(self.handle) })
+    }
+}
+
+impl HasDisplayHandle for ForeignWindow {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        match self.handle {
+            RawWindowHandle::Win32(_) | RawWindowHandle::WinRt(_) => Ok(DisplayHandle::windows()),
+            _ => Err(HandleError::Unavailable),
+        }
+    }
+}
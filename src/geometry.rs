@@ -0,0 +1,213 @@
+/// A position in a pixel buffer or window, in pixels from its top-left corner.
+///
+/// Functions that take a position accept anything that converts into a `Point`, including an
+/// `(x, y)` tuple.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// The width and height of a rectangle, in pixels.
+///
+/// Functions that take a size accept anything that converts into a `Size`, including a
+/// `(width, height)` tuple.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A rectangle of pixels, described by its top-left corner and its size.
+///
+/// Edges that would lie past `u32::MAX` are clamped to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub pos: Point,
+    pub size: Size,
+}
+
+impl Point {
+    /// The point at `(x, y)`.
+    pub const fn new(x: u32, y: u32) -> Point {
+        Point { x, y }
+    }
+}
+
+impl Size {
+    /// The size of a `width` by `height` rectangle.
+    pub const fn new(width: u32, height: u32) -> Size {
+        Size { width, height }
+    }
+
+    /// Whether the size covers no pixels, i.e. either dimension is zero.
+    pub fn is_empty(self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// The number of pixels covered.
+    pub fn area(self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+}
+
+impl Rect {
+    /// The rectangle of `size` pixels at `pos`.
+    pub fn new(pos: impl Into<Point>, size: impl Into<Size>) -> Rect {
+        Rect {
+            pos: pos.into(),
+            size: size.into(),
+        }
+    }
+
+    /// The rectangle spanning from `min`, inclusive, to `max`, exclusive. It's empty if `max`
+    /// isn't below and to the right of `min`.
+    pub fn from_corners(min: impl Into<Point>, max: impl Into<Point>) -> Rect {
+        let (min, max) = (min.into(), max.into());
+        Rect::new(
+            min,
+            (max.x.saturating_sub(min.x), max.y.saturating_sub(min.y)),
+        )
+    }
+
+    /// The column just past the right edge.
+    pub fn right(self) -> u32 {
+        self.pos.x.saturating_add(self.size.width)
+    }
+
+    /// The row just past the bottom edge.
+    pub fn bottom(self) -> u32 {
+        self.pos.y.saturating_add(self.size.height)
+    }
+
+    /// Whether the rectangle covers no pixels.
+    pub fn is_empty(self) -> bool {
+        self.size.is_empty()
+    }
+
+    /// Whether `point` lies inside the rectangle.
+    pub fn contains(self, point: impl Into<Point>) -> bool {
+        let point = point.into();
+        (self.pos.x..self.right()).contains(&point.x)
+            && (self.pos.y..self.bottom()).contains(&point.y)
+    }
+
+    /// The part of the rectangle that's also covered by `other`, or `None` if they don't overlap.
+    pub fn intersection(self, other: Rect) -> Option<Rect> {
+        let min = (self.pos.x.max(other.pos.x), self.pos.y.max(other.pos.y));
+        let max = (
+            self.right().min(other.right()),
+            self.bottom().min(other.bottom()),
+        );
+        Some(Rect::from_corners(min, max)).filter(|rect| !rect.is_empty())
+    }
+
+    /// The smallest rectangle covering both this one and `other`. Empty rectangles are ignored.
+    pub fn union(self, other: Rect) -> Rect {
+        match (self.is_empty(), other.is_empty()) {
+            (_, true) => self,
+            (true, false) => other,
+            (false, false) => Rect::from_corners(
+                (self.pos.x.min(other.pos.x), self.pos.y.min(other.pos.y)),
+                (
+                    self.right().max(other.right()),
+                    self.bottom().max(other.bottom()),
+                ),
+            ),
+        }
+    }
+
+    /// The part of the rectangle that lies inside a buffer of `bounds`. It's empty, positioned
+    /// at the nearest corner or edge, if none of it does.
+    pub fn clamp(self, bounds: impl Into<Size>) -> Rect {
+        let bounds = bounds.into();
+        Rect::from_corners(
+            (self.pos.x.min(bounds.width), self.pos.y.min(bounds.height)),
+            (
+                self.right().min(bounds.width),
+                self.bottom().min(bounds.height),
+            ),
+        )
+    }
+}
+
+impl From<(u32, u32)> for Point {
+    fn from((x, y): (u32, u32)) -> Point {
+        Point { x, y }
+    }
+}
+
+impl From<Point> for (u32, u32) {
+    fn from(point: Point) -> (u32, u32) {
+        (point.x, point.y)
+    }
+}
+
+impl From<(u32, u32)> for Size {
+    fn from((width, height): (u32, u32)) -> Size {
+        Size { width, height }
+    }
+}
+
+impl From<Size> for (u32, u32) {
+    fn from(size: Size) -> (u32, u32) {
+        (size.width, size.height)
+    }
+}
+
+impl From<((u32, u32), (u32, u32))> for Rect {
+    fn from((pos, size): ((u32, u32), (u32, u32))) -> Rect {
+        Rect::new(pos, size)
+    }
+}
+
+impl From<Rect> for ((u32, u32), (u32, u32)) {
+    fn from(rect: Rect) -> ((u32, u32), (u32, u32)) {
+        (rect.pos.into(), rect.size.into())
+    }
+}
+
+#[cfg(feature = "winit")]
+impl From<winit::dpi::PhysicalPosition<u32>> for Point {
+    fn from(position: winit::dpi::PhysicalPosition<u32>) -> Point {
+        Point::new(position.x, position.y)
+    }
+}
+
+#[cfg(feature = "winit")]
+impl From<winit::dpi::PhysicalSize<u32>> for Size {
+    fn from(size: winit::dpi::PhysicalSize<u32>) -> Size {
+        Size::new(size.width, size.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PixelBufferTyped, BGRA};
+
+    #[test]
+    /// The purpose of this test is to verify that `Rect`s intersect, merge and clamp to a
+    /// buffer's bounds without overflowing, and that tuples still work as positions and sizes.
+    fn rect_geometry() {
+        let a = Rect::new((2, 3), (10, 4));
+        let b = Rect::new(Point::new(8, 0), Size::new(10, 5));
+        assert_eq!(a.intersection(b), Some(Rect::new((8, 3), (4, 2))));
+        assert_eq!(a.intersection(Rect::new((12, 0), (1, 1))), None);
+        assert_eq!(a.union(b), Rect::new((2, 0), (16, 7)));
+        assert_eq!(a.union(Rect::default()), a);
+        assert!(a.contains((11, 6)) && !a.contains((12, 6)));
+
+        let huge = Rect::new((u32::MAX - 1, 5), (10, 10));
+        assert_eq!(huge.right(), u32::MAX);
+        assert_eq!(huge.clamp((20, 20)), Rect::new((20, 5), (0, 10)));
+        assert!(huge.clamp((20, 20)).is_empty());
+        assert_eq!(a.clamp((8, 5)), Rect::new((2, 3), (6, 2)));
+
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(31, 31).unwrap();
+        pb.take_damage();
+        pb.mark_dirty(Point::new(30, 2), (4, 4));
+        pb.mark_dirty((1, 1), Size::new(1, 1));
+        assert_eq!(pb.dirty_region(), Some(Rect::new((1, 1), (30, 5))));
+    }
+}
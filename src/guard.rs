@@ -0,0 +1,145 @@
+use crate::{PixelBuffer, PixelBufferFormat, PixelBufferTyped};
+use std::ops::{Deref, DerefMut};
+
+/// Write access to a pixel buffer that [finishes the frame](PixelBuffer::finish_writes) once
+/// it's dropped, if the buffer was borrowed mutably through it.
+///
+/// Returned by [`PixelBuffer::write`]. Dereferences to the buffer, so it's drawn into like the
+/// buffer itself.
+pub struct WriteGuard<'a> {
+    buffer: &'a mut PixelBuffer,
+    written: bool,
+}
+
+/// Write access to a pixel buffer with a statically-checked pixel format, that
+/// [finishes the frame](PixelBuffer::finish_writes) once it's dropped, if the buffer was
+/// borrowed mutably through it.
+///
+/// Returned by [`PixelBufferTyped::write`].
+pub struct WriteGuardTyped<'a, P: PixelBufferFormat> {
+    buffer: &'a mut PixelBufferTyped<P>,
+    written: bool,
+}
+
+impl PixelBuffer {
+    /// Borrows the pixel buffer for writing a frame, which is
+    /// [finished](Self::finish_writes) when the returned guard is dropped.
+    ///
+    /// Since the guard borrows the buffer mutably, the frame can't be presented until every
+    /// write to it is done, and the finishing step can't be forgotten. Guards that were only
+    /// read through don't finish the frame, so they don't pay for it.
+    pub fn write(&mut self) -> WriteGuard<'_> {
+        WriteGuard {
+            buffer: self,
+            written: false,
+        }
+    }
+
+    /// Marks the frame in the pixel buffer as fully written, so it's ready to be presented.
+    ///
+    /// State derived from the buffer's contents is brought up to date: if there are
+    /// [mipmaps](Self::build_mipmaps), they're rebuilt, which takes a pass over the whole
+    /// buffer. Writes made by other threads, or other processes through the
+    /// [shared memory](Self::shared_memory), have to be synchronized with this thread first,
+    /// e.g. by joining the threads.
+    pub fn finish_writes(&mut self) {
+        if self.p.mipmap_levels() > 0 {
+            self.p.build_mipmaps();
+        }
+    }
+}
+
+impl<P: PixelBufferFormat> PixelBufferTyped<P> {
+    /// Borrows the pixel buffer for writing a frame, which is finished when the returned guard is
+    /// dropped.
+    ///
+    /// See [`PixelBuffer::write`].
+    pub fn write(&mut self) -> WriteGuardTyped<'_, P> {
+        WriteGuardTyped {
+            buffer: self,
+            written: false,
+        }
+    }
+
+    /// Marks the frame in the pixel buffer as fully written, so it's ready to be presented.
+    ///
+    /// See [`PixelBuffer::finish_writes`].
+    pub fn finish_writes(&mut self) {
+        self.p.finish_writes()
+    }
+}
+
+impl Deref for WriteGuard<'_> {
+    type Target = PixelBuffer;
+
+    fn deref(&self) -> &PixelBuffer {
+        self.buffer
+    }
+}
+
+impl DerefMut for WriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut PixelBuffer {
+        self.written = true;
+        self.buffer
+    }
+}
+
+impl Drop for WriteGuard<'_> {
+    fn drop(&mut self) {
+        if self.written {
+            self.buffer.finish_writes();
+        }
+    }
+}
+
+impl<P: PixelBufferFormat> Deref for WriteGuardTyped<'_, P> {
+    type Target = PixelBufferTyped<P>;
+
+    fn deref(&self) -> &PixelBufferTyped<P> {
+        self.buffer
+    }
+}
+
+impl<P: PixelBufferFormat> DerefMut for WriteGuardTyped<'_, P> {
+    fn deref_mut(&mut self) -> &mut PixelBufferTyped<P> {
+        self.written = true;
+        self.buffer
+    }
+}
+
+impl<P: PixelBufferFormat> Drop for WriteGuardTyped<'_, P> {
+    fn drop(&mut self) {
+        if self.written {
+            self.buffer.finish_writes();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BGRA;
+
+    #[test]
+    /// The purpose of this test is to verify that dropping a `WriteGuardTyped` finishes the
+    /// frame, bringing the mipmaps up to date with what was written through it, and that guards
+    /// that were only read through leave the mipmaps alone.
+    fn write_guard_finishes_frame() {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(2, 2).unwrap();
+        pb.build_mipmaps();
+        assert_eq!(&pb.p.p.mipmaps[0].row(0)[..4], &[0, 0, 0, 0]);
+        pb.clear(BGRA::from_rgba(1, 2, 3, 4));
+        {
+            let frame = pb.write();
+            assert_eq!(frame.width(), 2);
+        }
+        assert_eq!(&pb.p.p.mipmaps[0].row(0)[..4], &[0, 0, 0, 0]);
+        {
+            let mut frame = pb.write();
+            for row in frame.rows_mut() {
+                row.fill(BGRA::from_rgba(10, 20, 30, 40));
+            }
+        }
+        assert_eq!(&pb.p.p.mipmaps[0].row(0)[..4], &[30, 20, 10, 40]);
+    }
+}
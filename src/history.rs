@@ -0,0 +1,78 @@
+use crate::PixelBufferFormat;
+use std::{cell::Ref, marker::PhantomData};
+
+/// The contents of a pixel buffer as of its most recent blit.
+///
+/// Returned by [`PixelBuffer::previous_frame`](crate::PixelBuffer::previous_frame). The frame's
+/// origin is in its top-left corner.
+pub struct PreviousFrame<'a> {
+    pub(crate) bytes: Ref<'a, [u8]>,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) bytes_per_pixel: usize,
+}
+
+/// The contents of a pixel buffer as of its most recent blit, with a statically-checked pixel
+/// format.
+///
+/// Returned by [`PixelBufferTyped::previous_frame`](crate::PixelBufferTyped::previous_frame).
+pub struct PreviousFrameTyped<'a, P: PixelBufferFormat> {
+    pub(crate) frame: PreviousFrame<'a>,
+    pub(crate) _format: PhantomData<P>,
+}
+
+impl PreviousFrame<'_> {
+    /// The width, in pixels, of the frame.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height, in pixels, of the frame.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The length, in bytes, of a single row of the frame.
+    ///
+    /// Rows of a previous frame are always tightly packed.
+    pub fn row_len(&self) -> usize {
+        self.width as usize * self.bytes_per_pixel
+    }
+
+    /// Gets the row at the particular height.
+    pub fn row(&self, row: u32) -> Option<&[u8]> {
+        if row < self.height {
+            let start = row as usize * self.row_len();
+            Some(&self.bytes[start..start + self.row_len()])
+        } else {
+            None
+        }
+    }
+
+    /// Iterate through all rows in the frame.
+    pub fn rows(&self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &[u8]> {
+        (0..self.height).map(move |row| self.row(row).unwrap())
+    }
+}
+
+impl<P: PixelBufferFormat> PreviousFrameTyped<'_, P> {
+    /// The width, in pixels, of the frame.
+    pub fn width(&self) -> u32 {
+        self.frame.width()
+    }
+
+    /// The height, in pixels, of the frame.
+    pub fn height(&self) -> u32 {
+        self.frame.height()
+    }
+
+    /// Gets the row at the particular height.
+    pub fn row(&self, row: u32) -> Option<&[P]> {
+        self.frame.row(row).map(P::from_raw_slice)
+    }
+
+    /// Iterate through all rows in the frame.
+    pub fn rows(&self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &[P]> {
+        self.frame.rows().map(P::from_raw_slice)
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{
+    convert::{convert_alpha, AlphaMode},
+    platform_impl, PixelBufferFormat, PixelBufferTyped, Point, Size,
+};
+use raw_window_handle::HasWindowHandle;
+use std::{ffi::c_void, io};
+
+/// A platform icon built from the contents of a pixel buffer.
+///
+/// Created with [`PixelBufferTyped::create_icon`]. The platform icon is destroyed when this is
+/// dropped, so it must outlive any window it's been set on.
+pub struct Icon {
+    p: platform_impl::Icon,
+}
+
+impl Icon {
+    /// Makes this the icon shown in `window`'s title bar and the taskbar.
+    pub fn set_as_window_icon<H: HasWindowHandle>(&self, window: &H) -> io::Result<()> {
+        let handle = window.window_handle().map_err(io::Error::other)?.as_raw();
+        unsafe { self.p.set_window_icon(handle) }.map_err(io::Error::other)
+    }
+
+    /// The platform's handle to the icon. On Windows, this is an `HICON`.
+    ///
+    /// The handle remains owned by this `Icon`.
+    pub fn raw_handle(&self) -> *mut c_void {
+        self.p.raw_handle()
+    }
+}
+
+/// A platform cursor built from the contents of a pixel buffer.
+///
+/// Created with [`PixelBufferTyped::create_cursor`]. The platform cursor is destroyed when this
+/// is dropped.
+pub struct Cursor {
+    p: platform_impl::Icon,
+}
+
+impl Cursor {
+    /// Makes this the cursor shown while the pointer is over the calling thread's windows.
+    ///
+    /// Windows may reset the cursor whenever the pointer moves, unless the window's class has no
+    /// cursor of its own.
+    pub fn set_as_current(&self) {
+        unsafe { self.p.set_current_cursor() }
+    }
+
+    /// The platform's handle to the cursor. On Windows, this is an `HCURSOR`.
+    ///
+    /// The handle remains owned by this `Cursor`.
+    pub fn raw_handle(&self) -> *mut c_void {
+        self.p.raw_handle()
+    }
+}
+
+impl<P: PixelBufferFormat> PixelBufferTyped<P> {
+    /// Creates an icon from the region with its top-left corner at `pos`.
+    ///
+    /// Transparency is taken from the buffer's alpha channel, interpreted according to its
+    /// [`alpha_mode`](Self::alpha_mode). Returns an error if the region doesn't fit inside the
+    /// buffer or is empty, or if the platform fails to create the icon.
+    pub fn create_icon(&self, pos: impl Into<Point>, size: impl Into<Size>) -> io::Result<Icon> {
+        let (pos, size): (Point, Size) = (pos.into(), size.into());
+        let bgra = self.region_bgra(pos, size)?;
+        let p = unsafe { platform_impl::Icon::new(size.width, size.height, &bgra, None)? };
+        Ok(Icon { p })
+    }
+
+    /// Creates a cursor from the region with its top-left corner at `pos`.
+    ///
+    /// `hotspot` is the point within the region that's aligned with the pointer's position.
+    /// Transparency is handled the same way as in [`create_icon`](Self::create_icon). Returns an
+    /// error if the region doesn't fit inside the buffer or is empty, if `hotspot` lies outside of
+    /// it, or if the platform fails to create the cursor.
+    pub fn create_cursor(
+        &self,
+        pos: impl Into<Point>,
+        size: impl Into<Size>,
+        hotspot: impl Into<Point>,
+    ) -> io::Result<Cursor> {
+        let (pos, size, hotspot): (Point, Size, Point) = (pos.into(), size.into(), hotspot.into());
+        if hotspot.x >= size.width || hotspot.y >= size.height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "hotspot lies outside of the cursor",
+            ));
+        }
+        let bgra = self.region_bgra(pos, size)?;
+        let hotspot = Some((hotspot.x, hotspot.y));
+        let p = unsafe { platform_impl::Icon::new(size.width, size.height, &bgra, hotspot)? };
+        Ok(Cursor { p })
+    }
+
+    /// Copies a region into top-down, tightly packed BGRA pixels with straight alpha, which is
+    /// what platform icon and cursor APIs expect.
+    pub(crate) fn region_bgra(&self, pos: Point, size: Size) -> io::Result<Vec<u8>> {
+        let fits = |pos: u32, size: u32, len: u32| {
+            size != 0 && matches!(pos.checked_add(size), Some(end) if end <= len)
+        };
+        if !fits(pos.x, size.width, self.width()) || !fits(pos.y, size.height, self.height()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "region is empty or doesn't fit inside the pixel buffer",
+            ));
+        }
+        let mode = self.alpha_mode();
+        let mut bgra = Vec::with_capacity(size.area() as usize * 4);
+        for y in pos.y..pos.y + size.height {
+            let row = &self.row(y).unwrap()[pos.x as usize..(pos.x + size.width) as usize];
+            for &pixel in row {
+                let [r, g, b, a] = convert_alpha(pixel.to_rgba(), mode, AlphaMode::Straight);
+                bgra.extend_from_slice(&[b, g, r, a]);
+            }
+        }
+        Ok(bgra)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BGRA;
+
+    #[test]
+    /// The purpose of this test is to verify that cursors are created from valid regions, and
+    /// that empty regions, regions outside the buffer and hotspots outside the cursor are
+    /// reported as errors.
+    fn create_cursor_checks_its_input() {
+        let pb = PixelBufferTyped::<BGRA>::new_offscreen(16, 16).unwrap();
+        let cursor = pb.create_cursor((0, 0), (16, 16), (15, 15)).unwrap();
+        assert!(!cursor.raw_handle().is_null());
+
+        for &(pos, size, hotspot) in &[
+            ((0, 0), (16, 16), (16, 0)),
+            ((0, 0), (16, 16), (0, 16)),
+            ((0, 0), (0, 16), (0, 0)),
+            ((8, 8), (16, 16), (0, 0)),
+            ((u32::MAX, 0), (2, 2), (0, 0)),
+        ] {
+            let error = pb.create_cursor(pos, size, hotspot).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
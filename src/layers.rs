@@ -0,0 +1,230 @@
+use crate::{fill::blend_src_over, PixelBufferFormat, Point, Rect, RegionMut, Size};
+use std::mem;
+
+/// A stack of equally-sized layers, composited into a pixel buffer on demand.
+///
+/// Each layer has its own pixels, so e.g. a background, the main content, UI and a cursor can be
+/// redrawn independently. The stack tracks which area has changed since the last call to
+/// [`composite`](Self::composite), and only that area is composited again.
+///
+/// Layers are stored in the format `L` and drawn bottom (index `0`) to top. The bottom visible
+/// layer is copied as-is, and every layer above it is blended over it using straight alpha. Layers
+/// in a format without an alpha channel cover everything beneath them.
+pub struct LayerStack<L: PixelBufferFormat> {
+    width: u32,
+    height: u32,
+    layers: Vec<Layer<L>>,
+    /// The bounding box of everything changed since the last composite.
+    dirty: Option<Rect>,
+}
+
+struct Layer<L> {
+    pixels: Vec<L>,
+    visible: bool,
+}
+
+impl<L: PixelBufferFormat> LayerStack<L> {
+    /// Creates a stack of `layers` fully-transparent layers.
+    ///
+    /// The whole stack starts out dirty, so the first composite covers the entire target.
+    pub fn new(width: u32, height: u32, layers: usize) -> LayerStack<L> {
+        let mut stack = LayerStack {
+            width,
+            height,
+            layers: Vec::with_capacity(layers),
+            dirty: None,
+        };
+        for _ in 0..layers {
+            stack.push_layer();
+        }
+        stack.mark_dirty((0, 0), (width, height));
+        stack
+    }
+
+    /// The width, in pixels, of every layer.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height, in pixels, of every layer.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The number of layers in the stack.
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Adds a fully-transparent layer on top of the stack, returning its index.
+    pub fn push_layer(&mut self) -> usize {
+        let len = self.width as usize * self.height as usize;
+        self.layers.push(Layer {
+            pixels: vec![L::from_rgba(0, 0, 0, 0); len],
+            visible: true,
+        });
+        self.layers.len() - 1
+    }
+
+    /// Gets a mutable view into the whole layer at `index`, marking the entire stack as dirty.
+    ///
+    /// # Panics
+    /// Panics if there's no layer at `index`.
+    pub fn layer_mut(&mut self, index: usize) -> RegionMut<'_, L> {
+        let (width, height) = (self.width, self.height);
+        self.layer_region_mut(index, (0, 0), (width, height))
+            .unwrap()
+    }
+
+    /// Gets a mutable view into the region of the layer at `index` with its top-left corner at
+    /// `pos`, marking only that region as dirty.
+    ///
+    /// Returns `None` if the region doesn't fit inside the stack.
+    ///
+    /// # Panics
+    /// Panics if there's no layer at `index`.
+    pub fn layer_region_mut(
+        &mut self,
+        index: usize,
+        pos: impl Into<Point>,
+        size: impl Into<Size>,
+    ) -> Option<RegionMut<'_, L>> {
+        let (pos, size): (Point, Size) = (pos.into(), size.into());
+        let row_pitch = (self.width as usize * mem::size_of::<L>()) as isize;
+        let (width, height) = (self.width, self.height);
+        let pixels = &mut self.layers[index].pixels;
+        let region = unsafe {
+            RegionMut::from_raw_parts(pixels.as_mut_ptr() as *mut u8, row_pitch, width, height)
+        }
+        .into_region(pos, size)?;
+        self.mark_dirty(pos, size);
+        Some(region)
+    }
+
+    /// Whether the layer at `index` is drawn when compositing.
+    ///
+    /// # Panics
+    /// Panics if there's no layer at `index`.
+    pub fn is_visible(&self, index: usize) -> bool {
+        self.layers[index].visible
+    }
+
+    /// Shows or hides the layer at `index`. Changing a layer's visibility marks the entire stack
+    /// as dirty.
+    ///
+    /// # Panics
+    /// Panics if there's no layer at `index`.
+    pub fn set_visible(&mut self, index: usize, visible: bool) {
+        if self.layers[index].visible != visible {
+            self.layers[index].visible = visible;
+            self.mark_dirty((0, 0), (self.width, self.height));
+        }
+    }
+
+    /// Marks a region as needing to be composited again, e.g. after the target buffer has been
+    /// drawn over.
+    pub fn mark_dirty(&mut self, pos: impl Into<Point>, size: impl Into<Size>) {
+        let rect = Rect::new(pos, size).clamp((self.width, self.height));
+        if !rect.is_empty() {
+            self.dirty = Some(self.dirty.unwrap_or_default().union(rect));
+        }
+    }
+
+    /// The region changed since the last composite.
+    pub fn dirty_region(&self) -> Option<Rect> {
+        self.dirty
+    }
+
+    /// Composites the dirty region of every visible layer into `target`, and clears the dirty
+    /// region.
+    ///
+    /// Returns the region of `target` that was written to, which is what needs to be blitted to
+    /// show the changes. Returns `None` if nothing changed. Parts of the stack
+    /// outside of `target` are skipped.
+    pub fn composite<'a, P: PixelBufferFormat>(
+        &mut self,
+        target: impl Into<RegionMut<'a, P>>,
+    ) -> Option<Rect> {
+        let mut target = target.into();
+        let dirty = self.dirty.take()?.clamp((target.width(), target.height()));
+        if dirty.is_empty() {
+            return None;
+        }
+        let mode = target.alpha_mode();
+        let intent = target.rendering_intent();
+        let columns = dirty.pos.x as usize..dirty.right() as usize;
+        let mut layers = self.layers.iter().filter(|layer| layer.visible);
+        let bottom = layers.next()?;
+        let layers: Vec<_> = layers.collect();
+        for y in dirty.pos.y..dirty.bottom() {
+            let start = y as usize * self.width as usize;
+            let span = start + columns.start..start + columns.end;
+            let row = &mut target.row_mut(y).unwrap()[columns.clone()];
+            for (dst, src) in row.iter_mut().zip(&bottom.pixels[span.clone()]) {
+                let [r, g, b, a] = src.to_rgba();
+                *dst = P::from_rgba(r, g, b, a);
+            }
+            for layer in &layers {
+                for (dst, src) in row.iter_mut().zip(&layer.pixels[span.clone()]) {
+                    let rgba = src.to_rgba();
+                    *dst = match rgba[3] {
+                        0 => continue,
+                        255 => P::from_rgba(rgba[0], rgba[1], rgba[2], 255),
+                        _ => blend_src_over(*dst, rgba, mode, intent),
+                    };
+                }
+            }
+        }
+        Some(dirty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AlphaMode, PixelBufferTyped, RenderingIntent, BGRA};
+
+    const RED: BGRA = BGRA::from_rgb(255, 0, 0);
+    const BLUE: BGRA = BGRA::from_rgb(0, 0, 255);
+    const WHITE: BGRA = BGRA::from_rgb(255, 255, 255);
+
+    #[test]
+    /// The purpose of this test is to verify that layers are composited bottom to top, with
+    /// opaque pixels covering the ones beneath them, translucent ones blended over them and
+    /// transparent or hidden ones leaving them alone.
+    fn composite_blends_layers_in_order() {
+        let mut stack = LayerStack::<BGRA>::new(4, 1, 4);
+        for pixel in stack.layer_mut(0).row_mut(0).unwrap() {
+            *pixel = RED;
+        }
+        let mut middle = stack.layer_mut(1);
+        let row = middle.row_mut(0).unwrap();
+        row[1] = BLUE;
+        row[2] = BLUE;
+        row[3] = BLUE;
+        let mut top = stack.layer_mut(2);
+        let row = top.row_mut(0).unwrap();
+        row[2] = BGRA::from_rgba(0, 255, 0, 128);
+        row[3] = WHITE;
+        stack.layer_mut(3).row_mut(0).unwrap()[0] = WHITE;
+        stack.set_visible(3, false);
+
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(4, 1).unwrap();
+        assert_eq!(stack.composite(&mut pb), Some(Rect::new((0, 0), (4, 1))));
+        let translucent = blend_src_over(
+            BLUE,
+            [0, 255, 0, 128],
+            AlphaMode::default(),
+            RenderingIntent::default(),
+        );
+        assert_ne!(translucent, BLUE);
+        assert_eq!(pb.row(0).unwrap(), [RED, BLUE, translucent, WHITE]);
+        assert_eq!(stack.composite(&mut pb), None);
+
+        stack.set_visible(3, true);
+        stack.set_visible(0, false);
+        assert_eq!(stack.composite(&mut pb), Some(Rect::new((0, 0), (4, 1))));
+        // The bottom visible layer is copied as-is, transparent pixels included.
+        assert_eq!(pb.row(0).unwrap()[..2], [WHITE, BLUE]);
+    }
+}
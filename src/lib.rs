@@ -1,10 +1,99 @@
+mod backend;
+mod buffer_slice;
+mod cancel;
+mod canvas;
+mod color;
+mod convert;
+mod converter;
+mod copy;
+mod custom;
+pub mod draw;
+mod fill;
+pub mod filters;
+mod fixed;
+mod font;
+pub mod foreign;
+mod geometry;
+mod guard;
+mod history;
+mod icon;
+mod layers;
+mod memory;
+mod overlay;
+#[cfg(feature = "winit")]
+mod pacing;
 mod platform_impl;
-use raw_window_handle::HasRawWindowHandle;
+mod record;
+mod region;
+mod resolution;
+mod scroll;
+mod surface;
+mod text;
+#[cfg(feature = "rayon")]
+mod threads;
+mod transform;
+#[cfg(feature = "unstable-win32")]
+pub mod unstable_win32;
+#[cfg(feature = "video")]
+mod video;
+mod view;
+#[cfg(feature = "vnc")]
+mod vnc;
+#[cfg(feature = "winit")]
+mod winit_window;
+
+use crate::memory::Tracked;
+#[cfg(feature = "winit")]
+pub use crate::pacing::FramePacer;
+#[cfg(feature = "rayon")]
+pub use crate::threads::{set_thread_pool, thread_pool};
+#[cfg(feature = "video")]
+pub use crate::video::VideoSink;
+#[cfg(feature = "vnc")]
+pub use crate::vnc::VncServer;
+pub use crate::{
+    backend::{BackendRows, CustomBackend},
+    buffer_slice::BufferSlice,
+    cancel::CancelToken,
+    canvas::Canvas,
+    color::Color,
+    convert::{
+        AlphaBehavior, AlphaMode, ChannelDepth, ColorAdjustment, ConversionOptions, DitherMode,
+        RenderingIntent,
+    },
+    converter::{Converter, ImageDesc},
+    custom::{CustomFormat, PixelBufferCustom},
+    fixed::PixelBufferFixed,
+    geometry::{Point, Rect, Size},
+    guard::{WriteGuard, WriteGuardTyped},
+    history::{PreviousFrame, PreviousFrameTyped},
+    icon::{Cursor, Icon},
+    layers::LayerStack,
+    memory::{memory_budget, set_memory_budget, total_memory_usage},
+    overlay::{DebugOverlay, DEBUG_OVERLAY_ENV_VAR},
+    record::{Recorder, RecordingBuffer, RecordingFormat},
+    region::RegionMut,
+    resolution::DynamicResolution,
+    surface::{Buffer, Context, Surface},
+    text::{Cell, TextGrid},
+    transform::{Affine2, Filter},
+    view::{FormatView, FormatViewMut},
+};
+use raw_window_handle::{HandleError, HasWindowHandle};
 use std::{
+    alloc::Layout,
     borrow::{Borrow, BorrowMut},
-    fmt::Debug,
+    cell::{Ref, RefCell},
+    error::Error,
+    fmt::{self, Debug},
     io,
     marker::PhantomData,
+    mem::{self, MaybeUninit},
+    num::NonZeroU32,
+    os::windows::io::RawHandle,
+    slice,
+    sync::atomic::{AtomicU8, Ordering},
+    time::{Duration, Instant},
 };
 
 #[cfg(feature = "rayon")]
@@ -13,6 +102,536 @@ use rayon::prelude::*;
 #[derive(Debug, Clone)]
 pub enum PixelBufferCreationError {
     FormatNotSupported,
+    /// The window's handle couldn't be retrieved, or isn't supported by the platform.
+    WindowHandle(HandleError),
+    /// The pixel buffer's memory couldn't be allocated by its [`BufferAllocator`].
+    AllocationFailed,
+    /// The pixel buffer is larger than the platform can present.
+    ///
+    /// On Windows, buffers are limited to 4 GiB, with rows of at most 2 GiB.
+    BufferTooLarge,
+    /// A call to the platform failed while creating the pixel buffer.
+    Platform(PlatformError),
+    /// The window can't display pixel buffers of the requested format, but can display ones of
+    /// the `suggested` format instead.
+    FormatMismatch {
+        requested: PixelBufferFormatType,
+        suggested: PixelBufferFormatType,
+    },
+    /// The window can't display pixel buffers at all, for the given reason.
+    ///
+    /// This is checked when the buffer is created, rather than left to fail the first blit.
+    IncompatibleWindow(&'static str),
+}
+
+impl fmt::Display for PixelBufferCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PixelBufferCreationError::FormatNotSupported => {
+                write!(f, "pixel format isn't supported by the platform")
+            }
+            PixelBufferCreationError::WindowHandle(_) => {
+                write!(f, "window handle couldn't be retrieved")
+            }
+            PixelBufferCreationError::AllocationFailed => {
+                write!(f, "pixel buffer memory couldn't be allocated")
+            }
+            PixelBufferCreationError::BufferTooLarge => {
+                write!(f, "pixel buffer is larger than the platform can present")
+            }
+            PixelBufferCreationError::Platform(_) => write!(f, "platform call failed"),
+            PixelBufferCreationError::FormatMismatch {
+                requested,
+                suggested,
+            } => write!(
+                f,
+                "window can't display {:?} pixel buffers; use {:?} instead",
+                requested, suggested
+            ),
+            PixelBufferCreationError::IncompatibleWindow(reason) => {
+                write!(f, "window can't display pixel buffers: {}", reason)
+            }
+        }
+    }
+}
+
+impl Error for PixelBufferCreationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PixelBufferCreationError::WindowHandle(error) => Some(error),
+            PixelBufferCreationError::Platform(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A failed call to the platform, along with the error code it reported.
+///
+/// The code is preserved as-is, e.g. the value of `GetLastError` on Windows, so it can be looked
+/// up in the platform's documentation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlatformError {
+    function: &'static str,
+    code: i32,
+}
+
+impl PlatformError {
+    /// Creates an error for `function` from the calling thread's last OS error.
+    pub(crate) fn last(function: &'static str) -> PlatformError {
+        PlatformError {
+            function,
+            code: io::Error::last_os_error().raw_os_error().unwrap_or(0),
+        }
+    }
+
+    /// Like `last`, but also reports the error according to the platform error action.
+    pub(crate) fn report(function: &'static str, window: *mut std::ffi::c_void) -> PlatformError {
+        let error = PlatformError::last(function);
+        report_platform_error(&error, window);
+        error
+    }
+
+    /// The name of the platform function that failed.
+    pub fn function(&self) -> &'static str {
+        self.function
+    }
+
+    /// The raw error code reported by the platform. `0` if the platform didn't report one.
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+}
+
+impl fmt::Display for PlatformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            0 => write!(f, "`{}` failed", self.function),
+            code => write!(
+                f,
+                "`{}` failed: {}",
+                self.function,
+                io::Error::from_raw_os_error(code)
+            ),
+        }
+    }
+}
+
+impl Error for PlatformError {}
+
+/// What the crate does when one of its platform calls fails, in debug builds.
+///
+/// Failures of the platform's drawing APIs are notoriously hard to track down, since the error
+/// usually surfaces far from the call that caused it. See [`set_platform_error_action`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlatformErrorAction {
+    /// Only return the error. The default.
+    Ignore,
+    /// Print the failed call, the window it was made for, and a backtrace to standard error, and
+    /// then return the error.
+    Print,
+    /// Panic with the failed call and the window it was made for, so the failure can be inspected
+    /// in a debugger.
+    Panic,
+}
+
+static PLATFORM_ERROR_ACTION: AtomicU8 = AtomicU8::new(PlatformErrorAction::Ignore as u8);
+
+/// Sets what happens when one of the crate's platform calls fails, for every pixel buffer.
+///
+/// Only takes effect in debug builds; in release builds, errors are always just returned.
+pub fn set_platform_error_action(action: PlatformErrorAction) {
+    PLATFORM_ERROR_ACTION.store(action as u8, Ordering::Relaxed);
+}
+
+/// Reports a failed platform call made for `window`, according to the platform error action.
+#[cfg_attr(not(debug_assertions), allow(unused_variables))]
+pub(crate) fn report_platform_error(error: &PlatformError, window: *mut std::ffi::c_void) {
+    #[cfg(debug_assertions)]
+    match PLATFORM_ERROR_ACTION.load(Ordering::Relaxed) {
+        action if action == PlatformErrorAction::Print as u8 => eprintln!(
+            "winit-blit: {} (window {:?})\n{}",
+            error,
+            window,
+            std::backtrace::Backtrace::force_capture()
+        ),
+        action if action == PlatformErrorAction::Panic as u8 => {
+            panic!("{} (window {:?})", error, window)
+        }
+        _ => {}
+    }
+}
+
+impl From<PlatformError> for io::Error {
+    fn from(error: PlatformError) -> io::Error {
+        let kind = io::Error::from_raw_os_error(error.code).kind();
+        io::Error::new(kind, error)
+    }
+}
+
+/// An error that occurred while blitting a pixel buffer onto a window.
+#[derive(Debug)]
+pub enum BlitError {
+    /// The window has been destroyed.
+    ///
+    /// Every later blit onto the same window fails with this error as well, so it's safe to
+    /// keep presenting while the window is being closed asynchronously.
+    WindowDestroyed,
+    /// The window's handle couldn't be retrieved, or isn't supported by the platform.
+    WindowHandle(HandleError),
+    /// The platform failed to present the buffer.
+    Io(io::Error),
+}
+
+impl fmt::Display for BlitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlitError::WindowDestroyed => write!(f, "window has been destroyed"),
+            BlitError::WindowHandle(_) => write!(f, "window handle couldn't be retrieved"),
+            BlitError::Io(_) => write!(f, "platform failed to present the pixel buffer"),
+        }
+    }
+}
+
+impl Error for BlitError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BlitError::WindowDestroyed => None,
+            BlitError::WindowHandle(error) => Some(error),
+            BlitError::Io(error) => Some(error),
+        }
+    }
+}
+
+impl From<HandleError> for PixelBufferCreationError {
+    fn from(error: HandleError) -> PixelBufferCreationError {
+        PixelBufferCreationError::WindowHandle(error)
+    }
+}
+
+impl From<HandleError> for BlitError {
+    fn from(error: HandleError) -> BlitError {
+        BlitError::WindowHandle(error)
+    }
+}
+
+impl From<io::Error> for BlitError {
+    fn from(error: io::Error) -> BlitError {
+        BlitError::Io(error)
+    }
+}
+
+impl From<BlitError> for io::Error {
+    fn from(error: BlitError) -> io::Error {
+        match error {
+            BlitError::WindowDestroyed => {
+                io::Error::new(io::ErrorKind::NotFound, "window has been destroyed")
+            }
+            BlitError::WindowHandle(error) => io::Error::other(error),
+            BlitError::Io(error) => error,
+        }
+    }
+}
+
+/// Supplies the memory backing a pixel buffer's pixels.
+///
+/// By default, pixel memory is allocated by the platform. Implementing this trait lets
+/// applications place it somewhere else instead, such as in huge pages, pinned memory, or an
+/// arena that's tracked by the application's own allocation bookkeeping.
+///
+/// # Safety
+///
+/// `allocate` must either return null or a pointer to a block of initialized memory that
+/// satisfies `layout` and remains valid until it's passed back to `deallocate`. The memory may
+/// hold anything, such as an earlier buffer's pixels; it's cleared unless the buffer is created
+/// with [`BufferInit::Uninit`].
+pub unsafe trait BufferAllocator: Send {
+    /// Allocates a block of memory for a pixel buffer, returning null on failure.
+    fn allocate(&self, layout: Layout) -> *mut u8;
+
+    /// Deallocates a block of memory previously returned by `allocate`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by a call to `allocate` on this allocator with the same
+    /// `layout`.
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout);
+}
+
+/// The shared memory backing a pixel buffer created with
+/// [`PixelBuffer::new_shared`](crate::PixelBuffer::new_shared).
+///
+/// The pixels are laid out exactly as they are in the buffer. On Windows, this means rows are
+/// stored bottom-up, with each row padded to a multiple of 4 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharedMemory {
+    /// The handle of the file mapping holding the pixels.
+    ///
+    /// The handle stays owned by the pixel buffer. Use `DuplicateHandle` to pass it to another
+    /// process, which can then map it with `MapViewOfFile`.
+    pub handle: RawHandle,
+    /// The size of the pixel data, in bytes.
+    pub len: usize,
+    /// The number of bytes between the start of one row and the start of the next.
+    pub row_len: usize,
+}
+
+/// What a pixel buffer's pixels start out as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BufferInit {
+    /// Every byte starts out zero.
+    Zeroed,
+    /// The pixels start out as whatever the memory held, which saves clearing large buffers that
+    /// a full-frame renderer overwrites anyway.
+    ///
+    /// The memory is always initialized, so reading it is safe, but its contents are
+    /// unspecified. On Windows, only memory from a [`BufferAllocator`] can hold anything other
+    /// than zeroes, since the system zeroes the rest itself. Renderers that fill such buffers
+    /// through `MaybeUninit` memory can use [`PixelBuffer::row_uninit_mut`].
+    Uninit,
+}
+
+/// What happens when a pixel buffer's format differs from the formats the platform can present,
+/// e.g. an [`RGBA`] buffer on Windows, which only presents blue-green-red pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MismatchPolicy {
+    /// Creating the buffer fails with [`PixelBufferCreationError::FormatMismatch`], which
+    /// suggests a format that would work.
+    #[default]
+    Error,
+    /// The buffer is created, and its pixels are converted to the platform's format every time
+    /// they're presented or copied out of it.
+    ///
+    /// This costs a copy of the presented pixels, and fails blits if
+    /// [conversion isn't allowed](PixelBuffer::set_allow_conversion).
+    /// [`emergency_present`](PixelBuffer::emergency_present) never converts, so it presents
+    /// such buffers with their colors swapped.
+    Convert,
+    /// The buffer is created, and its bytes are presented as if they were in the platform's
+    /// format, without any conversion.
+    ///
+    /// Channels end up swapped on screen, e.g. red and blue for an [`RGBA`] buffer on Windows.
+    /// Useful when the pixels were produced in the platform's order anyway, or when the colors
+    /// don't matter.
+    Reinterpret,
+}
+
+/// Options for creating a pixel buffer with [`PixelBuffer::with_options`] or
+/// [`PixelBuffer::with_allocator_options`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferOptions {
+    row_alignment: usize,
+    init: BufferInit,
+    mismatch_policy: MismatchPolicy,
+}
+
+impl Default for BufferOptions {
+    fn default() -> BufferOptions {
+        BufferOptions::new()
+    }
+}
+
+impl BufferOptions {
+    /// Options for a buffer laid out the platform's usual way.
+    pub fn new() -> BufferOptions {
+        BufferOptions {
+            row_alignment: 1,
+            init: BufferInit::Zeroed,
+            mismatch_policy: MismatchPolicy::Error,
+        }
+    }
+
+    /// Pads every row of the buffer to a multiple of `n` bytes, e.g. 16 or 64.
+    ///
+    /// This produces layouts that can be handed directly to APIs with stricter requirements,
+    /// such as Vulkan staging buffers or video encoders, without repacking them first. Rows are
+    /// never aligned less strictly than the platform requires, which is 4 bytes on Windows. The
+    /// resulting length of a row is reported by [`PixelBuffer::row_len`].
+    ///
+    /// # Panics
+    /// Panics if `n` isn't a power of two.
+    pub fn row_alignment(mut self, n: usize) -> BufferOptions {
+        assert!(n.is_power_of_two(), "row alignment must be a power of two");
+        self.row_alignment = n;
+        self
+    }
+
+    /// Sets what the buffer's pixels start out as. Defaults to [`BufferInit::Zeroed`].
+    pub fn init(mut self, init: BufferInit) -> BufferOptions {
+        self.init = init;
+        self
+    }
+
+    /// Sets what happens if the platform can't present the buffer's format. Defaults to
+    /// [`MismatchPolicy::Error`].
+    pub fn mismatch_policy(mut self, policy: MismatchPolicy) -> BufferOptions {
+        self.mismatch_policy = policy;
+        self
+    }
+}
+
+/// A rectangle of a pixel buffer and the rectangle of the window it's blitted onto, for
+/// [`PixelBuffer::blit_regions`].
+///
+/// Both positions are measured from the top-left corner. If the sizes differ, the pixels are
+/// scaled to fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlitRegion {
+    /// The position of the rectangle's top-left corner in the pixel buffer.
+    pub src_pos: Point,
+    /// The size of the rectangle in the pixel buffer.
+    pub src_size: Size,
+    /// The position of the rectangle's top-left corner in the window's client area.
+    pub dst_pos: Point,
+    /// The size the rectangle is blitted at.
+    pub dst_size: Size,
+}
+
+impl BlitRegion {
+    /// The region [`PixelBuffer::present_zoomed`] blits: the `src_size` pixels at `src_pos`,
+    /// scaled by `zoom` onto the top-left corner.
+    pub(crate) fn zoomed(src_pos: Point, src_size: Size, zoom: f32) -> BlitRegion {
+        let zoomed = |size: u32| (size as f32 * zoom).round().clamp(0.0, u32::MAX as f32) as u32;
+        BlitRegion {
+            src_pos,
+            src_size,
+            dst_pos: Point::new(0, 0),
+            dst_size: Size::new(zoomed(src_size.width), zoomed(src_size.height)),
+        }
+    }
+}
+
+/// A user-provided function that converts a pixel buffer's rows while presenting it, set with
+/// [`PixelBuffer::set_present_conversion`].
+///
+/// It's called with the rows being presented, the scratch rows to write the converted pixels to,
+/// the number of rows, and the number of bytes between the start of one row and the next in
+/// both. Rows may be stored bottom-up. A single present may call it several times, on
+/// consecutive bands of rows.
+pub type PresentConversion = Box<dyn Fn(&[u8], &mut [u8], u32, usize) + Send>;
+
+/// What a blit does when the compositor hasn't caught up with the pixel buffer's
+/// [present limit](PixelBuffer::set_present_limit).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backpressure {
+    /// Wait for the compositor to finish its next frame, then blit.
+    Block,
+    /// Don't blit, and return a [`WouldBlock`](io::ErrorKind::WouldBlock) error instead.
+    WouldBlock,
+}
+
+/// Statistics gathered during the most recent blit of a pixel buffer.
+///
+/// Useful for diagnosing slow frames, and for checking whether the buffer is being presented
+/// without any intermediate conversion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlitMetrics {
+    /// Time spent converting the buffer's contents into the format expected by the window.
+    ///
+    /// This is zero when the buffer can be presented as-is.
+    pub conversion_time: Duration,
+    /// Time spent copying the buffer's contents onto the window.
+    pub upload_time: Duration,
+    /// The number of bytes copied onto the window.
+    pub bytes_copied: usize,
+    /// The [index](PixelBuffer::frame_index) of the frame the statistics are about.
+    pub frame_index: u64,
+}
+
+/// What the platform supports when presenting onto a particular window.
+///
+/// Querying this up front lets applications adapt, e.g. by picking a smaller internal
+/// resolution, instead of discovering the platform's limits through errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Whether the buffer's alpha channel affects how it's presented. See [`AlphaBehavior`].
+    pub supports_alpha: bool,
+    /// Whether buffers can be scaled while they're presented.
+    pub supports_scaling: bool,
+    /// Whether buffers are presented straight from their own memory, without first being copied
+    /// somewhere the platform can read them.
+    ///
+    /// [`Capabilities::query`] reports this for buffers in the
+    /// [`preferred_format`](Self::preferred_format) with default settings. Converting a buffer's
+    /// contents while presenting them, e.g. for a
+    /// [color adjustment](PixelBuffer::set_color_adjustment), or storing them in memory from a
+    /// [custom allocator](PixelBuffer::with_allocator) copies them, which
+    /// [`PixelBuffer::target_capabilities`] takes into account.
+    pub zero_copy: bool,
+    /// The size, in bytes, of the largest buffer that can be created. Larger buffers fail with
+    /// [`PixelBufferCreationError::BufferTooLarge`].
+    pub max_buffer_size: u64,
+    /// The format that's presented without any conversion.
+    pub preferred_format: PixelBufferFormatType,
+    /// Whether the window is shown over a network connection, such as Remote Desktop, where
+    /// every blit costs bandwidth. See [`PixelBuffer::set_remote_depth`].
+    pub remote: bool,
+}
+
+impl Capabilities {
+    /// Queries the platform's capabilities for presenting onto `window`.
+    ///
+    /// Can return `Err` if the window has been destroyed, if its handle isn't available, or if
+    /// pixel buffers can't be presented onto it at all.
+    pub fn query<H: HasWindowHandle>(window: &H) -> Result<Capabilities, BlitError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe { platform_impl::capabilities(handle) }
+    }
+}
+
+/// What the display showing a window can reproduce, for renderers that tone-map HDR content
+/// before filling a buffer.
+///
+/// Luminances are in nits, and are `None` where the platform doesn't report them. On Windows,
+/// they're reported from Windows 10 on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayInfo {
+    /// The number of bits per color channel the display is driven with, e.g. `8` or `10`.
+    pub bits_per_color: u32,
+    /// Whether the display is in HDR mode, i.e. Windows' "Use HDR" setting is on. Presented
+    /// pixels are still interpreted as sRGB, mapped to the SDR brightness the user picked.
+    pub hdr: bool,
+    /// The lowest luminance the display can produce.
+    pub min_luminance: Option<f32>,
+    /// The highest luminance the display can produce in a small area.
+    pub max_luminance: Option<f32>,
+    /// The highest luminance the display can sustain across the whole screen.
+    pub max_full_frame_luminance: Option<f32>,
+}
+
+impl DisplayInfo {
+    /// Queries the display `window` is mostly shown on.
+    ///
+    /// Can return `Err` if the window has been destroyed, if its handle isn't available, or if
+    /// pixel buffers can't be presented onto it at all. The display can change whenever the
+    /// window is moved, so query it again when that happens.
+    pub fn query<H: HasWindowHandle>(window: &H) -> Result<DisplayInfo, BlitError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe { platform_impl::display_info(handle) }
+    }
+}
+
+/// Blits several pixel buffers onto their windows in lockstep.
+///
+/// The blits are issued back-to-back, after every window has been checked, so windows that are
+/// shown side by side (such as the screens of a video wall) change at as close to the same time
+/// as the platform allows. Buffers with [`frame_throttle`](PixelBuffer::frame_throttle) enabled
+/// wait for composition once, after all of them have been blitted. Use
+/// [`as_ref`](AsRef::as_ref) to include a [`PixelBufferTyped`].
+///
+/// If any window has been destroyed, nothing is blitted.
+///
+/// # Panics
+/// Every window must be one its buffer can be blitted onto, as with [`PixelBuffer::blit`].
+pub fn present_group(group: &[(&PixelBuffer, &dyn HasWindowHandle)]) -> Result<(), BlitError> {
+    let mut handles = Vec::with_capacity(group.len());
+    for &(buffer, window) in group {
+        handles.push((&buffer.p, window.window_handle()?.as_raw()));
+    }
+    unsafe { platform_impl::present_group(&handles) }?;
+    for &(buffer, _) in group {
+        buffer.record_frame();
+    }
+    Ok(())
 }
 
 /// A buffer of pixels that can be blitted onto a window.
@@ -20,6 +639,50 @@ pub enum PixelBufferCreationError {
 /// The pixel buffer's origin is in the top-left corner of the image.
 pub struct PixelBuffer {
     p: platform_impl::PixelBuffer,
+    alpha_mode: AlphaMode,
+    rendering_intent: RenderingIntent,
+    color_adjustment: Option<ColorAdjustment>,
+    keep_previous_frame: bool,
+    /// Copy of the buffer's contents as of the last blit, stored top-down and tightly packed.
+    previous_frame: RefCell<Option<Vec<u8>>>,
+    /// The previous frame, counted towards the process-wide memory usage.
+    previous_frame_memory: Tracked,
+    /// Everything changed since the last `present`.
+    dirty: Damage,
+    /// The number of successful presents so far.
+    frame_index: std::cell::Cell<u64>,
+}
+
+/// The most regions `Damage` keeps apart before merging them into their bounding box.
+const MAX_DAMAGE_RECTS: usize = 16;
+
+/// The regions of a pixel buffer that changed since it was last presented.
+#[derive(Debug, Clone, Default)]
+struct Damage {
+    /// The bounding box of `rects`, or `None` if nothing changed.
+    bounds: Option<Rect>,
+    /// The changed regions, none of which contains another.
+    rects: Vec<Rect>,
+}
+
+impl Damage {
+    /// Records that `rect`, which mustn't be empty, has changed.
+    fn add(&mut self, rect: Rect) {
+        if self
+            .rects
+            .iter()
+            .any(|&r| r.intersection(rect) == Some(rect))
+        {
+            return;
+        }
+        self.rects.retain(|&r| rect.intersection(r) != Some(r));
+        self.rects.push(rect);
+        let bounds = self.bounds.map_or(rect, |bounds| bounds.union(rect));
+        self.bounds = Some(bounds);
+        if self.rects.len() > MAX_DAMAGE_RECTS {
+            self.rects = vec![bounds];
+        }
+    }
 }
 
 /// A buffer of pixels with a statically-checked pixel format.
@@ -33,49 +696,1776 @@ pub struct PixelBufferTyped<P: PixelBufferFormat> {
 impl PixelBufferFormatType {
     /// The native pixel buffer format for the current plaform.
     pub const NATIVE: PixelBufferFormatType = NativeFormat::FORMAT_TYPE;
+
+    /// The index of the red, green, blue and alpha channels in a pixel of this format, if it has
+    /// them.
+    fn rgba_indices(self) -> [Option<usize>; 4] {
+        match self {
+            PixelBufferFormatType::BGR => [Some(2), Some(1), Some(0), None],
+            PixelBufferFormatType::BGRA => [Some(2), Some(1), Some(0), Some(3)],
+            PixelBufferFormatType::RGB => [Some(0), Some(1), Some(2), None],
+            PixelBufferFormatType::RGBA => [Some(0), Some(1), Some(2), Some(3)],
+        }
+    }
+
+    /// The number of bytes in a pixel of this format.
+    pub const fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelBufferFormatType::BGR | PixelBufferFormatType::RGB => 3,
+            PixelBufferFormatType::BGRA | PixelBufferFormatType::RGBA => 4,
+        }
+    }
 }
 
 impl PixelBuffer {
+    fn from_platform(p: platform_impl::PixelBuffer) -> PixelBuffer {
+        PixelBuffer {
+            p,
+            alpha_mode: AlphaMode::default(),
+            rendering_intent: RenderingIntent::default(),
+            color_adjustment: None,
+            keep_previous_frame: false,
+            previous_frame: RefCell::new(None),
+            previous_frame_memory: Tracked::default(),
+            dirty: Damage::default(),
+            frame_index: std::cell::Cell::new(0),
+        }
+        .all_dirty()
+    }
+
+    fn all_dirty(mut self) -> PixelBuffer {
+        self.mark_all_dirty();
+        self
+    }
+
     /// Initialize a new pixel buffer.
     ///
     /// Can return `Err` if the platform doesn't support the requested pixel buffer type.
-    pub fn new<H: HasRawWindowHandle>(
+    pub fn new<H: HasWindowHandle>(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+        window: &H,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe {
+            platform_impl::PixelBuffer::new(width, height, format, handle)
+                .map(PixelBuffer::from_platform)
+        }
+    }
+
+    /// Initialize a new pixel buffer laid out according to `options`.
+    ///
+    /// Can return `Err` if the platform doesn't support the requested pixel buffer type.
+    pub fn with_options<H: HasWindowHandle>(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+        window: &H,
+        options: BufferOptions,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe {
+            platform_impl::PixelBuffer::with_options(width, height, format, handle, options)
+                .map(PixelBuffer::from_platform)
+        }
+    }
+
+    /// Initialize a new pixel buffer that isn't associated with any window.
+    ///
+    /// Offscreen buffers support the whole drawing and conversion API, so they're useful for
+    /// rendering in servers and tests that don't have a display. They can still be presented
+    /// once a window has been [added](Self::add_window).
+    ///
+    /// Can return `Err` if the platform doesn't support the requested pixel buffer type.
+    pub fn new_offscreen(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        unsafe {
+            platform_impl::PixelBuffer::new_offscreen(width, height, format)
+                .map(PixelBuffer::from_platform)
+        }
+    }
+
+    /// Initialize a new pixel buffer that isn't associated with any window, laid out according
+    /// to `options`.
+    ///
+    /// Can return `Err` if the platform doesn't support the requested pixel buffer type.
+    pub fn new_offscreen_with_options(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+        options: BufferOptions,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        unsafe {
+            platform_impl::PixelBuffer::new_offscreen_with_options(width, height, format, options)
+                .map(PixelBuffer::from_platform)
+        }
+    }
+
+    /// Initialize a new pixel buffer whose pixels live in memory that can be shared with other
+    /// processes.
+    ///
+    /// This lets a helper process, such as a recorder or a sandboxed renderer, read or fill the
+    /// frame while this process keeps presenting it. See [`shared_memory`](Self::shared_memory).
+    ///
+    /// Can return `Err` if the platform doesn't support the requested pixel buffer type, or if the
+    /// shared memory can't be created.
+    pub fn new_shared<H: HasWindowHandle>(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+        window: &H,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe {
+            platform_impl::PixelBuffer::new_shared(width, height, format, handle)
+                .map(PixelBuffer::from_platform)
+        }
+    }
+
+    /// Initialize a new pixel buffer whose pixels are stored in memory from `allocator`.
+    ///
+    /// Can return `Err` if the platform doesn't support the requested pixel buffer type, or if
+    /// `allocator` fails to allocate the buffer.
+    pub fn with_allocator<H: HasWindowHandle, A: BufferAllocator + 'static>(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+        window: &H,
+        allocator: A,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe {
+            platform_impl::PixelBuffer::with_allocator(
+                width,
+                height,
+                format,
+                handle,
+                Box::new(allocator),
+                BufferOptions::new(),
+            )
+            .map(PixelBuffer::from_platform)
+        }
+    }
+
+    /// Initialize a new pixel buffer whose pixels are stored in memory from `allocator`, laid out
+    /// according to `options`.
+    ///
+    /// Can return `Err` if the platform doesn't support the requested pixel buffer type, or if
+    /// `allocator` fails to allocate the buffer.
+    pub fn with_allocator_options<H: HasWindowHandle, A: BufferAllocator + 'static>(
         width: u32,
         height: u32,
         format: PixelBufferFormatType,
         window: &H,
+        allocator: A,
+        options: BufferOptions,
     ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        let handle = window.window_handle()?.as_raw();
         unsafe {
-            platform_impl::PixelBuffer::new(width, height, format, window.raw_window_handle())
-                .map(|p| PixelBuffer { p })
+            platform_impl::PixelBuffer::with_allocator(
+                width,
+                height,
+                format,
+                handle,
+                Box::new(allocator),
+                options,
+            )
+            .map(PixelBuffer::from_platform)
         }
     }
 
     /// Blits the pixel buffer's contents onto `window`.
     ///
+    /// `window` can be the window itself or a [`WindowHandle`](raw_window_handle::WindowHandle)
+    /// borrowed from it. Either way, the borrow keeps the window alive for the duration of the
+    /// blit, so safe code can't present onto a window that's already been destroyed.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn blit<H: HasWindowHandle>(&self, window: &H) -> Result<(), BlitError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe { self.p.blit(handle) }?;
+        self.record_frame();
+        Ok(())
+    }
+
+    /// Blits the pixel buffer's contents onto `window` at `target_time`.
+    ///
+    /// The calling thread is blocked until `target_time` is reached, using the most precise timer
+    /// the platform offers. This lets applications schedule frames close to scanout, e.g. to keep
+    /// software-rendered video in sync with audio. If `target_time` has already passed, the buffer
+    /// is blitted immediately.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn present_at<H: HasWindowHandle>(
+        &self,
+        target_time: Instant,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe { self.p.present_at(target_time, handle) }?;
+        self.record_frame();
+        Ok(())
+    }
+
+    /// Fills the pixel buffer row by row with `produce_row`, blitting it onto `window` as it goes.
+    ///
+    /// `produce_row` is called with the index and contents of every row, from top to bottom, right
+    /// before the row is blitted. Rows are blitted in small bands while the next ones are being
+    /// produced, so e.g. a video decoder can overlap decoding with uploading, without having to
+    /// decode the whole frame into an intermediate buffer first.
+    ///
+    /// If a blit fails, no more rows are produced.
+    ///
     /// # Panics
-    /// The `window` passed to this function must be the same `window` passed to `new`. Failing to
-    /// do so will result in a panic.
-    pub fn blit<H: HasRawWindowHandle>(&self, window: &H) -> io::Result<()> {
-        unsafe { self.p.blit(window.raw_window_handle()) }
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn blit_streaming<H: HasWindowHandle>(
+        &mut self,
+        mut produce_row: impl FnMut(u32, &mut [u8]),
+        window: &H,
+    ) -> Result<(), BlitError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe { self.p.blit_streaming(handle, &mut produce_row) }?;
+        // Every row has just been presented.
+        self.dirty = Damage::default();
+        self.record_frame();
+        Ok(())
     }
 
     /// Blits a subsection of the pixel buffer's contents onto `window`.
     ///
+    /// The `blit_size` pixels at `src_pos` in the buffer are copied to `dst_pos` in the window's
+    /// client area. Both positions are measured from the top-left corner, the same way rows are
+    /// indexed, on every platform. The part of the rectangle that lies outside the buffer isn't
+    /// blitted.
+    ///
     /// # Panics
-    /// The `window` passed to this function must be the same `window` passed to `new`. Failing to
-    /// do so will result in a panic.
-    pub fn blit_rect<H: HasRawWindowHandle>(
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn blit_rect<H: HasWindowHandle>(
         &self,
-        src_pos: (u32, u32),
-        dst_pos: (u32, u32),
-        blit_size: (u32, u32),
+        src_pos: impl Into<Point>,
+        dst_pos: impl Into<Point>,
+        blit_size: impl Into<Size>,
         window: &H,
-    ) -> io::Result<()> {
+    ) -> Result<(), BlitError> {
+        let (src_pos, dst_pos): (Point, Point) = (src_pos.into(), dst_pos.into());
+        let blit_size: Size = blit_size.into();
+        let handle = window.window_handle()?.as_raw();
         unsafe {
-            self.p
-                .blit_rect(src_pos, dst_pos, blit_size, window.raw_window_handle())
+            self.p.blit_rect(
+                (src_pos.x, src_pos.y),
+                (dst_pos.x, dst_pos.y),
+                (blit_size.width, blit_size.height),
+                None,
+                handle,
+            )
+        }?;
+        self.record_frame();
+        Ok(())
+    }
+
+    /// Blits the `src_size` pixels at `src_pos` in the pixel buffer onto the `dst_size` rectangle
+    /// at `dst_pos` in `window`, scaling them to fit.
+    ///
+    /// Scaling picks the nearest pixel, which keeps pixel art crisp. The part of the source
+    /// rectangle that lies outside the buffer isn't blitted, and the destination shrinks with it.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn blit_rect_scaled<H: HasWindowHandle>(
+        &self,
+        src_pos: impl Into<Point>,
+        src_size: impl Into<Size>,
+        dst_pos: impl Into<Point>,
+        dst_size: impl Into<Size>,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        let region = BlitRegion {
+            src_pos: src_pos.into(),
+            src_size: src_size.into(),
+            dst_pos: dst_pos.into(),
+            dst_size: dst_size.into(),
+        };
+        self.blit_regions(&[region], window)
+    }
+
+    /// Blits several rectangles of the pixel buffer onto `window` in one present, each with its
+    /// own source and destination, e.g. for picture-in-picture views.
+    ///
+    /// Regions are blitted in order, so later ones are drawn over earlier ones where they
+    /// overlap. See [`BlitRegion`].
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn blit_regions<H: HasWindowHandle>(
+        &self,
+        regions: &[BlitRegion],
+        window: &H,
+    ) -> Result<(), BlitError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe { self.p.blit_regions(regions, Filter::Nearest, handle) }?;
+        self.record_frame();
+        Ok(())
+    }
+
+    /// Blits the `src_size` pixels at `src_pos` in the pixel buffer onto the top-left corner of
+    /// `window`, magnified by `zoom`, for magnifiers and "pixel peeping" debug views.
+    ///
+    /// The pixels are scaled while they're presented, without an intermediate copy at the
+    /// zoomed resolution. [`Filter::Nearest`] keeps individual pixels visible as crisp squares;
+    /// [`Filter::Bilinear`] smooths them instead, which on Windows uses GDI's halftoning. Zoom
+    /// factors below `1.0` shrink the region, which is best combined with
+    /// [`build_mipmaps`](Self::build_mipmaps).
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn present_zoomed<H: HasWindowHandle>(
+        &self,
+        src_pos: impl Into<Point>,
+        src_size: impl Into<Size>,
+        zoom: f32,
+        filter: Filter,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        let region = BlitRegion::zoomed(src_pos.into(), src_size.into(), zoom);
+        let handle = window.window_handle()?.as_raw();
+        unsafe { self.p.blit_regions(&[region], filter, handle) }?;
+        self.record_frame();
+        Ok(())
+    }
+
+    /// Builds a pyramid of downscaled copies of the pixel buffer, each half the size of the one
+    /// before, down to a single pixel. They take up a third of the buffer's memory.
+    ///
+    /// Presents with [`Filter::Bilinear`] that shrink the buffer to half its size or less, such as
+    /// [`present_zoomed`](Self::present_zoomed) for a thumbnail of a huge canvas, then start from
+    /// the smallest copy that's still large enough. That's faster than filtering the full
+    /// resolution, and doesn't alias, since every source pixel has been averaged in. The copies
+    /// are a snapshot: call this again after changing the buffer, or let
+    /// [`finish_writes`](Self::finish_writes) do it. [`clear_mipmaps`](Self::clear_mipmaps) goes
+    /// back to presenting from the buffer itself.
+    pub fn build_mipmaps(&mut self) {
+        self.p.build_mipmaps()
+    }
+
+    /// Frees the copies made by [`build_mipmaps`](Self::build_mipmaps).
+    pub fn clear_mipmaps(&mut self) {
+        self.p.clear_mipmaps()
+    }
+
+    /// The number of downscaled copies made by [`build_mipmaps`](Self::build_mipmaps), or `0` if
+    /// there are none.
+    pub fn mipmap_levels(&self) -> usize {
+        self.p.mipmap_levels()
+    }
+
+    /// Blits the pixel buffer onto `window` in interlaced passes, so a usable image shows up
+    /// quickly on slow connections, e.g. in a remote desktop session.
+    ///
+    /// The first pass presents every `interval`th row, each stretched over the rows below it, and
+    /// the following passes fill in the rest. Each pass is sent on its own, so instead of the
+    /// window filling top to bottom, the whole image appears at once and then sharpens. An
+    /// `interval` of `0` or `1` presents the buffer in a single pass, like [`blit`](Self::blit).
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn present_progressive<H: HasWindowHandle>(
+        &self,
+        interval: u32,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe { self.p.blit_progressive(interval, handle) }?;
+        self.record_frame();
+        Ok(())
+    }
+
+    /// Blits a subsection of the pixel buffer's contents onto `window`, filling the rest of the
+    /// window with `background`.
+    ///
+    /// Both happen in the same present, so the window doesn't flicker the way it would when
+    /// clearing it and blitting separately. Only `background`'s color channels are used.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn blit_rect_with_background<H: HasWindowHandle>(
+        &self,
+        src_pos: impl Into<Point>,
+        dst_pos: impl Into<Point>,
+        blit_size: impl Into<Size>,
+        background: impl Into<Color>,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        let (src_pos, dst_pos): (Point, Point) = (src_pos.into(), dst_pos.into());
+        let blit_size: Size = blit_size.into();
+        let Color { r, g, b, .. } = background.into();
+        let handle = window.window_handle()?.as_raw();
+        unsafe {
+            self.p.blit_rect(
+                (src_pos.x, src_pos.y),
+                (dst_pos.x, dst_pos.y),
+                (blit_size.width, blit_size.height),
+                Some([r, g, b]),
+                handle,
+            )
+        }?;
+        self.record_frame();
+        Ok(())
+    }
+
+    /// Blits everything that changed since the last `present` onto `window`, or nothing if the
+    /// buffer hasn't changed.
+    ///
+    /// Writes through the buffer's `_mut` accessors are tracked automatically, at the granularity
+    /// of a row for [`row_mut`](Self::row_mut) and of the whole buffer otherwise. Changes made
+    /// some other way, e.g. through a raw pointer, can be recorded with
+    /// [`mark_dirty`](Self::mark_dirty). A newly-created buffer counts as changed everywhere.
+    ///
+    /// Unlike [`blit`](Self::blit), which presents a snapshot of the whole buffer, this consumes
+    /// the tracked changes, so it takes `&mut self`. If the blit fails, the changes are kept and
+    /// presented again next time.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn present<H: HasWindowHandle>(&mut self, window: &H) -> Result<(), BlitError> {
+        let dirty = mem::take(&mut self.dirty);
+        if let Some(rect) = dirty.bounds {
+            if let Err(e) = self.blit_rect(rect.pos, rect.pos, rect.size, window) {
+                self.dirty = dirty;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Asks for everything that changed to be presented onto `window` by the next
+    /// [`flush_presents`](Self::flush_presents), instead of right away.
+    ///
+    /// Immediate-mode GUIs invalidate many widgets in one turn of the event loop. Deferring the
+    /// present for each of them, and flushing once the turn is over, e.g. on winit's
+    /// `AboutToWait`, blits the combined changes once instead of once per widget. Deferring onto
+    /// the same window again does nothing.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn defer_present<H: HasWindowHandle>(&mut self, window: &H) -> Result<(), BlitError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe { self.p.defer_present(handle) }
+    }
+
+    /// Presents everything that changed since the last present onto every window a present was
+    /// [deferred](Self::defer_present) for, in a single batch. Does nothing if none were.
+    ///
+    /// Like [`present`](Self::present), this consumes the changes. If a blit fails, the changes
+    /// are kept, and the windows that weren't blitted onto yet stay deferred, except for the one
+    /// that failed.
+    pub fn flush_presents(&mut self) -> Result<(), BlitError> {
+        if !self.p.has_deferred_presents() {
+            return Ok(());
+        }
+        let dirty = mem::take(&mut self.dirty);
+        if let Err(e) = unsafe { self.p.flush_presents(dirty.bounds.map(Into::into)) } {
+            self.dirty = dirty;
+            return Err(e);
+        }
+        if dirty.bounds.is_some() {
+            self.record_frame();
+        }
+        Ok(())
+    }
+
+    /// Presents everything that changed since the last `present` to a [`CustomBackend`] instead
+    /// of a window, or does nothing if the buffer hasn't changed.
+    ///
+    /// This shares its damage tracking with [`present`](Self::present), so a buffer is usually
+    /// only presented one way. Works with [offscreen](Self::new_offscreen) buffers. If the
+    /// backend fails, the changes are kept and presented again next time.
+    pub fn present_to(&mut self, backend: &mut dyn CustomBackend) -> io::Result<()> {
+        let dirty = mem::take(&mut self.dirty);
+        if dirty.bounds.is_some() {
+            if let Err(e) = backend.present(BackendRows::new(&self.p), &dirty.rects) {
+                self.dirty = dirty;
+                return Err(e);
+            }
+            self.record_frame();
+        }
+        Ok(())
+    }
+
+    /// Like [`present`](Self::present), but first resizes the pixel buffer to `size` if it has a
+    /// different size, e.g. the window's current inner size.
+    ///
+    /// Following the window's resize events with this can't present a buffer of a stale size,
+    /// which would be cropped or leave part of the window unpainted. Resizing recreates the
+    /// buffer with the same format, storage and settings. The part of the old contents that fits
+    /// is kept, the rest is undefined, and the whole buffer is presented.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn present_sized<H: HasWindowHandle>(
+        &mut self,
+        window: &H,
+        size: impl Into<Size>,
+    ) -> Result<(), BlitError> {
+        let size = size.into();
+        if size != Size::new(self.width(), self.height()) {
+            let handle = window.window_handle()?.as_raw();
+            let resized = unsafe { self.p.resized(size.width, size.height, handle) };
+            let resized = resized.map_err(|error| match error {
+                PixelBufferCreationError::WindowHandle(error) => BlitError::WindowHandle(error),
+                error => BlitError::Io(io::Error::other(error)),
+            })?;
+            let old = mem::replace(&mut self.p, resized);
+            for (dst, src) in self.p.rows_mut().zip(old.rows()) {
+                let len = dst.len().min(src.len());
+                dst[..len].copy_from_slice(&src[..len]);
+            }
+            drop(old);
+            *self.previous_frame.get_mut() = None;
+            self.previous_frame_memory.set(0);
+            self.dirty = Damage::default();
+            self.mark_all_dirty();
+        }
+        self.present(window)
+    }
+
+    /// Presents the whole pixel buffer onto every window it was created for or
+    /// [added](Self::add_window) to, for use in a panic hook or crash handler.
+    ///
+    /// Draw an error screen into the buffer beforehand, and call this once things have gone
+    /// wrong to make sure it actually reaches the screen. Unlike [`present`](Self::present), it
+    /// doesn't allocate, panic, lock or take a window handle, and it skips everything that could:
+    /// the [conversion](Self::set_present_conversion), [color adjustment](Self::set_color_adjustment), present
+    /// limits and error reporting are all bypassed, and the dirty region is left alone.
+    ///
+    /// Returns whether every window was presented to. Windows that have been destroyed count as
+    /// failures, but don't stop the others from being presented to.
+    pub fn emergency_present(&self) -> bool {
+        unsafe { self.p.emergency_present() }
+    }
+
+    /// Records that the region with its top-left corner at `pos` has changed, so the next
+    /// [`present`](Self::present) blits it. The region is clamped to the buffer.
+    pub fn mark_dirty(&mut self, pos: impl Into<Point>, size: impl Into<Size>) {
+        let rect = Rect::new(pos, size).clamp((self.width(), self.height()));
+        if !rect.is_empty() {
+            self.dirty.add(rect);
+        }
+    }
+
+    /// Records that the whole pixel buffer has changed.
+    pub fn mark_all_dirty(&mut self) {
+        self.mark_dirty((0, 0), (self.width(), self.height()));
+    }
+
+    /// The bounding box of everything that changed since the last [`present`](Self::present), or
+    /// `None` if nothing has.
+    pub fn dirty_region(&self) -> Option<Rect> {
+        self.dirty.bounds
+    }
+
+    /// Takes the regions that changed since the last [`present`](Self::present), so they can be
+    /// forwarded to other systems that need to know what changed, such as remote-desktop encoders.
+    ///
+    /// Each region passed to [`mark_dirty`](Self::mark_dirty), or written through an accessor, is
+    /// returned in the order it changed, except for regions inside ones that changed too. Past
+    /// a handful of regions, they're merged into their bounding box. The regions are cleared, so
+    /// a `present` right afterwards doesn't blit anything. Use
+    /// [`dirty_region`](Self::dirty_region) to look at their bounding box without consuming them.
+    pub fn take_damage(&mut self) -> Vec<Rect> {
+        mem::take(&mut self.dirty).rects
+    }
+
+    /// Allows the pixel buffer to be blitted onto `window`, in addition to the window it was created
+    /// for.
+    ///
+    /// This lets one buffer be presented to several windows, e.g. for mirror or preview windows,
+    /// without rendering the same frame twice. Returns an error if `window` is gone or can't
+    /// display the buffer's format. Adding a window that's already been added does nothing.
+    pub fn add_window<H: HasWindowHandle>(&mut self, window: &H) -> Result<(), BlitError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe { self.p.add_window(handle) }
+    }
+
+    /// Stops allowing the pixel buffer to be blitted onto `window`.
+    pub fn remove_window<H: HasWindowHandle>(&mut self, window: &H) {
+        if let Ok(handle) = window.window_handle() {
+            unsafe { self.p.remove_window(handle.as_raw()) }
+        }
+    }
+
+    /// Stops presenting the pixel buffer onto `window`, and has the window repaint itself so it
+    /// no longer shows the buffer's last frame.
+    ///
+    /// Like [`remove_window`](Self::remove_window), the buffer can't be blitted onto `window`
+    /// afterwards unless it's [added](Self::add_window) again. Presents copy the buffer's pixels
+    /// onto the window, so dropping the buffer without detaching it is always safe; the window
+    /// just keeps showing the last frame until it's repainted.
+    ///
+    /// Returns an error if the window handle isn't available, or if the window can't be
+    /// repainted. A destroyed window is only removed.
+    pub fn detach<H: HasWindowHandle>(&mut self, window: &H) -> Result<(), BlitError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe { self.p.detach(handle) }
+    }
+
+    /// What presenting the buffer onto its window does with the alpha channel.
+    ///
+    /// When this is [`AlphaBehavior::Ignored`], computing meaningful alpha values is wasted work.
+    pub fn alpha_behavior(&self) -> AlphaBehavior {
+        self.p.alpha_behavior()
+    }
+
+    /// How the alpha channel of the buffer's pixels is interpreted.
+    ///
+    /// Defaults to [`AlphaMode::Straight`].
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    /// Sets how the alpha channel of the buffer's pixels is interpreted.
+    ///
+    /// This only changes how the crate's blending and conversion routines treat the buffer's
+    /// contents; the stored pixels are left untouched. Use
+    /// [`PixelBufferTyped::convert_alpha_mode`] to convert them as well.
+    pub fn set_alpha_mode(&mut self, alpha_mode: AlphaMode) {
+        self.alpha_mode = alpha_mode;
+    }
+
+    /// Which color space the drawing helpers blend translucent colors in.
+    ///
+    /// Defaults to [`RenderingIntent::Gamma`].
+    pub fn rendering_intent(&self) -> RenderingIntent {
+        self.rendering_intent
+    }
+
+    /// Sets which color space the drawing helpers blend translucent colors in, e.g. to match
+    /// the blended edges of another toolkit drawing into the same window.
+    pub fn set_rendering_intent(&mut self, intent: RenderingIntent) {
+        self.rendering_intent = intent;
+    }
+
+    /// Sets whether the pixel buffer keeps a copy of its contents each time it's blitted.
+    ///
+    /// When enabled, the most recently blitted frame can be read through
+    /// [`previous_frame`](Self::previous_frame), which is handy for temporal effects such as motion
+    /// blur or damage diffing. Disabling this frees the stored frame.
+    pub fn set_keep_previous_frame(&mut self, keep: bool) {
+        self.keep_previous_frame = keep;
+        if !keep {
+            *self.previous_frame.get_mut() = None;
+            self.previous_frame_memory.set(0);
+        }
+    }
+
+    /// The pixel buffer's contents as of its most recent successful blit.
+    ///
+    /// Returns `None` unless [`set_keep_previous_frame`](Self::set_keep_previous_frame) has been
+    /// enabled and the buffer has been blitted since. Blits made while the returned frame is still
+    /// alive don't replace it, so it's as of the last blit before it was borrowed.
+    pub fn previous_frame(&self) -> Option<PreviousFrame<'_>> {
+        let frame = Ref::filter_map(self.previous_frame.borrow(), |frame| {
+            frame.as_ref().map(|frame| &frame[..])
+        })
+        .ok()?;
+        Some(PreviousFrame {
+            bytes: frame,
+            width: self.width(),
+            height: self.height(),
+            bytes_per_pixel: self.bytes_per_pixel(),
+        })
+    }
+
+    fn record_frame(&self) {
+        self.frame_index.set(self.frame_index.get() + 1);
+        #[cfg(feature = "tracy")]
+        if let Some(client) = tracy_client::Client::running() {
+            client.secondary_frame_mark(tracy_client::frame_name!("winit-blit present"));
+        }
+        if self.keep_previous_frame {
+            // A frame returned by `previous_frame` may still be borrowed, in which case it's kept.
+            let mut frame = match self.previous_frame.try_borrow_mut() {
+                Ok(frame) => frame,
+                Err(_) => return,
+            };
+            let frame = frame.get_or_insert_with(Vec::new);
+            frame.clear();
+            for row in self.rows() {
+                frame.extend_from_slice(row);
+            }
+            self.previous_frame_memory.set(frame.capacity());
+        }
+    }
+
+    /// Fills the pixel buffer with `window`'s current contents.
+    ///
+    /// The window's client area is drawn into the buffer starting at its top-left corner, and
+    /// anything that doesn't fit is cut off. This is a starting point for effects like magnifiers
+    /// or freeze frames that work on what's currently displayed. `window` doesn't need to be one
+    /// the buffer blits onto.
+    pub fn capture_window<H: HasWindowHandle>(&mut self, window: &H) -> Result<(), BlitError> {
+        let handle = window.window_handle()?.as_raw();
+        self.mark_all_dirty();
+        unsafe { self.p.capture_window(handle) }
+    }
+
+    /// Copies the region with its top-left corner at `pos` to the clipboard as an image.
+    ///
+    /// Returns an error if the region doesn't fit inside the buffer, or if the clipboard can't be
+    /// accessed.
+    pub fn copy_region_to_clipboard(
+        &self,
+        pos: impl Into<Point>,
+        size: impl Into<Size>,
+    ) -> io::Result<()> {
+        let (pos, size): (Point, Size) = (pos.into(), size.into());
+        unsafe {
+            self.p
+                .copy_to_clipboard((pos.x, pos.y), (size.width, size.height))
+        }
+    }
+
+    /// Queries the platform's capabilities for presenting the buffer onto `window`.
+    ///
+    /// The same as [`Capabilities::query`], except that [`zero_copy`](Capabilities::zero_copy)
+    /// reflects how the buffer is presented with its current format and settings.
+    pub fn target_capabilities<H: HasWindowHandle>(
+        &self,
+        window: &H,
+    ) -> Result<Capabilities, BlitError> {
+        Ok(Capabilities {
+            zero_copy: self.p.is_zero_copy(),
+            ..Capabilities::query(window)?
+        })
+    }
+
+    /// Queries the display `window` is shown on, so the buffer's contents can be tone-mapped for
+    /// it before they're drawn. The same as [`DisplayInfo::query`].
+    pub fn target_display_info<H: HasWindowHandle>(
+        &self,
+        window: &H,
+    ) -> Result<DisplayInfo, BlitError> {
+        DisplayInfo::query(window)
+    }
+
+    /// Statistics about the most recent successful blit.
+    ///
+    /// Before the first blit, every field is zero.
+    pub fn metrics(&self) -> BlitMetrics {
+        BlitMetrics {
+            frame_index: self.frame_index(),
+            ..self.p.metrics()
+        }
+    }
+
+    /// The number of frames presented so far, which is also the index of the most recent one,
+    /// counting from 1. It's `0` before the first present.
+    ///
+    /// Every successful blit or present counts, including [`present_to`](Self::present_to) and
+    /// [`present_group`], while presents that had nothing to do don't. Comparing it with the
+    /// [`frame_index`](BlitMetrics::frame_index) of the [metrics](Self::metrics), or recording
+    /// it alongside input timestamps, ties events to the frame that displayed their result,
+    /// without keeping a counter next to the buffer.
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index.get()
+    }
+
+    /// The adjustment applied to the buffer's colors while blitting it, if any.
+    pub fn color_adjustment(&self) -> Option<ColorAdjustment> {
+        self.color_adjustment
+    }
+
+    /// Sets an adjustment applied to the buffer's colors while blitting it, without changing the
+    /// buffer's contents.
+    ///
+    /// The adjusted pixels are written to a scratch copy during the blit, which shows up as
+    /// [`conversion_time`](BlitMetrics::conversion_time). Pass `None` to blit the buffer as-is.
+    pub fn set_color_adjustment(&mut self, adjustment: Option<ColorAdjustment>) {
+        self.color_adjustment = adjustment;
+        self.p.set_color_lut(
+            adjustment
+                .filter(|adjustment| *adjustment != ColorAdjustment::IDENTITY)
+                .map(|adjustment| adjustment.lut()),
+        );
+    }
+
+    /// Sets a function that converts the buffer's rows while presenting it, e.g. one with
+    /// hand-tuned SIMD or support for an exotic source layout. Pass `None` to use the built-in
+    /// conversion again.
+    ///
+    /// The function replaces the [color adjustment](Self::set_color_adjustment) while it's set.
+    /// Its output must be in the buffer's own format, since that's what the window is given on
+    /// Windows. Like the built-in conversion, it writes to the
+    /// [scratch space](Self::scratch_capacity), and is subject to
+    /// [`set_allow_conversion`](Self::set_allow_conversion).
+    pub fn set_present_conversion(&mut self, conversion: Option<PresentConversion>) {
+        self.p.set_present_conversion(conversion)
+    }
+
+    /// The capacity, in bytes, of the scratch space used for converting the buffer's contents
+    /// while blitting it.
+    ///
+    /// Conversion is only needed when a [color adjustment](Self::set_color_adjustment), a
+    /// [present conversion](Self::set_present_conversion) or a
+    /// [remote depth](Self::set_remote_depth) is set. The scratch space is kept between blits, so
+    /// it's only allocated once, and is freed when all of them are removed.
+    pub fn scratch_capacity(&self) -> usize {
+        self.p.scratch_capacity()
+    }
+
+    /// The number of bytes the pixel buffer holds: its pixels, its scratch space and its
+    /// [previous frame](Self::previous_frame).
+    ///
+    /// See [`total_memory_usage`] for the usage of every buffer in the process.
+    pub fn memory_usage(&self) -> usize {
+        self.p.memory_usage() + self.previous_frame_memory.get()
+    }
+
+    /// Allocates enough scratch space to convert the entire buffer, so that no blit has to.
+    pub fn reserve_scratch(&mut self) {
+        self.p.reserve_scratch()
+    }
+
+    /// Frees the scratch space. It's allocated again by the next blit that needs it.
+    pub fn release_scratch(&mut self) {
+        self.p.release_scratch()
+    }
+
+    /// The color depth the buffer is reduced to while blitting over a remote connection, if any.
+    pub fn remote_depth(&self) -> Option<(ChannelDepth, ConversionOptions)> {
+        self.p.remote_depth()
+    }
+
+    /// Sets a color depth to reduce the buffer's colors to while blitting, dithered according to
+    /// the conversion options, but only while the window is shown over a network connection.
+    ///
+    /// Remoting protocols compress what's presented, and fewer distinct colors compress far
+    /// better, which can dramatically reduce the bandwidth used by applications running over
+    /// Remote Desktop. Local blits are unaffected. [`Capabilities::remote`] tells whether the
+    /// reduction currently applies. The buffer's contents are left untouched, and the reduction
+    /// is skipped if [conversion isn't allowed](Self::set_allow_conversion).
+    pub fn set_remote_depth(&mut self, depth: Option<(ChannelDepth, ConversionOptions)>) {
+        self.p.set_remote_depth(depth)
+    }
+
+    /// Whether blits may convert the buffer's contents before presenting them.
+    pub fn allow_conversion(&self) -> bool {
+        self.p.allow_conversion()
+    }
+
+    /// Sets whether blits may convert the buffer's contents before presenting them. Defaults to
+    /// `true`.
+    ///
+    /// When disabled, blits that would need to convert the buffer's contents fail with an
+    /// [`Unsupported`](io::ErrorKind::Unsupported) error instead, which guarantees that presenting
+    /// never allocates or copies the buffer.
+    pub fn set_allow_conversion(&mut self, allow: bool) {
+        self.p.set_allow_conversion(allow)
+    }
+
+    /// Whether blits wait for the next frame to be composited before returning.
+    pub fn frame_throttle(&self) -> bool {
+        self.p.frame_throttle()
+    }
+
+    /// Sets whether blits wait for the next frame to be composited before returning.
+    ///
+    /// Throttling paces rendering to the display's refresh rate. Disabling it lets benchmarks and
+    /// latency-sensitive tools present as fast as possible. Throttling is disabled by default.
+    pub fn set_frame_throttle(&mut self, throttle: bool) {
+        self.p.set_frame_throttle(throttle)
+    }
+
+    /// The token that cancels blits of the pixel buffer, if any.
+    pub fn cancel_token(&self) -> Option<&CancelToken> {
+        self.p.cancel_token()
+    }
+
+    /// Attaches a token that cancels blits of the pixel buffer, or detaches it with `None`.
+    ///
+    /// A blit checks the token before it starts, between bands of rows while converting the
+    /// buffer's contents, and once more before handing them to the window. A cancelled blit
+    /// fails with an [`Interrupted`](io::ErrorKind::Interrupted) error, and leaves the window as
+    /// it was; [`present`](Self::present) keeps the changes, so they're presented again next
+    /// time. Copying the pixels to the window, and any scaling done by the platform along the
+    /// way, can't be interrupted once it's started.
+    pub fn set_cancel_token(&mut self, token: Option<CancelToken>) {
+        self.p.set_cancel_token(token)
+    }
+
+    /// The most presents that may wait for the compositor at once, and what happens when a blit
+    /// would exceed that. `None` by default.
+    pub fn present_limit(&self) -> Option<(NonZeroU32, Backpressure)> {
+        self.p.present_limit()
+    }
+
+    /// Limits how many presents may wait for the compositor at once.
+    ///
+    /// Without a limit, an application that blits faster than the display refreshes spends CPU
+    /// time on frames that are never shown. With one, a blit beyond the limit either waits for
+    /// the compositor's next frame or fails with [`WouldBlock`](io::ErrorKind::WouldBlock),
+    /// depending on the [`Backpressure`]. Every blit counts as one present, however small its
+    /// rectangle.
+    ///
+    /// Only applies while desktop composition is on. Redundant with
+    /// [`frame_throttle`](Self::set_frame_throttle), which waits after every blit anyway.
+    pub fn set_present_limit(&mut self, limit: Option<(NonZeroU32, Backpressure)>) {
+        self.p.set_present_limit(limit)
+    }
+
+    /// The shared memory backing the pixel buffer.
+    ///
+    /// Returns `None` unless the buffer was created with `new_shared`. Another process writing to
+    /// the memory while this one reads or blits the buffer will see torn frames; synchronizing
+    /// access is up to the application.
+    pub fn shared_memory(&self) -> Option<SharedMemory> {
+        self.p.shared_memory()
+    }
+
+    /// The total number of bits in an individual pixel.
+    ///
+    /// Will always be a multiple of `8`.
+    pub fn bits_per_pixel(&self) -> usize {
+        self.p.bits_per_pixel()
+    }
+
+    /// The total number of bytes in an individual pixel.
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.p.bytes_per_pixel()
+    }
+
+    /// The width, in pixels, of the pixel buffer.
+    pub fn width(&self) -> u32 {
+        self.p.width()
+    }
+
+    /// The length, in bytes, of a single row of the pixel buffer.
+    pub fn row_len(&self) -> usize {
+        self.p.row_len()
+    }
+
+    /// The signed distance, in bytes, from the start of a row to the start of the row below it
+    /// in the memory the platform presents from, e.g. the [shared memory](Self::shared_memory)
+    /// or memory from a [`BufferAllocator`].
+    ///
+    /// Its magnitude is [`row_len`](Self::row_len), which includes any padding the platform
+    /// requires, so rows mustn't be assumed to be tightly packed. On Windows it's negative, since
+    /// bitmaps are stored bottom-up: the top row comes last in memory.
+    pub fn backend_stride(&self) -> isize {
+        self.p.row_pitch()
+    }
+
+    /// The format of the pixels in the memory the platform presents from.
+    ///
+    /// Code writing into the backing memory directly has to produce pixels in this format for
+    /// them to show up with the right colors. It's the buffer's own format, unless the buffer was
+    /// created with [`MismatchPolicy::Reinterpret`], whose bytes are presented as the platform's
+    /// format. Buffers created with [`MismatchPolicy::Convert`] hold their own format, e.g.
+    /// red-green-blue pixels on Windows, and are converted while they're presented.
+    pub fn backend_format(&self) -> PixelBufferFormatType {
+        self.p.backend_format()
+    }
+
+    /// The height, in pixels, of the pixel buffer.
+    pub fn height(&self) -> u32 {
+        self.p.height()
+    }
+
+    /// Gets the row at the particular height.
+    pub fn row(&self, row: u32) -> Option<&[u8]> {
+        self.p.row(row)
+    }
+
+    /// Mutably gets the row at the particular height.
+    pub fn row_mut(&mut self, row: u32) -> Option<&mut [u8]> {
+        self.mark_dirty((0, row), (self.width(), 1));
+        self.p.row_mut(row)
+    }
+
+    /// Iterate through all rows in the pixel buffer.
+    pub fn rows<'a>(&'a self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a [u8]> {
+        self.p.rows()
+    }
+
+    /// Mutably iterate through all rows in the pixel buffer.
+    pub fn rows_mut<'a>(
+        &'a mut self,
+    ) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a mut [u8]> {
+        self.mark_all_dirty();
+        self.p.rows_mut()
+    }
+
+    /// Mutably gets the row at the particular height, as memory that's only meant to be written.
+    ///
+    /// This is for buffers created with [`BufferInit::Uninit`], whose contents are unspecified:
+    /// a full-frame renderer can hand the row straight to code that fills `MaybeUninit` memory,
+    /// such as a decoder, without clearing it first. The pixels can be read as usual afterwards.
+    ///
+    /// # Safety
+    /// Every value stored into the row must be initialized. Storing `MaybeUninit::uninit()`
+    /// leaves uninitialized bytes behind, which the buffer's other accessors would then read.
+    pub unsafe fn row_uninit_mut(&mut self, row: u32) -> Option<&mut [MaybeUninit<u8>]> {
+        self.row_mut(row).map(|row| as_uninit_mut(row))
+    }
+
+    /// Mutably iterate through all rows in the pixel buffer, as memory that's only meant to be
+    /// written.
+    ///
+    /// See [`row_uninit_mut`](Self::row_uninit_mut).
+    ///
+    /// # Safety
+    /// Every value stored into the rows must be initialized.
+    pub unsafe fn rows_uninit_mut(
+        &mut self,
+    ) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &mut [MaybeUninit<u8>]> + '_ {
+        self.rows_mut().map(|row| as_uninit_mut(row))
+    }
+
+    /// Iterate through all rows in the pixel buffer.
+    #[cfg(feature = "rayon")]
+    pub fn par_rows<'a>(&'a self) -> impl IndexedParallelIterator<Item = &'a [u8]> {
+        self.p.par_rows()
+    }
+
+    /// Mutably iterate through all rows in the pixel buffer.
+    #[cfg(feature = "rayon")]
+    pub fn par_rows_mut<'a>(&'a mut self) -> impl IndexedParallelIterator<Item = &'a mut [u8]> {
+        self.mark_all_dirty();
+        self.p.par_rows_mut()
+    }
+
+    /// Iterate through the pixel buffer in bands of `rows_per_chunk` rows, starting at the top.
+    ///
+    /// The last band may have fewer than `rows_per_chunk` rows.
+    ///
+    /// # Panics
+    /// Panics if `rows_per_chunk` is `0`.
+    pub fn row_chunks(
+        &self,
+        rows_per_chunk: usize,
+    ) -> impl ExactSizeIterator
+           + DoubleEndedIterator<Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &[u8]>>
+    {
+        self.p.row_chunks(rows_per_chunk)
+    }
+
+    /// Mutably iterate through the pixel buffer in bands of `rows_per_chunk` rows, starting at the
+    /// top.
+    ///
+    /// The last band may have fewer than `rows_per_chunk` rows.
+    ///
+    /// # Panics
+    /// Panics if `rows_per_chunk` is `0`.
+    pub fn row_chunks_mut(
+        &mut self,
+        rows_per_chunk: usize,
+    ) -> impl ExactSizeIterator
+           + DoubleEndedIterator<
+        Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &mut [u8]>,
+    > {
+        self.mark_all_dirty();
+        self.p.row_chunks_mut(rows_per_chunk)
+    }
+
+    /// Iterate through the pixel buffer in bands of `rows_per_chunk` rows.
+    ///
+    /// Picking a band size that fits in cache lets each thread work on several rows at a time,
+    /// rather than synchronizing after every row.
+    ///
+    /// # Panics
+    /// Panics if `rows_per_chunk` is `0`.
+    #[cfg(feature = "rayon")]
+    pub fn par_row_chunks(
+        &self,
+        rows_per_chunk: usize,
+    ) -> impl IndexedParallelIterator<Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &[u8]>>
+    {
+        self.p.par_row_chunks(rows_per_chunk)
+    }
+
+    /// Mutably iterate through the pixel buffer in bands of `rows_per_chunk` rows.
+    ///
+    /// Picking a band size that fits in cache lets each thread work on several rows at a time,
+    /// rather than synchronizing after every row.
+    ///
+    /// # Panics
+    /// Panics if `rows_per_chunk` is `0`.
+    #[cfg(feature = "rayon")]
+    pub fn par_row_chunks_mut(
+        &mut self,
+        rows_per_chunk: usize,
+    ) -> impl IndexedParallelIterator<
+        Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &mut [u8]>,
+    > {
+        self.mark_all_dirty();
+        self.p.par_row_chunks_mut(rows_per_chunk)
+    }
+
+    /// Iterate through all columns in the pixel buffer, from left to right.
+    ///
+    /// Each column yields its pixels from top to bottom. Consecutive pixels in a column are a whole
+    /// row apart in memory, so prefer [`rows`](Self::rows) where possible.
+    pub fn columns(
+        &self,
+    ) -> impl ExactSizeIterator
+           + DoubleEndedIterator<Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &[u8]>>
+    {
+        let bytes_per_pixel = self.bytes_per_pixel();
+        (0..self.width() as usize).map(move |x| {
+            self.rows()
+                .map(move |row| &row[x * bytes_per_pixel..(x + 1) * bytes_per_pixel])
+        })
+    }
+
+    /// Mutably iterate through all columns in the pixel buffer, from left to right.
+    ///
+    /// Each column yields its pixels from top to bottom. Consecutive pixels in a column are a whole
+    /// row apart in memory, so prefer [`rows_mut`](Self::rows_mut) where possible.
+    pub fn columns_mut(
+        &mut self,
+    ) -> impl ExactSizeIterator
+           + DoubleEndedIterator<
+        Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &mut [u8]>,
+    > {
+        self.mark_all_dirty();
+        let bytes_per_pixel = self.bytes_per_pixel();
+        let row_pitch = self.p.row_pitch();
+        let height = self.height();
+        let top = self.p.top_row_ptr();
+        (0..self.width() as usize).map(move |x| {
+            (0..height).map(move |y| unsafe {
+                // Every pixel is handed out exactly once, so none of these slices alias, and
+                // `self` stays mutably borrowed for as long as any of them are alive.
+                let pixel = top.offset(y as isize * row_pitch).add(x * bytes_per_pixel);
+                slice::from_raw_parts_mut(pixel, bytes_per_pixel)
+            })
+        })
+    }
+}
+
+impl<P: PixelBufferFormat> AsRef<PixelBuffer> for PixelBufferTyped<P> {
+    fn as_ref(&self) -> &PixelBuffer {
+        &self.p
+    }
+}
+
+impl<P: PixelBufferFormat> PixelBufferTyped<P> {
+    /// Initialize a new pixel buffer.
+    ///
+    /// Can return `Err` if the platform doesn't support the requested pixel buffer type.
+    pub fn new<H: HasWindowHandle>(
+        width: u32,
+        height: u32,
+        window: &H,
+    ) -> Result<PixelBufferTyped<P>, PixelBufferCreationError> {
+        Ok(PixelBufferTyped {
+            p: PixelBuffer::new(width, height, P::FORMAT_TYPE, window)?,
+            _format: PhantomData,
+        })
+    }
+
+    /// Initialize a new pixel buffer laid out according to `options`.
+    ///
+    /// See [`PixelBuffer::with_options`].
+    pub fn with_options<H: HasWindowHandle>(
+        width: u32,
+        height: u32,
+        window: &H,
+        options: BufferOptions,
+    ) -> Result<PixelBufferTyped<P>, PixelBufferCreationError> {
+        Ok(PixelBufferTyped {
+            p: PixelBuffer::with_options(width, height, P::FORMAT_TYPE, window, options)?,
+            _format: PhantomData,
+        })
+    }
+
+    /// Initialize a new pixel buffer that isn't associated with any window.
+    ///
+    /// See [`PixelBuffer::new_offscreen`].
+    pub fn new_offscreen(
+        width: u32,
+        height: u32,
+    ) -> Result<PixelBufferTyped<P>, PixelBufferCreationError> {
+        Ok(PixelBufferTyped {
+            p: PixelBuffer::new_offscreen(width, height, P::FORMAT_TYPE)?,
+            _format: PhantomData,
+        })
+    }
+
+    /// Initialize a new pixel buffer whose pixels live in memory that can be shared with other
+    /// processes.
+    ///
+    /// See [`PixelBuffer::new_shared`].
+    pub fn new_shared<H: HasWindowHandle>(
+        width: u32,
+        height: u32,
+        window: &H,
+    ) -> Result<PixelBufferTyped<P>, PixelBufferCreationError> {
+        Ok(PixelBufferTyped {
+            p: PixelBuffer::new_shared(width, height, P::FORMAT_TYPE, window)?,
+            _format: PhantomData,
+        })
+    }
+
+    /// Initialize a new pixel buffer whose pixels are stored in memory from `allocator`.
+    ///
+    /// Can return `Err` if the platform doesn't support the requested pixel buffer type, or if
+    /// `allocator` fails to allocate the buffer.
+    pub fn with_allocator<H: HasWindowHandle, A: BufferAllocator + 'static>(
+        width: u32,
+        height: u32,
+        window: &H,
+        allocator: A,
+    ) -> Result<PixelBufferTyped<P>, PixelBufferCreationError> {
+        Ok(PixelBufferTyped {
+            p: PixelBuffer::with_allocator(width, height, P::FORMAT_TYPE, window, allocator)?,
+            _format: PhantomData,
+        })
+    }
+
+    /// Initialize a new pixel buffer whose pixels are stored in memory from `allocator`, laid out
+    /// according to `options`.
+    ///
+    /// See [`PixelBuffer::with_allocator_options`].
+    pub fn with_allocator_options<H: HasWindowHandle, A: BufferAllocator + 'static>(
+        width: u32,
+        height: u32,
+        window: &H,
+        allocator: A,
+        options: BufferOptions,
+    ) -> Result<PixelBufferTyped<P>, PixelBufferCreationError> {
+        Ok(PixelBufferTyped {
+            p: PixelBuffer::with_allocator_options(
+                width,
+                height,
+                P::FORMAT_TYPE,
+                window,
+                allocator,
+                options,
+            )?,
+            _format: PhantomData,
+        })
+    }
+
+    /// Initialize a new pixel buffer.
+    ///
+    /// The pixel format is statically checked to be supported by the platform.
+    ///
+    /// # Panics
+    /// Panics if `window`'s handle isn't available, or if the buffer can't be allocated. Use
+    /// [`new`](Self::new) to handle those cases.
+    pub fn new_supported<H: HasWindowHandle>(
+        width: u32,
+        height: u32,
+        window: &H,
+    ) -> PixelBufferTyped<P>
+    where
+        P: PixelBufferFormatSupported,
+    {
+        Self::new(width, height, window).unwrap()
+    }
+
+    /// Blits the pixel buffer's contents onto `window`.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn blit<H: HasWindowHandle>(&self, window: &H) -> Result<(), BlitError> {
+        self.p.blit(window)
+    }
+
+    /// Blits the pixel buffer's contents onto `window` at `target_time`.
+    ///
+    /// The calling thread is blocked until `target_time` is reached, using the most precise timer
+    /// the platform offers. If `target_time` has already passed, the buffer is blitted immediately.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn present_at<H: HasWindowHandle>(
+        &self,
+        target_time: Instant,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        self.p.present_at(target_time, window)
+    }
+
+    /// Fills the pixel buffer row by row with `produce_row`, blitting it onto `window` as it goes.
+    ///
+    /// See [`PixelBuffer::blit_streaming`].
+    pub fn blit_streaming<H: HasWindowHandle>(
+        &mut self,
+        mut produce_row: impl FnMut(u32, &mut [P]),
+        window: &H,
+    ) -> Result<(), BlitError> {
+        self.p.blit_streaming(
+            |row, bytes| produce_row(row, P::from_raw_slice_mut(bytes)),
+            window,
+        )
+    }
+
+    /// Blits a subsection of the pixel buffer's contents onto `window`.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn blit_rect<H: HasWindowHandle>(
+        &self,
+        src_pos: impl Into<Point>,
+        dst_pos: impl Into<Point>,
+        blit_size: impl Into<Size>,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        self.p.blit_rect(src_pos, dst_pos, blit_size, window)
+    }
+
+    /// Blits a rectangle of the pixel buffer onto a rectangle of `window`, scaling it to fit.
+    ///
+    /// See [`PixelBuffer::blit_rect_scaled`].
+    pub fn blit_rect_scaled<H: HasWindowHandle>(
+        &self,
+        src_pos: impl Into<Point>,
+        src_size: impl Into<Size>,
+        dst_pos: impl Into<Point>,
+        dst_size: impl Into<Size>,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        self.p
+            .blit_rect_scaled(src_pos, src_size, dst_pos, dst_size, window)
+    }
+
+    /// Blits several rectangles of the pixel buffer onto `window` in one present.
+    ///
+    /// See [`PixelBuffer::blit_regions`].
+    pub fn blit_regions<H: HasWindowHandle>(
+        &self,
+        regions: &[BlitRegion],
+        window: &H,
+    ) -> Result<(), BlitError> {
+        self.p.blit_regions(regions, window)
+    }
+
+    /// Blits a rectangle of the pixel buffer onto the top-left corner of `window`, magnified by
+    /// `zoom`.
+    ///
+    /// See [`PixelBuffer::present_zoomed`].
+    pub fn present_zoomed<H: HasWindowHandle>(
+        &self,
+        src_pos: impl Into<Point>,
+        src_size: impl Into<Size>,
+        zoom: f32,
+        filter: Filter,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        self.p
+            .present_zoomed(src_pos, src_size, zoom, filter, window)
+    }
+
+    /// Builds a pyramid of downscaled copies of the pixel buffer, used to shrink it smoothly.
+    ///
+    /// See [`PixelBuffer::build_mipmaps`].
+    pub fn build_mipmaps(&mut self) {
+        self.p.build_mipmaps()
+    }
+
+    /// Frees the copies made by [`build_mipmaps`](Self::build_mipmaps).
+    pub fn clear_mipmaps(&mut self) {
+        self.p.clear_mipmaps()
+    }
+
+    /// The number of downscaled copies made by [`build_mipmaps`](Self::build_mipmaps).
+    pub fn mipmap_levels(&self) -> usize {
+        self.p.mipmap_levels()
+    }
+
+    /// Blits the pixel buffer onto `window` in interlaced passes.
+    ///
+    /// See [`PixelBuffer::present_progressive`].
+    pub fn present_progressive<H: HasWindowHandle>(
+        &self,
+        interval: u32,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        self.p.present_progressive(interval, window)
+    }
+
+    /// Blits a subsection of the pixel buffer's contents onto `window`, filling the rest of the
+    /// window with `background`.
+    ///
+    /// Both happen in the same present, so the window doesn't flicker the way it would when
+    /// clearing it and blitting separately. Only `background`'s color channels are used.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to `new`, or one added with
+    /// `add_window`. Failing to do so will result in a panic.
+    pub fn blit_rect_with_background<H: HasWindowHandle>(
+        &self,
+        src_pos: impl Into<Point>,
+        dst_pos: impl Into<Point>,
+        blit_size: impl Into<Size>,
+        background: impl Into<Color>,
+        window: &H,
+    ) -> Result<(), BlitError> {
+        self.p
+            .blit_rect_with_background(src_pos, dst_pos, blit_size, background, window)
+    }
+
+    /// Blits everything that changed since the last `present` onto `window`.
+    ///
+    /// See [`PixelBuffer::present`].
+    pub fn present<H: HasWindowHandle>(&mut self, window: &H) -> Result<(), BlitError> {
+        self.p.present(window)
+    }
+
+    /// Asks for everything that changed to be presented onto `window` by the next
+    /// [`flush_presents`](Self::flush_presents).
+    ///
+    /// See [`PixelBuffer::defer_present`].
+    pub fn defer_present<H: HasWindowHandle>(&mut self, window: &H) -> Result<(), BlitError> {
+        self.p.defer_present(window)
+    }
+
+    /// Presents everything that changed onto every window a present was deferred for.
+    ///
+    /// See [`PixelBuffer::flush_presents`].
+    pub fn flush_presents(&mut self) -> Result<(), BlitError> {
+        self.p.flush_presents()
+    }
+
+    /// Presents everything that changed since the last `present` to a [`CustomBackend`].
+    ///
+    /// See [`PixelBuffer::present_to`].
+    pub fn present_to(&mut self, backend: &mut dyn CustomBackend) -> io::Result<()> {
+        self.p.present_to(backend)
+    }
+
+    /// Resizes the pixel buffer to `size` if needed, then blits everything that changed onto
+    /// `window`.
+    ///
+    /// See [`PixelBuffer::present_sized`].
+    pub fn present_sized<H: HasWindowHandle>(
+        &mut self,
+        window: &H,
+        size: impl Into<Size>,
+    ) -> Result<(), BlitError> {
+        self.p.present_sized(window, size)
+    }
+
+    /// Presents the whole pixel buffer onto every window it's associated with, for use in a
+    /// panic hook or crash handler.
+    ///
+    /// See [`PixelBuffer::emergency_present`].
+    pub fn emergency_present(&self) -> bool {
+        self.p.emergency_present()
+    }
+
+    /// Records that the region with its top-left corner at `pos` has changed.
+    ///
+    /// See [`PixelBuffer::mark_dirty`].
+    pub fn mark_dirty(&mut self, pos: impl Into<Point>, size: impl Into<Size>) {
+        self.p.mark_dirty(pos, size)
+    }
+
+    /// Records that the whole pixel buffer has changed.
+    pub fn mark_all_dirty(&mut self) {
+        self.p.mark_all_dirty()
+    }
+
+    /// The bounding box of everything that changed since the last `present`.
+    pub fn dirty_region(&self) -> Option<Rect> {
+        self.p.dirty_region()
+    }
+
+    /// Takes the regions that changed since the last `present`.
+    ///
+    /// See [`PixelBuffer::take_damage`].
+    pub fn take_damage(&mut self) -> Vec<Rect> {
+        self.p.take_damage()
+    }
+
+    /// Fills the pixel buffer with `window`'s current contents.
+    ///
+    /// See [`PixelBuffer::capture_window`].
+    pub fn capture_window<H: HasWindowHandle>(&mut self, window: &H) -> Result<(), BlitError> {
+        self.p.capture_window(window)
+    }
+
+    /// Copies the region with its top-left corner at `pos` to the clipboard as an image.
+    ///
+    /// Returns an error if the region doesn't fit inside the buffer, or if the clipboard can't be
+    /// accessed.
+    pub fn copy_region_to_clipboard(
+        &self,
+        pos: impl Into<Point>,
+        size: impl Into<Size>,
+    ) -> io::Result<()> {
+        self.p.copy_region_to_clipboard(pos, size)
+    }
+
+    /// Queries the platform's capabilities for presenting the buffer onto `window`.
+    ///
+    /// See [`PixelBuffer::target_capabilities`].
+    pub fn target_capabilities<H: HasWindowHandle>(
+        &self,
+        window: &H,
+    ) -> Result<Capabilities, BlitError> {
+        self.p.target_capabilities(window)
+    }
+
+    /// Queries the display `window` is shown on, to tone-map the buffer's contents for it.
+    ///
+    /// See [`PixelBuffer::target_display_info`].
+    pub fn target_display_info<H: HasWindowHandle>(
+        &self,
+        window: &H,
+    ) -> Result<DisplayInfo, BlitError> {
+        self.p.target_display_info(window)
+    }
+
+    /// Statistics about the most recent successful blit.
+    ///
+    /// Before the first blit, every field is zero.
+    pub fn metrics(&self) -> BlitMetrics {
+        self.p.metrics()
+    }
+
+    /// The number of frames presented so far, which is also the index of the most recent one.
+    ///
+    /// See [`PixelBuffer::frame_index`].
+    pub fn frame_index(&self) -> u64 {
+        self.p.frame_index()
+    }
+
+    /// The adjustment applied to the buffer's colors while blitting it, if any.
+    pub fn color_adjustment(&self) -> Option<ColorAdjustment> {
+        self.p.color_adjustment()
+    }
+
+    /// Sets an adjustment applied to the buffer's colors while blitting it, without changing the
+    /// buffer's contents.
+    ///
+    /// See [`PixelBuffer::set_color_adjustment`].
+    pub fn set_color_adjustment(&mut self, adjustment: Option<ColorAdjustment>) {
+        self.p.set_color_adjustment(adjustment)
+    }
+
+    /// Sets a function that converts the buffer's rows while presenting it.
+    ///
+    /// See [`PixelBuffer::set_present_conversion`].
+    pub fn set_present_conversion(&mut self, conversion: Option<PresentConversion>) {
+        self.p.set_present_conversion(conversion)
+    }
+
+    /// The capacity, in bytes, of the scratch space used for converting the buffer's contents
+    /// while blitting it.
+    ///
+    /// See [`PixelBuffer::scratch_capacity`].
+    pub fn scratch_capacity(&self) -> usize {
+        self.p.scratch_capacity()
+    }
+
+    /// The number of bytes the pixel buffer holds.
+    ///
+    /// See [`PixelBuffer::memory_usage`].
+    pub fn memory_usage(&self) -> usize {
+        self.p.memory_usage()
+    }
+
+    /// Allocates enough scratch space to convert the entire buffer, so that no blit has to.
+    pub fn reserve_scratch(&mut self) {
+        self.p.reserve_scratch()
+    }
+
+    /// Frees the scratch space. It's allocated again by the next blit that needs it.
+    pub fn release_scratch(&mut self) {
+        self.p.release_scratch()
+    }
+
+    /// The color depth the buffer is reduced to while blitting over a remote connection, if any.
+    pub fn remote_depth(&self) -> Option<(ChannelDepth, ConversionOptions)> {
+        self.p.remote_depth()
+    }
+
+    /// Sets a color depth to reduce the buffer's colors to while blitting over a remote
+    /// connection.
+    ///
+    /// See [`PixelBuffer::set_remote_depth`].
+    pub fn set_remote_depth(&mut self, depth: Option<(ChannelDepth, ConversionOptions)>) {
+        self.p.set_remote_depth(depth)
+    }
+
+    /// Whether blits may convert the buffer's contents before presenting them.
+    pub fn allow_conversion(&self) -> bool {
+        self.p.allow_conversion()
+    }
+
+    /// Sets whether blits may convert the buffer's contents before presenting them.
+    ///
+    /// See [`PixelBuffer::set_allow_conversion`].
+    pub fn set_allow_conversion(&mut self, allow: bool) {
+        self.p.set_allow_conversion(allow)
+    }
+
+    /// Whether blits wait for the next frame to be composited before returning.
+    pub fn frame_throttle(&self) -> bool {
+        self.p.frame_throttle()
+    }
+
+    /// Sets whether blits wait for the next frame to be composited before returning.
+    ///
+    /// Throttling paces rendering to the display's refresh rate. Disabling it lets benchmarks and
+    /// latency-sensitive tools present as fast as possible. Throttling is disabled by default.
+    pub fn set_frame_throttle(&mut self, throttle: bool) {
+        self.p.set_frame_throttle(throttle)
+    }
+
+    /// The token that cancels blits of the pixel buffer, if any.
+    pub fn cancel_token(&self) -> Option<&CancelToken> {
+        self.p.cancel_token()
+    }
+
+    /// Attaches a token that cancels blits of the pixel buffer, or detaches it with `None`.
+    ///
+    /// See [`PixelBuffer::set_cancel_token`].
+    pub fn set_cancel_token(&mut self, token: Option<CancelToken>) {
+        self.p.set_cancel_token(token)
+    }
+
+    /// The most presents that may wait for the compositor at once.
+    ///
+    /// See [`PixelBuffer::present_limit`].
+    pub fn present_limit(&self) -> Option<(NonZeroU32, Backpressure)> {
+        self.p.present_limit()
+    }
+
+    /// Limits how many presents may wait for the compositor at once.
+    ///
+    /// See [`PixelBuffer::set_present_limit`].
+    pub fn set_present_limit(&mut self, limit: Option<(NonZeroU32, Backpressure)>) {
+        self.p.set_present_limit(limit)
+    }
+
+    /// The shared memory backing the pixel buffer.
+    ///
+    /// Returns `None` unless the buffer was created with `new_shared`. Another process writing to
+    /// the memory while this one reads or blits the buffer will see torn frames; synchronizing
+    /// access is up to the application.
+    pub fn shared_memory(&self) -> Option<SharedMemory> {
+        self.p.shared_memory()
+    }
+
+    /// Sets whether the pixel buffer keeps a copy of its contents each time it's blitted.
+    ///
+    /// When enabled, the most recently blitted frame can be read through
+    /// [`previous_frame`](Self::previous_frame), which is handy for temporal effects such as motion
+    /// blur or damage diffing. Disabling this frees the stored frame.
+    pub fn set_keep_previous_frame(&mut self, keep: bool) {
+        self.p.set_keep_previous_frame(keep)
+    }
+
+    /// Allows the pixel buffer to be blitted onto `window`, in addition to the window it was created
+    /// for.
+    ///
+    /// This lets one buffer be presented to several windows, e.g. for mirror or preview windows,
+    /// without rendering the same frame twice. Returns an error if `window` is gone or can't
+    /// display the buffer's format. Adding a window that's already been added does nothing.
+    pub fn add_window<H: HasWindowHandle>(&mut self, window: &H) -> Result<(), BlitError> {
+        self.p.add_window(window)
+    }
+
+    /// Stops allowing the pixel buffer to be blitted onto `window`.
+    pub fn remove_window<H: HasWindowHandle>(&mut self, window: &H) {
+        self.p.remove_window(window)
+    }
+
+    /// Stops presenting the pixel buffer onto `window`, and has the window repaint itself.
+    ///
+    /// See [`PixelBuffer::detach`].
+    pub fn detach<H: HasWindowHandle>(&mut self, window: &H) -> Result<(), BlitError> {
+        self.p.detach(window)
+    }
+
+    /// What presenting the buffer onto its window does with the alpha channel.
+    ///
+    /// When this is [`AlphaBehavior::Ignored`], computing meaningful alpha values is wasted work.
+    pub fn alpha_behavior(&self) -> AlphaBehavior {
+        self.p.alpha_behavior()
+    }
+
+    /// How the alpha channel of the buffer's pixels is interpreted.
+    ///
+    /// Defaults to [`AlphaMode::Straight`].
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.p.alpha_mode()
+    }
+
+    /// Sets how the alpha channel of the buffer's pixels is interpreted.
+    ///
+    /// This only changes how the crate's blending and conversion routines treat the buffer's
+    /// contents; the stored pixels are left untouched. Use
+    /// [`convert_alpha_mode`](Self::convert_alpha_mode) to convert them as well.
+    pub fn set_alpha_mode(&mut self, alpha_mode: AlphaMode) {
+        self.p.set_alpha_mode(alpha_mode)
+    }
+
+    /// Which color space the drawing helpers blend translucent colors in.
+    ///
+    /// See [`PixelBuffer::rendering_intent`].
+    pub fn rendering_intent(&self) -> RenderingIntent {
+        self.p.rendering_intent()
+    }
+
+    /// Sets which color space the drawing helpers blend translucent colors in.
+    ///
+    /// See [`PixelBuffer::set_rendering_intent`].
+    pub fn set_rendering_intent(&mut self, intent: RenderingIntent) {
+        self.p.set_rendering_intent(intent)
+    }
+
+    /// Converts every pixel in the buffer to `alpha_mode`, then switches the buffer to it.
+    ///
+    /// Converting from [`AlphaMode::Straight`] to [`AlphaMode::Ignored`] composites the pixels
+    /// over black. Converting from premultiplied to straight alpha loses precision for
+    /// translucent pixels, and fully transparent pixels become transparent black.
+    pub fn convert_alpha_mode(&mut self, alpha_mode: AlphaMode) {
+        let from = self.alpha_mode();
+        if from != alpha_mode {
+            for row in self.rows_mut() {
+                for pixel in row {
+                    let [r, g, b, a] = convert::convert_alpha(pixel.to_rgba(), from, alpha_mode);
+                    *pixel = P::from_rgba(r, g, b, a);
+                }
+            }
         }
+        self.set_alpha_mode(alpha_mode);
+    }
+
+    /// The pixel buffer's contents as of its most recent successful blit.
+    ///
+    /// Returns `None` unless [`set_keep_previous_frame`](Self::set_keep_previous_frame) has been
+    /// enabled and the buffer has been blitted since.
+    pub fn previous_frame(&self) -> Option<PreviousFrameTyped<'_, P>> {
+        self.p.previous_frame().map(|frame| PreviousFrameTyped {
+            frame,
+            _format: PhantomData,
+        })
     }
 
     /// The total number of bits in an individual pixel.
@@ -100,165 +2490,335 @@ impl PixelBuffer {
         self.p.row_len()
     }
 
+    /// The signed distance, in bytes, from the start of a row to the start of the row below it
+    /// in the memory the platform presents from.
+    ///
+    /// See [`PixelBuffer::backend_stride`].
+    pub fn backend_stride(&self) -> isize {
+        self.p.backend_stride()
+    }
+
+    /// The format of the pixels in the memory the platform presents from.
+    ///
+    /// See [`PixelBuffer::backend_format`].
+    pub fn backend_format(&self) -> PixelBufferFormatType {
+        self.p.backend_format()
+    }
+
     /// The height, in pixels, of the pixel buffer.
     pub fn height(&self) -> u32 {
         self.p.height()
     }
 
     /// Gets the row at the particular height.
-    pub fn row(&self, row: u32) -> Option<&[u8]> {
-        self.p.row(row)
+    pub fn row(&self, row: u32) -> Option<&[P]> {
+        self.p.row(row).map(P::from_raw_slice)
     }
 
     /// Mutably gets the row at the particular height.
-    pub fn row_mut(&mut self, row: u32) -> Option<&mut [u8]> {
-        self.p.row_mut(row)
+    pub fn row_mut(&mut self, row: u32) -> Option<&mut [P]> {
+        self.p.row_mut(row).map(P::from_raw_slice_mut)
     }
 
     /// Iterate through all rows in the pixel buffer.
-    pub fn rows<'a>(&'a self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a [u8]> {
-        self.p.rows()
+    pub fn rows<'a>(&'a self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a [P]> {
+        self.p.rows().map(P::from_raw_slice)
     }
 
     /// Mutably iterate through all rows in the pixel buffer.
     pub fn rows_mut<'a>(
         &'a mut self,
-    ) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a mut [u8]> {
-        self.p.rows_mut()
+    ) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a mut [P]> {
+        self.p.rows_mut().map(P::from_raw_slice_mut)
+    }
+
+    /// Mutably gets the row at the particular height, as memory that's only meant to be written.
+    ///
+    /// See [`PixelBuffer::row_uninit_mut`].
+    ///
+    /// # Safety
+    /// Every value stored into the row must be initialized.
+    pub unsafe fn row_uninit_mut(&mut self, row: u32) -> Option<&mut [MaybeUninit<P>]> {
+        self.row_mut(row).map(|row| as_uninit_mut(row))
+    }
+
+    /// Mutably iterate through all rows in the pixel buffer, as memory that's only meant to be
+    /// written.
+    ///
+    /// See [`PixelBuffer::row_uninit_mut`].
+    ///
+    /// # Safety
+    /// Every value stored into the rows must be initialized.
+    pub unsafe fn rows_uninit_mut(
+        &mut self,
+    ) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &mut [MaybeUninit<P>]> + '_ {
+        self.rows_mut().map(|row| as_uninit_mut(row))
     }
 
     /// Iterate through all rows in the pixel buffer.
     #[cfg(feature = "rayon")]
-    pub fn par_rows<'a>(&'a self) -> impl IndexedParallelIterator<Item = &'a [u8]> {
-        self.p.par_rows()
+    pub fn par_rows<'a>(&'a self) -> impl IndexedParallelIterator<Item = &'a [P]>
+    where
+        P: Send + Sync,
+    {
+        self.p.par_rows().map(P::from_raw_slice)
     }
 
     /// Mutably iterate through all rows in the pixel buffer.
     #[cfg(feature = "rayon")]
-    pub fn par_rows_mut<'a>(&'a mut self) -> impl IndexedParallelIterator<Item = &'a mut [u8]> {
-        self.p.par_rows_mut()
+    pub fn par_rows_mut<'a>(&'a mut self) -> impl IndexedParallelIterator<Item = &'a mut [P]>
+    where
+        P: Send + Sync,
+    {
+        self.p.par_rows_mut().map(P::from_raw_slice_mut)
     }
-}
 
-impl<P: PixelBufferFormat> PixelBufferTyped<P> {
-    /// Initialize a new pixel buffer.
+    /// Iterate through the pixel buffer in bands of `rows_per_chunk` rows, starting at the top.
     ///
-    /// Can return `Err` if the platform doesn't support the requested pixel buffer type.
-    pub fn new<H: HasRawWindowHandle>(
-        width: u32,
-        height: u32,
-        window: &H,
-    ) -> Result<PixelBufferTyped<P>, PixelBufferCreationError> {
-        Ok(PixelBufferTyped {
-            p: PixelBuffer::new(width, height, P::FORMAT_TYPE, window)?,
-            _format: PhantomData,
-        })
+    /// The last band may have fewer than `rows_per_chunk` rows.
+    ///
+    /// # Panics
+    /// Panics if `rows_per_chunk` is `0`.
+    pub fn row_chunks(
+        &self,
+        rows_per_chunk: usize,
+    ) -> impl ExactSizeIterator
+           + DoubleEndedIterator<Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &[P]>>
+    {
+        self.p
+            .row_chunks(rows_per_chunk)
+            .map(|chunk| chunk.map(P::from_raw_slice))
     }
 
-    /// Initialize a new pixel buffer.
+    /// Mutably iterate through the pixel buffer in bands of `rows_per_chunk` rows, starting at the
+    /// top.
     ///
-    /// This always works, since we've statically checked that the pixel format is supported by
-    /// the platform.
-    pub fn new_supported<H: HasRawWindowHandle>(
-        width: u32,
-        height: u32,
-        window: &H,
-    ) -> PixelBufferTyped<P>
-    where
-        P: PixelBufferFormatSupported,
+    /// The last band may have fewer than `rows_per_chunk` rows.
+    ///
+    /// # Panics
+    /// Panics if `rows_per_chunk` is `0`.
+    pub fn row_chunks_mut(
+        &mut self,
+        rows_per_chunk: usize,
+    ) -> impl ExactSizeIterator
+           + DoubleEndedIterator<Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &mut [P]>>
     {
-        Self::new(width, height, window).unwrap()
+        self.p
+            .row_chunks_mut(rows_per_chunk)
+            .map(|chunk| chunk.map(P::from_raw_slice_mut))
     }
 
-    /// Blits the pixel buffer's contents onto `window`.
+    /// Iterate through the pixel buffer in bands of `rows_per_chunk` rows.
+    ///
+    /// Picking a band size that fits in cache lets each thread work on several rows at a time,
+    /// rather than synchronizing after every row.
     ///
     /// # Panics
-    /// The `window` passed to this function must be the same `window` passed to `new`. Failing to
-    /// do so will result in a panic.
-    pub fn blit<H: HasRawWindowHandle>(&self, window: &H) -> io::Result<()> {
-        self.p.blit(window)
+    /// Panics if `rows_per_chunk` is `0`.
+    #[cfg(feature = "rayon")]
+    pub fn par_row_chunks(
+        &self,
+        rows_per_chunk: usize,
+    ) -> impl IndexedParallelIterator<Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &[P]>>
+    where
+        P: Send + Sync,
+    {
+        self.p
+            .par_row_chunks(rows_per_chunk)
+            .map(|chunk| chunk.map(P::from_raw_slice))
     }
 
-    /// Blits a subsection of the pixel buffer's contents onto `window`.
+    /// Mutably iterate through the pixel buffer in bands of `rows_per_chunk` rows.
+    ///
+    /// Picking a band size that fits in cache lets each thread work on several rows at a time,
+    /// rather than synchronizing after every row.
     ///
     /// # Panics
-    /// The `window` passed to this function must be the same `window` passed to `new`. Failing to
-    /// do so will result in a panic.
-    pub fn blit_rect<H: HasRawWindowHandle>(
-        &self,
-        src_pos: (u32, u32),
-        dst_pos: (u32, u32),
-        blit_size: (u32, u32),
-        window: &H,
-    ) -> io::Result<()> {
-        self.p.blit_rect(src_pos, dst_pos, blit_size, window)
+    /// Panics if `rows_per_chunk` is `0`.
+    #[cfg(feature = "rayon")]
+    pub fn par_row_chunks_mut(
+        &mut self,
+        rows_per_chunk: usize,
+    ) -> impl IndexedParallelIterator<Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &mut [P]>>
+    where
+        P: Send + Sync,
+    {
+        self.p
+            .par_row_chunks_mut(rows_per_chunk)
+            .map(|chunk| chunk.map(P::from_raw_slice_mut))
     }
 
-    /// The total number of bits in an individual pixel.
+    /// Iterate through all columns in the pixel buffer, from left to right.
     ///
-    /// Will always be a multiple of `8`.
-    pub fn bits_per_pixel(&self) -> usize {
-        self.p.bits_per_pixel()
+    /// Each column yields its pixels from top to bottom. Consecutive pixels in a column are a whole
+    /// row apart in memory, so prefer [`rows`](Self::rows) where possible.
+    pub fn columns(
+        &self,
+    ) -> impl ExactSizeIterator
+           + DoubleEndedIterator<Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &P>>
+    {
+        self.p
+            .columns()
+            .map(|column| column.map(|pixel| &P::from_raw_slice(pixel)[0]))
     }
 
-    /// The total number of bytes in an individual pixel.
-    pub fn bytes_per_pixel(&self) -> usize {
-        self.p.bytes_per_pixel()
+    /// Mutably iterate through all columns in the pixel buffer, from left to right.
+    ///
+    /// Each column yields its pixels from top to bottom. Consecutive pixels in a column are a whole
+    /// row apart in memory; for effects that process entire columns at a time,
+    /// [`for_each_column_mut`](Self::for_each_column_mut) is usually much faster.
+    pub fn columns_mut(
+        &mut self,
+    ) -> impl ExactSizeIterator
+           + DoubleEndedIterator<Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &mut P>>
+    {
+        self.p
+            .columns_mut()
+            .map(|column| column.map(|pixel| &mut P::from_raw_slice_mut(pixel)[0]))
     }
 
-    /// The width, in pixels, of the pixel buffer.
-    pub fn width(&self) -> u32 {
-        self.p.width()
+    /// Gets a mutable view into the whole pixel buffer.
+    pub fn as_region_mut(&mut self) -> RegionMut<'_, P> {
+        self.mark_all_dirty();
+        self.untracked_region_mut()
     }
 
-    /// The length, in bytes, of a single row of the pixel buffer.
-    pub fn row_len(&self) -> usize {
-        self.p.row_len()
+    /// Gets a mutable view into the region with its top-left corner at `pos`.
+    ///
+    /// Returns `None` if the requested region doesn't fit inside the pixel buffer.
+    pub fn region_mut(
+        &mut self,
+        pos: impl Into<Point>,
+        size: impl Into<Size>,
+    ) -> Option<RegionMut<'_, P>> {
+        let (pos, size) = (pos.into(), size.into());
+        // Only the region itself can change, so it's all that needs to be marked dirty.
+        self.untracked_region_mut().into_region(pos, size)?;
+        self.mark_dirty(pos, size);
+        self.untracked_region_mut().into_region(pos, size)
     }
 
-    /// The height, in pixels, of the pixel buffer.
-    pub fn height(&self) -> u32 {
-        self.p.height()
+    /// Like `as_region_mut`, but without marking the buffer as changed.
+    fn untracked_region_mut(&mut self) -> RegionMut<'_, P> {
+        let (width, height) = (self.width(), self.height());
+        let row_pitch = self.p.p.row_pitch();
+        let top = self.p.p.top_row_ptr();
+        unsafe { RegionMut::from_raw_parts(top, row_pitch, width, height) }
+            .with_alpha_mode(self.alpha_mode())
+            .with_rendering_intent(self.rendering_intent())
     }
 
-    /// Gets the row at the particular height.
-    pub fn row(&self, row: u32) -> Option<&[P]> {
-        self.p.row(row).map(P::from_raw_slice)
+    /// Splits the pixel buffer into the rows above `row` and the rows starting at `row`.
+    ///
+    /// The two halves can be rendered into from different threads at the same time.
+    ///
+    /// # Panics
+    /// Panics if `row` is greater than the pixel buffer's height.
+    pub fn split_at_row(&mut self, row: u32) -> (RegionMut<'_, P>, RegionMut<'_, P>) {
+        self.as_region_mut().split_at_row(row)
     }
 
-    /// Mutably gets the row at the particular height.
-    pub fn row_mut(&mut self, row: u32) -> Option<&mut [P]> {
-        self.p.row_mut(row).map(P::from_raw_slice_mut)
+    /// Splits the pixel buffer into a grid of `columns` by `rows` regions of (nearly) equal size.
+    ///
+    /// The regions are returned in row-major order, starting from the top-left, and can be
+    /// rendered into from different threads at the same time.
+    ///
+    /// # Panics
+    /// Panics if `columns` or `rows` is `0`.
+    pub fn split_grid(&mut self, columns: u32, rows: u32) -> Vec<RegionMut<'_, P>> {
+        self.as_region_mut().split_grid(columns, rows)
     }
 
-    /// Iterate through all rows in the pixel buffer.
-    pub fn rows<'a>(&'a self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a [P]> {
-        self.p.rows().map(P::from_raw_slice)
+    /// Calls `f` with the index and contents of every column in the pixel buffer, from left to
+    /// right.
+    ///
+    /// Each column is passed as a contiguous slice, ordered from top to bottom, and any changes
+    /// made to it are written back to the buffer. Internally, the buffer is transposed into scratch
+    /// memory one cache-sized tile at a time, which keeps vertical effects (such as motion blur)
+    /// fast.
+    pub fn for_each_column_mut(&mut self, mut f: impl FnMut(u32, &mut [P])) {
+        let height = self.height() as usize;
+        if height == 0 {
+            return;
+        }
+        let mut transposed = vec![P::DEFAULT; self.width() as usize * height];
+        transpose_into(&self.rows().collect::<Vec<_>>(), &mut transposed);
+        for (x, column) in transposed.chunks_mut(height).enumerate() {
+            f(x as u32, column);
+        }
+        transpose_from(&transposed, &mut self.rows_mut().collect::<Vec<_>>());
     }
 
-    /// Mutably iterate through all rows in the pixel buffer.
-    pub fn rows_mut<'a>(
-        &'a mut self,
-    ) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a mut [P]> {
-        self.p.rows_mut().map(P::from_raw_slice_mut)
+    /// Fills the pixel buffer by calling `f` with the coordinates of every pixel.
+    ///
+    /// `f` is called with `(x, y)` and returns the pixel's new value.
+    pub fn generate(&mut self, mut f: impl FnMut(u32, u32) -> P) {
+        for (y, row) in self.rows_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = f(x as u32, y as u32);
+            }
+        }
     }
 
-    /// Iterate through all rows in the pixel buffer.
+    /// Fills the pixel buffer in parallel by calling `f` with the coordinates of every pixel.
+    ///
+    /// `f` is called with `(x, y)` and returns the pixel's new value. Rows are distributed across
+    /// rayon's thread pool, or the one set with [`set_thread_pool`].
     #[cfg(feature = "rayon")]
-    pub fn par_rows<'a>(&'a self) -> impl IndexedParallelIterator<Item = &'a [P]>
+    pub fn par_generate(&mut self, f: impl Fn(u32, u32) -> P + Sync)
     where
         P: Send + Sync,
     {
-        self.p.par_rows().map(P::from_raw_slice)
+        let rows = self.par_rows_mut();
+        threads::install(|| {
+            rows.enumerate().for_each(|(y, row)| {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    *pixel = f(x as u32, y as u32);
+                }
+            })
+        });
     }
+}
 
-    /// Mutably iterate through all rows in the pixel buffer.
-    #[cfg(feature = "rayon")]
-    pub fn par_rows_mut<'a>(&'a mut self) -> impl IndexedParallelIterator<Item = &'a mut [P]>
-    where
-        P: Send + Sync,
-    {
-        self.p.par_rows_mut().map(P::from_raw_slice_mut)
+/// Views initialized memory as memory that may be uninitialized.
+///
+/// # Safety
+/// Only initialized values may be stored through the result.
+unsafe fn as_uninit_mut<T>(slice: &mut [T]) -> &mut [MaybeUninit<T>] {
+    // `MaybeUninit<T>` has the same layout as `T`.
+    slice::from_raw_parts_mut(slice.as_mut_ptr() as *mut MaybeUninit<T>, slice.len())
+}
+
+/// The side length, in pixels, of the tiles used when transposing a pixel buffer.
+const TRANSPOSE_TILE: usize = 32;
+
+/// Transposes `rows` into `columns`, which is laid out column-by-column.
+fn transpose_into<P: Copy>(rows: &[&[P]], columns: &mut [P]) {
+    let height = rows.len();
+    let width = rows.first().map_or(0, |row| row.len());
+    for y0 in (0..height).step_by(TRANSPOSE_TILE) {
+        for x0 in (0..width).step_by(TRANSPOSE_TILE) {
+            for (y, row) in rows.iter().enumerate().skip(y0).take(TRANSPOSE_TILE) {
+                for x in x0..(x0 + TRANSPOSE_TILE).min(width) {
+                    columns[x * height + y] = row[x];
+                }
+            }
+        }
+    }
+}
+
+/// Transposes `columns`, which is laid out column-by-column, back into `rows`.
+fn transpose_from<P: Copy>(columns: &[P], rows: &mut [&mut [P]]) {
+    let height = rows.len();
+    let width = rows.first().map_or(0, |row| row.len());
+    for y0 in (0..height).step_by(TRANSPOSE_TILE) {
+        for x0 in (0..width).step_by(TRANSPOSE_TILE) {
+            for (y, row) in rows.iter_mut().enumerate().skip(y0).take(TRANSPOSE_TILE) {
+                for x in x0..(x0 + TRANSPOSE_TILE).min(width) {
+                    row[x] = columns[x * height + y];
+                }
+            }
+        }
     }
 }
 
@@ -282,6 +2842,9 @@ pub enum PixelBufferFormatType {
 /// |         | [`BGR`] | [`BGRA`] | [`RGB`] | [`RGBA`] |
 /// | ------- | ------- | -------- | ------- | -------- |
 /// | Windows | ✔      | ✔      | ❌      | ❌      |
+///
+/// Other formats can be defined by implementing [`CustomFormat`], and are converted to a supported
+/// one before presenting.
 pub trait PixelBufferFormatSupported: PixelBufferFormat {}
 /// The format of each individual pixel in the pixel buffer.
 ///
@@ -310,12 +2873,56 @@ pub unsafe trait PixelBufferFormat:
     const FORMAT_TYPE: PixelBufferFormatType;
 
     fn from_rgb(r: u8, g: u8, b: u8) -> Self;
+    /// Creates a pixel from straight (non-premultiplied) RGBA channels. The alpha channel is
+    /// dropped by formats that don't have one.
+    ///
+    /// The default implementation places the channels according to `FORMAT_TYPE`.
+    fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        let mut pixel = Self::DEFAULT;
+        let bytes = AsMut::<[u8]>::as_mut(pixel.as_mut());
+        for (channel, index) in [r, g, b, a].iter().zip(&Self::FORMAT_TYPE.rgba_indices()) {
+            if let Some(index) = *index {
+                bytes[index] = *channel;
+            }
+        }
+        pixel
+    }
+    /// The pixel's channels in RGBA order. Formats without an alpha channel report an alpha of
+    /// `255`.
+    ///
+    /// The default implementation reads the channels according to `FORMAT_TYPE`.
+    fn to_rgba(self) -> [u8; 4] {
+        let bytes = AsRef::<[u8]>::as_ref(self.as_ref());
+        let mut rgba = [255; 4];
+        for (channel, index) in rgba.iter_mut().zip(&Self::FORMAT_TYPE.rgba_indices()) {
+            if let Some(index) = *index {
+                *channel = bytes[index];
+            }
+        }
+        rgba
+    }
     fn from_raw_slice(raw: &[u8]) -> &[Self];
     fn from_raw_slice_mut(raw: &mut [u8]) -> &mut [Self];
     fn to_raw_slice(slice: &[Self]) -> &[u8];
     fn to_raw_slice_mut(slice: &mut [Self]) -> &mut [u8];
 }
 
+/// Picks the argument corresponding to the channel named `$c`, in RGBA order.
+macro_rules! channel {
+    (r, $r:expr, $g:expr, $b:expr, $a:expr) => {
+        $r
+    };
+    (g, $r:expr, $g:expr, $b:expr, $a:expr) => {
+        $g
+    };
+    (b, $r:expr, $g:expr, $b:expr, $a:expr) => {
+        $b
+    };
+    (a, $r:expr, $g:expr, $b:expr, $a:expr) => {
+        $a
+    };
+}
+
 macro_rules! pixel_buffer_format {
     ($(#[$attr:meta])* pub struct $pixel:ident($($c:ident),+): $array:ty = $default:expr;) => {
         $(#[$attr])*
@@ -346,6 +2953,17 @@ macro_rules! pixel_buffer_format {
                     ..Self::DEFAULT
                 }
             }
+            #[allow(unused_variables)]
+            pub const fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+                Self {
+                    $($c: channel!($c, r, g, b, a)),+
+                }
+            }
+            pub fn to_rgba(self) -> [u8; 4] {
+                let mut rgba = [0, 0, 0, 255];
+                $(rgba[channel!($c, 0, 1, 2, 3)] = self.$c;)+
+                rgba
+            }
             #[inline(always)]
             pub fn from_raw_slice(raw: &[u8]) -> &[Self] {
                 let size = Self::size();
@@ -396,6 +3014,12 @@ macro_rules! pixel_buffer_format {
             fn from_rgb(r: u8, g: u8, b: u8) -> Self {
                 Self::from_rgb(r, g, b)
             }
+            fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+                Self::from_rgba(r, g, b, a)
+            }
+            fn to_rgba(self) -> [u8; 4] {
+                Self::to_rgba(self)
+            }
             #[inline(always)]
             fn from_raw_slice(raw: &[u8]) -> &[Self] {
                 Self::from_raw_slice(raw)
@@ -472,6 +3096,16 @@ macro_rules! pixel_buffer_format {
                 Self::DEFAULT
             }
         }
+        impl From<Color> for $pixel {
+            fn from(color: Color) -> $pixel {
+                color.to_pixel()
+            }
+        }
+        impl From<$pixel> for Color {
+            fn from(pixel: $pixel) -> Color {
+                Color::from_pixel(pixel)
+            }
+        }
     };
 }
 
@@ -499,3 +3133,234 @@ pixel_buffer_format! {
     /// A red-green-blue-alpha formatted pixel type.
     pub struct RGBA(r, g, b, a): [u8; 4] = Self::new(0, 0, 0, 255);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        borrow::{Borrow, BorrowMut},
+        slice,
+    };
+
+    #[test]
+    /// The purpose of this test is to verify that pixel formats defined outside the crate only
+    /// need the trait's required methods, and get RGBA conversions that follow their format type.
+    fn format_default_rgba_conversions() {
+        #[repr(transparent)]
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Bgr([u8; 3]);
+
+        unsafe impl PixelBufferFormat for Bgr {
+            type Array = [u8; 3];
+            const DEFAULT: Bgr = Bgr([0; 3]);
+            const FORMAT_TYPE: PixelBufferFormatType = PixelBufferFormatType::BGR;
+
+            fn from_rgb(r: u8, g: u8, b: u8) -> Bgr {
+                Bgr([b, g, r])
+            }
+            fn from_raw_slice(raw: &[u8]) -> &[Bgr] {
+                unsafe { slice::from_raw_parts(raw.as_ptr() as *const Bgr, raw.len() / 3) }
+            }
+            fn from_raw_slice_mut(raw: &mut [u8]) -> &mut [Bgr] {
+                unsafe { slice::from_raw_parts_mut(raw.as_mut_ptr() as *mut Bgr, raw.len() / 3) }
+            }
+            fn to_raw_slice(slice: &[Bgr]) -> &[u8] {
+                unsafe { slice::from_raw_parts(slice.as_ptr() as *const u8, slice.len() * 3) }
+            }
+            fn to_raw_slice_mut(slice: &mut [Bgr]) -> &mut [u8] {
+                unsafe { slice::from_raw_parts_mut(slice.as_mut_ptr() as *mut u8, slice.len() * 3) }
+            }
+        }
+        impl AsRef<[u8; 3]> for Bgr {
+            fn as_ref(&self) -> &[u8; 3] {
+                &self.0
+            }
+        }
+        impl AsMut<[u8; 3]> for Bgr {
+            fn as_mut(&mut self) -> &mut [u8; 3] {
+                &mut self.0
+            }
+        }
+        impl Borrow<[u8; 3]> for Bgr {
+            fn borrow(&self) -> &[u8; 3] {
+                &self.0
+            }
+        }
+        impl BorrowMut<[u8; 3]> for Bgr {
+            fn borrow_mut(&mut self) -> &mut [u8; 3] {
+                &mut self.0
+            }
+        }
+        impl AsRef<Bgr> for [u8; 3] {
+            fn as_ref(&self) -> &Bgr {
+                unsafe { &*(self as *const [u8; 3] as *const Bgr) }
+            }
+        }
+        impl AsMut<Bgr> for [u8; 3] {
+            fn as_mut(&mut self) -> &mut Bgr {
+                unsafe { &mut *(self as *mut [u8; 3] as *mut Bgr) }
+            }
+        }
+        impl Borrow<Bgr> for [u8; 3] {
+            fn borrow(&self) -> &Bgr {
+                self.as_ref()
+            }
+        }
+        impl BorrowMut<Bgr> for [u8; 3] {
+            fn borrow_mut(&mut self) -> &mut Bgr {
+                self.as_mut()
+            }
+        }
+
+        assert_eq!(
+            <Bgr as PixelBufferFormat>::from_rgba(1, 2, 3, 4),
+            Bgr([3, 2, 1])
+        );
+        assert_eq!(
+            <Bgr as PixelBufferFormat>::to_rgba(Bgr([3, 2, 1])),
+            [1, 2, 3, 255]
+        );
+    }
+
+    /// The pixel at `(x, y)` of a buffer whose pixels record their own position.
+    fn numbered(x: u32, y: u32) -> BGRA {
+        BGRA::from_rgb(x as u8, y as u8, 1)
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that writes through `columns_mut` land on the pixels
+    /// `rows` reads back, in both the typed and untyped buffers.
+    fn columns_mut_writes_pixels() {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(5, 3).unwrap();
+        assert_eq!(pb.columns_mut().len(), 5);
+        for (x, column) in pb.columns_mut().enumerate() {
+            assert_eq!(column.len(), 3);
+            for (y, pixel) in column.enumerate() {
+                *pixel = numbered(x as u32, y as u32);
+            }
+        }
+        for (y, row) in pb.rows().enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                assert_eq!(pixel, numbered(x as u32, y as u32));
+            }
+        }
+
+        let mut pb = PixelBuffer::new_offscreen(5, 3, PixelBufferFormatType::BGR).unwrap();
+        for (x, column) in pb.columns_mut().rev().enumerate() {
+            for (y, pixel) in column.rev().enumerate() {
+                pixel.copy_from_slice(&[x as u8, y as u8, 1]);
+            }
+        }
+        for (y, row) in pb.rows().enumerate() {
+            for (x, pixel) in row.chunks(3).enumerate() {
+                assert_eq!(pixel, [4 - x as u8, 2 - y as u8, 1]);
+            }
+        }
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that `for_each_column_mut` hands out every column
+    /// top to bottom and writes changes back, for buffers that don't divide into whole tiles.
+    fn for_each_column_mut_round_trips() {
+        let (width, height) = (TRANSPOSE_TILE as u32 + 13, 2 * TRANSPOSE_TILE as u32 + 7);
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(width, height).unwrap();
+        pb.generate(numbered);
+        let mut visited = vec![];
+        pb.for_each_column_mut(|x, column| {
+            visited.push(x);
+            assert_eq!(column.len(), height as usize);
+            for (y, pixel) in column.iter_mut().enumerate() {
+                assert_eq!(*pixel, numbered(x, y as u32));
+                *pixel = numbered(y as u32, x);
+            }
+        });
+        assert_eq!(visited, (0..width).collect::<Vec<_>>());
+        for (y, row) in pb.rows().enumerate() {
+            for (x, &pixel) in row.iter().enumerate() {
+                assert_eq!(pixel, numbered(y as u32, x as u32));
+            }
+        }
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that `take_damage` hands out each region that
+    /// changed, skipping ones inside others and merging them once there are too many, and clears
+    /// them.
+    fn take_damage_clears_dirty_region() {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(31, 31).unwrap();
+        assert_eq!(pb.take_damage(), [Rect::new((0, 0), (31, 31))]);
+        assert_eq!(pb.take_damage(), []);
+        pb.row_mut(4).unwrap();
+        pb.mark_dirty((2, 8), (3, 3));
+        pb.mark_dirty((3, 9), (1, 1));
+        assert_eq!(pb.dirty_region(), Some(Rect::new((0, 4), (31, 7))));
+        assert_eq!(
+            pb.take_damage(),
+            [Rect::new((0, 4), (31, 1)), Rect::new((2, 8), (3, 3))]
+        );
+        assert_eq!(pb.dirty_region(), None);
+
+        pb.mark_dirty((3, 9), (1, 1));
+        pb.mark_dirty((2, 8), (3, 3));
+        assert_eq!(pb.take_damage(), [Rect::new((2, 8), (3, 3))]);
+
+        for i in 0..=MAX_DAMAGE_RECTS as u32 {
+            pb.mark_dirty((i, i), (1, 1));
+        }
+        let bounds = Rect::new(
+            (0, 0),
+            (MAX_DAMAGE_RECTS as u32 + 1, MAX_DAMAGE_RECTS as u32 + 1),
+        );
+        assert_eq!(pb.dirty_region(), Some(bounds));
+        assert_eq!(pb.take_damage(), [bounds]);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that the frame index counts successful presents
+    /// only, and is reported alongside the metrics.
+    fn frame_index_counts_presents() {
+        struct Discard;
+        impl CustomBackend for Discard {
+            fn present(&mut self, _: BackendRows<'_>, _: &[Rect]) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(7, 7).unwrap();
+        assert_eq!(pb.frame_index(), 0);
+        pb.mark_all_dirty();
+        pb.present_to(&mut Discard).unwrap();
+        assert_eq!(pb.frame_index(), 1);
+        // Nothing changed, so there's nothing to present.
+        pb.present_to(&mut Discard).unwrap();
+        assert_eq!(pb.frame_index(), 1);
+        pb.mark_all_dirty();
+        pb.present_to(&mut Discard).unwrap();
+        assert_eq!(pb.frame_index(), 2);
+        assert_eq!(pb.metrics().frame_index, 2);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that rows written as `MaybeUninit` memory read back
+    /// as what was written, and are marked dirty.
+    fn rows_uninit_mut_writes_pixels() {
+        let options = BufferOptions::new().init(BufferInit::Uninit);
+        let mut pb =
+            PixelBuffer::new_offscreen_with_options(3, 2, PixelBufferFormatType::BGRA, options)
+                .unwrap();
+        pb.take_damage();
+        for (y, row) in unsafe { pb.rows_uninit_mut() }.enumerate() {
+            for (i, byte) in row.iter_mut().enumerate() {
+                byte.write((y * 16 + i) as u8);
+            }
+        }
+        assert_eq!(pb.take_damage(), [Rect::new((0, 0), (3, 2))]);
+        assert_eq!(pb.row(1).unwrap()[..4], [16, 17, 18, 19]);
+
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(3, 2).unwrap();
+        let row = unsafe { pb.row_uninit_mut(1) }.unwrap();
+        row[2].write(BGRA::from_rgb(1, 2, 3));
+        assert_eq!(pb.row(1).unwrap()[2], BGRA::from_rgb(1, 2, 3));
+        assert!(unsafe { pb.row_uninit_mut(2) }.is_none());
+    }
+}
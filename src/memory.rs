@@ -0,0 +1,82 @@
+use std::{
+    cell::Cell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static USAGE: AtomicUsize = AtomicUsize::new(0);
+/// `usize::MAX` when there's no budget.
+static BUDGET: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// The number of bytes held by every pixel buffer in the process, including their scratch space
+/// and previous frames.
+///
+/// Useful for applications with dozens of windows or very large canvases, to see what their
+/// buffers cost. See [`PixelBuffer::memory_usage`](crate::PixelBuffer::memory_usage) for the
+/// usage of a single buffer.
+pub fn total_memory_usage() -> usize {
+    USAGE.load(Ordering::Relaxed)
+}
+
+/// The process-wide memory budget set with [`set_memory_budget`], if any.
+pub fn memory_budget() -> Option<usize> {
+    match BUDGET.load(Ordering::Relaxed) {
+        usize::MAX => None,
+        budget => Some(budget),
+    }
+}
+
+/// Hints how many bytes all pixel buffers in the process should hold at most.
+///
+/// This is a hint rather than a limit: buffers are never refused or freed because of it. While
+/// the [total usage](total_memory_usage) is over budget, memory that's only kept around to speed
+/// up later blits, such as the [scratch space](crate::PixelBuffer::scratch_capacity), is released
+/// after every blit instead.
+pub fn set_memory_budget(bytes: Option<usize>) {
+    BUDGET.store(bytes.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+/// Plots the total usage in Tracy, so it shows up next to the application's own allocations.
+fn plot_usage() {
+    #[cfg(feature = "tracy")]
+    if let Some(client) = tracy_client::Client::running() {
+        let usage = total_memory_usage() as f64;
+        client.plot(tracy_client::plot_name!("winit-blit memory"), usage);
+    }
+}
+
+pub(crate) fn over_budget() -> bool {
+    total_memory_usage() > BUDGET.load(Ordering::Relaxed)
+}
+
+/// A number of bytes counted towards the process-wide total for as long as it's alive.
+#[derive(Debug, Default)]
+pub(crate) struct Tracked(Cell<usize>);
+
+impl Tracked {
+    pub fn new(bytes: usize) -> Tracked {
+        USAGE.fetch_add(bytes, Ordering::Relaxed);
+        plot_usage();
+        Tracked(Cell::new(bytes))
+    }
+
+    pub fn get(&self) -> usize {
+        self.0.get()
+    }
+
+    pub fn set(&self, bytes: usize) {
+        let old = self.0.replace(bytes);
+        if bytes >= old {
+            USAGE.fetch_add(bytes - old, Ordering::Relaxed);
+        } else {
+            USAGE.fetch_sub(old - bytes, Ordering::Relaxed);
+        }
+        plot_usage();
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        USAGE.fetch_sub(self.0.get(), Ordering::Relaxed);
+        plot_usage();
+    }
+}
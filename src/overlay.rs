@@ -0,0 +1,124 @@
+use crate::{draw, PixelBufferFormat, PixelBufferTyped, TextGrid};
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+/// The environment variable that enables [`DebugOverlay`]s created with
+/// [`DebugOverlay::from_env`].
+pub const DEBUG_OVERLAY_ENV_VAR: &str = "WINIT_BLIT_DEBUG_OVERLAY";
+
+/// Draws frame pacing diagnostics onto a pixel buffer right before it's presented.
+///
+/// The overlay shows the time since the previous frame, how long the previous blit took, and
+/// outlines every region reported through [`add_dirty_rect`](Self::add_dirty_rect).
+#[derive(Debug, Clone, Default)]
+pub struct DebugOverlay {
+    enabled: bool,
+    last_frame: Option<Instant>,
+    dirty_rects: Vec<((i32, i32), (u32, u32))>,
+}
+
+impl DebugOverlay {
+    /// Creates an enabled overlay.
+    pub fn new() -> DebugOverlay {
+        DebugOverlay {
+            enabled: true,
+            ..DebugOverlay::default()
+        }
+    }
+
+    /// Creates an overlay that's only enabled if the `WINIT_BLIT_DEBUG_OVERLAY` environment
+    /// variable is set to something other than an empty string or `0`.
+    pub fn from_env() -> DebugOverlay {
+        let enabled = env::var_os(DEBUG_OVERLAY_ENV_VAR)
+            .is_some_and(|value| !value.is_empty() && value != "0");
+        DebugOverlay {
+            enabled,
+            ..DebugOverlay::default()
+        }
+    }
+
+    /// Whether the overlay draws anything.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Sets whether the overlay draws anything.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Reports a region that was redrawn this frame, so it gets outlined by the next call to
+    /// [`draw`](Self::draw).
+    pub fn add_dirty_rect(&mut self, pos: (i32, i32), size: (u32, u32)) {
+        if self.enabled {
+            self.dirty_rects.push((pos, size));
+        }
+    }
+
+    /// Draws the overlay onto `buffer`. Call this right before blitting the buffer.
+    ///
+    /// Frame times are measured between successive calls, so this should be called once per
+    /// frame. The dirty rectangles reported since the previous call are cleared.
+    pub fn draw<P: PixelBufferFormat + PartialEq>(&mut self, buffer: &mut PixelBufferTyped<P>) {
+        let now = Instant::now();
+        let frame_time = self.last_frame.map(|last| now - last);
+        self.last_frame = Some(now);
+        if !self.enabled {
+            return;
+        }
+
+        let highlight = P::from_rgb(255, 0, 255);
+        for (pos, size) in self.dirty_rects.drain(..) {
+            draw::rect(&mut *buffer, pos, size, highlight);
+        }
+
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let frame = match frame_time {
+            Some(frame_time) => format!("frame   {:7.2} ms", millis(frame_time)),
+            None => "frame         - ms".to_string(),
+        };
+        let present = format!("present {:7.2} ms", millis(buffer.metrics().upload_time));
+
+        let (fg, bg) = (P::from_rgb(255, 255, 255), P::from_rgb(0, 0, 0));
+        let mut text = TextGrid::new(frame.len() as u32, 2, fg, bg);
+        text.print(0, 0, &frame, fg, bg);
+        text.print(0, 1, &present, fg, bg);
+        text.render(&mut *buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BGRA;
+
+    const BLANK: BGRA = BGRA::new(0, 0, 0, 0);
+    const HIGHLIGHT: BGRA = BGRA::from_rgb(255, 0, 255);
+
+    #[test]
+    /// The purpose of this test is to verify that an enabled overlay outlines the reported dirty
+    /// rectangles once and draws its text panel, and that a disabled one leaves the buffer alone.
+    fn draw_outlines_dirty_rects_and_text() {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(200, 40).unwrap();
+        let mut overlay = DebugOverlay::default();
+        overlay.add_dirty_rect((160, 20), (10, 10));
+        overlay.draw(&mut pb);
+        assert!(pb.rows().flatten().all(|&pixel| pixel == BLANK));
+
+        overlay.set_enabled(true);
+        overlay.add_dirty_rect((160, 20), (10, 10));
+        overlay.draw(&mut pb);
+        assert_eq!(pb.row(20).unwrap()[160], HIGHLIGHT);
+        assert_eq!(pb.row(29).unwrap()[169], HIGHLIGHT);
+        assert_eq!(pb.row(25).unwrap()[165], BLANK);
+        // The text panel is drawn with an opaque background in the top-left corner.
+        assert_ne!(pb.row(0).unwrap()[0], BLANK);
+        assert_eq!(pb.row(16).unwrap()[0], BLANK);
+
+        pb.clear(BLANK);
+        overlay.draw(&mut pb);
+        assert_eq!(pb.row(20).unwrap()[160], BLANK);
+    }
+}
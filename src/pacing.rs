@@ -0,0 +1,94 @@
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+use winit::event_loop::EventLoopProxy;
+
+use crate::platform_impl;
+
+/// Wakes a winit event loop once the compositor has consumed the previously presented frame.
+///
+/// Call [`frame_presented`](Self::frame_presented) after every blit. Once the compositor has
+/// picked up that frame, `event` is sent to the event loop as an [`Event::UserEvent`], which is
+/// the right moment to call [`Window::request_redraw`]. Rendering in response to
+/// [`WindowEvent::RedrawRequested`] then runs in step with the display, without any timers.
+///
+/// The waiting happens on a background thread, which exits when the pacer is dropped or the event
+/// loop closes.
+///
+/// [`Event::UserEvent`]: winit::event::Event::UserEvent
+/// [`Window::request_redraw`]: winit::window::Window::request_redraw
+/// [`WindowEvent::RedrawRequested`]: winit::event::WindowEvent::RedrawRequested
+pub struct FramePacer {
+    presented: Sender<()>,
+}
+
+impl FramePacer {
+    /// Creates a pacer that sends `event` through `proxy` whenever a frame has been consumed.
+    pub fn new<T: Clone + Send + 'static>(proxy: EventLoopProxy<T>, event: T) -> FramePacer {
+        let (presented, frames) = mpsc::channel::<()>();
+        thread::Builder::new()
+            .name("winit-blit frame pacer".to_string())
+            .spawn(move || pace(frames, || proxy.send_event(event.clone()).is_ok()))
+            .expect("failed to spawn frame pacing thread");
+        FramePacer { presented }
+    }
+
+    /// Reports that a frame has just been presented, so the event loop gets woken up once the
+    /// compositor has consumed it.
+    pub fn frame_presented(&self) {
+        // The thread only exits once the event loop is closed, at which point there's nobody left
+        // to wake up.
+        let _ = self.presented.send(());
+    }
+}
+
+/// Calls `wake` once the compositor has consumed the frames reported through `frames`, until
+/// either every sender is gone or `wake` returns `false`.
+fn pace(frames: Receiver<()>, mut wake: impl FnMut() -> bool) {
+    while frames.recv().is_ok() {
+        // Frames presented while waiting are covered by the same wakeup.
+        while frames.try_recv().is_ok() {}
+        platform_impl::wait_for_composition();
+        if !wake() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// The purpose of this test is to verify that frames presented before the pacer gets around
+    /// to waiting share a single wakeup, and that pacing stops once the pacer is dropped.
+    fn pace_coalesces_presented_frames() {
+        let (presented, frames) = mpsc::channel();
+        for _ in 0..3 {
+            presented.send(()).unwrap();
+        }
+        drop(presented);
+        let mut wakeups = 0;
+        pace(frames, || {
+            wakeups += 1;
+            true
+        });
+        assert_eq!(wakeups, 1);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that pacing stops once the event loop can't be woken
+    /// up anymore, even while frames are still being presented.
+    fn pace_stops_when_wakeup_fails() {
+        let (presented, frames) = mpsc::channel();
+        presented.send(()).unwrap();
+        let mut wakeups = 0;
+        pace(frames, || {
+            wakeups += 1;
+            false
+        });
+        assert_eq!(wakeups, 1);
+        assert!(presented.send(()).is_err());
+    }
+}
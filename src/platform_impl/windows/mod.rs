@@ -1,352 +1,3640 @@
-use crate::{PixelBufferCreationError, PixelBufferFormatSupported, PixelBufferFormatType};
-use raw_window_handle::{windows::WindowsHandle, RawWindowHandle};
-use std::{convert::TryInto, io, ptr};
+use crate::{
+    converter::PresentStage,
+    memory::{self, Tracked},
+    report_platform_error, AlphaBehavior, Backpressure, BlitError, BlitMetrics, BlitRegion,
+    BufferAllocator, BufferInit, BufferOptions, CancelToken, Capabilities, ChannelDepth,
+    ConversionOptions, DisplayInfo, Filter, MismatchPolicy, PixelBufferCreationError,
+    PixelBufferFormatSupported, PixelBufferFormatType, PlatformError, PresentConversion,
+    SharedMemory,
+};
+use raw_window_handle::{HandleError, RawWindowHandle, Win32WindowHandle};
+use std::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    convert::TryFrom,
+    ffi::c_void,
+    io, mem,
+    num::{NonZeroIsize, NonZeroU32},
+    os::windows::io::RawHandle,
+    ptr, slice,
+    time::Instant,
+};
 use winapi::{
-    shared::windef::{HBITMAP, HWND},
+    shared::{
+        dxgi::{CreateDXGIFactory1, IDXGIAdapter1, IDXGIFactory1, IDXGIOutput},
+        dxgi1_6::{IDXGIOutput6, DXGI_OUTPUT_DESC1},
+        dxgitype::DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020,
+        minwindef::{FALSE, TRUE},
+        ntdef::LARGE_INTEGER,
+        windef::{HBITMAP, HDC, HICON, HMONITOR, HWND, RECT},
+    },
     um::{
+        dwmapi, handleapi, memoryapi, synchapi, winbase,
         wingdi::{self, BITMAP, BITMAPINFOHEADER},
+        winnt::{self, HANDLE},
         winuser,
     },
+    Interface,
 };
 
 #[cfg(feature = "rayon")]
 use rayon::prelude::*;
 
+/// How many rows are converted between checks of the cancel token.
+const CANCEL_BAND_ROWS: usize = 64;
+
 pub struct PixelBuffer {
     handle: HBITMAP,
+    /// Describes the pixels. Its width includes the padding added for the row alignment.
     bitmap: BITMAP,
+    width: u32,
+    /// The format the buffer was created with, which may differ from the bitmap's if it was
+    /// created with a `MismatchPolicy` other than `Error`.
+    format: PixelBufferFormatType,
+    /// The options the buffer was created with.
+    options: BufferOptions,
     len: usize,
-    hwnd: HWND,
+    /// The windows the buffer may be blitted onto. The first is the one it was created for.
+    windows: Vec<HWND>,
+    metrics: Cell<BlitMetrics>,
+    /// Lookup table applied to every color channel while blitting, if any.
+    color_lut: Option<Box<[u8; 256]>>,
+    /// User-provided conversion used while blitting instead of `color_lut`, if any.
+    conversion: Option<PresentConversion>,
+    /// Holds the adjusted pixels when blitting with a `conversion`, `color_lut` or
+    /// `remote_depth`.
+    scratch: RefCell<Vec<u8>>,
+    /// The pixels and the scratch space, counted towards the process-wide memory usage.
+    memory: Tracked,
+    /// The color depth the buffer is reduced to while blitting in a remote session, if any.
+    remote_depth: Option<(ChannelDepth, ConversionOptions)>,
+    allow_conversion: bool,
+    /// Whether blits wait for the compositor to finish its next frame before returning.
+    frame_throttle: bool,
+    present_limit: Option<(NonZeroU32, Backpressure)>,
+    /// Checked while blitting, to abandon the blit once it's cancelled.
+    cancel: Option<CancelToken>,
+    /// The compositor frame the buffer was last presented during, and how many presents were
+    /// made during it.
+    pending_presents: Cell<(u64, u32)>,
+    /// The file mapping the DIB section lives in, for buffers created with `new_shared`. Null
+    /// otherwise.
+    ///
+    /// Unlike System V shared memory, the mapping is a kernel object that's released once every
+    /// process holding a handle to it exits, so a crashing application can't leak it.
+    section: HANDLE,
+    /// Set when the pixels live in memory from a user-supplied allocator rather than in a DIB
+    /// section. Such buffers are blitted with `SetDIBitsToDevice`.
+    allocation: Option<Allocation>,
+    /// The windows `flush_presents` blits onto, in the order their presents were deferred.
+    deferred: Vec<HWND>,
+    /// The levels built by `build_mipmaps`, each half the size of the one before, starting at
+    /// half the size of the buffer.
+    pub(crate) mipmaps: Vec<Mipmap>,
+    mipmap_memory: Tracked,
+}
+
+enum Storage {
+    Dib,
+    /// A DIB section in a file mapping that can be shared with other processes.
+    SharedDib,
+    Allocator(Box<dyn BufferAllocator>),
+}
+
+/// Forwards to an allocator owned by another buffer. See `PixelBuffer::resized`.
+struct AllocatorRef(*const dyn BufferAllocator);
+
+// The allocator it points to is `Send`, and outlives it: `resized` hands the allocator over to
+// the buffer holding the reference as soon as it's been created.
+unsafe impl Send for AllocatorRef {}
+
+unsafe impl BufferAllocator for AllocatorRef {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        unsafe { (*self.0).allocate(layout) }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        (*self.0).deallocate(ptr, layout)
+    }
+}
+
+struct Allocation {
+    allocator: Box<dyn BufferAllocator>,
+    layout: Layout,
+    info: BITMAPINFOHEADER,
+}
+
+/// A downscaled copy of the buffer, blitted with `StretchDIBits`. Its rows are stored bottom-up,
+/// like the buffer's.
+pub(crate) struct Mipmap {
+    info: BITMAPINFOHEADER,
+    bits: Vec<u8>,
+}
+
+impl Mipmap {
+    fn width(&self) -> u32 {
+        self.info.biWidth as u32
+    }
+
+    fn height(&self) -> u32 {
+        self.info.biHeight as u32
+    }
+
+    fn row_len(&self) -> usize {
+        self.bits.len() / self.height() as usize
+    }
+
+    pub(crate) fn row(&self, row: u32) -> &[u8] {
+        let index = (self.height() - 1 - row) as usize * self.row_len();
+        &self.bits[index..index + self.row_len()]
+    }
+
+    /// Averages every 2x2 block of the `width` by `height` pixels returned by `row` into one
+    /// pixel. A trailing row or column left over by an odd size is dropped, and a size of 1
+    /// stays 1.
+    fn halve<'a>(
+        width: u32,
+        height: u32,
+        bytes_per_pixel: usize,
+        row: impl Fn(u32) -> &'a [u8],
+    ) -> Mipmap {
+        let (dst_width, dst_height) = ((width / 2).max(1), (height / 2).max(1));
+        // Rows of a DIB are padded to a multiple of 4 bytes.
+        let row_len = (dst_width as usize * bytes_per_pixel + 3) & !3;
+        let mut bits = vec![0; row_len * dst_height as usize];
+        for y in 0..dst_height {
+            let (top, bottom) = (row(2 * y), row((2 * y + 1).min(height - 1)));
+            let start = (dst_height - 1 - y) as usize * row_len;
+            let dst = &mut bits[start..start + dst_width as usize * bytes_per_pixel];
+            for (x, dst) in dst.chunks_exact_mut(bytes_per_pixel).enumerate() {
+                let left = 2 * x * bytes_per_pixel;
+                let right = (2 * x + 1).min(width as usize - 1) * bytes_per_pixel;
+                for (i, dst) in dst.iter_mut().enumerate() {
+                    let sum = top[left + i] as u32
+                        + top[right + i] as u32
+                        + bottom[left + i] as u32
+                        + bottom[right + i] as u32;
+                    *dst = ((sum + 2) / 4) as u8;
+                }
+            }
+        }
+        Mipmap {
+            info: BITMAPINFOHEADER {
+                biSize: mem::size_of::<BITMAPINFOHEADER>() as _,
+                biWidth: px_cast(dst_width),
+                biHeight: px_cast(dst_height),
+                biPlanes: 1,
+                biBitCount: (bytes_per_pixel * 8) as _,
+                biCompression: wingdi::BI_RGB,
+                biSizeImage: 0,
+                biXPelsPerMeter: 1,
+                biYPelsPerMeter: 1,
+                biClrUsed: 0,
+                biClrImportant: 0,
+            },
+            bits,
+        }
+    }
 }
 
+// GDI bitmaps, file mappings and window handles can be used from any thread, and `GetDC` works
+// on windows owned by other threads. The buffer only selects its bitmap into device contexts
+// that live for the duration of a single call, so moving it between calls is sound. It's
+// deliberately not `Sync`: a bitmap can only be selected into one device context at a time, and
+// the metrics and scratch space are kept in cells.
 unsafe impl Send for PixelBuffer {}
 
+/// The size, in bytes, of the largest pixel buffer GDI can describe.
+const MAX_BUFFER_LEN: u64 = u32::MAX as u64;
+/// The number of rows `blit_streaming` produces before blitting them.
+const STREAMING_BAND_ROWS: u32 = 32;
+
+/// Swaps the first and third byte of the first `width` pixels in `row`, which converts between
+/// red-green-blue and blue-green-red pixels, with or without alpha.
+fn swap_red_blue(row: &mut [u8], width: u32, bytes_per_pixel: usize) {
+    for pixel in row.chunks_exact_mut(bytes_per_pixel).take(width as usize) {
+        pixel.swap(0, 2);
+    }
+}
+
+/// The rows of a `height` row buffer presented by pass `pass` of a progressive present with the
+/// given `interval`, as `(row, height)`, where `height` is the number of rows of the window the
+/// row is stretched over.
+fn progressive_pass(pass: u32, interval: u32, height: u32) -> impl Iterator<Item = (u32, u32)> {
+    (pass..height)
+        .step_by(interval as usize)
+        .map(move |y| match pass {
+            // The first pass stretches every row over the ones that are still missing, so the whole
+            // image shows up at once, just blocky.
+            0 => (y, interval.min(height - y)),
+            _ => (y, 1),
+        })
+}
+
+/// Converts a coordinate to the signed type GDI uses. Coordinates too large to fit are clamped,
+/// which GDI clips away anyway; buffer dimensions are validated when the buffer is created.
 fn px_cast(u: u32) -> i32 {
-    u.try_into()
-        .expect("Pixel value too large; must be less than 2,147,483,647")
+    u.min(i32::MAX as u32) as i32
 }
 
 impl PixelBufferFormatSupported for crate::BGRA {}
 impl PixelBufferFormatSupported for crate::BGR {}
 pub type NativeFormat = crate::BGRA;
 
-fn hwnd(handle: RawWindowHandle) -> HWND {
+fn hwnd(handle: RawWindowHandle) -> Result<HWND, HandleError> {
     match handle {
-        RawWindowHandle::Windows(WindowsHandle { hwnd, .. }) => hwnd as _,
-        _ => panic!("Unsupported window handle type"),
+        RawWindowHandle::Win32(handle) => Ok(handle.hwnd.get() as _),
+        _ => Err(HandleError::NotSupported),
     }
 }
 
-impl PixelBuffer {
-    pub unsafe fn new(
-        width: u32,
-        height: u32,
-        format: PixelBufferFormatType,
-        raw_window_handle: RawWindowHandle,
-    ) -> Result<PixelBuffer, PixelBufferCreationError> {
-        let bit_count = match format {
-            PixelBufferFormatType::BGRA => 32,
-            PixelBufferFormatType::BGR => 24,
-            _ => return Err(PixelBufferCreationError::FormatNotSupported),
-        };
-        let handle: HBITMAP;
-        let bitmap: BITMAP;
-        if width != 0 && height != 0 {
-            handle = {
-                let info = BITMAPINFOHEADER {
-                    biSize: std::mem::size_of::<BITMAPINFOHEADER>() as _,
-                    biWidth: px_cast(width),
-                    biHeight: px_cast(height),
-                    biPlanes: 1,
-                    biBitCount: bit_count,
-                    biCompression: wingdi::BI_RGB,
-                    biSizeImage: 0,
-                    biXPelsPerMeter: 1,
-                    biYPelsPerMeter: 1,
-                    biClrUsed: 0,
-                    biClrImportant: 0,
-                };
-                let dc = winuser::GetDC(ptr::null_mut());
-                let dib_section = wingdi::CreateDIBSection(
-                    dc,
-                    &info as *const BITMAPINFOHEADER as _,
-                    wingdi::DIB_RGB_COLORS,
-                    &mut ptr::null_mut(),
-                    ptr::null_mut(),
-                    0,
-                );
-                winuser::ReleaseDC(ptr::null_mut(), dc);
-                dib_section
-            };
+/// The inverse of `hwnd`, for windows the buffer only kept the `HWND` of.
+fn raw_handle(hwnd: HWND) -> RawWindowHandle {
+    let hwnd = NonZeroIsize::new(hwnd as isize).expect("null window handle");
+    RawWindowHandle::Win32(Win32WindowHandle::new(hwnd))
+}
 
-            assert_ne!(std::ptr::null_mut(), handle);
-            bitmap = {
-                let mut bitmap: BITMAP = std::mem::zeroed();
-                let bytes_written = wingdi::GetObjectW(
-                    handle as _,
-                    std::mem::size_of::<BITMAP>() as i32,
-                    &mut bitmap as *mut BITMAP as *mut _,
-                );
-                assert_ne!(0, bytes_written);
-                bitmap
-            };
-        } else {
-            handle = ptr::null_mut();
-            bitmap = BITMAP {
-                bmType: 0,
-                bmWidth: px_cast(width),
-                bmHeight: px_cast(height),
-                bmWidthBytes: px_cast(width * bit_count as u32 / 8),
-                bmPlanes: 1,
-                bmBitsPixel: bit_count,
-                bmBits: ptr::null_mut(),
-            };
-        }
-        Ok(PixelBuffer {
-            handle,
-            bitmap,
-            len: (bitmap.bmWidthBytes * bitmap.bmHeight) as usize,
-            hwnd: hwnd(raw_window_handle),
-        })
+/// Checks that pixel buffers can be presented onto the window behind `handle`.
+unsafe fn check_window(handle: RawWindowHandle) -> Result<HWND, BlitError> {
+    let hwnd = hwnd(handle)?;
+    if winuser::IsWindow(hwnd) == 0 {
+        return Err(BlitError::WindowDestroyed);
     }
-    pub unsafe fn blit(&self, handle: RawWindowHandle) -> io::Result<()> {
-        self.blit_rect((0, 0), (0, 0), (self.width(), self.height()), handle)
+    if !supports_bitmaps(hwnd).map_err(io::Error::from)? {
+        return Err(io::Error::other(NO_BITMAP_SUPPORT).into());
     }
+    Ok(hwnd)
+}
 
-    pub unsafe fn blit_rect(
-        &self,
-        src_pos: (u32, u32),
-        dst_pos: (u32, u32),
-        blit_size: (u32, u32),
-        handle: RawWindowHandle,
-    ) -> io::Result<()> {
-        if self.handle == ptr::null_mut() {
-            return Ok(());
-        }
-        let hwnd = hwnd(handle);
-        assert_eq!(hwnd, self.hwnd);
-        let hdc = winuser::GetDC(hwnd as _);
+const NO_BITMAP_SUPPORT: &str = "window's device doesn't support bitmap transfers";
 
-        let src_dc = wingdi::CreateCompatibleDC(hdc);
-        let prev_bmp = wingdi::SelectObject(src_dc, self.handle as _);
-        let result = wingdi::BitBlt(
-            hdc,
-            px_cast(src_pos.0),
-            px_cast(src_pos.1),
-            px_cast(blit_size.0),
-            px_cast(blit_size.1),
-            src_dc,
-            px_cast(dst_pos.0),
-            px_cast(dst_pos.1),
-            wingdi::SRCCOPY,
-        );
-        let error = io::Error::last_os_error();
+/// Whether the device behind `hwnd` accepts bitmaps, which both present paths need.
+unsafe fn supports_bitmaps(hwnd: HWND) -> Result<bool, PlatformError> {
+    let hdc = winuser::GetDC(hwnd);
+    if hdc.is_null() {
+        return Err(PlatformError::report("GetDC", hwnd as _));
+    }
+    let caps = wingdi::GetDeviceCaps(hdc, wingdi::RASTERCAPS) as u32;
+    winuser::ReleaseDC(hwnd, hdc);
+    let required = wingdi::RC_BITBLT | wingdi::RC_DIBTODEV;
+    Ok(caps & required == required)
+}
 
-        wingdi::SelectObject(src_dc, prev_bmp);
-        wingdi::DeleteDC(src_dc);
-        winuser::ReleaseDC(hwnd, hdc);
+pub unsafe fn capabilities(handle: RawWindowHandle) -> Result<Capabilities, BlitError> {
+    check_window(handle)?;
+    Ok(Capabilities {
+        // Every GDI path drops alpha, and the stretching ones scale.
+        supports_alpha: false,
+        supports_scaling: true,
+        // Buffers are DIB sections in the native format by default, which are blitted straight
+        // from their own memory.
+        zero_copy: true,
+        max_buffer_size: MAX_BUFFER_LEN,
+        preferred_format: PixelBufferFormatType::NATIVE,
+        remote: is_remote_session(),
+    })
+}
 
-        if result != 0 {
-            Ok(())
-        } else {
-            Err(error)
-        }
+pub unsafe fn display_info(handle: RawWindowHandle) -> Result<DisplayInfo, BlitError> {
+    let hwnd = check_window(handle)?;
+    let monitor = winuser::MonitorFromWindow(hwnd, winuser::MONITOR_DEFAULTTONEAREST);
+    if let Some(desc) = output_desc(monitor) {
+        return Ok(DisplayInfo {
+            bits_per_color: desc.BitsPerColor,
+            hdr: desc.ColorSpace == DXGI_COLOR_SPACE_RGB_FULL_G2084_NONE_P2020,
+            min_luminance: Some(desc.MinLuminance),
+            max_luminance: Some(desc.MaxLuminance),
+            max_full_frame_luminance: Some(desc.MaxFullFrameLuminance),
+        });
     }
-
-    pub fn bits_per_pixel(&self) -> usize {
-        self.bitmap.bmBitsPixel as usize
+    // `IDXGIOutput6` needs Windows 10. GDI only knows the desktop's depth, which is never more
+    // than 8 bits per color.
+    let hdc = winuser::GetDC(hwnd);
+    if hdc.is_null() {
+        let error = PlatformError::report("GetDC", hwnd as _);
+        return Err(io::Error::from(error).into());
     }
+    let bits = wingdi::GetDeviceCaps(hdc, wingdi::BITSPIXEL) as u32;
+    winuser::ReleaseDC(hwnd, hdc);
+    Ok(DisplayInfo {
+        bits_per_color: bits.min(24) / 3,
+        hdr: false,
+        min_luminance: None,
+        max_luminance: None,
+        max_full_frame_luminance: None,
+    })
+}
 
-    pub fn bytes_per_pixel(&self) -> usize {
-        self.bits_per_pixel() / 8
+/// Finds the DXGI output driving `monitor`, and describes it.
+unsafe fn output_desc(monitor: HMONITOR) -> Option<DXGI_OUTPUT_DESC1> {
+    let mut factory: *mut IDXGIFactory1 = ptr::null_mut();
+    let iid = IDXGIFactory1::uuidof();
+    if CreateDXGIFactory1(&iid, &mut factory as *mut _ as *mut *mut _) < 0 {
+        return None;
     }
-
-    pub fn width(&self) -> u32 {
-        self.bitmap.bmWidth as u32
+    let mut found = None;
+    let mut adapter_index = 0;
+    while found.is_none() {
+        let mut adapter: *mut IDXGIAdapter1 = ptr::null_mut();
+        if (*factory).EnumAdapters1(adapter_index, &mut adapter) < 0 {
+            break;
+        }
+        let mut output_index = 0;
+        while found.is_none() {
+            let mut output: *mut IDXGIOutput = ptr::null_mut();
+            if (*adapter).EnumOutputs(output_index, &mut output) < 0 {
+                break;
+            }
+            let mut output6: *mut IDXGIOutput6 = ptr::null_mut();
+            let iid = IDXGIOutput6::uuidof();
+            if (*output).QueryInterface(&iid, &mut output6 as *mut _ as *mut *mut _) >= 0 {
+                let mut desc: DXGI_OUTPUT_DESC1 = mem::zeroed();
+                if (*output6).GetDesc1(&mut desc) >= 0 && desc.Monitor == monitor {
+                    found = Some(desc);
+                }
+                (*output6).Release();
+            }
+            (*output).Release();
+            output_index += 1;
+        }
+        (*adapter).Release();
+        adapter_index += 1;
     }
+    (*factory).Release();
+    found
+}
 
-    pub fn row_len(&self) -> usize {
-        self.bitmap.bmWidthBytes as usize
-    }
+/// Whether the desktop is shown over Remote Desktop, where every blit is sent over the network.
+fn is_remote_session() -> bool {
+    unsafe { winuser::GetSystemMetrics(winuser::SM_REMOTESESSION) != 0 }
+}
 
-    pub fn height(&self) -> u32 {
-        self.bitmap.bmHeight as u32
-    }
+/// Blocks until the compositor has consumed the frames presented so far.
+pub fn wait_for_composition() {
+    // Fails when desktop composition is off, in which case there's nothing to wait for.
+    unsafe { dwmapi::DwmFlush() };
+}
 
-    fn bytes(&self) -> &[u8] {
-        if self.handle == ptr::null_mut() {
-            return &[];
+/// The number of the frame the compositor finished most recently, or `None` if desktop
+/// composition is off.
+fn composed_frame() -> Option<u64> {
+    unsafe {
+        let mut info: dwmapi::DWM_TIMING_INFO = mem::zeroed();
+        info.cbSize = mem::size_of::<dwmapi::DWM_TIMING_INFO>() as u32;
+        match dwmapi::DwmGetCompositionTimingInfo(ptr::null_mut(), &mut info) {
+            result if result < 0 => None,
+            _ => Some(info.cFrame),
         }
-        unsafe { std::slice::from_raw_parts(self.bitmap.bmBits as *const u8, self.len) }
     }
+}
 
-    fn bytes_mut(&mut self) -> &mut [u8] {
-        if self.handle == ptr::null_mut() {
-            return &mut [];
+/// Blits every buffer in `group` onto its window back-to-back, waiting for composition at most
+/// once at the end.
+pub unsafe fn present_group(group: &[(&PixelBuffer, RawWindowHandle)]) -> Result<(), BlitError> {
+    // Check every window up front, so a destroyed window doesn't leave the group half-presented.
+    for &(buffer, handle) in group {
+        let hwnd = hwnd(handle)?;
+        assert!(
+            buffer.windows.contains(&hwnd),
+            "window wasn't registered with the pixel buffer"
+        );
+        if winuser::IsWindow(hwnd) == 0 {
+            return Err(BlitError::WindowDestroyed);
         }
-        unsafe { std::slice::from_raw_parts_mut(self.bitmap.bmBits as *mut u8, self.len) }
     }
-
-    pub fn row(&self, row: u32) -> Option<&[u8]> {
-        let index = self.tlo_to_blo(row) as usize * self.row_len();
-        let pixel_len = self.width() as usize * self.bytes_per_pixel();
-        self.bytes().get(index..index + pixel_len)
+    for &(buffer, handle) in group {
+        let size = (buffer.width(), buffer.height());
+        buffer.blit_rect_inner(
+            (0, 0),
+            (0, 0),
+            size,
+            size,
+            Filter::Nearest,
+            None,
+            handle,
+            false,
+        )?;
     }
-
-    pub fn row_mut(&mut self, row: u32) -> Option<&mut [u8]> {
-        let index = self.tlo_to_blo(row) as usize * self.row_len();
-        let pixel_len = self.width() as usize * self.bytes_per_pixel();
-        self.bytes_mut().get_mut(index..index + pixel_len)
+    // Make sure none of the blits are still sitting in GDI's batch.
+    wingdi::GdiFlush();
+    if group.iter().any(|(buffer, _)| buffer.frame_throttle) {
+        wait_for_composition();
     }
+    Ok(())
+}
 
-    pub fn rows<'a>(&'a self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a [u8]> {
-        let stride = match self.row_len() {
-            0 => 1,
-            l => l,
+/// An icon or cursor created from pixels. Destroyed on drop.
+pub struct Icon {
+    handle: HICON,
+    cursor: bool,
+}
+
+// Icons and cursors aren't tied to the thread that created them, and can be destroyed from any
+// thread.
+unsafe impl Send for Icon {}
+
+impl Icon {
+    /// Creates an icon from top-down, tightly packed BGRA pixels with straight alpha. Passing a
+    /// `hotspot` creates a cursor instead.
+    pub unsafe fn new(
+        width: u32,
+        height: u32,
+        bgra: &[u8],
+        hotspot: Option<(u32, u32)>,
+    ) -> io::Result<Icon> {
+        assert_eq!(bgra.len(), width as usize * height as usize * 4);
+        let info = BITMAPINFOHEADER {
+            biSize: mem::size_of::<BITMAPINFOHEADER>() as _,
+            biWidth: px_cast(width),
+            // Negative heights make the DIB top-down.
+            biHeight: -px_cast(height),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: wingdi::BI_RGB,
+            biSizeImage: 0,
+            biXPelsPerMeter: 1,
+            biYPelsPerMeter: 1,
+            biClrUsed: 0,
+            biClrImportant: 0,
         };
-        let pixel_len = self.width() as usize * self.bytes_per_pixel();
-        self.bytes()
-            .chunks(stride)
-            .rev()
-            .map(move |row| &row[..pixel_len])
-    }
+        let mut bits = ptr::null_mut();
+        let dc = winuser::GetDC(ptr::null_mut());
+        if dc.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let color = wingdi::CreateDIBSection(
+            dc,
+            &info as *const BITMAPINFOHEADER as _,
+            wingdi::DIB_RGB_COLORS,
+            &mut bits,
+            ptr::null_mut(),
+            0,
+        );
+        let error = io::Error::last_os_error();
+        winuser::ReleaseDC(ptr::null_mut(), dc);
+        if color.is_null() {
+            return Err(error);
+        }
+        ptr::copy_nonoverlapping(bgra.as_ptr(), bits as *mut u8, bgra.len());
 
-    pub fn rows_mut<'a>(
-        &'a mut self,
-    ) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a mut [u8]> {
-        let stride = match self.row_len() {
-            0 => 1,
-            l => l,
+        // The mask is ignored for 32-bit icons, but must still be present. Rows of monochrome
+        // bitmaps are aligned to a `WORD` boundary.
+        let mask_bits = vec![0u8; (width as usize).div_ceil(16) * 2 * height as usize];
+        let mask = wingdi::CreateBitmap(
+            px_cast(width),
+            px_cast(height),
+            1,
+            1,
+            mask_bits.as_ptr() as _,
+        );
+        if mask.is_null() {
+            let error = io::Error::last_os_error();
+            wingdi::DeleteObject(color as _);
+            return Err(error);
+        }
+
+        let (x_hotspot, y_hotspot) = hotspot.unwrap_or((0, 0));
+        let mut icon_info = winuser::ICONINFO {
+            fIcon: hotspot.is_none() as _,
+            xHotspot: x_hotspot,
+            yHotspot: y_hotspot,
+            hbmMask: mask,
+            hbmColor: color,
         };
-        let pixel_len = self.width() as usize * self.bytes_per_pixel();
-        self.bytes_mut()
-            .chunks_mut(stride)
-            .rev()
-            .map(move |row| &mut row[..pixel_len])
+        let handle = winuser::CreateIconIndirect(&mut icon_info);
+        let error = io::Error::last_os_error();
+        // The icon keeps its own copies of the bitmaps.
+        wingdi::DeleteObject(color as _);
+        wingdi::DeleteObject(mask as _);
+        if handle.is_null() {
+            return Err(error);
+        }
+        Ok(Icon {
+            handle,
+            cursor: hotspot.is_some(),
+        })
     }
 
-    #[cfg(feature = "rayon")]
-    pub fn par_rows<'a>(&'a self) -> impl IndexedParallelIterator<Item = &'a [u8]> {
-        let stride = match self.row_len() {
-            0 => 1,
-            l => l,
-        };
-        let pixel_len = self.width() as usize * self.bytes_per_pixel();
-        self.bytes()
-            .par_chunks(stride)
-            .rev()
-            .map(move |row| &row[..pixel_len])
+    pub fn raw_handle(&self) -> *mut c_void {
+        self.handle as _
     }
 
-    #[cfg(feature = "rayon")]
-    pub fn par_rows_mut<'a>(&'a mut self) -> impl IndexedParallelIterator<Item = &'a mut [u8]> {
-        let stride = match self.row_len() {
-            0 => 1,
-            l => l,
-        };
-        let pixel_len = self.width() as usize * self.bytes_per_pixel();
-        self.bytes_mut()
-            .par_chunks_mut(stride)
-            .rev()
-            .map(move |row| &mut row[..pixel_len])
+    pub unsafe fn set_current_cursor(&self) {
+        winuser::SetCursor(self.handle);
     }
 
-    fn tlo_to_blo(&self, tlo_row: u32) -> u32 {
-        self.height() - 1 - tlo_row
+    pub unsafe fn set_window_icon(&self, handle: RawWindowHandle) -> Result<(), HandleError> {
+        let hwnd = hwnd(handle)?;
+        for &size in &[winuser::ICON_SMALL, winuser::ICON_BIG] {
+            winuser::SendMessageW(hwnd, winuser::WM_SETICON, size as _, self.handle as _);
+        }
+        Ok(())
     }
 }
 
-impl Drop for PixelBuffer {
+impl Drop for Icon {
     fn drop(&mut self) {
         unsafe {
-            wingdi::DeleteObject(self.handle as _);
+            if self.cursor {
+                winuser::DestroyCursor(self.handle);
+            } else {
+                winuser::DestroyIcon(self.handle);
+            }
         }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    use winapi::shared::windef::HWND;
-    use winapi::um::{
-        processthreadsapi::GetCurrentProcess, winnt::HANDLE, winuser::GetDesktopWindow,
-    };
-    // Resource consumption tests observe per-process state. To ensure that tests do not interfere
-    // with each other, they must not execute concurrently. The `#[serial]` attribute macro ensures
-    // that tests are run in serial.
-    use serial_test::serial;
-
-    // TODO: Remove the following once `GetGuiResources` is available from the winapi crate.
-    // See [this issue](https://github.com/retep998/winapi-rs/issues/888) for reference.
-    const GR_GDIOBJECTS: u32 = 0x0;
-    // const GR_USEROBJECTS: u32 = 0x1;
-    // const GR_GDIOBJECTS_PEAK: u32 = 0x2;
-    // const GR_USEROBJECTS_PEAK: u32 = 0x4;
-    extern "system" {
-        fn GetGuiResources(hProcess: HANDLE, uiFlags: u32) -> u32;
+/// Fills the parts of `hwnd`'s client area outside of `covered` with `color`.
+///
+/// Only the uncovered parts are painted, so the blitted area doesn't flicker.
+unsafe fn fill_uncovered(hwnd: HWND, hdc: HDC, covered: RECT, [r, g, b]: [u8; 3]) {
+    let mut client = mem::zeroed::<RECT>();
+    if winuser::GetClientRect(hwnd, &mut client) == 0 {
+        return;
     }
-
-    /// Returns the number of GDI objects currently in use by the calling process.
-    fn gdi_obj_count() -> u32 {
-        unsafe {
-            let proc = GetCurrentProcess();
-            GetGuiResources(proc, GR_GDIOBJECTS)
+    let middle_top = covered.top.clamp(client.top, client.bottom);
+    let middle_bottom = covered.bottom.clamp(middle_top, client.bottom);
+    let bands = [
+        // Above and below the covered area, spanning the whole width.
+        RECT {
+            bottom: middle_top,
+            ..client
+        },
+        RECT {
+            top: middle_bottom,
+            ..client
+        },
+        // Left and right of the covered area.
+        RECT {
+            top: middle_top,
+            right: covered.left.clamp(client.left, client.right),
+            bottom: middle_bottom,
+            ..client
+        },
+        RECT {
+            left: covered.right.clamp(client.left, client.right),
+            top: middle_top,
+            bottom: middle_bottom,
+            ..client
+        },
+    ];
+    let brush = wingdi::CreateSolidBrush(wingdi::RGB(r, g, b));
+    for band in bands.iter() {
+        if band.left < band.right && band.top < band.bottom {
+            winuser::FillRect(hdc, band, brush);
         }
     }
+    wingdi::DeleteObject(brush as _);
+}
 
-    /// Constructs a `RawWindowHandle` from an `HWND`.
-    fn from_hwnd(hwnd: HWND) -> RawWindowHandle {
-        let handle = WindowsHandle {
-            hwnd: hwnd as _,
-            ..WindowsHandle::empty()
-        };
-        RawWindowHandle::Windows(handle)
+/// Blocks the current thread until `target_time` using a waitable timer, which has a much finer
+/// resolution than `Sleep`.
+fn wait_until(target_time: Instant) -> io::Result<()> {
+    let now = Instant::now();
+    if target_time <= now {
+        return Ok(());
     }
+    // Negative due times are relative to the current time, in 100-nanosecond intervals.
+    let intervals = ((target_time - now).as_nanos() / 100).min(i64::MAX as u128) as i64;
+    unsafe {
+        let timer = synchapi::CreateWaitableTimerW(ptr::null_mut(), TRUE, ptr::null());
+        if timer.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let mut due_time: LARGE_INTEGER = mem::zeroed();
+        *due_time.QuadPart_mut() = -intervals;
 
-    #[test]
-    #[serial]
-    /// The purpose of this test is to verify that `PixelBuffer::new` doesn't leak any resources.
-    ///
-    /// The test creates a new `PixelBuffer` and immediately drops it again. It is expected that the
-    /// GDI object count stays the same across this test.
-    fn pixelbuffer_new_resource_leaks() {
-        let obj_count_base = gdi_obj_count();
-
-        // Perform test(s).
-        unsafe {
-            let raw_handle = from_hwnd(GetDesktopWindow());
-            let _pb = PixelBuffer::new(256, 256, PixelBufferFormatType::BGRA, raw_handle).unwrap();
-        } // <- drop PixelBuffer and release resources
+        let set = synchapi::SetWaitableTimer(timer, &due_time, 0, None, ptr::null_mut(), FALSE);
+        let result = if set != 0
+            && synchapi::WaitForSingleObject(timer, winbase::INFINITE) == winbase::WAIT_OBJECT_0
+        {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        };
 
-        // Compare GDI object count at test end.
-        let obj_count_current = gdi_obj_count();
-        assert_eq!(
-            obj_count_base, obj_count_current,
-            "Expected GDI object count: {}; observed GDI object count: {}",
-            obj_count_base, obj_count_current
-        );
+        handleapi::CloseHandle(timer);
+        result
     }
+}
 
-    #[test]
-    #[serial]
-    /// The purpose of this test is to verify that `PixelBuffer::blit` doesn't leak resources.
-    fn pixelbuffer_blit_resource_leaks() {
+impl PixelBuffer {
+    pub unsafe fn new(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+        raw_window_handle: RawWindowHandle,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        Self::new_inner(
+            width,
+            height,
+            format,
+            Some(raw_window_handle),
+            Storage::Dib,
+            BufferOptions::new(),
+        )
+    }
+
+    pub unsafe fn new_offscreen(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        Self::new_inner(
+            width,
+            height,
+            format,
+            None,
+            Storage::Dib,
+            BufferOptions::new(),
+        )
+    }
+
+    pub unsafe fn new_offscreen_with_options(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+        options: BufferOptions,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        Self::new_inner(width, height, format, None, Storage::Dib, options)
+    }
+
+    pub unsafe fn with_options(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+        raw_window_handle: RawWindowHandle,
+        options: BufferOptions,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        Self::new_inner(
+            width,
+            height,
+            format,
+            Some(raw_window_handle),
+            Storage::Dib,
+            options,
+        )
+    }
+
+    pub unsafe fn new_shared(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+        raw_window_handle: RawWindowHandle,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        Self::new_inner(
+            width,
+            height,
+            format,
+            Some(raw_window_handle),
+            Storage::SharedDib,
+            BufferOptions::new(),
+        )
+    }
+
+    pub unsafe fn with_allocator(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+        raw_window_handle: RawWindowHandle,
+        allocator: Box<dyn BufferAllocator>,
+        options: BufferOptions,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        Self::new_inner(
+            width,
+            height,
+            format,
+            Some(raw_window_handle),
+            Storage::Allocator(allocator),
+            options,
+        )
+    }
+
+    /// Creates a `width` by `height` buffer with the same format, storage and settings, which
+    /// takes this one's place. The contents are undefined.
+    ///
+    /// This buffer must be dropped right after the new one replaces it, since its memory may be
+    /// deallocated through the new buffer's allocator.
+    pub unsafe fn resized(
+        &mut self,
+        width: u32,
+        height: u32,
+        raw_window_handle: RawWindowHandle,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        let format = self.format;
+        let storage = match (&self.allocation, self.section.is_null()) {
+            (Some(allocation), _) => {
+                Storage::Allocator(Box::new(AllocatorRef(&*allocation.allocator)))
+            }
+            (None, false) => Storage::SharedDib,
+            (None, true) => Storage::Dib,
+        };
+        let mut buffer = Self::new_inner(
+            width,
+            height,
+            format,
+            Some(raw_window_handle),
+            storage,
+            self.options,
+        )?;
+        // The new buffer's memory came from this buffer's allocator, which it takes over.
+        if let (Some(old), Some(new)) = (&mut self.allocation, &mut buffer.allocation) {
+            mem::swap(&mut old.allocator, &mut new.allocator);
+        }
+        for &hwnd in &self.windows {
+            if !buffer.windows.contains(&hwnd) {
+                buffer.windows.push(hwnd);
+            }
+        }
+        buffer.color_lut = self.color_lut.take();
+        buffer.conversion = self.conversion.take();
+        buffer.remote_depth = self.remote_depth;
+        buffer.allow_conversion = self.allow_conversion;
+        buffer.frame_throttle = self.frame_throttle;
+        buffer.present_limit = self.present_limit;
+        buffer.cancel = self.cancel.take();
+        Ok(buffer)
+    }
+
+    unsafe fn new_inner(
+        width: u32,
+        height: u32,
+        format: PixelBufferFormatType,
+        raw_window_handle: Option<RawWindowHandle>,
+        storage: Storage,
+        options: BufferOptions,
+    ) -> Result<PixelBuffer, PixelBufferCreationError> {
+        let bit_count = match (format, options.mismatch_policy) {
+            (PixelBufferFormatType::BGRA, _) => 32,
+            (PixelBufferFormatType::BGR, _) => 24,
+            // GDI only takes blue-green-red bitmaps.
+            (PixelBufferFormatType::RGBA, MismatchPolicy::Error) => {
+                return Err(PixelBufferCreationError::FormatMismatch {
+                    requested: format,
+                    suggested: PixelBufferFormatType::BGRA,
+                })
+            }
+            (PixelBufferFormatType::RGB, MismatchPolicy::Error) => {
+                return Err(PixelBufferCreationError::FormatMismatch {
+                    requested: format,
+                    suggested: PixelBufferFormatType::BGR,
+                })
+            }
+            (PixelBufferFormatType::RGBA, _) => 32,
+            (PixelBufferFormatType::RGB, _) => 24,
+        };
+        // Offscreen buffers aren't associated with any window, and are created with a null one.
+        let hwnd = match raw_window_handle {
+            Some(raw_window_handle) => hwnd(raw_window_handle)?,
+            None => ptr::null_mut(),
+        };
+        // Catch windows that can't display the buffer now, rather than on the first blit.
+        if !hwnd.is_null() && winuser::IsWindow(hwnd) == 0 {
+            return Err(PixelBufferCreationError::IncompatibleWindow(
+                "window has been destroyed",
+            ));
+        }
+        if !hwnd.is_null() && !supports_bitmaps(hwnd).map_err(PixelBufferCreationError::Platform)? {
+            return Err(PixelBufferCreationError::IncompatibleWindow(
+                NO_BITMAP_SUPPORT,
+            ));
+        }
+        // DIB rows must be aligned to a `DWORD` boundary. GDI describes bitmaps with `i32`s, so
+        // the dimensions and the length of a row must all fit into one, and the size of the
+        // image must fit into a `DWORD`.
+        let row_alignment = options.row_alignment.max(4) as u64;
+        let row_len = (width as u64 * bit_count as u64).div_ceil(8 * row_alignment) * row_alignment;
+        let size = row_len * height as u64;
+        if width > i32::MAX as u32
+            || height > i32::MAX as u32
+            || row_len > i32::MAX as u64
+            || size > MAX_BUFFER_LEN
+        {
+            return Err(PixelBufferCreationError::BufferTooLarge);
+        }
+        // GDI derives the length of a row from the bitmap's width, so wider rows are described
+        // as a wider bitmap whose extra columns are never blitted.
+        let bitmap_width = row_len * 8 / bit_count as u64;
+        let size = usize::try_from(size).map_err(|_| PixelBufferCreationError::BufferTooLarge)?;
+        #[cfg(feature = "tracy")]
+        let _zone = tracy_client::Client::running().map(|client| {
+            let zone = client.span(
+                tracy_client::span_location!("winit-blit buffer allocation"),
+                0,
+            );
+            zone.emit_value(size as u64);
+            zone
+        });
+        let info = BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as _,
+            biWidth: bitmap_width as i32,
+            biHeight: px_cast(height),
+            biPlanes: 1,
+            biBitCount: bit_count,
+            biCompression: wingdi::BI_RGB,
+            biSizeImage: 0,
+            biXPelsPerMeter: 1,
+            biYPelsPerMeter: 1,
+            biClrUsed: 0,
+            biClrImportant: 0,
+        };
+        let mut handle: HBITMAP = ptr::null_mut();
+        let mut section: HANDLE = ptr::null_mut();
+        let mut allocation = None;
+        let bitmap: BITMAP;
+        if width == 0 || height == 0 {
+            bitmap = BITMAP {
+                bmType: 0,
+                bmWidth: px_cast(width),
+                bmHeight: px_cast(height),
+                bmWidthBytes: row_len as i32,
+                bmPlanes: 1,
+                bmBitsPixel: bit_count,
+                bmBits: ptr::null_mut(),
+            };
+        } else if let Storage::Allocator(allocator) = storage {
+            let layout = Layout::from_size_align(size, row_alignment as usize)
+                .map_err(|_| PixelBufferCreationError::AllocationFailed)?;
+            let bits = allocator.allocate(layout);
+            if bits.is_null() {
+                return Err(PixelBufferCreationError::AllocationFailed);
+            }
+            // DIB sections are zeroed by the system, but allocators may hand out memory that's
+            // still holding an earlier buffer's pixels.
+            if options.init == BufferInit::Zeroed {
+                ptr::write_bytes(bits, 0, size);
+            }
+            bitmap = BITMAP {
+                bmType: 0,
+                bmWidth: bitmap_width as i32,
+                bmHeight: px_cast(height),
+                bmWidthBytes: row_len as i32,
+                bmPlanes: 1,
+                bmBitsPixel: bit_count,
+                bmBits: bits as _,
+            };
+            allocation = Some(Allocation {
+                allocator,
+                layout,
+                info,
+            });
+        } else {
+            if let Storage::SharedDib = storage {
+                let size = size as u64;
+                section = memoryapi::CreateFileMappingW(
+                    handleapi::INVALID_HANDLE_VALUE,
+                    ptr::null_mut(),
+                    winnt::PAGE_READWRITE,
+                    (size >> 32) as u32,
+                    size as u32,
+                    ptr::null(),
+                );
+                if section.is_null() {
+                    return Err(PixelBufferCreationError::Platform(PlatformError::report(
+                        "CreateFileMappingW",
+                        hwnd as _,
+                    )));
+                }
+            }
+            let mut error;
+            (handle, error) = {
+                let dc = winuser::GetDC(ptr::null_mut());
+                let dib_section = wingdi::CreateDIBSection(
+                    dc,
+                    &info as *const BITMAPINFOHEADER as _,
+                    wingdi::DIB_RGB_COLORS,
+                    &mut ptr::null_mut(),
+                    section,
+                    0,
+                );
+                let error = PlatformError::last("CreateDIBSection");
+                winuser::ReleaseDC(ptr::null_mut(), dc);
+                (dib_section, error)
+            };
+
+            let mut dib: BITMAP = std::mem::zeroed();
+            let mut bytes_written = 0;
+            if !handle.is_null() {
+                bytes_written = wingdi::GetObjectW(
+                    handle as _,
+                    std::mem::size_of::<BITMAP>() as i32,
+                    &mut dib as *mut BITMAP as *mut _,
+                );
+                error = PlatformError::last("GetObjectW");
+            }
+            if bytes_written == 0 {
+                report_platform_error(&error, hwnd as _);
+                // Don't leave the bitmap or the file mapping behind.
+                if !handle.is_null() {
+                    wingdi::DeleteObject(handle as _);
+                }
+                if !section.is_null() {
+                    handleapi::CloseHandle(section);
+                }
+                return Err(PixelBufferCreationError::Platform(error));
+            }
+            bitmap = dib;
+        }
+        let len = bitmap.bmWidthBytes as usize * bitmap.bmHeight as usize;
+        let buffer = PixelBuffer {
+            handle,
+            bitmap,
+            width,
+            format,
+            options,
+            len,
+            windows: match hwnd.is_null() {
+                true => Vec::new(),
+                false => vec![hwnd],
+            },
+            metrics: Cell::new(BlitMetrics::default()),
+            color_lut: None,
+            conversion: None,
+            scratch: RefCell::new(Vec::new()),
+            memory: Tracked::new(len),
+            remote_depth: None,
+            allow_conversion: true,
+            frame_throttle: false,
+            present_limit: None,
+            pending_presents: Cell::new((0, 0)),
+            section,
+            allocation,
+            deferred: Vec::new(),
+            cancel: None,
+            mipmaps: Vec::new(),
+            mipmap_memory: Tracked::default(),
+        };
+        #[cfg(feature = "log")]
+        buffer.log_creation(format, hwnd);
+        Ok(buffer)
+    }
+
+    /// Summarizes how the buffer is stored and presented, for bug reports about e.g. wrong colors.
+    #[cfg(feature = "log")]
+    unsafe fn log_creation(&self, format: PixelBufferFormatType, hwnd: HWND) {
+        let hdc = winuser::GetDC(hwnd);
+        let display_depth = match hdc.is_null() {
+            true => 0,
+            false => {
+                let depth = wingdi::GetDeviceCaps(hdc, wingdi::BITSPIXEL)
+                    * wingdi::GetDeviceCaps(hdc, wingdi::PLANES);
+                winuser::ReleaseDC(hwnd, hdc);
+                depth
+            }
+        };
+        let (storage, present_path) = match (&self.allocation, self.section.is_null()) {
+            _ if self.bitmap.bmBits.is_null() => ("none (empty buffer)", "none"),
+            (Some(_), _) => ("custom allocator", "SetDIBitsToDevice"),
+            (None, true) => ("DIB section", "BitBlt"),
+            (None, false) => ("shared DIB section", "BitBlt"),
+        };
+        log::debug!(
+            "created {}x{} {:?} pixel buffer: backend GDI, display depth {} bits, storage {}, \
+             row pitch {} bytes (bottom-up), present path {}, conversion none, remote session {}",
+            self.width(),
+            self.height(),
+            format,
+            display_depth,
+            storage,
+            self.row_len(),
+            present_path,
+            is_remote_session(),
+        );
+    }
+
+    pub unsafe fn add_window(&mut self, handle: RawWindowHandle) -> Result<(), BlitError> {
+        let hwnd = check_window(handle)?;
+        if !self.windows.contains(&hwnd) {
+            self.windows.push(hwnd);
+        }
+        Ok(())
+    }
+
+    pub unsafe fn remove_window(&mut self, handle: RawWindowHandle) {
+        if let Ok(hwnd) = hwnd(handle) {
+            self.windows.retain(|&window| window != hwnd);
+            self.deferred.retain(|&window| window != hwnd);
+        }
+    }
+
+    pub unsafe fn detach(&mut self, handle: RawWindowHandle) -> Result<(), BlitError> {
+        let hwnd = hwnd(handle)?;
+        self.windows.retain(|&window| window != hwnd);
+        self.deferred.retain(|&window| window != hwnd);
+        if winuser::IsWindow(hwnd) == 0 {
+            return Ok(());
+        }
+        // Erasing the background makes the window draw over the last blitted frame, even if it
+        // doesn't paint anything itself.
+        if winuser::InvalidateRect(hwnd, ptr::null(), TRUE) == 0 {
+            return Err(io::Error::from(PlatformError::report("InvalidateRect", hwnd as _)).into());
+        }
+        Ok(())
+    }
+
+    /// Blits the whole buffer onto every window without allocating, converting or reporting
+    /// errors, so it can run while the process is going down.
+    pub unsafe fn emergency_present(&self) -> bool {
+        if self.bitmap.bmBits.is_null() {
+            return true;
+        }
+        let dib = self
+            .allocation
+            .as_ref()
+            .map(|allocation| (self.bitmap.bmBits as *const _, allocation.info));
+        let size = (self.width(), self.height());
+        let mut presented = true;
+        for &hwnd in &self.windows {
+            if winuser::IsWindow(hwnd) == 0 {
+                presented = false;
+                continue;
+            }
+            let hdc = winuser::GetDC(hwnd);
+            if hdc.is_null() {
+                presented = false;
+                continue;
+            }
+            presented &= self.upload(hdc, dib, (0, 0), (0, 0), size, size, Filter::Nearest) != 0;
+            winuser::ReleaseDC(hwnd, hdc);
+        }
+        wingdi::GdiFlush();
+        presented
+    }
+
+    pub unsafe fn blit(&self, handle: RawWindowHandle) -> Result<(), BlitError> {
+        self.blit_rect((0, 0), (0, 0), (self.width(), self.height()), None, handle)
+    }
+
+    pub unsafe fn present_at(
+        &self,
+        target_time: Instant,
+        handle: RawWindowHandle,
+    ) -> Result<(), BlitError> {
+        wait_until(target_time)?;
+        self.blit(handle)
+    }
+
+    pub unsafe fn blit_rect(
+        &self,
+        src_pos: (u32, u32),
+        dst_pos: (u32, u32),
+        blit_size: (u32, u32),
+        background: Option<[u8; 3]>,
+        handle: RawWindowHandle,
+    ) -> Result<(), BlitError> {
+        self.apply_backpressure()?;
+        let throttle = self.frame_throttle;
+        self.blit_rect_inner(
+            src_pos,
+            dst_pos,
+            blit_size,
+            blit_size,
+            Filter::Nearest,
+            background,
+            handle,
+            throttle,
+        )
+    }
+
+    /// Counts a present towards the present limit, waiting or failing if it's been reached.
+    unsafe fn apply_backpressure(&self) -> Result<(), BlitError> {
+        let (limit, backpressure) = match self.present_limit {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+        let mut frame = match composed_frame() {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+        let (last_frame, mut pending) = self.pending_presents.get();
+        if frame != last_frame {
+            // The compositor has picked up everything presented so far.
+            pending = 0;
+        }
+        if pending >= limit.get() {
+            match backpressure {
+                Backpressure::Block => {
+                    wait_for_composition();
+                    frame = composed_frame().unwrap_or(frame);
+                    pending = 0;
+                }
+                Backpressure::WouldBlock => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WouldBlock,
+                        "too many presents are waiting for the compositor",
+                    )
+                    .into())
+                }
+            }
+        }
+        self.pending_presents.set((frame, pending + 1));
+        Ok(())
+    }
+
+    pub unsafe fn blit_streaming(
+        &mut self,
+        handle: RawWindowHandle,
+        produce_row: &mut dyn FnMut(u32, &mut [u8]),
+    ) -> Result<(), BlitError> {
+        self.apply_backpressure()?;
+        let (width, height) = (self.width(), self.height());
+        let mut top = 0;
+        while top < height {
+            let rows = STREAMING_BAND_ROWS.min(height - top);
+            for row in top..top + rows {
+                produce_row(row, self.row_mut(row).unwrap());
+            }
+            // Each band is handed to GDI as soon as it's complete, while the next one is produced.
+            let size = (width, rows);
+            self.blit_rect_inner(
+                (0, top),
+                (0, top),
+                size,
+                size,
+                Filter::Nearest,
+                None,
+                handle,
+                false,
+            )?;
+            top += rows;
+        }
+        wingdi::GdiFlush();
+        if self.frame_throttle {
+            wait_for_composition();
+        }
+        Ok(())
+    }
+
+    pub unsafe fn blit_regions(
+        &self,
+        regions: &[BlitRegion],
+        filter: Filter,
+        handle: RawWindowHandle,
+    ) -> Result<(), BlitError> {
+        self.apply_backpressure()?;
+        for region in regions {
+            self.blit_rect_inner(
+                region.src_pos.into(),
+                region.dst_pos.into(),
+                region.src_size.into(),
+                region.dst_size.into(),
+                filter,
+                None,
+                handle,
+                false,
+            )?;
+        }
+        // Make sure none of the blits are still sitting in GDI's batch.
+        wingdi::GdiFlush();
+        if self.frame_throttle {
+            wait_for_composition();
+        }
+        Ok(())
+    }
+
+    pub unsafe fn defer_present(&mut self, handle: RawWindowHandle) -> Result<(), BlitError> {
+        let hwnd = hwnd(handle)?;
+        assert!(
+            self.windows.contains(&hwnd),
+            "window wasn't registered with the pixel buffer"
+        );
+        if !self.deferred.contains(&hwnd) {
+            self.deferred.push(hwnd);
+        }
+        Ok(())
+    }
+
+    pub fn has_deferred_presents(&self) -> bool {
+        !self.deferred.is_empty()
+    }
+
+    /// Blits `region`, as `(pos, size)`, onto every window a present was deferred for, and
+    /// forgets them. Windows that haven't been blitted onto when an error occurs, other than the
+    /// one that failed, stay deferred.
+    pub unsafe fn flush_presents(
+        &mut self,
+        region: Option<((u32, u32), (u32, u32))>,
+    ) -> Result<(), BlitError> {
+        let (pos, size) = match region {
+            Some(region) => region,
+            None => {
+                self.deferred.clear();
+                return Ok(());
+            }
+        };
+        self.apply_backpressure()?;
+        while !self.deferred.is_empty() {
+            let handle = raw_handle(self.deferred.remove(0));
+            self.blit_rect_inner(pos, pos, size, size, Filter::Nearest, None, handle, false)?;
+        }
+        wingdi::GdiFlush();
+        if self.frame_throttle {
+            wait_for_composition();
+        }
+        Ok(())
+    }
+
+    pub unsafe fn blit_progressive(
+        &self,
+        interval: u32,
+        handle: RawWindowHandle,
+    ) -> Result<(), BlitError> {
+        if interval <= 1 {
+            return self.blit(handle);
+        }
+        self.apply_backpressure()?;
+        let (width, height) = (self.width(), self.height());
+        for pass in 0..interval.min(height) {
+            for (y, dst_height) in progressive_pass(pass, interval, height) {
+                self.blit_rect_inner(
+                    (0, y),
+                    (0, y),
+                    (width, 1),
+                    (width, dst_height),
+                    Filter::Nearest,
+                    None,
+                    handle,
+                    false,
+                )?;
+            }
+            // Send every pass on its own, rather than letting GDI batch them together.
+            wingdi::GdiFlush();
+        }
+        if self.frame_throttle {
+            wait_for_composition();
+        }
+        Ok(())
+    }
+
+    /// Blits the `blit_size` pixels at `src_pos` onto the `dst_size` rectangle at `dst_pos`,
+    /// scaling them if the sizes differ.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn blit_rect_inner(
+        &self,
+        src_pos: (u32, u32),
+        dst_pos: (u32, u32),
+        blit_size: (u32, u32),
+        dst_size: (u32, u32),
+        filter: Filter,
+        background: Option<[u8; 3]>,
+        handle: RawWindowHandle,
+        throttle: bool,
+    ) -> Result<(), BlitError> {
+        let hwnd = hwnd(handle)?;
+        assert!(
+            self.windows.contains(&hwnd),
+            "window wasn't registered with the pixel buffer"
+        );
+        if winuser::IsWindow(hwnd) == 0 {
+            return Err(BlitError::WindowDestroyed);
+        }
+        if self.bitmap.bmBits.is_null() {
+            self.metrics.set(BlitMetrics::default());
+            return Ok(());
+        }
+        let convert = self.converts();
+        if convert && !self.allow_conversion {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "presenting the buffer requires converting its contents",
+            )
+            .into());
+        }
+        self.check_cancelled()?;
+        let hdc = winuser::GetDC(hwnd as _);
+
+        // GDI clips the source rectangle against the bitmap, so only that much gets copied.
+        let copied_width = blit_size.0.min(self.width().saturating_sub(src_pos.0));
+        let copied_height = blit_size.1.min(self.height().saturating_sub(src_pos.1));
+        // The destination shrinks along with the source, so the scale stays the same.
+        let scaled = |dst: u32, copied: u32, src: u32| match src {
+            0 => 0,
+            _ => (dst as u64 * copied as u64 / src as u64) as u32,
+        };
+        let dst_size = (
+            scaled(dst_size.0, copied_width, blit_size.0),
+            scaled(dst_size.1, copied_height, blit_size.1),
+        );
+        if let Some(color) = background {
+            let covered = RECT {
+                left: px_cast(dst_pos.0),
+                top: px_cast(dst_pos.1),
+                right: px_cast(dst_pos.0.saturating_add(dst_size.0)),
+                bottom: px_cast(dst_pos.1.saturating_add(dst_size.1)),
+            };
+            fill_uncovered(hwnd, hdc, covered, color);
+        }
+
+        let conversion_start = Instant::now();
+        let mut scratch = self.scratch.borrow_mut();
+        let remote_depth = self.active_remote_depth();
+        let dib = match (convert, remote_depth, &self.allocation) {
+            (false, None, Some(allocation)) => {
+                Some((self.bitmap.bmBits as *const _, allocation.info))
+            }
+            (false, None, None) => None,
+            (_, remote_depth, _) => {
+                // Convert only the rows being blitted. DIB rows are stored bottom-up.
+                let bottom = (self.height() as usize).saturating_sub(src_pos.1 as usize);
+                let top = bottom - copied_height as usize;
+                let rows = top * self.row_len()..bottom * self.row_len();
+                scratch.resize(self.len, 0);
+                // A present conversion replaces the color adjustment.
+                let lut = self
+                    .color_lut
+                    .as_deref()
+                    .filter(|_| self.conversion.is_none());
+                let stage =
+                    PresentStage::new(self.backend_format(), self.gdi_format(), lut, remote_depth);
+                // Converted a band at a time, so a cancelled blit stops soon after.
+                let band_len = CANCEL_BAND_ROWS * self.row_len().max(1);
+                for start in rows.clone().step_by(band_len) {
+                    if let Err(e) = self.check_cancelled() {
+                        winuser::ReleaseDC(hwnd, hdc);
+                        return Err(e);
+                    }
+                    let band = start..(start + band_len).min(rows.end);
+                    let (src, dst) = (&self.bytes()[band.clone()], &mut scratch[band]);
+                    match &self.conversion {
+                        Some(conversion) => {
+                            let rows = (dst.len() / self.row_len()) as u32;
+                            conversion(src, dst, rows, self.row_len());
+                            stage.convert_rows_in_place(dst, self.width(), self.row_len());
+                        }
+                        None => stage.convert_rows(src, dst, self.width(), self.row_len()),
+                    }
+                }
+                stage.reduce_depth(&mut scratch[rows], self.width(), self.row_len(), true);
+                Some((scratch.as_ptr() as *const _, self.bitmap_info()))
+            }
+        };
+        let conversion_time = conversion_start.elapsed();
+        if let Err(e) = self.check_cancelled() {
+            winuser::ReleaseDC(hwnd, hdc);
+            return Err(e);
+        }
+
+        let upload_start = Instant::now();
+        let copied_size = (copied_width, copied_height);
+        // Heavy downscales are smoothed from the closest mipmap instead, so the filter never has
+        // to shrink its source by more than half, which would skip over pixels and alias.
+        let mipmap = match (filter, convert || remote_depth.is_some()) {
+            (Filter::Bilinear, false) => self.mipmap_for(copied_size, dst_size),
+            _ => None,
+        };
+        let (dib, src_pos, copied_size) = match mipmap {
+            Some((level, mipmap)) => {
+                let shrink = |size: u32| (size >> level).max(1);
+                let src_pos = (
+                    (src_pos.0 >> level).min(mipmap.width() - 1),
+                    (src_pos.1 >> level).min(mipmap.height() - 1),
+                );
+                let copied_size = (
+                    shrink(copied_size.0).min(mipmap.width() - src_pos.0),
+                    shrink(copied_size.1).min(mipmap.height() - src_pos.1),
+                );
+                let dib = (mipmap.bits.as_ptr() as *const _, mipmap.info);
+                (Some(dib), src_pos, copied_size)
+            }
+            None => (dib, src_pos, copied_size),
+        };
+        let result = self.upload(hdc, dib, src_pos, dst_pos, copied_size, dst_size, filter);
+        let error = PlatformError::last(match (dib, copied_size == dst_size) {
+            (Some(_), true) => "SetDIBitsToDevice",
+            (Some(_), false) => "StretchDIBits",
+            (None, true) => "BitBlt",
+            (None, false) => "StretchBlt",
+        });
+        let upload_time = upload_start.elapsed();
+        if memory::over_budget() {
+            *scratch = Vec::new();
+        }
+        self.memory.set(self.len + scratch.capacity());
+        drop(scratch);
+
+        winuser::ReleaseDC(hwnd, hdc);
+
+        if result != 0 && throttle {
+            wait_for_composition();
+        }
+
+        if result != 0 {
+            self.metrics.set(BlitMetrics {
+                conversion_time,
+                upload_time,
+                bytes_copied: copied_width as usize
+                    * copied_height as usize
+                    * self.bytes_per_pixel(),
+                // Counted by the public buffer, which knows which blits make up a present.
+                frame_index: 0,
+            });
+            Ok(())
+        } else {
+            report_platform_error(&error, hwnd as _);
+            Err(io::Error::from(error).into())
+        }
+    }
+
+    /// Copies the `size` pixels at `src_pos` in the buffer onto the `dst_size` rectangle at
+    /// `dst_pos` on `hdc`, either from the bitmap or from `dib`, scaling them if the sizes differ.
+    /// Both positions are measured from the top-left corner, and `size` must lie inside the
+    /// buffer.
+    ///
+    /// Returns zero if the copy failed.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn upload(
+        &self,
+        hdc: HDC,
+        dib: Option<(*const winapi::ctypes::c_void, BITMAPINFOHEADER)>,
+        src_pos: (u32, u32),
+        dst_pos: (u32, u32),
+        size: (u32, u32),
+        dst_size: (u32, u32),
+        filter: Filter,
+    ) -> i32 {
+        if size.0 == 0 || size.1 == 0 || dst_size.0 == 0 || dst_size.1 == 0 {
+            return 1;
+        }
+        let stretch = size != dst_size;
+        if stretch {
+            // The default mode blends rows together with AND, which mangles colors.
+            match filter {
+                Filter::Nearest => {
+                    wingdi::SetStretchBltMode(hdc, wingdi::COLORONCOLOR as _);
+                }
+                Filter::Bilinear => {
+                    // `HALFTONE` requires the brush origin to be reset after it's selected.
+                    wingdi::SetStretchBltMode(hdc, wingdi::HALFTONE as _);
+                    wingdi::SetBrushOrgEx(hdc, 0, 0, ptr::null_mut());
+                }
+            }
+        }
+        match dib {
+            Some((bits, info)) => {
+                // The source origin of `SetDIBitsToDevice` and `StretchDIBits` is the bottom-left
+                // corner of the DIB, since its rows are stored bottom-up.
+                let src_y = info.biHeight as u32 - (src_pos.1 + size.1);
+                match stretch {
+                    false => wingdi::SetDIBitsToDevice(
+                        hdc,
+                        px_cast(dst_pos.0),
+                        px_cast(dst_pos.1),
+                        size.0,
+                        size.1,
+                        px_cast(src_pos.0),
+                        px_cast(src_y),
+                        0,
+                        info.biHeight as u32,
+                        bits,
+                        &info as *const BITMAPINFOHEADER as _,
+                        wingdi::DIB_RGB_COLORS,
+                    ),
+                    // Returns `GDI_ERROR` on some failures, rather than zero.
+                    true => wingdi::StretchDIBits(
+                        hdc,
+                        px_cast(dst_pos.0),
+                        px_cast(dst_pos.1),
+                        px_cast(dst_size.0),
+                        px_cast(dst_size.1),
+                        px_cast(src_pos.0),
+                        px_cast(src_y),
+                        px_cast(size.0),
+                        px_cast(size.1),
+                        bits,
+                        &info as *const BITMAPINFOHEADER as _,
+                        wingdi::DIB_RGB_COLORS,
+                        wingdi::SRCCOPY,
+                    )
+                    .max(0),
+                }
+            }
+            None => {
+                // Device contexts are top-down, regardless of how the bitmap stores its rows.
+                let src_dc = wingdi::CreateCompatibleDC(hdc);
+                let prev_bmp = wingdi::SelectObject(src_dc, self.handle as _);
+                let result = match stretch {
+                    false => wingdi::BitBlt(
+                        hdc,
+                        px_cast(dst_pos.0),
+                        px_cast(dst_pos.1),
+                        px_cast(size.0),
+                        px_cast(size.1),
+                        src_dc,
+                        px_cast(src_pos.0),
+                        px_cast(src_pos.1),
+                        wingdi::SRCCOPY,
+                    ),
+                    true => wingdi::StretchBlt(
+                        hdc,
+                        px_cast(dst_pos.0),
+                        px_cast(dst_pos.1),
+                        px_cast(dst_size.0),
+                        px_cast(dst_size.1),
+                        src_dc,
+                        px_cast(src_pos.0),
+                        px_cast(src_pos.1),
+                        px_cast(size.0),
+                        px_cast(size.1),
+                        wingdi::SRCCOPY,
+                    ),
+                };
+                wingdi::SelectObject(src_dc, prev_bmp);
+                wingdi::DeleteDC(src_dc);
+                result
+            }
+        }
+    }
+
+    /// Picks the smallest mipmap that's still at least `dst_size`, along with its level, i.e. the
+    /// power of two it's shrunk by. Returns `None` if the buffer itself is the best fit.
+    fn mipmap_for(&self, size: (u32, u32), dst_size: (u32, u32)) -> Option<(u32, &Mipmap)> {
+        let fits = |level: u32| size.0 >> level >= dst_size.0 && size.1 >> level >= dst_size.1;
+        (1..)
+            .zip(&self.mipmaps)
+            .take_while(|&(level, _)| fits(level))
+            .last()
+    }
+
+    pub fn build_mipmaps(&mut self) {
+        self.mipmaps.clear();
+        let bytes_per_pixel = self.bytes_per_pixel();
+        let (width, height) = (self.width(), self.height());
+        if width > 0 && height > 0 && (width > 1 || height > 1) {
+            let first = Mipmap::halve(width, height, bytes_per_pixel, |row| self.row(row).unwrap());
+            self.mipmaps.push(first);
+        }
+        while let Some(last) = self
+            .mipmaps
+            .last()
+            .filter(|m| m.width() > 1 || m.height() > 1)
+        {
+            let next = Mipmap::halve(last.width(), last.height(), bytes_per_pixel, |row| {
+                last.row(row)
+            });
+            self.mipmaps.push(next);
+        }
+        let len = self.mipmaps.iter().map(|mipmap| mipmap.bits.len()).sum();
+        self.mipmap_memory.set(len);
+    }
+
+    pub fn clear_mipmaps(&mut self) {
+        self.mipmaps = Vec::new();
+        self.mipmap_memory.set(0);
+    }
+
+    pub fn mipmap_levels(&self) -> usize {
+        self.mipmaps.len()
+    }
+
+    pub fn metrics(&self) -> BlitMetrics {
+        self.metrics.get()
+    }
+
+    pub unsafe fn copy_to_clipboard(&self, pos: (u32, u32), size: (u32, u32)) -> io::Result<()> {
+        let fits = |pos: u32, size: u32, len: u32| matches!(pos.checked_add(size), Some(end) if end <= len);
+        if !fits(pos.0, size.0, self.width()) || !fits(pos.1, size.1, self.height()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "region doesn't fit inside the pixel buffer",
+            ));
+        }
+
+        // Build a packed DIB: the header, followed by DWORD-aligned rows stored bottom-up.
+        let bytes_per_pixel = self.bytes_per_pixel();
+        let pixels_len = size.0 as usize * bytes_per_pixel;
+        let row_len = pixels_len.div_ceil(4) * 4;
+        let header = BITMAPINFOHEADER {
+            biWidth: px_cast(size.0),
+            biHeight: px_cast(size.1),
+            ..self.bitmap_info()
+        };
+        let header_len = mem::size_of::<BITMAPINFOHEADER>();
+        let len = header_len + row_len * size.1 as usize;
+        let memory = winbase::GlobalAlloc(winbase::GMEM_MOVEABLE | winbase::GMEM_ZEROINIT, len);
+        if memory.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let dib = winbase::GlobalLock(memory) as *mut u8;
+        if dib.is_null() {
+            let error = io::Error::last_os_error();
+            winbase::GlobalFree(memory);
+            return Err(error);
+        }
+        ptr::write_unaligned(dib as *mut BITMAPINFOHEADER, header);
+        let start = pos.0 as usize * bytes_per_pixel;
+        for (i, y) in (pos.1..pos.1 + size.1).rev().enumerate() {
+            let row = &self.row(y).unwrap()[start..start + pixels_len];
+            let dst = dib.add(header_len + i * row_len);
+            ptr::copy_nonoverlapping(row.as_ptr(), dst, pixels_len);
+            if self.swaps_red_blue() {
+                let dst = slice::from_raw_parts_mut(dst, pixels_len);
+                swap_red_blue(dst, size.0, bytes_per_pixel);
+            }
+        }
+        winbase::GlobalUnlock(memory);
+
+        let owner = self.windows.first().copied().unwrap_or(ptr::null_mut());
+        if winuser::OpenClipboard(owner) == 0 {
+            let error = io::Error::last_os_error();
+            winbase::GlobalFree(memory);
+            return Err(error);
+        }
+        winuser::EmptyClipboard();
+        // On success, the clipboard takes ownership of the memory.
+        let result = if winuser::SetClipboardData(winuser::CF_DIB, memory).is_null() {
+            let error = io::Error::last_os_error();
+            winbase::GlobalFree(memory);
+            Err(error)
+        } else {
+            Ok(())
+        };
+        winuser::CloseClipboard();
+        result
+    }
+
+    pub unsafe fn scroll_window(
+        &self,
+        dx: i32,
+        dy: i32,
+        handle: RawWindowHandle,
+    ) -> Result<(), BlitError> {
+        let hwnd = hwnd(handle)?;
+        if winuser::IsWindow(hwnd) == 0 {
+            return Err(BlitError::WindowDestroyed);
+        }
+        // Without `SW_INVALIDATE`, the exposed area isn't repainted by the window itself, and is
+        // left for the caller to blit.
+        let result = winuser::ScrollWindowEx(
+            hwnd,
+            dx,
+            dy,
+            ptr::null(),
+            ptr::null(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            0,
+        );
+        if result == wingdi::ERROR {
+            return Err(io::Error::from(PlatformError::report("ScrollWindowEx", hwnd as _)).into());
+        }
+        Ok(())
+    }
+
+    pub unsafe fn capture_window(&mut self, handle: RawWindowHandle) -> Result<(), BlitError> {
+        let hwnd = hwnd(handle)?;
+        if winuser::IsWindow(hwnd) == 0 {
+            return Err(BlitError::WindowDestroyed);
+        }
+        if self.bitmap.bmBits.is_null() {
+            return Ok(());
+        }
+
+        // `PrintWindow` can only draw into bitmaps, so allocator-backed buffers go through a
+        // temporary DIB section.
+        let mut temp_bits = ptr::null_mut();
+        let target = match self.allocation {
+            Some(_) => {
+                let info = self.bitmap_info();
+                let dib = wingdi::CreateDIBSection(
+                    ptr::null_mut(),
+                    &info as *const BITMAPINFOHEADER as _,
+                    wingdi::DIB_RGB_COLORS,
+                    &mut temp_bits,
+                    ptr::null_mut(),
+                    0,
+                );
+                if dib.is_null() {
+                    let error = PlatformError::report("CreateDIBSection", hwnd as _);
+                    return Err(io::Error::from(error).into());
+                }
+                dib
+            }
+            None => self.handle,
+        };
+
+        let mem_dc = wingdi::CreateCompatibleDC(ptr::null_mut());
+        let prev_bmp = wingdi::SelectObject(mem_dc, target as _);
+        let result = winuser::PrintWindow(hwnd, mem_dc, winuser::PW_CLIENTONLY);
+        let error = PlatformError::last("PrintWindow");
+        wingdi::SelectObject(mem_dc, prev_bmp);
+        wingdi::DeleteDC(mem_dc);
+        // GDI may batch drawing calls; make sure they've landed before reading the bits.
+        wingdi::GdiFlush();
+
+        if target != self.handle {
+            if result != 0 {
+                ptr::copy_nonoverlapping(
+                    temp_bits as *const u8,
+                    self.bitmap.bmBits as *mut u8,
+                    self.len,
+                );
+            }
+            wingdi::DeleteObject(target as _);
+        }
+        if result != 0 && self.swaps_red_blue() {
+            let (width, bytes_per_pixel) = (self.width(), self.bytes_per_pixel());
+            for row in self.rows_mut() {
+                swap_red_blue(row, width, bytes_per_pixel);
+            }
+        }
+        if result != 0 {
+            Ok(())
+        } else {
+            report_platform_error(&error, hwnd as _);
+            Err(io::Error::from(error).into())
+        }
+    }
+
+    pub fn set_color_lut(&mut self, lut: Option<Box<[u8; 256]>>) {
+        if lut.is_none() && self.conversion.is_none() && self.remote_depth.is_none() {
+            self.release_scratch();
+        }
+        self.color_lut = lut;
+    }
+
+    pub fn set_present_conversion(&mut self, conversion: Option<PresentConversion>) {
+        if conversion.is_none() && self.color_lut.is_none() && self.remote_depth.is_none() {
+            self.release_scratch();
+        }
+        self.conversion = conversion;
+    }
+
+    pub fn remote_depth(&self) -> Option<(ChannelDepth, ConversionOptions)> {
+        self.remote_depth
+    }
+
+    pub fn set_remote_depth(&mut self, depth: Option<(ChannelDepth, ConversionOptions)>) {
+        if depth.is_none() && self.color_lut.is_none() && self.conversion.is_none() {
+            self.release_scratch();
+        }
+        self.remote_depth = depth;
+    }
+
+    pub fn scratch_capacity(&self) -> usize {
+        self.scratch.borrow().capacity()
+    }
+
+    pub fn reserve_scratch(&mut self) {
+        let scratch = self.scratch.get_mut();
+        scratch.reserve(self.len.saturating_sub(scratch.len()));
+        self.memory.set(self.len + scratch.capacity());
+    }
+
+    pub fn release_scratch(&mut self) {
+        *self.scratch.get_mut() = Vec::new();
+        self.memory.set(self.len);
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        self.memory.get() + self.mipmap_memory.get()
+    }
+
+    #[cfg(feature = "unstable-win32")]
+    pub fn hbitmap(&self) -> HBITMAP {
+        self.handle
+    }
+
+    #[cfg(feature = "unstable-win32")]
+    pub fn bitmap(&self) -> &BITMAP {
+        &self.bitmap
+    }
+
+    #[cfg(feature = "unstable-win32")]
+    pub fn bitmap_mut(&mut self) -> &mut BITMAP {
+        &mut self.bitmap
+    }
+
+    pub fn allow_conversion(&self) -> bool {
+        self.allow_conversion
+    }
+
+    pub fn set_allow_conversion(&mut self, allow: bool) {
+        self.allow_conversion = allow;
+    }
+
+    /// Whether blits present straight from the DIB section. Otherwise, they either convert the
+    /// buffer into the scratch space first, or copy it with `SetDIBitsToDevice` because it comes
+    /// from a custom allocator.
+    pub fn is_zero_copy(&self) -> bool {
+        !self.converts() && self.active_remote_depth().is_none() && self.allocation.is_none()
+    }
+
+    /// Whether the buffer's contents have to be converted to present them, other than to reduce
+    /// their color depth.
+    fn converts(&self) -> bool {
+        self.conversion.is_some() || self.color_lut.is_some() || self.swaps_red_blue()
+    }
+
+    /// The color depth to reduce the buffer to while presenting it right now, if any.
+    fn active_remote_depth(&self) -> Option<(ChannelDepth, ConversionOptions)> {
+        // Reducing the color depth is only an optimization, so it's skipped when conversion isn't
+        // allowed rather than failing the blit.
+        self.remote_depth
+            .filter(|_| self.allow_conversion && is_remote_session())
+    }
+
+    /// Whether the buffer's red and blue channels have to be swapped to present it, because it
+    /// was created with `MismatchPolicy::Convert`.
+    fn swaps_red_blue(&self) -> bool {
+        let mismatched = matches!(
+            self.format,
+            PixelBufferFormatType::RGBA | PixelBufferFormatType::RGB
+        );
+        mismatched && self.options.mismatch_policy == MismatchPolicy::Convert
+    }
+
+    /// Describes the pixels to `SetDIBitsToDevice`.
+    fn bitmap_info(&self) -> BITMAPINFOHEADER {
+        BITMAPINFOHEADER {
+            biSize: mem::size_of::<BITMAPINFOHEADER>() as _,
+            biWidth: self.bitmap.bmWidth,
+            biHeight: self.bitmap.bmHeight,
+            biPlanes: 1,
+            biBitCount: self.bitmap.bmBitsPixel,
+            biCompression: wingdi::BI_RGB,
+            biSizeImage: 0,
+            biXPelsPerMeter: 1,
+            biYPelsPerMeter: 1,
+            biClrUsed: 0,
+            biClrImportant: 0,
+        }
+    }
+
+    pub fn frame_throttle(&self) -> bool {
+        self.frame_throttle
+    }
+
+    pub fn set_frame_throttle(&mut self, throttle: bool) {
+        self.frame_throttle = throttle;
+    }
+
+    pub fn cancel_token(&self) -> Option<&CancelToken> {
+        self.cancel.as_ref()
+    }
+
+    pub fn set_cancel_token(&mut self, token: Option<CancelToken>) {
+        self.cancel = token;
+    }
+
+    /// Fails with `Interrupted` if the cancel token has been cancelled.
+    fn check_cancelled(&self) -> Result<(), BlitError> {
+        match &self.cancel {
+            Some(token) if token.is_cancelled() => {
+                Err(io::Error::new(io::ErrorKind::Interrupted, "the blit was cancelled").into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn present_limit(&self) -> Option<(NonZeroU32, Backpressure)> {
+        self.present_limit
+    }
+
+    pub fn set_present_limit(&mut self, limit: Option<(NonZeroU32, Backpressure)>) {
+        self.present_limit = limit;
+    }
+
+    pub fn shared_memory(&self) -> Option<SharedMemory> {
+        if self.section.is_null() {
+            return None;
+        }
+        Some(SharedMemory {
+            handle: self.section as RawHandle,
+            len: self.len,
+            row_len: self.row_len(),
+        })
+    }
+
+    pub fn alpha_behavior(&self) -> AlphaBehavior {
+        // Both `BitBlt` and `SetDIBitsToDevice` copy the color channels and drop alpha.
+        AlphaBehavior::Ignored
+    }
+
+    pub fn bits_per_pixel(&self) -> usize {
+        self.bitmap.bmBitsPixel as usize
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.bits_per_pixel() / 8
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn row_len(&self) -> usize {
+        self.bitmap.bmWidthBytes as usize
+    }
+
+    pub fn height(&self) -> u32 {
+        self.bitmap.bmHeight as u32
+    }
+
+    /// The format of the bitmap's pixels. It's what GDI reads them as, except for buffers whose
+    /// red and blue channels are swapped while presenting them.
+    pub fn backend_format(&self) -> PixelBufferFormatType {
+        match self.swaps_red_blue() {
+            true => self.format,
+            false => self.gdi_format(),
+        }
+    }
+
+    /// The format GDI reads the bitmap as.
+    fn gdi_format(&self) -> PixelBufferFormatType {
+        match self.bytes_per_pixel() {
+            3 => PixelBufferFormatType::BGR,
+            _ => PixelBufferFormatType::BGRA,
+        }
+    }
+
+    /// The signed distance, in bytes, from the start of a row to the start of the row below it.
+    pub fn row_pitch(&self) -> isize {
+        // DIBs are stored bottom-up.
+        -(self.row_len() as isize)
+    }
+
+    fn bytes(&self) -> &[u8] {
+        if self.bitmap.bmBits.is_null() {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.bitmap.bmBits as *const u8, self.len) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        if self.bitmap.bmBits.is_null() {
+            return &mut [];
+        }
+        unsafe { std::slice::from_raw_parts_mut(self.bitmap.bmBits as *mut u8, self.len) }
+    }
+
+    /// A pointer to the start of the top row, which may be used to reach every other row through
+    /// `row_pitch`. Null if the buffer has no rows.
+    pub fn top_row_ptr(&mut self) -> *mut u8 {
+        match self.height() {
+            0 => ptr::null_mut(),
+            // Derived from the whole buffer, rather than from the top row alone, so it's valid
+            // for the rows below it as well.
+            _ => unsafe {
+                let offset = (self.height() - 1) as usize * self.row_len();
+                self.bytes_mut().as_mut_ptr().add(offset)
+            },
+        }
+    }
+
+    pub fn row(&self, row: u32) -> Option<&[u8]> {
+        let index = self.tlo_to_blo(row)? as usize * self.row_len();
+        let pixel_len = self.width() as usize * self.bytes_per_pixel();
+        self.bytes().get(index..index + pixel_len)
+    }
+
+    pub fn row_mut(&mut self, row: u32) -> Option<&mut [u8]> {
+        let index = self.tlo_to_blo(row)? as usize * self.row_len();
+        let pixel_len = self.width() as usize * self.bytes_per_pixel();
+        self.bytes_mut().get_mut(index..index + pixel_len)
+    }
+
+    pub fn rows<'a>(&'a self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a [u8]> {
+        let stride = match self.row_len() {
+            0 => 1,
+            l => l,
+        };
+        let pixel_len = self.width() as usize * self.bytes_per_pixel();
+        self.bytes()
+            .chunks(stride)
+            .rev()
+            .map(move |row| &row[..pixel_len])
+    }
+
+    pub fn rows_mut<'a>(
+        &'a mut self,
+    ) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &'a mut [u8]> {
+        let stride = match self.row_len() {
+            0 => 1,
+            l => l,
+        };
+        let pixel_len = self.width() as usize * self.bytes_per_pixel();
+        self.bytes_mut()
+            .chunks_mut(stride)
+            .rev()
+            .map(move |row| &mut row[..pixel_len])
+    }
+
+    #[cfg(feature = "rayon")]
+    pub fn par_rows<'a>(&'a self) -> impl IndexedParallelIterator<Item = &'a [u8]> {
+        let stride = match self.row_len() {
+            0 => 1,
+            l => l,
+        };
+        let pixel_len = self.width() as usize * self.bytes_per_pixel();
+        self.bytes()
+            .par_chunks(stride)
+            .rev()
+            .map(move |row| &row[..pixel_len])
+    }
+
+    #[cfg(feature = "rayon")]
+    pub fn par_rows_mut<'a>(&'a mut self) -> impl IndexedParallelIterator<Item = &'a mut [u8]> {
+        let stride = match self.row_len() {
+            0 => 1,
+            l => l,
+        };
+        let pixel_len = self.width() as usize * self.bytes_per_pixel();
+        self.bytes_mut()
+            .par_chunks_mut(stride)
+            .rev()
+            .map(move |row| &mut row[..pixel_len])
+    }
+
+    pub fn row_chunks(
+        &self,
+        rows_per_chunk: usize,
+    ) -> impl ExactSizeIterator
+           + DoubleEndedIterator<Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &[u8]>>
+    {
+        let stride = match self.row_len() {
+            0 => 1,
+            l => l,
+        };
+        let pixel_len = self.width() as usize * self.bytes_per_pixel();
+        // Rows are stored bottom-up, so chunking from the end of the buffer yields the top rows
+        // first.
+        self.bytes()
+            .rchunks(stride * rows_per_chunk)
+            .map(move |chunk| chunk.chunks(stride).rev().map(move |row| &row[..pixel_len]))
+    }
+
+    pub fn row_chunks_mut(
+        &mut self,
+        rows_per_chunk: usize,
+    ) -> impl ExactSizeIterator
+           + DoubleEndedIterator<
+        Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &mut [u8]>,
+    > {
+        let stride = match self.row_len() {
+            0 => 1,
+            l => l,
+        };
+        let pixel_len = self.width() as usize * self.bytes_per_pixel();
+        self.bytes_mut()
+            .rchunks_mut(stride * rows_per_chunk)
+            .map(move |chunk| {
+                chunk
+                    .chunks_mut(stride)
+                    .rev()
+                    .map(move |row| &mut row[..pixel_len])
+            })
+    }
+
+    #[cfg(feature = "rayon")]
+    pub fn par_row_chunks(
+        &self,
+        rows_per_chunk: usize,
+    ) -> impl IndexedParallelIterator<Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &[u8]>>
+    {
+        let stride = match self.row_len() {
+            0 => 1,
+            l => l,
+        };
+        let pixel_len = self.width() as usize * self.bytes_per_pixel();
+        self.bytes()
+            .par_rchunks(stride * rows_per_chunk)
+            .map(move |chunk| chunk.chunks(stride).rev().map(move |row| &row[..pixel_len]))
+    }
+
+    #[cfg(feature = "rayon")]
+    pub fn par_row_chunks_mut(
+        &mut self,
+        rows_per_chunk: usize,
+    ) -> impl IndexedParallelIterator<
+        Item = impl ExactSizeIterator + DoubleEndedIterator<Item = &mut [u8]>,
+    > {
+        let stride = match self.row_len() {
+            0 => 1,
+            l => l,
+        };
+        let pixel_len = self.width() as usize * self.bytes_per_pixel();
+        self.bytes_mut()
+            .par_rchunks_mut(stride * rows_per_chunk)
+            .map(move |chunk| {
+                chunk
+                    .chunks_mut(stride)
+                    .rev()
+                    .map(move |row| &mut row[..pixel_len])
+            })
+    }
+
+    fn tlo_to_blo(&self, tlo_row: u32) -> Option<u32> {
+        self.height().checked_sub(1)?.checked_sub(tlo_row)
+    }
+}
+
+impl Drop for PixelBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            match self.allocation {
+                Some(ref allocation) => allocation
+                    .allocator
+                    .deallocate(self.bitmap.bmBits as *mut u8, allocation.layout),
+                None => {
+                    wingdi::DeleteObject(self.handle as _);
+                }
+            }
+            if !self.section.is_null() {
+                handleapi::CloseHandle(self.section);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use raw_window_handle::Win32WindowHandle;
+    use std::num::NonZeroIsize;
+    use winapi::shared::windef::HWND;
+    use winapi::um::{
+        processthreadsapi::GetCurrentProcess, winnt::HANDLE, winuser::GetDesktopWindow,
+    };
+    // Resource consumption tests observe per-process state. To ensure that tests do not interfere
+    // with each other, they must not execute concurrently. The `#[serial]` attribute macro ensures
+    // that tests are run in serial.
+    use serial_test::serial;
+
+    // TODO: Remove the following once `GetGuiResources` is available from the winapi crate.
+    // See [this issue](https://github.com/retep998/winapi-rs/issues/888) for reference.
+    const GR_GDIOBJECTS: u32 = 0x0;
+    // const GR_USEROBJECTS: u32 = 0x1;
+    // const GR_GDIOBJECTS_PEAK: u32 = 0x2;
+    // const GR_USEROBJECTS_PEAK: u32 = 0x4;
+    extern "system" {
+        fn GetGuiResources(hProcess: HANDLE, uiFlags: u32) -> u32;
+    }
+
+    /// Returns the number of GDI objects currently in use by the calling process.
+    fn gdi_obj_count() -> u32 {
+        unsafe {
+            let proc = GetCurrentProcess();
+            GetGuiResources(proc, GR_GDIOBJECTS)
+        }
+    }
+
+    /// Runs `test`, and checks that every GDI object it created has been released by the time it
+    /// returns.
+    fn assert_no_gdi_leaks(test: impl FnOnce()) {
         let obj_count_base = gdi_obj_count();
+        test();
+        let obj_count_current = gdi_obj_count();
+        assert_eq!(
+            obj_count_base, obj_count_current,
+            "Expected GDI object count: {}; observed GDI object count: {}",
+            obj_count_base, obj_count_current
+        );
+    }
+
+    /// Constructs a `RawWindowHandle` from an `HWND`.
+    fn from_hwnd(hwnd: HWND) -> RawWindowHandle {
+        let hwnd = NonZeroIsize::new(hwnd as isize).unwrap();
+        RawWindowHandle::Win32(Win32WindowHandle::new(hwnd))
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that `PixelBuffer::new` doesn't leak any resources.
+    ///
+    /// The test creates a new `PixelBuffer` and immediately drops it again. It is expected that the
+    /// GDI object count stays the same across this test.
+    fn pixelbuffer_new_resource_leaks() {
+        assert_no_gdi_leaks(|| unsafe {
+            let raw_handle = from_hwnd(GetDesktopWindow());
+            let _pb = PixelBuffer::new(256, 256, PixelBufferFormatType::BGRA, raw_handle).unwrap();
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that buffers created with a custom allocator hand
+    /// their memory back to it, and don't create any GDI objects of their own.
+    fn pixelbuffer_with_allocator_resource_leaks() {
+        use std::{
+            alloc::{self, Layout},
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            },
+        };
+
+        struct CountingAllocator(Arc<AtomicUsize>);
+        unsafe impl BufferAllocator for CountingAllocator {
+            fn allocate(&self, layout: Layout) -> *mut u8 {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                unsafe { alloc::alloc_zeroed(layout) }
+            }
+            unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+                self.0.fetch_sub(1, Ordering::SeqCst);
+                alloc::dealloc(ptr, layout)
+            }
+        }
+
+        let live_allocations = Arc::new(AtomicUsize::new(0));
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            let pb = PixelBuffer::with_allocator(
+                31,
+                31,
+                PixelBufferFormatType::BGR,
+                desktop_wnd,
+                Box::new(CountingAllocator(live_allocations.clone())),
+                BufferOptions::new(),
+            )
+            .unwrap();
+            assert_eq!(1, live_allocations.load(Ordering::SeqCst));
+            pb.blit(desktop_wnd).unwrap();
+        });
+        assert_eq!(0, live_allocations.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that shared buffers expose their pixels through the
+    /// file mapping, and release both the mapping and the DIB section when dropped.
+    fn pixelbuffer_new_shared_resource_leaks() {
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            let mut pb =
+                PixelBuffer::new_shared(7, 3, PixelBufferFormatType::BGR, desktop_wnd).unwrap();
+            let shared = pb.shared_memory().unwrap();
+            assert_eq!(24, shared.row_len);
+            assert_eq!(72, shared.len);
+
+            let view = memoryapi::MapViewOfFile(
+                shared.handle as HANDLE,
+                memoryapi::FILE_MAP_WRITE,
+                0,
+                0,
+                shared.len,
+            );
+            assert!(!view.is_null());
+            *(view as *mut u8) = 0xAB;
+            memoryapi::UnmapViewOfFile(view);
+            assert_eq!(0xAB, pb.bytes_mut()[0]);
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that creating and dropping icons releases both the
+    /// icon and the bitmaps it was built from.
+    fn icon_resource_leaks() {
+        assert_no_gdi_leaks(|| unsafe {
+            let bgra = vec![0x80; 16 * 16 * 4];
+            let _icon = Icon::new(16, 16, &bgra, None).unwrap();
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that icons and cursors GDI can't create are reported
+    /// as errors, without leaking the bitmaps created before the failure.
+    fn icon_failure_resource_leaks() {
+        assert_no_gdi_leaks(|| unsafe {
+            for &hotspot in &[None, Some((0, 0))] {
+                assert!(Icon::new(0, 0, &[], hotspot).is_err());
+                assert!(Icon::new(0, 16, &[], hotspot).is_err());
+            }
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that copying a region to the clipboard stores it as a
+    /// packed, bottom-up DIB with padded rows, and that regions outside the buffer are rejected
+    /// without touching the clipboard.
+    fn copy_to_clipboard_stores_dib() {
+        assert_no_gdi_leaks(|| unsafe {
+            let raw_handle = from_hwnd(GetDesktopWindow());
+            let mut pb = PixelBuffer::new(4, 3, PixelBufferFormatType::BGR, raw_handle).unwrap();
+            for y in 0..3 {
+                for (x, pixel) in pb.row_mut(y).unwrap().chunks_exact_mut(3).enumerate() {
+                    pixel.copy_from_slice(&[x as u8, y as u8, 7]);
+                }
+            }
+            let error = pb.copy_to_clipboard((2, 0), (3, 1)).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+            pb.copy_to_clipboard((1, 1), (3, 2)).unwrap();
+
+            assert_ne!(winuser::OpenClipboard(ptr::null_mut()), 0);
+            let memory = winuser::GetClipboardData(winuser::CF_DIB);
+            assert!(!memory.is_null());
+            let dib = winbase::GlobalLock(memory) as *const u8;
+            assert!(!dib.is_null());
+            let header = ptr::read_unaligned(dib as *const BITMAPINFOHEADER);
+            assert_eq!((header.biWidth, header.biHeight), (3, 2));
+            assert_eq!(header.biBitCount, 24);
+            let header_len = mem::size_of::<BITMAPINFOHEADER>();
+            // 9 bytes of pixels, padded to 12.
+            let rows = slice::from_raw_parts(dib.add(header_len), 12 * 2);
+            for (i, row) in rows.chunks_exact(12).enumerate() {
+                let y = 2 - i as u8;
+                for (x, pixel) in row[..9].chunks_exact(3).enumerate() {
+                    assert_eq!(pixel, [1 + x as u8, y, 7]);
+                }
+            }
+            winbase::GlobalUnlock(memory);
+            winuser::CloseClipboard();
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that buffers too large for GDI are rejected before
+    /// any resources are allocated for them.
+    fn pixelbuffer_too_large_resource_leaks() {
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            for &(width, height) in &[(32768, 32768), (u32::MAX, 1), (1, u32::MAX)] {
+                let result =
+                    PixelBuffer::new(width, height, PixelBufferFormatType::BGRA, desktop_wnd);
+                assert!(matches!(
+                    result,
+                    Err(PixelBufferCreationError::BufferTooLarge)
+                ));
+            }
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that `PixelBuffer::blit` doesn't leak resources.
+    fn pixelbuffer_blit_resource_leaks() {
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            let pb = PixelBuffer::new(31, 31, PixelBufferFormatType::BGR, desktop_wnd).unwrap();
+            pb.blit(desktop_wnd).unwrap();
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that `PixelBuffer::present_at` doesn't blit before
+    /// the target time, returns right away for target times in the past, and doesn't leak
+    /// resources.
+    fn present_at_waits_for_target_time() {
+        use std::time::Duration;
+
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            let pb = PixelBuffer::new(31, 31, PixelBufferFormatType::BGR, desktop_wnd).unwrap();
+            let target_time = Instant::now() + Duration::from_millis(30);
+            pb.present_at(target_time, desktop_wnd).unwrap();
+            assert!(Instant::now() >= target_time);
+
+            let start = Instant::now();
+            pb.present_at(start - Duration::from_secs(1), desktop_wnd)
+                .unwrap();
+            assert!(start.elapsed() < Duration::from_secs(1));
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that `PixelBuffer::capture_window` doesn't leak
+    /// resources.
+    fn pixelbuffer_capture_window_resource_leaks() {
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            let mut pb = PixelBuffer::new(31, 31, PixelBufferFormatType::BGR, desktop_wnd).unwrap();
+            pb.capture_window(desktop_wnd).unwrap();
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that a `Surface` presents and releases its buffers
+    /// the same way a `PixelBuffer` does, including across resizes.
+    fn surface_resource_leaks() {
+        use crate::{Context, Surface};
+        use raw_window_handle::{DisplayHandle, WindowHandle};
+        use std::num::{NonZeroU32, NonZeroUsize};
+
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = WindowHandle::borrow_raw(from_hwnd(GetDesktopWindow()));
+            let context = Context::new(DisplayHandle::windows()).unwrap();
+            let mut surface: Surface<_> = Surface::new(&context, desktop_wnd).unwrap();
+            for size in [31, 31, 17] {
+                let size = NonZeroU32::new(size).unwrap();
+                surface.resize(size, size).unwrap();
+                let buffer = surface.buffer_mut().unwrap();
+                buffer.present().unwrap();
+            }
+            surface.set_buffer_count(NonZeroUsize::new(3).unwrap());
+            for _ in 0..4 {
+                surface.buffer_mut().unwrap().present().unwrap();
+            }
+            assert_eq!(surface.buffer_mut().unwrap().age(), 3);
+
+            let desktop_wnd = surface.suspend().unwrap();
+            surface.resume(desktop_wnd).unwrap();
+            assert_eq!(surface.buffer_mut().unwrap().age(), 3);
+
+            // The new window doesn't show any of the buffers yet.
+            let desktop_wnd = surface.rebind(desktop_wnd).unwrap();
+            assert_eq!(surface.buffer_mut().unwrap().age(), 0);
+            for _ in 0..3 {
+                surface.buffer_mut().unwrap().present().unwrap();
+            }
+            assert_eq!(surface.buffer_mut().unwrap().age(), 3);
+
+            // A window that can't display the buffers leaves the surface as it was.
+            let gone = WindowHandle::borrow_raw(from_hwnd(usize::MAX as _));
+            assert!(matches!(
+                surface.rebind(gone),
+                Err(BlitError::WindowDestroyed)
+            ));
+            assert_eq!(surface.buffer_mut().unwrap().age(), 3);
+            surface.buffer_mut().unwrap().present().unwrap();
+            surface.rebind(desktop_wnd).unwrap();
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that querying a window's capabilities doesn't leak
+    /// resources, and that `zero_copy` follows how a buffer is actually presented.
+    fn capabilities_resource_leaks() {
+        use crate::{foreign::ForeignWindow, ColorAdjustment, PixelBuffer as PublicPixelBuffer};
+        use std::alloc::{self, Layout};
+
+        struct Heap;
+        unsafe impl BufferAllocator for Heap {
+            fn allocate(&self, layout: Layout) -> *mut u8 {
+                unsafe { alloc::alloc_zeroed(layout) }
+            }
+            unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+                alloc::dealloc(ptr, layout)
+            }
+        }
+
+        assert_no_gdi_leaks(|| {
+            let window = unsafe { ForeignWindow::from_hwnd(GetDesktopWindow() as _) }.unwrap();
+            let caps = Capabilities::query(&window).unwrap();
+            assert_eq!(caps.preferred_format, PixelBufferFormatType::NATIVE);
+            assert_eq!(caps.max_buffer_size, MAX_BUFFER_LEN);
+            assert!(caps.zero_copy);
+
+            let format = PixelBufferFormatType::BGRA;
+            let mut pb = PublicPixelBuffer::new(31, 31, format, &window).unwrap();
+            assert_eq!(pb.target_capabilities(&window).unwrap(), caps);
+            pb.set_color_adjustment(Some(ColorAdjustment {
+                brightness: 0.5,
+                ..ColorAdjustment::IDENTITY
+            }));
+            assert!(!pb.target_capabilities(&window).unwrap().zero_copy);
+            pb.set_color_adjustment(None);
+            assert!(pb.target_capabilities(&window).unwrap().zero_copy);
+
+            let options = BufferOptions::new().mismatch_policy(MismatchPolicy::Convert);
+            let format = PixelBufferFormatType::RGBA;
+            let pb = PublicPixelBuffer::with_options(31, 31, format, &window, options).unwrap();
+            assert!(!pb.target_capabilities(&window).unwrap().zero_copy);
+
+            let format = PixelBufferFormatType::BGRA;
+            let pb = PublicPixelBuffer::with_allocator(31, 31, format, &window, Heap).unwrap();
+            assert!(!pb.target_capabilities(&window).unwrap().zero_copy);
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that `present_group` doesn't leak resources.
+    fn present_group_resource_leaks() {
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            let a = PixelBuffer::new(31, 31, PixelBufferFormatType::BGR, desktop_wnd).unwrap();
+            let mut b = PixelBuffer::new(17, 17, PixelBufferFormatType::BGRA, desktop_wnd).unwrap();
+            b.set_frame_throttle(true);
+            present_group(&[(&a, desktop_wnd), (&b, desktop_wnd)]).unwrap();
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that windows wrapped in a `ForeignWindow`, as
+    /// provided by libraries like GLFW and SDL2, work like any other window.
+    fn foreign_window_resource_leaks() {
+        use crate::{foreign::ForeignWindow, Context, PixelBuffer as PublicPixelBuffer};
+
+        assert_no_gdi_leaks(|| unsafe {
+            assert!(ForeignWindow::from_hwnd(ptr::null_mut()).is_none());
+            let window = ForeignWindow::from_hwnd(GetDesktopWindow() as _).unwrap();
+            Context::new(window).unwrap();
+            let pb = PublicPixelBuffer::new(31, 31, PixelBufferFormatType::BGRA, &window).unwrap();
+            pb.blit(&window).unwrap();
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that presenting only the dirty region doesn't leak
+    /// any resources, and that it keeps track of what's been changed.
+    fn present_dirty_resource_leaks() {
+        use crate::{foreign::ForeignWindow, PixelBufferTyped, Rect, BGRA};
+
+        assert_no_gdi_leaks(|| unsafe {
+            let window = ForeignWindow::from_hwnd(GetDesktopWindow() as _).unwrap();
+            let mut pb = PixelBufferTyped::<BGRA>::new(31, 31, &window).unwrap();
+            assert_eq!(pb.dirty_region(), Some(Rect::new((0, 0), (31, 31))));
+            pb.present(&window).unwrap();
+            assert_eq!(pb.dirty_region(), None);
+            pb.row_mut(4).unwrap()[0] = BGRA::from_rgb(255, 0, 0);
+            pb.region_mut((2, 8), (3, 3)).unwrap();
+            pb.mark_dirty((30, 30), (10, 10));
+            assert_eq!(pb.dirty_region(), Some(Rect::new((0, 4), (31, 27))));
+            pb.present(&window).unwrap();
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that presenting a viewport of a canvas larger than
+    /// the window doesn't leak any resources.
+    fn canvas_resource_leaks() {
+        use crate::{foreign::ForeignWindow, Canvas, BGRA};
+
+        assert_no_gdi_leaks(|| unsafe {
+            let window = ForeignWindow::from_hwnd(GetDesktopWindow() as _).unwrap();
+            let canvas = Canvas::<BGRA>::new(64, 48, &window).unwrap();
+            assert_eq!(canvas.clamp_offset((50, 10), (32, 32)), (32, 10));
+            assert_eq!(canvas.clamp_offset((50, 10), (80, 32)), (0, 10));
+            canvas.present_viewport((8, 8), (32, 32), &window).unwrap();
+            canvas.present_viewport((8, 8), (80, 80), &window).unwrap();
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that a failed scroll doesn't leak any resources, and
+    /// leaves the pixel buffer as-is.
+    fn scroll_resource_leaks() {
+        use crate::{foreign::ForeignWindow, BlitError, PixelBufferTyped, BGRA};
+
+        assert_no_gdi_leaks(|| unsafe {
+            let window = ForeignWindow::from_hwnd(GetDesktopWindow() as _).unwrap();
+            let mut pb = PixelBufferTyped::<BGRA>::new(31, 31, &window).unwrap();
+            pb.clear(BGRA::from_rgb(0, 255, 0));
+            // Scrolling the desktop itself would be rather rude, so a stale handle is used.
+            let gone = ForeignWindow::from_hwnd(usize::MAX as _).unwrap();
+            let res = pb.scroll(4, -4, BGRA::from_rgb(0, 0, 0), &gone);
+            assert!(matches!(res, Err(BlitError::WindowDestroyed)));
+            assert!(pb.rows().flatten().all(|&p| p == BGRA::from_rgb(0, 255, 0)));
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that blitting a pixel buffer as its rows are
+    /// produced doesn't leak any resources.
+    fn blit_streaming_resource_leaks() {
+        use crate::{foreign::ForeignWindow, PixelBufferTyped, BGRA};
+
+        assert_no_gdi_leaks(|| unsafe {
+            let window = ForeignWindow::from_hwnd(GetDesktopWindow() as _).unwrap();
+            let mut pb = PixelBufferTyped::<BGRA>::new(31, 71, &window).unwrap();
+            let mut produced = Vec::new();
+            pb.blit_streaming(
+                |row, pixels| {
+                    produced.push(row);
+                    pixels
+                        .iter_mut()
+                        .for_each(|p| *p = BGRA::from_rgb(row as u8, 0, 0));
+                },
+                &window,
+            )
+            .unwrap();
+            assert_eq!(produced, (0..71).collect::<Vec<_>>());
+            assert_eq!(pb.row(70).unwrap()[0], BGRA::from_rgb(70, 0, 0));
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that reducing the color depth for remote sessions
+    /// doesn't leak any resources.
+    fn remote_depth_resource_leaks() {
+        use crate::{ChannelDepth, ConversionOptions, DitherMode};
+
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            let options = ConversionOptions {
+                dither: DitherMode::Ordered,
+            };
+            for &format in &[PixelBufferFormatType::BGR, PixelBufferFormatType::BGRA] {
+                let mut pb = PixelBuffer::new(31, 31, format, desktop_wnd).unwrap();
+                pb.set_remote_depth(Some((ChannelDepth::RGB565, options)));
+                pb.blit_rect((3, 5), (3, 5), (20, 20), None, desktop_wnd)
+                    .unwrap();
+                pb.set_remote_depth(None);
+                assert_eq!(pb.scratch_capacity(), 0);
+            }
+        });
+    }
+
+    /// Random pixels for a buffer with an odd-ish size, so that rows need padding to be aligned.
+    fn pixels() -> impl proptest::strategy::Strategy<Value = (u32, u32, Vec<[u8; 4]>)> {
+        use proptest::prelude::*;
+        (1u32..70, 1u32..20).prop_flat_map(|(width, height)| {
+            let len = (width * height) as usize;
+            (
+                Just(width),
+                Just(height),
+                prop::collection::vec(any::<[u8; 4]>(), len),
+            )
+        })
+    }
+
+    /// Writes `pixels` into a `P` buffer as `Q`, and checks that they read back unchanged as both.
+    fn assert_view_round_trip<P, Q>(width: u32, height: u32, pixels: &[[u8; 4]])
+    where
+        P: crate::PixelBufferFormatSupported,
+        Q: crate::PixelBufferFormat,
+    {
+        use crate::PixelBufferTyped;
+
+        let desktop_wnd = unsafe { from_hwnd(GetDesktopWindow()) };
+        let desktop_wnd = unsafe { raw_window_handle::WindowHandle::borrow_raw(desktop_wnd) };
+        let mut pb = PixelBufferTyped::<P>::new(width, height, &desktop_wnd).unwrap();
+        let expected = |[r, g, b, a]: [u8; 4]| Q::from_rgba(r, g, b, a).to_rgba();
+
+        let mut view = pb.view_as_mut::<Q>();
+        for (i, &[r, g, b, a]) in pixels.iter().enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            view.set_pixel(x, y, Q::from_rgba(r, g, b, a));
+        }
+        for (i, &pixel) in pixels.iter().enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            assert_eq!(view.pixel(x, y).unwrap().to_rgba(), expected(pixel));
+        }
+        assert!(view.pixel(width, 0).is_none());
+        assert!(view.pixel(0, height).is_none());
+        assert!(view.pixel(0, u32::MAX).is_none());
+        view.set_pixel(0, height, Q::from_rgba(1, 2, 3, 4));
+
+        assert_eq!(pb.rows().len(), height as usize);
+        for (row, expected_row) in pb.rows().zip(pixels.chunks(width as usize)) {
+            assert_eq!(row.len(), width as usize);
+            for (&pixel, &expected_pixel) in row.iter().zip(expected_row) {
+                assert_eq!(pixel.to_rgba(), expected(expected_pixel));
+            }
+        }
+        for (x, column) in pb.columns().enumerate() {
+            for (y, &pixel) in column.enumerate() {
+                assert_eq!(pixel.to_rgba(), pb.row(y as u32).unwrap()[x].to_rgba());
+            }
+        }
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(32))]
+
+        #[test]
+        #[serial]
+        /// The purpose of this test is to verify that every pixel format converts to and from
+        /// RGBA without losing anything but the alpha channel of formats that don't have one.
+        fn format_rgba_round_trip(rgba in proptest::prelude::any::<[u8; 4]>()) {
+            use crate::{BGR, BGRA, RGB, RGBA};
+
+            let [r, g, b, a] = rgba;
+            assert_eq!(BGRA::from_rgba(r, g, b, a).to_rgba(), rgba);
+            assert_eq!(RGBA::from_rgba(r, g, b, a).to_rgba(), rgba);
+            assert_eq!(BGR::from_rgba(r, g, b, a).to_rgba(), [r, g, b, 255]);
+            assert_eq!(RGB::from_rgba(r, g, b, a).to_rgba(), [r, g, b, 255]);
+            assert_eq!(BGRA::to_raw_slice(&[BGRA::from_rgba(r, g, b, a)]), [b, g, r, a]);
+            assert_eq!(BGR::to_raw_slice(&[BGR::from_rgb(r, g, b)]), [b, g, r]);
+            assert_eq!(RGBA::to_raw_slice(&[RGBA::from_rgba(r, g, b, a)]), [r, g, b, a]);
+            assert_eq!(RGB::to_raw_slice(&[RGB::from_rgb(r, g, b)]), [r, g, b]);
+        }
+
+        #[test]
+        #[serial]
+        /// The purpose of this test is to verify that pixels written to a native buffer through
+        /// every format of the same size read back exactly, whatever the buffer's row padding.
+        fn format_view_round_trip((width, height, pixels) in pixels()) {
+            use crate::{BGR, BGRA, RGB, RGBA};
+
+            assert_view_round_trip::<BGRA, BGRA>(width, height, &pixels);
+            assert_view_round_trip::<BGRA, RGBA>(width, height, &pixels);
+            assert_view_round_trip::<BGR, BGR>(width, height, &pixels);
+            assert_view_round_trip::<BGR, RGB>(width, height, &pixels);
+        }
+
+        #[test]
+        #[serial]
+        /// The purpose of this test is to verify that the raw rows of a buffer are exactly as long
+        /// as its pixels, and keep what's written to them, for every format type.
+        fn format_raw_rows_round_trip((width, height, pixels) in pixels()) {
+            let desktop_wnd = unsafe { from_hwnd(GetDesktopWindow()) };
+            for &format in &[
+                PixelBufferFormatType::BGR,
+                PixelBufferFormatType::BGRA,
+                PixelBufferFormatType::RGB,
+                PixelBufferFormatType::RGBA,
+            ] {
+                let mut pb = match unsafe { PixelBuffer::new(width, height, format, desktop_wnd) } {
+                    Ok(pb) => pb,
+                    Err(PixelBufferCreationError::FormatMismatch { .. }) => continue,
+                    Err(e) => panic!("{:?}", e),
+                };
+                let bytes_per_pixel = pb.bytes_per_pixel();
+                for (row, pixels) in pb.rows_mut().zip(pixels.chunks(width as usize)) {
+                    assert_eq!(row.len(), width as usize * bytes_per_pixel);
+                    for (dst, src) in row.chunks_exact_mut(bytes_per_pixel).zip(pixels) {
+                        dst.copy_from_slice(&src[..bytes_per_pixel]);
+                    }
+                }
+                for (y, pixels) in pixels.chunks(width as usize).enumerate() {
+                    let row = pb.row(y as u32).unwrap();
+                    for (src, expected) in row.chunks_exact(bytes_per_pixel).zip(pixels) {
+                        assert_eq!(src, &expected[..bytes_per_pixel]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    /// Pixels are blitted to and from top-left-origin positions, regardless of which path is used
+    /// to present them.
+    fn blit_rect_top_left_origin() {
+        const TARGET_WIDTH: usize = 8;
+        unsafe {
+            let raw_handle = from_hwnd(GetDesktopWindow());
+            let mut pb = PixelBuffer::new(4, 4, PixelBufferFormatType::BGRA, raw_handle).unwrap();
+            for y in 0..4 {
+                for (x, pixel) in pb.row_mut(y).unwrap().chunks_exact_mut(4).enumerate() {
+                    pixel.copy_from_slice(&[x as u8, y as u8, 7, 255]);
+                }
+            }
+
+            // A top-down target, so its bits are laid out like the buffer's rows.
+            let mut info = pb.bitmap_info();
+            info.biWidth = TARGET_WIDTH as i32;
+            info.biHeight = -(TARGET_WIDTH as i32);
+            let mut bits = ptr::null_mut();
+            let target = wingdi::CreateDIBSection(
+                ptr::null_mut(),
+                &info as *const BITMAPINFOHEADER as _,
+                wingdi::DIB_RGB_COLORS,
+                &mut bits,
+                ptr::null_mut(),
+                0,
+            );
+            assert!(!target.is_null());
+            let dc = wingdi::CreateCompatibleDC(ptr::null_mut());
+            let prev_bmp = wingdi::SelectObject(dc, target as _);
+            let target_pixel = |x: usize, y: usize| {
+                let start = (y * TARGET_WIDTH + x) * 4;
+                std::slice::from_raw_parts(bits as *const u8, TARGET_WIDTH * TARGET_WIDTH * 4)
+                    [start..start + 3]
+                    .to_vec()
+            };
+
+            let dibs = [None, Some((pb.bitmap.bmBits as *const _, pb.bitmap_info()))];
+            for dib in dibs.iter().copied() {
+                ptr::write_bytes(bits as *mut u8, 0, TARGET_WIDTH * TARGET_WIDTH * 4);
+                assert_ne!(
+                    pb.upload(dc, dib, (1, 2), (3, 1), (2, 2), (2, 2), Filter::Nearest),
+                    0
+                );
+                wingdi::GdiFlush();
+                for y in 0..2 {
+                    for x in 0..2 {
+                        let expected = vec![1 + x as u8, 2 + y as u8, 7];
+                        assert_eq!(
+                            target_pixel(3 + x, 1 + y),
+                            expected,
+                            "dib: {}",
+                            dib.is_some()
+                        );
+                    }
+                }
+                assert_eq!(target_pixel(2, 1), vec![0, 0, 0]);
+                assert_eq!(target_pixel(3, 3), vec![0, 0, 0]);
+            }
+
+            // Scaled blits pick the nearest pixel, from either path.
+            for dib in dibs.iter().copied() {
+                ptr::write_bytes(bits as *mut u8, 0, TARGET_WIDTH * TARGET_WIDTH * 4);
+                assert_ne!(
+                    pb.upload(dc, dib, (1, 2), (2, 2), (2, 2), (4, 4), Filter::Nearest),
+                    0
+                );
+                wingdi::GdiFlush();
+                for y in 0..4 {
+                    for x in 0..4 {
+                        let expected = vec![1 + x as u8 / 2, 2 + y as u8 / 2, 7];
+                        assert_eq!(
+                            target_pixel(2 + x, 2 + y),
+                            expected,
+                            "dib: {}",
+                            dib.is_some()
+                        );
+                    }
+                }
+                assert_eq!(target_pixel(6, 2), vec![0, 0, 0]);
+            }
+
+            wingdi::SelectObject(dc, prev_bmp);
+            wingdi::DeleteDC(dc);
+            wingdi::DeleteObject(target as _);
+        }
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that the present path behaves as `alpha_behavior`
+    /// reports: with `AlphaBehavior::Ignored`, pixels that only differ in alpha are presented
+    /// identically.
+    fn alpha_behavior_matches_presented_pixels() {
+        assert_no_gdi_leaks(|| unsafe {
+            let raw_handle = from_hwnd(GetDesktopWindow());
+            let mut pb = PixelBuffer::new(2, 1, PixelBufferFormatType::BGRA, raw_handle).unwrap();
+            assert_eq!(pb.alpha_behavior(), AlphaBehavior::Ignored);
+            pb.row_mut(0)
+                .unwrap()
+                .copy_from_slice(&[10, 20, 30, 0, 10, 20, 30, 255]);
+
+            let mut info = pb.bitmap_info();
+            info.biHeight = -1;
+            let mut bits = ptr::null_mut();
+            let target = wingdi::CreateDIBSection(
+                ptr::null_mut(),
+                &info as *const BITMAPINFOHEADER as _,
+                wingdi::DIB_RGB_COLORS,
+                &mut bits,
+                ptr::null_mut(),
+                0,
+            );
+            assert!(!target.is_null());
+            let dc = wingdi::CreateCompatibleDC(ptr::null_mut());
+            let prev_bmp = wingdi::SelectObject(dc, target as _);
+
+            let dibs = [None, Some((pb.bitmap.bmBits as *const _, pb.bitmap_info()))];
+            for dib in dibs.iter().copied() {
+                ptr::write_bytes(bits as *mut u8, 0, 8);
+                assert_ne!(
+                    pb.upload(dc, dib, (0, 0), (0, 0), (2, 1), (2, 1), Filter::Nearest),
+                    0
+                );
+                wingdi::GdiFlush();
+                let presented = std::slice::from_raw_parts(bits as *const u8, 8);
+                assert_eq!(presented[..3], [10, 20, 30], "dib: {}", dib.is_some());
+                assert_eq!(presented[4..7], [10, 20, 30], "dib: {}", dib.is_some());
+            }
+
+            wingdi::SelectObject(dc, prev_bmp);
+            wingdi::DeleteDC(dc);
+            wingdi::DeleteObject(target as _);
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// Pixels copied between buffers of different formats keep their colors, and are clipped to
+    /// both buffers.
+    fn copy_from_buffer_converts_formats() {
+        use crate::{foreign::ForeignWindow, PixelBufferTyped, Rect, BGR, BGRA};
 
-        // Perform test
         unsafe {
+            let window = ForeignWindow::from_hwnd(GetDesktopWindow() as _).unwrap();
+            let mut src = PixelBufferTyped::<BGRA>::new(8, 8, &window).unwrap();
+            let mut dst = PixelBufferTyped::<BGR>::new(4, 4, &window).unwrap();
+            src.clear(BGRA::from_rgb(10, 20, 30));
+            dst.clear(BGR::from_rgb(0, 0, 0));
+            dst.present(&window).unwrap();
+
+            dst.copy_from_buffer(&src, (6, 0), (4, 4), (1, 2));
+            assert_eq!(dst.dirty_region(), Some(Rect::new((1, 2), (2, 2))));
+            for y in 0..4 {
+                for x in 0..4 {
+                    let expected = match (1..3).contains(&x) && (2..4).contains(&y) {
+                        true => BGR::from_rgb(10, 20, 30),
+                        false => BGR::from_rgb(0, 0, 0),
+                    };
+                    assert_eq!(dst.row(y).unwrap()[x as usize], expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that detaching a pixel buffer from its window doesn't
+    /// leak any resources, and that the buffer can't be blitted onto the window afterwards.
+    fn detach_resource_leaks() {
+        use crate::{foreign::ForeignWindow, PixelBufferTyped, BGRA};
+
+        assert_no_gdi_leaks(|| unsafe {
+            let window = ForeignWindow::from_hwnd(GetDesktopWindow() as _).unwrap();
+            let mut pb = PixelBufferTyped::<BGRA>::new(31, 31, &window).unwrap();
+            pb.blit(&window).unwrap();
+            pb.detach(&window).unwrap();
+            assert!(pb.p.p.windows.is_empty());
+            pb.add_window(&window).unwrap();
+            pb.blit(&window).unwrap();
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that windows and formats that can't be presented are
+    /// reported when the buffer is created, without leaking any resources.
+    fn incompatible_window_resource_leaks() {
+        assert_no_gdi_leaks(|| unsafe {
             let desktop_wnd = from_hwnd(GetDesktopWindow());
-            let pb = PixelBuffer::new(31, 31, PixelBufferFormatType::BGR, desktop_wnd).unwrap();
-            let _res = pb.blit(desktop_wnd);
+            match PixelBuffer::new(31, 31, PixelBufferFormatType::RGBA, desktop_wnd) {
+                Err(PixelBufferCreationError::FormatMismatch {
+                    requested: PixelBufferFormatType::RGBA,
+                    suggested: PixelBufferFormatType::BGRA,
+                }) => {}
+                result => panic!("unexpected result: {:?}", result.err()),
+            }
+            let gone = from_hwnd(usize::MAX as _);
+            match PixelBuffer::new(31, 31, PixelBufferFormatType::BGRA, gone) {
+                Err(PixelBufferCreationError::IncompatibleWindow(_)) => {}
+                result => panic!("unexpected result: {:?}", result.err()),
+            }
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that presenting with a present limit doesn't leak any
+    /// resources.
+    fn present_limit_resource_leaks() {
+        use crate::Backpressure;
+
+        assert_no_gdi_leaks(|| unsafe {
+            let raw_handle = from_hwnd(GetDesktopWindow());
+            let mut pb = PixelBuffer::new(31, 31, PixelBufferFormatType::BGRA, raw_handle).unwrap();
+            for &backpressure in &[Backpressure::Block, Backpressure::WouldBlock] {
+                let limit = Some((NonZeroU32::new(2).unwrap(), backpressure));
+                pb.set_present_limit(limit);
+                assert_eq!(pb.present_limit(), limit);
+                for _ in 0..4 {
+                    match pb.blit(raw_handle) {
+                        Err(BlitError::Io(e)) if backpressure == Backpressure::WouldBlock => {
+                            assert_eq!(e.kind(), io::ErrorKind::WouldBlock)
+                        }
+                        result => result.unwrap(),
+                    }
+                }
+            }
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that a buffer's memory usage accounts for its scratch
+    /// space, and that the scratch space isn't kept while over the memory budget.
+    fn memory_budget_resource_leaks() {
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            let mut pb =
+                PixelBuffer::new(31, 31, PixelBufferFormatType::BGRA, desktop_wnd).unwrap();
+            assert_eq!(pb.memory_usage(), pb.len);
+            pb.set_color_lut(Some(Box::new([0; 256])));
+            pb.reserve_scratch();
+            assert_eq!(pb.memory_usage(), pb.len + pb.scratch_capacity());
+
+            crate::set_memory_budget(Some(0));
+            pb.blit(desktop_wnd).unwrap();
+            crate::set_memory_budget(None);
+            assert_eq!(pb.scratch_capacity(), 0);
+            assert_eq!(pb.memory_usage(), pb.len);
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that a present conversion is given the rows being
+    /// blitted, and that removing it frees the scratch space without leaking any resources.
+    fn present_conversion_resource_leaks() {
+        use std::sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        };
+
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            let mut pb =
+                PixelBuffer::new(31, 31, PixelBufferFormatType::BGRA, desktop_wnd).unwrap();
+            let converted_rows = Arc::new(AtomicU32::new(0));
+            let counter = converted_rows.clone();
+            let row_len = pb.row_len();
+            pb.set_present_conversion(Some(Box::new(move |src, dst, rows, stride| {
+                assert_eq!(stride, row_len);
+                assert_eq!(src.len(), rows as usize * stride);
+                dst.copy_from_slice(src);
+                counter.fetch_add(rows, Ordering::SeqCst);
+            })));
+            pb.blit_rect((3, 5), (3, 5), (20, 20), None, desktop_wnd)
+                .unwrap();
+            assert_eq!(converted_rows.load(Ordering::SeqCst), 20);
+            pb.set_present_conversion(None);
+            assert_eq!(pb.scratch_capacity(), 0);
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that `present_sized` resizes allocator-backed buffers
+    /// using the same allocator, and that the old buffer's memory is handed back.
+    fn present_sized_resource_leaks() {
+        use crate::{foreign::ForeignWindow, PixelBuffer as PublicPixelBuffer};
+        use std::{
+            alloc::{self, Layout},
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            },
+        };
+
+        struct CountingAllocator(Arc<AtomicUsize>);
+        unsafe impl BufferAllocator for CountingAllocator {
+            fn allocate(&self, layout: Layout) -> *mut u8 {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                unsafe { alloc::alloc_zeroed(layout) }
+            }
+            unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+                self.0.fetch_sub(1, Ordering::SeqCst);
+                alloc::dealloc(ptr, layout)
+            }
         }
 
-        // It is expected that all resources have been released at this point.
-        let obj_count_current = gdi_obj_count();
+        let live = Arc::new(AtomicUsize::new(0));
+        assert_no_gdi_leaks(|| {
+            let window = unsafe { ForeignWindow::from_hwnd(GetDesktopWindow() as _) }.unwrap();
+            let mut pb = PublicPixelBuffer::with_allocator(
+                31,
+                31,
+                PixelBufferFormatType::BGR,
+                &window,
+                CountingAllocator(live.clone()),
+            )
+            .unwrap();
+            for size in [(31, 31), (64, 17), (8, 40)].iter() {
+                pb.present_sized(&window, *size).unwrap();
+                assert_eq!((pb.width(), pb.height()), *size);
+                assert_eq!(live.load(Ordering::SeqCst), 1);
+            }
+        });
+        assert_eq!(live.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that `emergency_present` doesn't leak the device
+    /// contexts it blits through.
+    fn emergency_present_resource_leaks() {
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            let pb = PixelBuffer::new(31, 31, PixelBufferFormatType::BGRA, desktop_wnd).unwrap();
+            for _ in 0..16 {
+                assert!(pb.emergency_present());
+            }
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that offscreen buffers can be drawn into without a
+    /// window, and that dropping them releases their bitmap.
+    fn new_offscreen_resource_leaks() {
+        assert_no_gdi_leaks(|| unsafe {
+            let mut pb = PixelBuffer::new_offscreen(31, 31, PixelBufferFormatType::BGR).unwrap();
+            pb.row_mut(30).unwrap()[..3].copy_from_slice(&[1, 2, 3]);
+            assert_eq!(&pb.row(30).unwrap()[..3], &[1, 2, 3]);
+            assert!(pb.emergency_present());
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that rows past the bottom of the buffer, including
+    /// every row of an empty buffer, are reported as missing instead of panicking.
+    fn row_out_of_range() {
+        assert_no_gdi_leaks(|| unsafe {
+            let mut pb = PixelBuffer::new_offscreen(5, 4, PixelBufferFormatType::BGR).unwrap();
+            assert!(pb.row(3).is_some());
+            assert!(pb.row(4).is_none());
+            assert!(pb.row_mut(4).is_none());
+            assert!(pb.row(u32::MAX).is_none());
+
+            let mut pb = PixelBuffer::new_offscreen(5, 0, PixelBufferFormatType::BGR).unwrap();
+            assert!(pb.row(0).is_none());
+            assert!(pb.row_mut(0).is_none());
+            assert!(pb.top_row_ptr().is_null());
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that rows are padded to the requested alignment
+    /// without changing the size of the buffer.
+    fn row_alignment_pads_rows() {
+        assert_no_gdi_leaks(|| unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            for &(alignment, row_len) in &[(1, 96), (16, 96), (64, 128), (256, 256)] {
+                let mut pb = PixelBuffer::with_options(
+                    31,
+                    7,
+                    PixelBufferFormatType::BGR,
+                    desktop_wnd,
+                    BufferOptions::new().row_alignment(alignment),
+                )
+                .unwrap();
+                assert_eq!((pb.width(), pb.height()), (31, 7));
+                assert_eq!(pb.row_len(), row_len);
+                assert_eq!(pb.bytes().len(), row_len * 7);
+                assert!(pb.rows_mut().all(|row| row.len() == 31 * 3));
+                pb.blit(desktop_wnd).unwrap();
+            }
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that memory from an allocator is only cleared when
+    /// the buffer is created zeroed.
+    fn buffer_init_allocator_memory() {
+        use std::alloc::{self, Layout};
+
+        struct StaleAllocator;
+        unsafe impl BufferAllocator for StaleAllocator {
+            fn allocate(&self, layout: Layout) -> *mut u8 {
+                unsafe {
+                    let ptr = alloc::alloc(layout);
+                    if !ptr.is_null() {
+                        ptr::write_bytes(ptr, 0xab, layout.size());
+                    }
+                    ptr
+                }
+            }
+            unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+                alloc::dealloc(ptr, layout)
+            }
+        }
+
+        unsafe {
+            let desktop_wnd = from_hwnd(GetDesktopWindow());
+            for &(init, byte) in &[(BufferInit::Zeroed, 0), (BufferInit::Uninit, 0xab)] {
+                let pb = PixelBuffer::with_allocator(
+                    31,
+                    31,
+                    PixelBufferFormatType::BGR,
+                    desktop_wnd,
+                    Box::new(StaleAllocator),
+                    BufferOptions::new().init(init),
+                )
+                .unwrap();
+                assert!(pb.rows().flatten().all(|&b| b == byte));
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that zoomed presents copy only the requested
+    /// rectangle, clipped to the buffer, and don't leak any GDI objects with either filter.
+    fn present_zoomed_resource_leaks() {
+        use crate::{foreign::ForeignWindow, Filter, PixelBufferTyped, BGRA};
+
+        assert_no_gdi_leaks(|| {
+            let window = unsafe { ForeignWindow::from_hwnd(GetDesktopWindow() as _) }.unwrap();
+            let pb = PixelBufferTyped::<BGRA>::new(31, 31, &window).unwrap();
+            for &filter in &[Filter::Nearest, Filter::Bilinear] {
+                for &zoom in &[0.5, 1.0, 4.0] {
+                    pb.present_zoomed((8, 8), (16, 16), zoom, filter, &window)
+                        .unwrap();
+                    assert_eq!(pb.metrics().bytes_copied, 16 * 16 * 4);
+                }
+            }
+            assert_eq!(pb.frame_index(), 6);
+            // Only the 7x7 pixels inside the buffer are copied.
+            pb.present_zoomed((24, 24), (16, 16), 2.0, Filter::Nearest, &window)
+                .unwrap();
+            assert_eq!(pb.metrics().bytes_copied, 7 * 7 * 4);
+        });
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that zoomed regions are scaled around the top-left
+    /// corner, rounding to the nearest pixel, and that extreme zoom factors saturate instead of
+    /// overflowing.
+    fn zoomed_region_scales_destination() {
+        use crate::{BlitRegion, Point, Size};
+
+        let region = BlitRegion::zoomed((8, 9).into(), (16, 5).into(), 4.0);
         assert_eq!(
-            obj_count_base, obj_count_current,
-            "Expedted GDI object count: {}; observed GDI object count: {}",
-            obj_count_base, obj_count_current
+            region,
+            BlitRegion {
+                src_pos: Point::new(8, 9),
+                src_size: Size::new(16, 5),
+                dst_pos: Point::new(0, 0),
+                dst_size: Size::new(64, 20),
+            }
         );
+        assert_eq!(
+            BlitRegion::zoomed((0, 0).into(), (16, 5).into(), 0.5).dst_size,
+            Size::new(8, 3)
+        );
+        assert_eq!(
+            BlitRegion::zoomed((0, 0).into(), (16, 5).into(), 0.0).dst_size,
+            Size::new(0, 0)
+        );
+        assert_eq!(
+            BlitRegion::zoomed((0, 0).into(), (16, 5).into(), -2.0).dst_size,
+            Size::new(0, 0)
+        );
+        assert_eq!(
+            BlitRegion::zoomed((0, 0).into(), (16, 5).into(), f32::INFINITY).dst_size,
+            Size::new(u32::MAX, u32::MAX)
+        );
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that blitting several disjoint regions, scaled or
+    /// partly outside the buffer, counts as a single present and doesn't leak any GDI objects.
+    fn blit_regions_resource_leaks() {
+        use crate::{foreign::ForeignWindow, BlitRegion, PixelBufferTyped, Point, Size, BGRA};
+
+        assert_no_gdi_leaks(|| {
+            let window = unsafe { ForeignWindow::from_hwnd(GetDesktopWindow() as _) }.unwrap();
+            let pb = PixelBufferTyped::<BGRA>::new(31, 31, &window).unwrap();
+            let region =
+                |src: (u32, u32), size: (u32, u32), dst: (u32, u32), dst_size| BlitRegion {
+                    src_pos: Point::from(src),
+                    src_size: Size::from(size),
+                    dst_pos: Point::from(dst),
+                    dst_size: Size::from(dst_size),
+                };
+            let regions = [
+                region((0, 0), (8, 8), (0, 0), (8, 8)),
+                region((16, 0), (8, 8), (40, 0), (16, 16)),
+                region((0, 16), (4, 4), (0, 40), (2, 2)),
+                // Only the 7x5 pixels inside the buffer are copied.
+                region((24, 26), (10, 10), (80, 80), (10, 10)),
+            ];
+            pb.blit_regions(&regions, &window).unwrap();
+            assert_eq!(pb.frame_index(), 1);
+            assert_eq!(pb.metrics().bytes_copied, 7 * 5 * 4);
+
+            pb.blit_regions(&regions[..2], &window).unwrap();
+            assert_eq!(pb.frame_index(), 2);
+            assert_eq!(pb.metrics().bytes_copied, 8 * 8 * 4);
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that progressive presents don't leak any GDI
+    /// objects, including with intervals larger than the buffer.
+    fn present_progressive_resource_leaks() {
+        use crate::{foreign::ForeignWindow, PixelBufferTyped, BGRA};
+
+        assert_no_gdi_leaks(|| {
+            let window = unsafe { ForeignWindow::from_hwnd(GetDesktopWindow() as _) }.unwrap();
+            let pb = PixelBufferTyped::<BGRA>::new(31, 31, &window).unwrap();
+            for &interval in &[0, 1, 8, 64] {
+                pb.present_progressive(interval, &window).unwrap();
+                // Progressive presents finish with a single row.
+                let rows = if interval <= 1 { 31 } else { 1 };
+                assert_eq!(pb.metrics().bytes_copied, 31 * rows * 4);
+            }
+            assert_eq!(pb.frame_index(), 4);
+        });
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that the first pass of a progressive present
+    /// stretches its rows over the whole buffer, and that the later passes fill in every other
+    /// row exactly once.
+    fn progressive_passes_cover_every_row() {
+        let passes = |interval: u32, height: u32| {
+            (0..interval.min(height))
+                .map(|pass| progressive_pass(pass, interval, height).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+
+        let eight = passes(8, 20);
+        assert_eq!(eight.len(), 8);
+        assert_eq!(eight[0], [(0, 8), (8, 8), (16, 4)]);
+        assert_eq!(eight[3], [(3, 1), (11, 1), (19, 1)]);
+        assert_eq!(eight[7], [(7, 1), (15, 1)]);
+        let mut rows: Vec<u32> = eight.iter().flatten().map(|&(y, _)| y).collect();
+        rows.sort_unstable();
+        assert_eq!(rows, (0..20).collect::<Vec<_>>());
+
+        // Intervals past the end of the buffer stretch the first row over all of it.
+        let wide = passes(64, 5);
+        assert_eq!(wide.len(), 5);
+        assert_eq!(wide[0], [(0, 5)]);
+        assert!(wide[1..].iter().all(|pass| pass.len() == 1));
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that `build_mipmaps` averages every 2x2 block of
+    /// the level above, down to a single pixel, and counts the copies towards the memory usage.
+    fn build_mipmaps_averages_blocks() {
+        use crate::{PixelBufferTyped, BGR};
+
+        let mut pb = PixelBufferTyped::<BGR>::new_offscreen(4, 3).unwrap();
+        let usage = pb.memory_usage();
+        for (y, row) in pb.rows_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = BGR::from_rgb((x * 40) as u8, (y * 40) as u8, 200);
+            }
+        }
+        pb.build_mipmaps();
+        assert_eq!(pb.mipmap_levels(), 2);
+        assert!(pb.memory_usage() > usage);
+
+        let mipmaps = &pb.p.p.mipmaps;
+        assert_eq!((mipmaps[0].width(), mipmaps[0].height()), (2, 1));
+        // BGR pixels store blue first.
+        assert_eq!(&mipmaps[0].row(0)[..6], &[200, 20, 20, 200, 20, 100]);
+        assert_eq!((mipmaps[1].width(), mipmaps[1].height()), (1, 1));
+        assert_eq!(&mipmaps[1].row(0)[..3], &[200, 20, 60]);
+
+        pb.clear_mipmaps();
+        assert_eq!(pb.mipmap_levels(), 0);
+        assert_eq!(pb.memory_usage(), usage);
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that downscaling presents pick the smallest mipmap
+    /// that's still large enough, and that presenting from a mipmap doesn't leak GDI objects.
+    fn present_mipmapped_resource_leaks() {
+        use crate::{foreign::ForeignWindow, BlitRegion, Filter, PixelBufferTyped, BGRA};
+
+        assert_no_gdi_leaks(|| {
+            let window = unsafe { ForeignWindow::from_hwnd(GetDesktopWindow() as _) }.unwrap();
+            let mut pb = PixelBufferTyped::<BGRA>::new(301, 203, &window).unwrap();
+            pb.build_mipmaps();
+            // 150x101, 75x50, 37x25, 18x12, 9x6, 4x3, 2x1 and 1x1.
+            assert_eq!(pb.mipmap_levels(), 8);
+            for &(zoom, level) in &[
+                (0.01, Some(6)),
+                (0.1, Some(3)),
+                (0.3, Some(1)),
+                (0.5, Some(1)),
+                (1.0, None),
+            ] {
+                let region = BlitRegion::zoomed((7, 9).into(), (250, 180).into(), zoom);
+                let picked =
+                    pb.p.p
+                        .mipmap_for(region.src_size.into(), region.dst_size.into());
+                assert_eq!(picked.map(|(level, _)| level), level, "zoom {}", zoom);
+
+                pb.present_zoomed((7, 9), (250, 180), zoom, Filter::Bilinear, &window)
+                    .unwrap();
+                // The copied bytes are counted at the buffer's resolution.
+                assert_eq!(pb.metrics().bytes_copied, 250 * 180 * 4);
+            }
+            pb.clear_mipmaps();
+            assert_eq!(
+                pb.p.p
+                    .mipmap_for((250, 180), (3, 2))
+                    .map(|(level, _)| level),
+                None
+            );
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that buffers in a format GDI can't present are
+    /// converted or reinterpreted according to their `MismatchPolicy`, without leaking any
+    /// resources.
+    fn mismatch_policy_resource_leaks() {
+        use crate::{foreign::ForeignWindow, BlitError, PixelBufferTyped, RGBA};
+
+        assert_no_gdi_leaks(|| {
+            let window = unsafe { ForeignWindow::from_hwnd(GetDesktopWindow() as _) }.unwrap();
+            let options = BufferOptions::new();
+            match PixelBufferTyped::<RGBA>::with_options(4, 2, &window, options) {
+                Err(PixelBufferCreationError::FormatMismatch { .. }) => {}
+                result => panic!("unexpected result: {:?}", result.err()),
+            }
+
+            let options = options.mismatch_policy(MismatchPolicy::Convert);
+            let mut pb = PixelBufferTyped::<RGBA>::with_options(4, 2, &window, options).unwrap();
+            for row in pb.rows_mut() {
+                row.fill(RGBA::from_rgba(10, 20, 30, 40));
+            }
+            assert_eq!(pb.backend_format(), PixelBufferFormatType::RGBA);
+            pb.blit(&window).unwrap();
+            let scratch = pb.p.p.scratch.borrow().clone();
+            assert_eq!(&scratch[..8], &[30, 20, 10, 40, 30, 20, 10, 40]);
+            assert_eq!(pb.row(0).unwrap()[0], RGBA::from_rgba(10, 20, 30, 40));
+            pb.set_allow_conversion(false);
+            match pb.blit(&window) {
+                Err(BlitError::Io(e)) if e.kind() == io::ErrorKind::Unsupported => {}
+                result => panic!("unexpected result: {:?}", result),
+            }
+
+            let options = options.mismatch_policy(MismatchPolicy::Reinterpret);
+            let pb = PixelBufferTyped::<RGBA>::with_options(4, 2, &window, options).unwrap();
+            assert_eq!(pb.backend_format(), PixelBufferFormatType::BGRA);
+            pb.blit(&window).unwrap();
+            assert_eq!(pb.scratch_capacity(), 0);
+        });
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that the backend's stride and format describe the
+    /// bottom-up, padded bitmap GDI presents from.
+    fn backend_stride_and_format() {
+        use crate::{PixelBufferTyped, BGR};
+
+        let pb = PixelBufferTyped::<BGR>::new_offscreen(5, 3).unwrap();
+        assert_eq!(pb.row_len(), 16);
+        assert_eq!(pb.backend_stride(), -16);
+        assert_eq!(pb.backend_format(), PixelBufferFormatType::BGR);
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that querying the display a window is shown on
+    /// doesn't leak GDI objects.
+    fn display_info_resource_leaks() {
+        use crate::{foreign::ForeignWindow, PixelBufferTyped, BGRA};
+
+        assert_no_gdi_leaks(|| {
+            let window = unsafe { ForeignWindow::from_hwnd(GetDesktopWindow() as _) }.unwrap();
+            let pb = PixelBufferTyped::<BGRA>::new(31, 31, &window).unwrap();
+            let info = pb.target_display_info(&window).unwrap();
+            assert!(info.bits_per_color > 0);
+            assert_eq!(info, DisplayInfo::query(&window).unwrap());
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that deferred presents are flushed in one batch,
+    /// consuming the changes, without leaking any resources.
+    fn deferred_presents_resource_leaks() {
+        use crate::{foreign::ForeignWindow, PixelBufferTyped, BGRA};
+
+        assert_no_gdi_leaks(|| {
+            let window = unsafe { ForeignWindow::from_hwnd(GetDesktopWindow() as _) }.unwrap();
+            let mut pb = PixelBufferTyped::<BGRA>::new(31, 31, &window).unwrap();
+            pb.mark_dirty((2, 2), (3, 3));
+            pb.defer_present(&window).unwrap();
+            pb.mark_dirty((20, 20), (3, 3));
+            pb.defer_present(&window).unwrap();
+            assert_eq!(pb.as_ref().p.deferred.len(), 1);
+            assert!(pb.dirty_region().is_some());
+
+            pb.flush_presents().unwrap();
+            assert_eq!(pb.dirty_region(), None);
+            assert!(!pb.as_ref().p.has_deferred_presents());
+
+            pb.defer_present(&window).unwrap();
+            pb.remove_window(&window);
+            assert!(!pb.as_ref().p.has_deferred_presents());
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that a cancelled token fails blits that need
+    /// converting as well as those that don't, without leaking the device context, and that
+    /// blits succeed again once it's reset.
+    fn cancel_token_interrupts_blits() {
+        use crate::{
+            foreign::ForeignWindow, BlitError, CancelToken, ColorAdjustment, PixelBufferTyped, BGRA,
+        };
+
+        let window = unsafe { ForeignWindow::from_hwnd(GetDesktopWindow() as _) }.unwrap();
+        let mut pb = PixelBufferTyped::<BGRA>::new_supported(128, 128, &window);
+        let token = CancelToken::new();
+        pb.set_cancel_token(Some(token.clone()));
+
+        let darken = ColorAdjustment {
+            brightness: 0.5,
+            ..ColorAdjustment::IDENTITY
+        };
+        assert_no_gdi_leaks(|| {
+            for adjustment in [None, Some(darken)] {
+                pb.set_color_adjustment(adjustment);
+                token.cancel();
+                let res = pb.blit(&window);
+                assert!(
+                    matches!(res, Err(BlitError::Io(e)) if e.kind() == io::ErrorKind::Interrupted)
+                );
+                token.reset();
+                pb.blit(&window).unwrap();
+            }
+            pb.set_color_adjustment(None);
+        });
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that blitting while a previous frame is borrowed
+    /// keeps the borrowed frame instead of panicking, and that later blits replace it.
+    fn previous_frame_kept_while_borrowed() {
+        use crate::{foreign::ForeignWindow, PixelBufferTyped, BGRA};
+
+        let window = unsafe { ForeignWindow::from_hwnd(GetDesktopWindow() as _) }.unwrap();
+        let mut pb = PixelBufferTyped::<BGRA>::new_supported(2, 1, &window);
+        pb.set_keep_previous_frame(true);
+        let (red, blue) = (BGRA::from_rgb(255, 0, 0), BGRA::from_rgb(0, 0, 255));
+        pb.row_mut(0).unwrap()[0] = red;
+        pb.blit(&window).unwrap();
+
+        let frame = pb.previous_frame().unwrap();
+        pb.blit(&window).unwrap();
+        assert_eq!(frame.row(0).unwrap()[0], red);
+        drop(frame);
+
+        pb.row_mut(0).unwrap()[0] = blue;
+        pb.blit(&window).unwrap();
+        assert_eq!(pb.previous_frame().unwrap().row(0).unwrap()[0], blue);
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that a `Surface` whose buffers can't be recreated
+    /// stays suspended, and can still be resumed once they can.
+    fn surface_stays_suspended_when_resume_fails() {
+        use crate::{Context, Surface};
+        use raw_window_handle::{DisplayHandle, WindowHandle};
+        use std::num::NonZeroU32;
+
+        unsafe {
+            let desktop_wnd = WindowHandle::borrow_raw(from_hwnd(GetDesktopWindow()));
+            let context = Context::new(DisplayHandle::windows()).unwrap();
+            let mut surface: Surface<_> = Surface::new(&context, desktop_wnd).unwrap();
+            let size = NonZeroU32::new(16).unwrap();
+            surface.resize(size, size).unwrap();
+            surface.buffer_mut().unwrap().present().unwrap();
+
+            // Resizing a suspended surface doesn't allocate, so the size is only rejected once
+            // it's resumed.
+            let desktop_wnd = surface.suspend().unwrap();
+            surface
+                .resize(NonZeroU32::new(u32::MAX).unwrap(), size)
+                .unwrap();
+            assert!(matches!(
+                surface.resume(desktop_wnd),
+                Err(PixelBufferCreationError::BufferTooLarge)
+            ));
+            assert!(surface.is_suspended());
+            assert!(surface.window().is_none());
+
+            surface.resize(size, size).unwrap();
+            surface.resume(desktop_wnd).unwrap();
+            assert!(!surface.is_suspended());
+            assert_eq!(surface.buffer_mut().unwrap().age(), 0);
+        }
+    }
+
+    #[test]
+    #[serial]
+    /// The purpose of this test is to verify that resuming a `Surface` that isn't suspended onto a
+    /// window its buffers can't be created for keeps its old window and buffers.
+    fn surface_keeps_window_when_resume_fails() {
+        use crate::{Context, Surface, BGRA};
+        use raw_window_handle::{DisplayHandle, WindowHandle};
+        use std::num::NonZeroU32;
+
+        unsafe {
+            let desktop_wnd = WindowHandle::borrow_raw(from_hwnd(GetDesktopWindow()));
+            let context = Context::new(DisplayHandle::windows()).unwrap();
+            let mut surface: Surface<_> = Surface::new(&context, desktop_wnd).unwrap();
+            let size = NonZeroU32::new(16).unwrap();
+            surface.resize(size, size).unwrap();
+            let mut buffer = surface.buffer_mut().unwrap();
+            buffer.row_mut(3).unwrap()[5] = BGRA::from_rgb(1, 2, 3);
+            buffer.present().unwrap();
+
+            // Not a window, so no buffer can be created for it.
+            let not_a_window = WindowHandle::borrow_raw(from_hwnd(1 as HWND));
+            assert!(matches!(
+                surface.resume(not_a_window),
+                Err(PixelBufferCreationError::IncompatibleWindow(_))
+            ));
+            assert!(!surface.is_suspended());
+            assert_eq!(surface.window(), Some(&desktop_wnd));
+
+            surface.resume(desktop_wnd).unwrap();
+            let buffer = surface.buffer_mut().unwrap();
+            assert_eq!(buffer.row(3).unwrap()[5], BGRA::from_rgb(1, 2, 3));
+        }
     }
 }
@@ -0,0 +1,629 @@
+use crate::{convert::to_ycbcr, PixelBufferFormat, PixelBufferTyped};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    ops::{Deref, DerefMut},
+    path::Path,
+};
+
+/// The most bytes a PNG chunk can hold.
+const MAX_CHUNK_LEN: u64 = i32::MAX as u64;
+
+/// The file format a [`Recorder`] writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordingFormat {
+    /// An animated GIF. Colors are reduced to a fixed palette of 216 colors, so gradients show
+    /// banding, but the files are small and display everywhere.
+    Gif,
+    /// An animated PNG with lossless, 24-bit color. Frames are stored uncompressed, so the files
+    /// are large; run them through an optimizer before publishing them.
+    Apng,
+    /// A YUV4MPEG2 video, with uncompressed 4:4:4 frames. Meant to be piped into a video encoder
+    /// like `ffmpeg`.
+    Y4m,
+}
+
+/// Records presented frames into an animation file, e.g. to capture demos or make GIFs for
+/// documentation.
+///
+/// Every [`present`](Self::present)ed buffer becomes one frame, shown for `1 / fps` seconds. The
+/// recording is completed by [`finish`](Self::finish), or when the recorder is dropped, in which
+/// case errors are ignored. To record a buffer as if it were blitted onto a window, see
+/// [`RecordingBuffer`].
+pub struct Recorder<W: Write + Seek = BufWriter<File>> {
+    out: Option<W>,
+    format: RecordingFormat,
+    width: u32,
+    height: u32,
+    fps: u32,
+    frame_count: u32,
+    /// The APNG sequence number of the next chunk.
+    sequence: u32,
+    /// The file offset of the frame count in the APNG `acTL` chunk, filled in by `finish`.
+    frame_count_offset: u64,
+}
+
+impl Recorder {
+    /// Creates the file at `path` and starts recording `width` by `height` frames into it.
+    ///
+    /// See [`Recorder::new`].
+    pub fn create<Q: AsRef<Path>>(
+        path: Q,
+        format: RecordingFormat,
+        width: u32,
+        height: u32,
+        fps: u32,
+    ) -> io::Result<Recorder> {
+        Recorder::new(
+            BufWriter::new(File::create(path)?),
+            format,
+            width,
+            height,
+            fps,
+        )
+    }
+}
+
+impl<W: Write + Seek> Recorder<W> {
+    /// Starts recording `width` by `height` frames into `out`, played back at `fps` frames per
+    /// second.
+    ///
+    /// Returns an error if the format can't store frames of that size or rate, e.g. GIFs larger
+    /// than 65535 pixels or faster than 50 frames per second, or if writing the file's header
+    /// fails.
+    pub fn new(
+        mut out: W,
+        format: RecordingFormat,
+        width: u32,
+        height: u32,
+        fps: u32,
+    ) -> io::Result<Recorder<W>> {
+        let invalid = |message| Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        if width == 0 || height == 0 {
+            return invalid("frames must be at least 1x1");
+        }
+        if fps == 0 {
+            return invalid("the frame rate must be at least 1");
+        }
+        let mut frame_count_offset = 0;
+        match format {
+            RecordingFormat::Gif => {
+                let (width, height) = match (u16::try_from(width), u16::try_from(height)) {
+                    (Ok(width), Ok(height)) => (width, height),
+                    _ => return invalid("GIF frames must be at most 65535x65535"),
+                };
+                // GIF delays are counted in hundredths of a second, and many viewers slow down
+                // delays shorter than two of them.
+                if fps > 50 {
+                    return invalid("GIFs can't play at more than 50 frames per second");
+                }
+                out.write_all(b"GIF89a")?;
+                out.write_all(&width.to_le_bytes())?;
+                out.write_all(&height.to_le_bytes())?;
+                // A 256-entry global color table, with 8 bits per channel.
+                out.write_all(&[0xf7, 0, 0])?;
+                let mut palette = [0; 256 * 3];
+                for (i, color) in palette.chunks_exact_mut(3).take(216).enumerate() {
+                    color.copy_from_slice(&[i / 36, i / 6 % 6, i % 6].map(|c| (c * 51) as u8));
+                }
+                out.write_all(&palette)?;
+                // Loop forever.
+                out.write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+            }
+            RecordingFormat::Apng => {
+                if i32::try_from(width).is_err() || i32::try_from(height).is_err() {
+                    return invalid("PNG frames must be at most 2147483647x2147483647");
+                }
+                if apng_frame_chunk_len(width, height) > MAX_CHUNK_LEN {
+                    return invalid("APNG frames must fit into a PNG chunk");
+                }
+                if fps > u16::MAX as u32 {
+                    return invalid("APNGs can't play at more than 65535 frames per second");
+                }
+                out.write_all(b"\x89PNG\r\n\x1a\n")?;
+                let mut header = Vec::with_capacity(13);
+                header.extend_from_slice(&width.to_be_bytes());
+                header.extend_from_slice(&height.to_be_bytes());
+                // 8-bit RGB, with the standard compression and filtering, and no interlacing.
+                header.extend_from_slice(&[8, 2, 0, 0, 0]);
+                write_chunk(&mut out, b"IHDR", &header)?;
+                frame_count_offset = out.stream_position()? + 8;
+                // The frame count is filled in once it's known, and the animation loops forever.
+                write_chunk(&mut out, b"acTL", &[0; 8])?;
+            }
+            RecordingFormat::Y4m => {
+                writeln!(
+                    out,
+                    "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                    width, height, fps
+                )?;
+            }
+        }
+        Ok(Recorder {
+            out: Some(out),
+            format,
+            width,
+            height,
+            fps,
+            frame_count: 0,
+            sequence: 0,
+            frame_count_offset,
+        })
+    }
+
+    /// The file format being written.
+    pub fn format(&self) -> RecordingFormat {
+        self.format
+    }
+
+    /// The width, in pixels, of the recorded frames.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height, in pixels, of the recorded frames.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The number of frames recorded so far.
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Appends `buffer` to the recording as the next frame, starting at its top-left corner.
+    ///
+    /// Pixels that don't fit are cut off, and any part of the frame that `buffer` doesn't cover
+    /// is black. The alpha channel is ignored.
+    pub fn present<P: PixelBufferFormat>(
+        &mut self,
+        buffer: &PixelBufferTyped<P>,
+    ) -> io::Result<()> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut rows = buffer.rows();
+        let mut pixels = Vec::with_capacity(width * height);
+        for _ in 0..height {
+            let row = rows.next().unwrap_or(&[]);
+            let start = pixels.len();
+            pixels.extend(row.iter().take(width).map(|pixel| {
+                let [r, g, b, _] = pixel.to_rgba();
+                [r, g, b]
+            }));
+            pixels.resize(start + width, [0; 3]);
+        }
+        self.write_frame(&pixels)?;
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    fn write_frame(&mut self, pixels: &[[u8; 3]]) -> io::Result<()> {
+        let out = self.out.as_mut().unwrap();
+        match self.format {
+            RecordingFormat::Gif => {
+                let delay = (100 / self.fps) as u16;
+                // A graphic control extension setting the frame's delay.
+                out.write_all(&[0x21, 0xf9, 4, 0])?;
+                out.write_all(&delay.to_le_bytes())?;
+                out.write_all(&[0, 0])?;
+                // An image descriptor covering the whole frame, using the global color table.
+                out.write_all(&[0x2c, 0, 0, 0, 0])?;
+                out.write_all(&(self.width as u16).to_le_bytes())?;
+                out.write_all(&(self.height as u16).to_le_bytes())?;
+                out.write_all(&[0])?;
+                let indices = pixels.iter().map(|&[r, g, b]| {
+                    let level = |c: u8| (c as u16 * 5 + 127) / 255;
+                    level(r) * 36 + level(g) * 6 + level(b)
+                });
+                out.write_all(&[8])?;
+                let data = lzw_encode(indices);
+                for block in data.chunks(255) {
+                    out.write_all(&[block.len() as u8])?;
+                    out.write_all(block)?;
+                }
+                out.write_all(&[0])?;
+            }
+            RecordingFormat::Apng => {
+                let mut control = Vec::with_capacity(26);
+                control.extend_from_slice(&self.sequence.to_be_bytes());
+                control.extend_from_slice(&self.width.to_be_bytes());
+                control.extend_from_slice(&self.height.to_be_bytes());
+                control.extend_from_slice(&[0; 8]);
+                // A delay of `1 / fps` seconds, without disposing of or blending with anything.
+                control.extend_from_slice(&1u16.to_be_bytes());
+                control.extend_from_slice(&(self.fps as u16).to_be_bytes());
+                control.extend_from_slice(&[0, 0]);
+                write_chunk(out, b"fcTL", &control)?;
+                self.sequence += 1;
+
+                let mut scanlines = Vec::with_capacity(pixels.len() * 3 + self.height as usize);
+                for row in pixels.chunks(self.width as usize) {
+                    scanlines.push(0);
+                    scanlines.extend(row.iter().flatten());
+                }
+                let data = zlib_stored(&scanlines);
+                // The first frame doubles as the image shown by viewers that don't support APNG.
+                if self.frame_count == 0 {
+                    write_chunk(out, b"IDAT", &data)?;
+                } else {
+                    let mut chunk = Vec::with_capacity(data.len() + 4);
+                    chunk.extend_from_slice(&self.sequence.to_be_bytes());
+                    chunk.extend_from_slice(&data);
+                    write_chunk(out, b"fdAT", &chunk)?;
+                    self.sequence += 1;
+                }
+            }
+            RecordingFormat::Y4m => {
+                out.write_all(b"FRAME\n")?;
+                let ycbcr: Vec<_> = pixels
+                    .iter()
+                    .map(|&[r, g, b]| to_ycbcr([r, g, b, 255]))
+                    .collect();
+                let y: Vec<_> = ycbcr.iter().map(|&(y, _, _)| y).collect();
+                let u: Vec<_> = ycbcr.iter().map(|&(_, u, _)| u).collect();
+                let v: Vec<_> = ycbcr.iter().map(|&(_, _, v)| v).collect();
+                out.write_all(&y)?;
+                out.write_all(&u)?;
+                out.write_all(&v)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Completes the recording, and returns the writer it was written to.
+    ///
+    /// Returns an error if writing fails, or if an APNG recording doesn't have any frames, which
+    /// the format can't represent.
+    pub fn finish(mut self) -> io::Result<W> {
+        // Taken out first, so a failed attempt isn't repeated when the recorder is dropped.
+        let mut out = self.out.take().unwrap();
+        self.write_trailer(&mut out)?;
+        Ok(out)
+    }
+
+    fn write_trailer(&self, out: &mut W) -> io::Result<()> {
+        match self.format {
+            RecordingFormat::Gif => out.write_all(&[0x3b])?,
+            RecordingFormat::Apng => {
+                if self.frame_count == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "APNGs must have at least one frame",
+                    ));
+                }
+                write_chunk(out, b"IEND", &[])?;
+                let end = out.stream_position()?;
+                let mut control = [0; 8];
+                control[..4].copy_from_slice(&self.frame_count.to_be_bytes());
+                out.seek(SeekFrom::Start(self.frame_count_offset))?;
+                out.write_all(&control)?;
+                // The chunk's checksum covers its type as well.
+                let mut checksum = crc32(0, b"acTL");
+                checksum = crc32(checksum, &control);
+                out.write_all(&checksum.to_be_bytes())?;
+                out.seek(SeekFrom::Start(end))?;
+            }
+            RecordingFormat::Y4m => {}
+        }
+        out.flush()
+    }
+}
+
+impl<W: Write + Seek> Drop for Recorder<W> {
+    fn drop(&mut self) {
+        if let Some(mut out) = self.out.take() {
+            let _ = self.write_trailer(&mut out);
+        }
+    }
+}
+
+/// A pixel buffer whose [`blit`](Self::blit) appends a frame to a [`Recorder`] instead of
+/// presenting it onto a window, e.g. to capture demos and documentation GIFs without a display.
+///
+/// Dereferences to an [offscreen](PixelBufferTyped::new_offscreen) [`PixelBufferTyped`] of the
+/// recording's size, which is drawn into like any other buffer.
+pub struct RecordingBuffer<P: PixelBufferFormat, W: Write + Seek = BufWriter<File>> {
+    buffer: PixelBufferTyped<P>,
+    recorder: Recorder<W>,
+}
+
+impl<P: PixelBufferFormat> RecordingBuffer<P> {
+    /// Creates the file at `path`, and a `width` by `height` buffer whose blits are recorded into
+    /// it.
+    ///
+    /// See [`Recorder::new`].
+    pub fn create<Q: AsRef<Path>>(
+        path: Q,
+        format: RecordingFormat,
+        width: u32,
+        height: u32,
+        fps: u32,
+    ) -> io::Result<RecordingBuffer<P>> {
+        RecordingBuffer::new(Recorder::create(path, format, width, height, fps)?)
+    }
+}
+
+impl<P: PixelBufferFormat, W: Write + Seek> RecordingBuffer<P, W> {
+    /// Creates a buffer of `recorder`'s frame size, whose blits are recorded by it.
+    ///
+    /// Returns an error if the buffer can't be created, e.g. because the frames are too large.
+    pub fn new(recorder: Recorder<W>) -> io::Result<RecordingBuffer<P, W>> {
+        let buffer = PixelBufferTyped::new_offscreen(recorder.width(), recorder.height())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Ok(RecordingBuffer { buffer, recorder })
+    }
+
+    /// Appends the buffer's contents to the recording as the next frame.
+    pub fn blit(&mut self) -> io::Result<()> {
+        self.recorder.present(&self.buffer)
+    }
+
+    /// The recorder the buffer's frames are written to.
+    pub fn recorder(&self) -> &Recorder<W> {
+        &self.recorder
+    }
+
+    /// Completes the recording, and returns the writer it was written to.
+    ///
+    /// See [`Recorder::finish`].
+    pub fn finish(self) -> io::Result<W> {
+        self.recorder.finish()
+    }
+}
+
+impl<P: PixelBufferFormat, W: Write + Seek> Deref for RecordingBuffer<P, W> {
+    type Target = PixelBufferTyped<P>;
+    fn deref(&self) -> &PixelBufferTyped<P> {
+        &self.buffer
+    }
+}
+
+impl<P: PixelBufferFormat, W: Write + Seek> DerefMut for RecordingBuffer<P, W> {
+    fn deref_mut(&mut self) -> &mut PixelBufferTyped<P> {
+        &mut self.buffer
+    }
+}
+
+/// The length of the largest chunk holding a `width` by `height` APNG frame: an `fdAT` chunk's
+/// sequence number, followed by the frame's scanlines in a zlib stream of uncompressed blocks.
+fn apng_frame_chunk_len(width: u32, height: u32) -> u64 {
+    let scanlines = height as u64 * (1 + 3 * width as u64);
+    let blocks = scanlines.div_ceil(65535).max(1);
+    4 + 2 + blocks * 5 + scanlines + 4
+}
+
+/// Writes a PNG chunk, along with its length and checksum.
+fn write_chunk(out: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    if data.len() as u64 > MAX_CHUNK_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "PNG chunks can't hold more than 2147483647 bytes",
+        ));
+    }
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let checksum = crc32(crc32(0, kind), data);
+    out.write_all(&checksum.to_be_bytes())
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            c = match c & 1 {
+                1 => 0xedb8_8320 ^ (c >> 1),
+                _ => c >> 1,
+            };
+            bit += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// Continues the CRC-32 `crc` of some data with `bytes`. Start with `0`.
+fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    let mut c = !crc;
+    for &byte in bytes {
+        c = CRC_TABLE[((c ^ byte as u32) & 0xff) as usize] ^ (c >> 8);
+    }
+    !c
+}
+
+/// Wraps `bytes` into a zlib stream of uncompressed deflate blocks.
+fn zlib_stored(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len() + bytes.len() / 65535 * 5 + 11);
+    out.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = bytes.chunks(65535).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        // 5552 bytes is the most that can be summed before `b` could overflow.
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
+
+/// Compresses 8-bit color indices with the variable-length LZW flavor used by GIF.
+fn lzw_encode(indices: impl IntoIterator<Item = u16>) -> Vec<u8> {
+    const CLEAR: u16 = 256;
+    const END: u16 = 257;
+    const MAX_CODE: u16 = 4095;
+
+    let mut out = BitWriter::default();
+    let mut codes: HashMap<(u16, u16), u16> = HashMap::new();
+    let mut next_code = END;
+    let mut width = 9;
+    out.write(CLEAR, width);
+    let mut indices = indices.into_iter();
+    if let Some(mut prefix) = indices.next() {
+        for index in indices {
+            if let Some(&code) = codes.get(&(prefix, index)) {
+                prefix = code;
+                continue;
+            }
+            out.write(prefix, width);
+            next_code += 1;
+            codes.insert((prefix, index), next_code);
+            if next_code >= 1 << width {
+                width += 1;
+            }
+            if next_code == MAX_CODE {
+                // The table is full, so start over.
+                out.write(CLEAR, width);
+                codes.clear();
+                next_code = END;
+                width = 9;
+            }
+            prefix = index;
+        }
+        out.write(prefix, width);
+    }
+    out.write(END, width);
+    out.finish()
+}
+
+/// Packs codes into bytes, starting from the least significant bit.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, width: u32) {
+        self.bits |= (code as u32) << self.bit_count;
+        self.bit_count += width;
+        while self.bit_count >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BGRA;
+    use std::{cell::RefCell, convert::TryInto, io::Cursor, rc::Rc};
+
+    /// Writes into a shared buffer, which outlives the recorder, and fails to flush.
+    #[derive(Clone, Default)]
+    struct FailingFlush(Rc<RefCell<Cursor<Vec<u8>>>>);
+
+    impl Write for FailingFlush {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(bytes)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::other("flush failed"))
+        }
+    }
+
+    impl Seek for FailingFlush {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.0.borrow_mut().seek(pos)
+        }
+    }
+
+    /// Splits a PNG file into its chunks, checking their checksums along the way.
+    fn png_chunks(png: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let mut chunks = Vec::new();
+        let mut rest = &png[8..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let kind: [u8; 4] = rest[4..8].try_into().unwrap();
+            let data = &rest[8..8 + len];
+            let checksum = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+            assert_eq!(checksum, crc32(crc32(0, &kind), data));
+            chunks.push((kind, data));
+            rest = &rest[12 + len..];
+        }
+        chunks
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that an APNG recording counts its frames, and that
+    /// every chunk is well-formed.
+    fn apng_records_frames() {
+        let mut buffer = RecordingBuffer::<BGRA, _>::new(
+            Recorder::new(Cursor::new(Vec::new()), RecordingFormat::Apng, 3, 2, 10).unwrap(),
+        )
+        .unwrap();
+        buffer.row_mut(1).unwrap()[2] = BGRA::from_rgb(1, 2, 3);
+        buffer.blit().unwrap();
+        buffer.blit().unwrap();
+        assert_eq!(buffer.recorder().frame_count(), 2);
+        let png = buffer.finish().unwrap().into_inner();
+
+        let chunks = png_chunks(&png);
+        let kinds: Vec<_> = chunks.iter().map(|(kind, _)| kind).collect();
+        assert_eq!(
+            kinds,
+            [b"IHDR", b"acTL", b"fcTL", b"IDAT", b"fcTL", b"fdAT", b"IEND"]
+        );
+        assert_eq!(chunks[1].1, [0, 0, 0, 2, 0, 0, 0, 0]);
+        // The second row's scanline ends with the pixel that was set, after the zlib and deflate
+        // block headers, and the first row's filter byte and pixels.
+        let data = chunks[3].1;
+        assert_eq!(&data[2 + 5 + 10..][..10], &[0, 0, 0, 0, 0, 0, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that frames too large for a PNG chunk are rejected
+    /// when the recording starts, before anything is written.
+    fn apng_rejects_frames_too_large_for_a_chunk() {
+        let mut out = Cursor::new(Vec::new());
+        let error = Recorder::new(&mut out, RecordingFormat::Apng, 30000, 30000, 10)
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(out.into_inner().is_empty());
+        assert!(apng_frame_chunk_len(26753, 26753) <= MAX_CHUNK_LEN);
+        assert!(apng_frame_chunk_len(26754, 26754) > MAX_CHUNK_LEN);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that a recording whose completion fails isn't
+    /// completed a second time when it's dropped.
+    fn failed_finish_writes_trailer_once() {
+        let out = FailingFlush::default();
+        let recorder = Recorder::new(out.clone(), RecordingFormat::Gif, 4, 4, 10).unwrap();
+        assert!(recorder.finish().is_err());
+        let gif = out.0.borrow().get_ref().clone();
+        assert_eq!(gif.iter().filter(|&&byte| byte == 0x3b).count(), 1);
+        assert_eq!(gif.last(), Some(&0x3b));
+    }
+}
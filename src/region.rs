@@ -0,0 +1,321 @@
+use crate::{AlphaMode, PixelBufferFormat, PixelBufferTyped, Point, RenderingIntent, Size};
+use std::{marker::PhantomData, slice};
+
+/// A mutable view into a rectangular region of a pixel buffer.
+///
+/// Regions are created by splitting a [`PixelBufferTyped`](crate::PixelBufferTyped) into
+/// non-overlapping parts, which can then be rendered into from separate threads. The region's
+/// origin is in its top-left corner.
+pub struct RegionMut<'a, P: PixelBufferFormat> {
+    /// Pointer to the region's top-left pixel.
+    top: *mut u8,
+    /// The signed distance, in bytes, from the start of a row to the start of the row below it.
+    row_pitch: isize,
+    width: u32,
+    height: u32,
+    alpha_mode: AlphaMode,
+    rendering_intent: RenderingIntent,
+    _marker: PhantomData<(&'a mut (), P)>,
+}
+
+unsafe impl<P: PixelBufferFormat + Send> Send for RegionMut<'_, P> {}
+unsafe impl<P: PixelBufferFormat + Sync> Sync for RegionMut<'_, P> {}
+
+impl<'a, P: PixelBufferFormat> RegionMut<'a, P> {
+    /// Creates a region from its top-left pixel and row pitch.
+    ///
+    /// # Safety
+    ///
+    /// Every row of the region must be valid for reads and writes for `'a`, and mustn't be
+    /// accessed through anything other than the returned region for that long.
+    pub(crate) unsafe fn from_raw_parts(
+        top: *mut u8,
+        row_pitch: isize,
+        width: u32,
+        height: u32,
+    ) -> RegionMut<'a, P> {
+        RegionMut {
+            top,
+            row_pitch,
+            width,
+            height,
+            alpha_mode: AlphaMode::default(),
+            rendering_intent: RenderingIntent::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn with_alpha_mode(mut self, alpha_mode: AlphaMode) -> RegionMut<'a, P> {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    pub(crate) fn with_rendering_intent(mut self, intent: RenderingIntent) -> RegionMut<'a, P> {
+        self.rendering_intent = intent;
+        self
+    }
+
+    /// Creates a region sharing this region's row pitch, alpha mode and rendering intent.
+    ///
+    /// # Safety
+    ///
+    /// Same as `from_raw_parts`.
+    unsafe fn sibling<'b>(&self, top: *mut u8, width: u32, height: u32) -> RegionMut<'b, P> {
+        RegionMut::from_raw_parts(top, self.row_pitch, width, height)
+            .with_alpha_mode(self.alpha_mode)
+            .with_rendering_intent(self.rendering_intent)
+    }
+
+    /// The width, in pixels, of the region.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height, in pixels, of the region.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// How the alpha channel of the region's pixels is interpreted.
+    ///
+    /// Inherited from the pixel buffer the region was created from.
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    /// Which color space the drawing helpers blend in when drawing into the region.
+    ///
+    /// Inherited from the pixel buffer the region was created from.
+    pub fn rendering_intent(&self) -> RenderingIntent {
+        self.rendering_intent
+    }
+
+    fn row_ptr(&self, row: u32) -> *mut P {
+        unsafe { self.top.offset(row as isize * self.row_pitch) as *mut P }
+    }
+
+    /// Gets the row at the particular height.
+    pub fn row(&self, row: u32) -> Option<&[P]> {
+        if row < self.height {
+            Some(unsafe { slice::from_raw_parts(self.row_ptr(row), self.width as usize) })
+        } else {
+            None
+        }
+    }
+
+    /// Mutably gets the row at the particular height.
+    pub fn row_mut(&mut self, row: u32) -> Option<&mut [P]> {
+        if row < self.height {
+            Some(unsafe { slice::from_raw_parts_mut(self.row_ptr(row), self.width as usize) })
+        } else {
+            None
+        }
+    }
+
+    /// Iterate through all rows in the region.
+    pub fn rows(&self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &[P]> {
+        (0..self.height).map(move |row| unsafe {
+            slice::from_raw_parts(self.row_ptr(row), self.width as usize)
+        })
+    }
+
+    /// Mutably iterate through all rows in the region.
+    pub fn rows_mut(&mut self) -> impl ExactSizeIterator + DoubleEndedIterator<Item = &mut [P]> {
+        let region = &*self;
+        // Rows never overlap, so handing each of them out once doesn't create aliasing slices.
+        (0..self.height).map(move |row| unsafe {
+            slice::from_raw_parts_mut(region.row_ptr(row), region.width as usize)
+        })
+    }
+
+    /// Reborrows the region, so it can be split without giving it up.
+    pub fn reborrow(&mut self) -> RegionMut<'_, P> {
+        unsafe { self.sibling(self.top, self.width, self.height) }
+    }
+
+    /// Gets a view into a smaller region, with its top-left corner at `pos` in this region.
+    ///
+    /// Returns `None` if the requested region doesn't fit inside this one.
+    pub fn region_mut(
+        &mut self,
+        pos: impl Into<Point>,
+        size: impl Into<Size>,
+    ) -> Option<RegionMut<'_, P>> {
+        self.reborrow().into_region(pos.into(), size.into())
+    }
+
+    /// Like `region_mut`, but consumes the region so the result keeps its lifetime.
+    pub(crate) fn into_region(self, pos: Point, size: Size) -> Option<RegionMut<'a, P>> {
+        let fits = |pos: u32, size: u32, len: u32| matches!(pos.checked_add(size), Some(end) if end <= len);
+        if !fits(pos.x, size.width, self.width) || !fits(pos.y, size.height, self.height) {
+            return None;
+        }
+        let top = match size.height {
+            0 => self.top,
+            _ => unsafe {
+                (self.row_ptr(pos.y) as *mut u8).add(pos.x as usize * std::mem::size_of::<P>())
+            },
+        };
+        Some(unsafe { self.sibling(top, size.width, size.height) })
+    }
+
+    /// Splits the region into the rows above `row` and the rows starting at `row`.
+    ///
+    /// # Panics
+    /// Panics if `row` is greater than the region's height.
+    pub fn split_at_row(self, row: u32) -> (RegionMut<'a, P>, RegionMut<'a, P>) {
+        assert!(row <= self.height, "row {} out of bounds", row);
+        let (width, height) = (self.width, self.height);
+        let top = unsafe { self.sibling(self.top, width, row) };
+        let bottom = self
+            .into_region(Point::new(0, row), Size::new(width, height - row))
+            .unwrap();
+        (top, bottom)
+    }
+
+    /// Splits the region into the columns left of `column` and the columns starting at `column`.
+    ///
+    /// # Panics
+    /// Panics if `column` is greater than the region's width.
+    pub fn split_at_column(self, column: u32) -> (RegionMut<'a, P>, RegionMut<'a, P>) {
+        assert!(column <= self.width, "column {} out of bounds", column);
+        let (width, height) = (self.width, self.height);
+        let left = unsafe { self.sibling(self.top, column, height) };
+        let right = self
+            .into_region(Point::new(column, 0), Size::new(width - column, height))
+            .unwrap();
+        (left, right)
+    }
+
+    /// Splits the region into a grid of `columns` by `rows` regions of (nearly) equal size.
+    ///
+    /// The regions are returned in row-major order, starting from the top-left.
+    ///
+    /// # Panics
+    /// Panics if `columns` or `rows` is `0`.
+    pub fn split_grid(self, columns: u32, rows: u32) -> Vec<RegionMut<'a, P>> {
+        assert!(
+            columns != 0 && rows != 0,
+            "grid must have at least one cell"
+        );
+        // The boundary before cell `i` of `n` along an axis of length `len`.
+        let boundary = |i: u32, n: u32, len: u32| (len as u64 * i as u64 / n as u64) as u32;
+        let (width, height) = (self.width, self.height);
+        let mut cells = Vec::with_capacity(columns as usize * rows as usize);
+        let mut below = self;
+        for y in 0..rows {
+            let band_height = boundary(y + 1, rows, height) - boundary(y, rows, height);
+            let (band, rest) = below.split_at_row(band_height);
+            below = rest;
+            let mut right = band;
+            for x in 0..columns {
+                let cell_width = boundary(x + 1, columns, width) - boundary(x, columns, width);
+                let (cell, rest) = right.split_at_column(cell_width);
+                cells.push(cell);
+                right = rest;
+            }
+        }
+        cells
+    }
+}
+
+impl<'a, P: PixelBufferFormat> From<&'a mut PixelBufferTyped<P>> for RegionMut<'a, P> {
+    fn from(buffer: &'a mut PixelBufferTyped<P>) -> RegionMut<'a, P> {
+        buffer.as_region_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BGRA;
+
+    /// The pixel marking cell `i` of a grid.
+    fn cell_pixel(i: usize) -> BGRA {
+        BGRA::from_rgb(i as u8 + 1, 0, 0)
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that the cells of a grid are disjoint, cover the
+    /// whole buffer, and write to the rows they describe, including for uneven splits and grids
+    /// with more columns than the buffer has pixels.
+    fn split_grid_covers_buffer() {
+        for &(width, height, columns, rows) in
+            &[(8, 6, 2, 3), (7, 5, 3, 2), (3, 4, 5, 1), (2, 1, 3, 4)]
+        {
+            let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(width, height).unwrap();
+            // Bottom-up, so the region's rows run backwards through memory.
+            assert!(pb.backend_stride() < 0);
+            let cells = pb.split_grid(columns, rows);
+            assert_eq!(cells.len(), (columns * rows) as usize);
+            let sizes: Vec<_> = cells.iter().map(|c| (c.width(), c.height())).collect();
+            for (i, mut cell) in cells.into_iter().enumerate() {
+                for row in cell.rows_mut() {
+                    row.fill(cell_pixel(i));
+                }
+            }
+
+            // Cell boundaries along an axis of length `len`, split into `n`.
+            let bounds = |n: u32, len: u32| -> Vec<u32> { (0..=n).map(|i| len * i / n).collect() };
+            let (xs, ys) = (bounds(columns, width), bounds(rows, height));
+            for y in 0..height {
+                let cell_y = ys
+                    .windows(2)
+                    .position(|b| (b[0]..b[1]).contains(&y))
+                    .unwrap();
+                for x in 0..width {
+                    let cell_x = xs
+                        .windows(2)
+                        .position(|b| (b[0]..b[1]).contains(&x))
+                        .unwrap();
+                    let i = cell_y * columns as usize + cell_x;
+                    assert_eq!(
+                        pb.row(y).unwrap()[x as usize],
+                        cell_pixel(i),
+                        "at {:?}",
+                        (x, y)
+                    );
+                }
+            }
+            for (i, &(w, h)) in sizes.iter().enumerate() {
+                let (cell_x, cell_y) = (i % columns as usize, i / columns as usize);
+                assert_eq!(w, xs[cell_x + 1] - xs[cell_x]);
+                assert_eq!(h, ys[cell_y + 1] - ys[cell_y]);
+            }
+            assert_eq!(
+                sizes.iter().map(|&(w, h)| w * h).sum::<u32>(),
+                width * height
+            );
+        }
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that splitting at a row writes each half to its own
+    /// rows, and that splitting at the height leaves the second half empty.
+    fn split_at_row_writes_each_half() {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(3, 4).unwrap();
+        let (mut top, mut bottom) = pb.split_at_row(1);
+        assert_eq!((top.width(), top.height()), (3, 1));
+        assert_eq!((bottom.width(), bottom.height()), (3, 3));
+        top.row_mut(0).unwrap().fill(cell_pixel(0));
+        bottom.row_mut(0).unwrap().fill(cell_pixel(1));
+        bottom.row_mut(2).unwrap().fill(cell_pixel(2));
+        assert!(bottom.row_mut(3).is_none());
+        let rows: Vec<_> = pb.rows().map(|row| row[0]).collect();
+        assert_eq!(
+            rows,
+            [
+                cell_pixel(0),
+                cell_pixel(1),
+                BGRA::new(0, 0, 0, 0),
+                cell_pixel(2)
+            ]
+        );
+
+        let (top, bottom) = pb.split_at_row(4);
+        assert_eq!(top.height(), 4);
+        assert_eq!((bottom.width(), bottom.height()), (3, 0));
+        assert!(bottom.row(0).is_none());
+        assert_eq!(bottom.rows().len(), 0);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{BlitError, PixelBufferFormat, PixelBufferTyped};
+use raw_window_handle::HasWindowHandle;
+use std::time::{Duration, Instant};
+
+/// Scales are snapped to multiples of this, so small swings in frame time don't change the
+/// resolution every frame.
+const SCALE_STEP: f32 = 1.0 / 16.0;
+/// How much weight the latest frame gets in the running average frame time.
+const SMOOTHING: f64 = 0.2;
+/// The resolution is only raised again while frames take less than this fraction of the budget.
+const HEADROOM: f64 = 0.8;
+
+/// Lowers the resolution a frame is rendered at while frames take longer than a time budget, and
+/// raises it again once there's time to spare.
+///
+/// Software renderers spend most of their time filling pixels, so rendering fewer of them is the
+/// most effective way to hold a frame rate. Each frame, render into the top-left
+/// [`render_size`](Self::render_size) pixels of a window-sized buffer, and let
+/// [`present`](Self::present) stretch them over the window. It measures the time between
+/// presents, which covers both filling and presenting, and picks the scale of the next frame.
+/// Applications that present some other way can report their frame times with
+/// [`record_frame_time`](Self::record_frame_time) and only use the suggested scale.
+#[derive(Debug, Clone)]
+pub struct DynamicResolution {
+    budget: Duration,
+    min_scale: f32,
+    scale: f32,
+    /// The running average frame time in seconds, since the scale last changed.
+    average: Option<f64>,
+    last_present: Option<Instant>,
+}
+
+impl DynamicResolution {
+    /// Creates a controller that aims to keep frames within `budget`, e.g. 16.6ms for 60 frames
+    /// per second. Rendering starts at full resolution.
+    pub fn new(budget: Duration) -> DynamicResolution {
+        DynamicResolution {
+            budget,
+            min_scale: 0.5,
+            scale: 1.0,
+            average: None,
+            last_present: None,
+        }
+    }
+
+    /// The time budget of a frame.
+    pub fn budget(&self) -> Duration {
+        self.budget
+    }
+
+    /// Sets the time budget of a frame.
+    pub fn set_budget(&mut self, budget: Duration) {
+        self.budget = budget;
+    }
+
+    /// The lowest scale the resolution is reduced to. Defaults to `0.5`.
+    pub fn min_scale(&self) -> f32 {
+        self.min_scale
+    }
+
+    /// Sets the lowest scale the resolution is reduced to, clamped to `0.0625..=1.0`.
+    pub fn set_min_scale(&mut self, min_scale: f32) {
+        self.min_scale = min_scale.clamp(SCALE_STEP, 1.0);
+        self.scale = self.scale.max(self.min_scale);
+    }
+
+    /// The scale the next frame should be rendered at, relative to the window's size.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// The size the next frame should be rendered at for a window of `window_size`.
+    ///
+    /// Neither dimension is rounded below 1 pixel, unless the window's is 0.
+    pub fn render_size(&self, window_size: (u32, u32)) -> (u32, u32) {
+        let scaled = |size: u32| ((size as f32 * self.scale).round() as u32).clamp(1, size.max(1));
+        (scaled(window_size.0), scaled(window_size.1))
+    }
+
+    /// Reports how long a frame took, and updates the [`scale`](Self::scale) accordingly.
+    ///
+    /// The cost of a frame is assumed to grow with its pixel count, so the scale moves by the
+    /// square root of how far over or under budget frames are on average.
+    pub fn record_frame_time(&mut self, frame_time: Duration) {
+        let frame_time = frame_time.as_secs_f64();
+        let average = match self.average {
+            Some(average) => average + (frame_time - average) * SMOOTHING,
+            None => frame_time,
+        };
+        self.average = Some(average);
+
+        let budget = self.budget.as_secs_f64();
+        if average <= budget && average >= budget * HEADROOM {
+            return;
+        }
+        let target = self.scale * (budget / average).sqrt() as f32;
+        let target = if average > budget {
+            (target / SCALE_STEP).floor()
+        } else {
+            (target / SCALE_STEP).ceil()
+        } * SCALE_STEP;
+        let target = target.clamp(self.min_scale, 1.0);
+        if target != self.scale {
+            self.scale = target;
+            // Frames rendered at the old scale say nothing about the new one.
+            self.average = None;
+        }
+    }
+
+    /// Stretches the top-left [`render_size`](Self::render_size) pixels of `buffer` over
+    /// `window_size` pixels of `window`, then records the time since the previous call as a frame
+    /// time.
+    ///
+    /// Call this once per frame, after rendering at the size returned by `render_size`. The
+    /// first call only starts the clock.
+    ///
+    /// # Panics
+    /// The `window` passed to this function must be the `window` passed to the buffer's `new`,
+    /// or one added with `add_window`. Failing to do so will result in a panic.
+    pub fn present<P: PixelBufferFormat, H: HasWindowHandle>(
+        &mut self,
+        buffer: &PixelBufferTyped<P>,
+        window_size: (u32, u32),
+        window: &H,
+    ) -> Result<(), BlitError> {
+        let render_size = self.render_size(window_size);
+        buffer.blit_rect_scaled((0, 0), render_size, (0, 0), window_size, window)?;
+        let now = Instant::now();
+        if let Some(last_present) = self.last_present.replace(now) {
+            self.record_frame_time(now - last_present);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// The purpose of this test is to verify that `DynamicResolution` lowers the scale while
+    /// frames are over budget, never below the minimum, and raises it again once they're fast.
+    fn dynamic_resolution_follows_frame_time() {
+        let mut resolution = DynamicResolution::new(Duration::from_millis(16));
+        assert_eq!(resolution.render_size((800, 600)), (800, 600));
+
+        resolution.record_frame_time(Duration::from_millis(32));
+        let scale = resolution.scale();
+        assert!((0.5..1.0).contains(&scale), "unexpected scale {}", scale);
+        assert!(resolution.render_size((800, 600)).0 < 800);
+
+        for _ in 0..20 {
+            resolution.record_frame_time(Duration::from_millis(100));
+        }
+        assert_eq!(resolution.scale(), 0.5);
+        assert_eq!(resolution.render_size((800, 600)), (400, 300));
+
+        for _ in 0..20 {
+            resolution.record_frame_time(Duration::from_millis(1));
+        }
+        assert_eq!(resolution.scale(), 1.0);
+    }
+}
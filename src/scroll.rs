@@ -0,0 +1,191 @@
+use crate::{fill::fill_row, BlitError, PixelBufferFormat, PixelBufferTyped, Rect};
+use raw_window_handle::HasWindowHandle;
+use std::mem;
+
+impl<P: PixelBufferFormat> PixelBufferTyped<P> {
+    /// Scrolls both the pixel buffer and `window`'s contents by `dx` pixels to the right and `dy`
+    /// pixels downwards, filling the area exposed in the buffer with `fill`.
+    ///
+    /// The window's contents are moved by the platform, without blitting anything, so only the
+    /// exposed area has to be rendered and blitted afterwards. That area is returned as up to two
+    /// rectangles, and is marked [dirty](Self::mark_dirty) so that the next
+    /// [`present`](Self::present) blits it. This is the classic optimization for terminals and
+    /// text editors.
+    ///
+    /// The buffer is assumed to be blitted onto the window's top-left corner, and the whole client
+    /// area of the window is scrolled. If scrolling the window fails, the buffer is left as-is.
+    pub fn scroll<H: HasWindowHandle>(
+        &mut self,
+        dx: i32,
+        dy: i32,
+        fill: impl Into<P>,
+        window: &H,
+    ) -> Result<impl Iterator<Item = Rect>, BlitError> {
+        let handle = window.window_handle()?.as_raw();
+        unsafe { self.p.p.scroll_window(dx, dy, handle) }?;
+        Ok(IntoIterator::into_iter(self.scroll_pixels(dx, dy, fill.into())).flatten())
+    }
+
+    /// Moves the buffer's pixels for [`scroll`](Self::scroll), and returns the exposed area.
+    fn scroll_pixels(&mut self, dx: i32, dy: i32, fill: P) -> [Option<Rect>; 2] {
+        let (width, height) = (self.width(), self.height());
+        let shift_x = dx.unsigned_abs().min(width) as usize;
+        let shift_y = dy.unsigned_abs().min(height) as usize;
+        let (width, height) = (width as usize, height as usize);
+
+        let mut rows: Vec<&mut [P]> = self
+            .p
+            .p
+            .rows_mut()
+            .map(|row| P::from_raw_slice_mut(row))
+            .collect();
+        let kept = height - shift_y;
+        for i in 0..kept {
+            // Rows are visited so that each one is read before it's overwritten.
+            let (dst, src) = match dy >= 0 {
+                true => (height - 1 - i, height - 1 - i - shift_y),
+                false => (i, i + shift_y),
+            };
+            let row = match dst == src {
+                true => &mut *rows[dst],
+                false => {
+                    let (low, high) = rows.split_at_mut(dst.max(src));
+                    let (dst_row, src_row) = match dst < src {
+                        true => (&mut *low[dst], &*high[0]),
+                        false => (&mut *high[0], &*low[src]),
+                    };
+                    dst_row.copy_from_slice(src_row);
+                    dst_row
+                }
+            };
+            match dx >= 0 {
+                true => {
+                    row.copy_within(..width - shift_x, shift_x);
+                    fill_row(&mut row[..shift_x], fill);
+                }
+                false => {
+                    row.copy_within(shift_x.., 0);
+                    fill_row(&mut row[width - shift_x..], fill);
+                }
+            }
+        }
+
+        let (exposed_rows, kept_rows) = match dy >= 0 {
+            true => (0..shift_y, shift_y..height),
+            false => (kept..height, 0..kept),
+        };
+        for y in exposed_rows.clone() {
+            fill_row(&mut *rows[y], fill);
+        }
+        let exposed_columns = match dx >= 0 {
+            true => 0..shift_x,
+            false => width - shift_x..width,
+        };
+
+        let rect = |columns: std::ops::Range<usize>, rows: std::ops::Range<usize>| {
+            let pos = (columns.start as u32, rows.start as u32);
+            let size = (columns.len() as u32, rows.len() as u32);
+            Some(Rect::new(pos, size)).filter(|rect| !rect.is_empty())
+        };
+        let exposed = [
+            rect(0..width, exposed_rows),
+            rect(exposed_columns, kept_rows),
+        ];
+        self.scroll_dirty(dx, dy);
+        for rect in exposed.iter().flatten() {
+            self.mark_dirty(rect.pos, rect.size);
+        }
+        exposed
+    }
+
+    /// Moves the regions that still have to be presented along with the buffer's contents.
+    fn scroll_dirty(&mut self, dx: i32, dy: i32) {
+        let shift = |pos: u32, size: u32, d: i32| {
+            let start = (pos as i64 + d as i64).max(0);
+            let end = (pos as i64 + size as i64 + d as i64).max(0);
+            (start.min(u32::MAX as i64) as u32, (end - start) as u32)
+        };
+        for rect in mem::take(&mut self.p.dirty).rects {
+            let (x, width) = shift(rect.pos.x, rect.size.width, dx);
+            let (y, height) = shift(rect.pos.y, rect.size.height, dy);
+            self.mark_dirty((x, y), (width, height));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BGRA;
+
+    const FILL: BGRA = BGRA::from_rgb(0, 0, 0);
+
+    /// A buffer whose pixels record their own position.
+    fn numbered(width: u32, height: u32) -> PixelBufferTyped<BGRA> {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(width, height).unwrap();
+        pb.generate(|x, y| BGRA::from_rgb(x as u8 + 1, y as u8 + 1, 0));
+        pb
+    }
+
+    /// The pixel `numbered` put at `(x, y)`.
+    fn at(x: u32, y: u32) -> BGRA {
+        BGRA::from_rgb(x as u8 + 1, y as u8 + 1, 0)
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that scrolling moves the pixels in either direction,
+    /// fills the area it exposes, and returns that area.
+    fn scroll_moves_pixels_and_fills_exposed_area() {
+        let mut pb = numbered(4, 3);
+        let exposed = pb.scroll_pixels(1, 1, FILL);
+        assert_eq!(
+            exposed,
+            [
+                Some(Rect::new((0, 0), (4, 1))),
+                Some(Rect::new((0, 1), (1, 2)))
+            ]
+        );
+        assert!(pb.row(0).unwrap().iter().all(|&pixel| pixel == FILL));
+        assert_eq!(pb.row(1).unwrap(), [FILL, at(0, 0), at(1, 0), at(2, 0)]);
+        assert_eq!(pb.row(2).unwrap(), [FILL, at(0, 1), at(1, 1), at(2, 1)]);
+
+        let mut pb = numbered(4, 3);
+        let exposed = pb.scroll_pixels(-2, -1, FILL);
+        assert_eq!(
+            exposed,
+            [
+                Some(Rect::new((0, 2), (4, 1))),
+                Some(Rect::new((2, 0), (2, 2)))
+            ]
+        );
+        assert_eq!(pb.row(0).unwrap(), [at(2, 1), at(3, 1), FILL, FILL]);
+        assert_eq!(pb.row(1).unwrap(), [at(2, 2), at(3, 2), FILL, FILL]);
+        assert!(pb.row(2).unwrap().iter().all(|&pixel| pixel == FILL));
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that scrolling further than the buffer's size
+    /// exposes all of it, without overflowing.
+    fn scroll_past_the_edge_exposes_everything() {
+        let mut pb = numbered(4, 3);
+        let exposed = pb.scroll_pixels(i32::MIN, 0, FILL);
+        assert_eq!(exposed, [None, Some(Rect::new((0, 0), (4, 3)))]);
+        assert!(pb.rows().flatten().all(|&pixel| pixel == FILL));
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that the region still waiting to be presented moves
+    /// along with the pixels, and that the exposed area is added to it.
+    fn scroll_moves_dirty_region() {
+        let mut pb = numbered(6, 6);
+        pb.take_damage();
+        pb.mark_dirty((2, 2), (1, 1));
+        pb.scroll_pixels(0, 1, FILL);
+        // The exposed top row, and the dirty pixel a row further down.
+        assert_eq!(pb.dirty_region(), Some(Rect::new((0, 0), (6, 4))));
+        assert_eq!(
+            pb.take_damage(),
+            [Rect::new((2, 3), (1, 1)), Rect::new((0, 0), (6, 1))]
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{
+    BlitError, NativeFormat, PixelBufferCreationError, PixelBufferFormat,
+    PixelBufferFormatSupported, PixelBufferTyped, Rect,
+};
+use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
+use std::{
+    convert::TryFrom,
+    num::{NonZeroU32, NonZeroUsize},
+    ops::{Deref, DerefMut},
+};
+
+/// A connection to the display that [`Surface`]s are presented on.
+///
+/// Create one context per display, and a surface for every window on it. This mirrors the
+/// `Context`/`Surface` split used by other software presentation libraries, so code written
+/// against them ports over with few changes.
+pub struct Context<D> {
+    display: D,
+}
+
+impl<D: HasDisplayHandle> Context<D> {
+    /// Creates a context for `display`.
+    ///
+    /// Can return `Err` if the display handle isn't currently available.
+    pub fn new(display: D) -> Result<Context<D>, PixelBufferCreationError> {
+        display.display_handle()?;
+        Ok(Context { display })
+    }
+
+    /// The display the context was created for.
+    pub fn display(&self) -> &D {
+        &self.display
+    }
+}
+
+/// A window that frames of pixels are presented onto.
+///
+/// A surface has no pixel buffers until it's first [`resize`](Self::resize)d. Afterwards,
+/// [`buffer_mut`](Self::buffer_mut) hands out the buffer to draw the next frame into, along with
+/// how old its contents are.
+///
+/// By default, a surface has a single buffer that's drawn into and presented every frame. With
+/// [`set_buffer_count`](Self::set_buffer_count), it instead cycles through several buffers, so the
+/// previous frame's buffer stays untouched while the next one is drawn.
+///
+/// When the platform takes the window away, e.g. when an Android app is sent to the background,
+/// [`suspend`](Self::suspend) the surface and [`resume`](Self::resume) it once there's a window
+/// again. The buffers' contents are kept in the meantime.
+pub struct Surface<W, P: PixelBufferFormatSupported = NativeFormat> {
+    /// `None` while the surface is suspended.
+    window: Option<W>,
+    size: Option<(u32, u32)>,
+    buffers: Vec<Slot<P>>,
+    /// The contents of every buffer as of the last `suspend`, stored tightly packed.
+    suspended: Vec<(Vec<P>, Option<u64>)>,
+    buffer_count: usize,
+    /// The index of the buffer handed out by `buffer_mut`.
+    current: usize,
+    /// The number of frames presented so far.
+    frame: u64,
+}
+
+struct Slot<P: PixelBufferFormat> {
+    buffer: PixelBufferTyped<P>,
+    /// The value of `Surface::frame` when the buffer was last presented.
+    presented_at: Option<u64>,
+}
+
+impl<P: PixelBufferFormat> Slot<P> {
+    /// Copies the buffer's pixels out, along with when it was presented, so it can be recreated.
+    fn save(&self) -> (Vec<P>, Option<u64>) {
+        let pixels = self.buffer.rows().flatten().copied().collect();
+        (pixels, self.presented_at)
+    }
+}
+
+impl<W: HasWindowHandle, P: PixelBufferFormatSupported> Surface<W, P> {
+    /// Creates a surface for `window`, which must be on the display `context` was created for.
+    ///
+    /// Can return `Err` if the window handle isn't currently available.
+    pub fn new<D: HasDisplayHandle>(
+        _context: &Context<D>,
+        window: W,
+    ) -> Result<Surface<W, P>, PixelBufferCreationError> {
+        window.window_handle()?;
+        Ok(Surface {
+            window: Some(window),
+            size: None,
+            buffers: Vec::new(),
+            suspended: Vec::new(),
+            buffer_count: 1,
+            current: 0,
+            frame: 0,
+        })
+    }
+
+    /// The window the surface presents onto, or `None` if the surface is suspended.
+    pub fn window(&self) -> Option<&W> {
+        self.window.as_ref()
+    }
+
+    /// Whether the surface is [`suspend`](Self::suspend)ed.
+    pub fn is_suspended(&self) -> bool {
+        self.window.is_none()
+    }
+
+    /// Releases the surface's window and platform resources, keeping the buffers' contents in
+    /// memory. Returns the window, or `None` if the surface was already suspended.
+    ///
+    /// While suspended, the surface can still be resized, but no buffers can be drawn into.
+    pub fn suspend(&mut self) -> Option<W> {
+        let window = self.window.take()?;
+        self.suspended = self.buffers.drain(..).map(|slot| slot.save()).collect();
+        Some(window)
+    }
+
+    /// Recreates the surface's platform resources for `window`, restoring the buffers' contents
+    /// from before it was suspended. The ages of the buffers carry over, so the next frame can
+    /// still be redrawn incrementally.
+    ///
+    /// If the surface was resized while it was suspended, the buffers' contents are undefined
+    /// instead. Resuming a surface that isn't suspended replaces its window, and carries its
+    /// buffers' contents over to the new one.
+    ///
+    /// Can return `Err` if the window handle isn't currently available, or if the buffers can't
+    /// be recreated. The surface is left unchanged in that case: a suspended surface stays
+    /// suspended and keeps the buffers' contents, and any other surface keeps its window.
+    pub fn resume(&mut self, window: W) -> Result<(), PixelBufferCreationError> {
+        window.window_handle()?;
+        // The current buffers are only released once their replacements exist.
+        let active: Vec<_> = self.buffers.iter().map(Slot::save).collect();
+        let saved = match self.window {
+            Some(_) => &active,
+            None => &self.suspended,
+        };
+        let mut buffers = Vec::with_capacity(self.buffer_count);
+        if let Some((width, height)) = self.size {
+            for (pixels, presented_at) in saved {
+                let mut buffer = PixelBufferTyped::new(width, height, &window)?;
+                for (row, pixels) in buffer.rows_mut().zip(pixels.chunks(width as usize)) {
+                    row.copy_from_slice(pixels);
+                }
+                buffers.push(Slot {
+                    buffer,
+                    presented_at: *presented_at,
+                });
+            }
+            while buffers.len() < self.buffer_count {
+                buffers.push(Slot {
+                    buffer: PixelBufferTyped::new(width, height, &window)?,
+                    presented_at: None,
+                });
+            }
+        }
+        // Only leave the suspended state once every buffer exists.
+        self.suspended.clear();
+        self.buffers = buffers;
+        self.window = Some(window);
+        Ok(())
+    }
+
+    /// Re-targets the surface's buffers onto `window`, keeping them and their contents, and
+    /// returns the window the surface presented onto before.
+    ///
+    /// Use this when a toolkit recreates or reparents the native window, e.g. when embedding it
+    /// into another application, so the buffers don't have to be reallocated the way
+    /// [`resume`](Self::resume) does. The buffers' contents are kept, but the new window doesn't
+    /// show any of them yet, so their ages are reset to `0` and the next frame has to be drawn in
+    /// full.
+    ///
+    /// Can return `Err` if the window handle isn't currently available, or if the window can't
+    /// display the buffers. The surface keeps its old window in that case.
+    ///
+    /// # Panics
+    /// Panics if the surface is suspended.
+    pub fn rebind(&mut self, window: W) -> Result<W, BlitError> {
+        assert!(!self.is_suspended(), "the surface is suspended");
+        let handle = window.window_handle()?.as_raw();
+        // A recreated window can get the same handle as the one it replaces.
+        let old_handle = self.window().and_then(|old| old.window_handle().ok());
+        let same_handle = old_handle.map(|old| old.as_raw()) == Some(handle);
+        for added in 0..self.buffers.len() {
+            if let Err(e) = self.buffers[added].buffer.add_window(&window) {
+                if !same_handle {
+                    for slot in &mut self.buffers[..added] {
+                        slot.buffer.remove_window(&window);
+                    }
+                }
+                return Err(e);
+            }
+        }
+        let old = self.window.replace(window).unwrap();
+        for slot in &mut self.buffers {
+            if !same_handle {
+                slot.buffer.remove_window(&old);
+            }
+            slot.presented_at = None;
+        }
+        Ok(old)
+    }
+
+    /// The number of bytes held by all of the surface's buffers, including the contents kept
+    /// while it's suspended.
+    pub fn memory_usage(&self) -> usize {
+        let buffers: usize = self
+            .buffers
+            .iter()
+            .map(|slot| slot.buffer.memory_usage())
+            .sum();
+        let suspended: usize = self
+            .suspended
+            .iter()
+            .map(|(pixels, _)| pixels.capacity() * std::mem::size_of::<P>())
+            .sum();
+        buffers + suspended
+    }
+
+    /// The number of buffers the surface cycles through.
+    pub fn buffer_count(&self) -> usize {
+        self.buffer_count
+    }
+
+    /// Sets the number of buffers the surface cycles through. Defaults to `1`.
+    ///
+    /// The new buffers are allocated by the next call to [`resize`](Self::resize) or
+    /// [`buffer_mut`](Self::buffer_mut), and their contents start out undefined.
+    pub fn set_buffer_count(&mut self, count: NonZeroUsize) {
+        self.buffer_count = count.get();
+        self.buffers.truncate(self.buffer_count);
+        self.suspended.truncate(self.buffer_count);
+        if self.current >= self.buffer_count {
+            self.current = 0;
+        }
+    }
+
+    /// Sets the size of the surface's pixel buffers, which should match the window's inner size.
+    ///
+    /// Resizing to a different size replaces every buffer, so their contents become undefined.
+    /// Resizing to the current size does nothing.
+    pub fn resize(
+        &mut self,
+        width: NonZeroU32,
+        height: NonZeroU32,
+    ) -> Result<(), PixelBufferCreationError> {
+        let size = (width.get(), height.get());
+        if self.size != Some(size) {
+            self.buffers.clear();
+            self.suspended.clear();
+            self.current = 0;
+            self.size = Some(size);
+        }
+        self.allocate_buffers()
+    }
+
+    /// Allocates any buffers that are missing, e.g. after a resize.
+    fn allocate_buffers(&mut self) -> Result<(), PixelBufferCreationError> {
+        let ((width, height), window) = match (self.size, &self.window) {
+            (Some(size), Some(window)) => (size, window),
+            _ => return Ok(()),
+        };
+        while self.buffers.len() < self.buffer_count {
+            self.buffers.push(Slot {
+                buffer: PixelBufferTyped::new(width, height, window)?,
+                presented_at: None,
+            });
+        }
+        Ok(())
+    }
+
+    /// Gets the buffer to draw the next frame into.
+    ///
+    /// Can return `Err` if a buffer needs to be allocated and that fails.
+    ///
+    /// # Panics
+    /// Panics if the surface hasn't been [`resize`](Self::resize)d yet, or if it's suspended.
+    pub fn buffer_mut(&mut self) -> Result<Buffer<'_, W, P>, PixelBufferCreationError> {
+        assert!(
+            self.size.is_some(),
+            "`Surface::resize` must be called before `Surface::buffer_mut`"
+        );
+        assert!(!self.is_suspended(), "the surface is suspended");
+        self.allocate_buffers()?;
+        Ok(Buffer { surface: self })
+    }
+
+    fn current(&self) -> &Slot<P> {
+        &self.buffers[self.current]
+    }
+
+    /// Marks the current buffer as presented, and moves on to the next one.
+    fn advance(&mut self) {
+        self.frame += 1;
+        self.buffers[self.current].presented_at = Some(self.frame);
+        self.current = (self.current + 1) % self.buffers.len();
+    }
+}
+
+/// The pixel buffer of a [`Surface`], borrowed to draw a single frame.
+///
+/// Dereferences to a [`PixelBufferTyped`]. Dropping the buffer without presenting it keeps any
+/// changes, but doesn't show them, and the same buffer is handed out again next time.
+pub struct Buffer<'a, W, P: PixelBufferFormatSupported = NativeFormat> {
+    surface: &'a mut Surface<W, P>,
+}
+
+impl<W: HasWindowHandle, P: PixelBufferFormatSupported> Buffer<'_, W, P> {
+    /// How many frames ago the buffer's contents were presented.
+    ///
+    /// `0` means the contents are undefined and the whole frame must be drawn. Otherwise, the
+    /// buffer holds the frame presented `age` frames ago, so only the regions that changed since
+    /// then have to be redrawn. With a single buffer, this is always `0` or `1`. Ages too large
+    /// to fit into a `u8` are reported as `0`.
+    pub fn age(&self) -> u8 {
+        let surface = &*self.surface;
+        match surface.current().presented_at {
+            Some(presented_at) => u8::try_from(surface.frame - presented_at + 1).unwrap_or(0),
+            None => 0,
+        }
+    }
+
+    /// Presents the whole buffer onto the surface's window.
+    pub fn present(self) -> Result<(), BlitError> {
+        let surface = self.surface;
+        surface.current().buffer.blit(surface.window().unwrap())?;
+        surface.advance();
+        Ok(())
+    }
+
+    /// Presents only the `damage`d regions of the buffer onto the surface's window.
+    ///
+    /// The rest of the window is expected to still show what was presented previously.
+    pub fn present_with_damage(
+        self,
+        damage: impl IntoIterator<Item = impl Into<Rect>>,
+    ) -> Result<(), BlitError> {
+        let surface = self.surface;
+        let buffer = &surface.current().buffer;
+        for rect in damage {
+            let Rect { pos, size } = rect.into();
+            buffer.blit_rect(pos, pos, size, surface.window().unwrap())?;
+        }
+        surface.advance();
+        Ok(())
+    }
+}
+
+impl<W, P: PixelBufferFormatSupported> Deref for Buffer<'_, W, P> {
+    type Target = PixelBufferTyped<P>;
+    fn deref(&self) -> &PixelBufferTyped<P> {
+        &self.surface.buffers[self.surface.current].buffer
+    }
+}
+
+impl<W, P: PixelBufferFormatSupported> DerefMut for Buffer<'_, W, P> {
+    fn deref_mut(&mut self) -> &mut PixelBufferTyped<P> {
+        &mut self.surface.buffers[self.surface.current].buffer
+    }
+}
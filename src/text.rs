@@ -0,0 +1,197 @@
+use crate::{
+    font::{glyph, GLYPH_SIZE},
+    PixelBufferFormat, Rect, RegionMut,
+};
+
+/// A single character cell of a [`TextGrid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell<P: PixelBufferFormat> {
+    /// The cell's character. Characters outside of printable ASCII are drawn as `?`.
+    pub ch: char,
+    /// The color of the character's glyph.
+    pub fg: P,
+    /// The color of the rest of the cell.
+    pub bg: P,
+}
+
+/// A fixed-size grid of character cells, rendered with a bundled 8x8 bitmap font.
+///
+/// Useful for debug consoles, ROM monitors and terminal emulators. The grid remembers which cells
+/// changed since it was last rendered, and only redraws those.
+pub struct TextGrid<P: PixelBufferFormat> {
+    columns: u32,
+    rows: u32,
+    cells: Vec<Cell<P>>,
+    dirty: Vec<bool>,
+}
+
+impl<P: PixelBufferFormat + PartialEq> TextGrid<P> {
+    /// The width, in pixels, of every cell.
+    pub const CELL_WIDTH: u32 = GLYPH_SIZE;
+    /// The height, in pixels, of every cell.
+    pub const CELL_HEIGHT: u32 = GLYPH_SIZE;
+
+    /// Creates a grid of blank cells with the given colors.
+    ///
+    /// Every cell starts out dirty, so the first render draws the whole grid.
+    pub fn new(columns: u32, rows: u32, fg: P, bg: P) -> TextGrid<P> {
+        let len = columns as usize * rows as usize;
+        TextGrid {
+            columns,
+            rows,
+            cells: vec![Cell { ch: ' ', fg, bg }; len],
+            dirty: vec![true; len],
+        }
+    }
+
+    /// The number of cells in each row.
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// The number of rows of cells.
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    fn index(&self, column: u32, row: u32) -> Option<usize> {
+        if column < self.columns && row < self.rows {
+            Some(row as usize * self.columns as usize + column as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Gets the cell at the particular column and row.
+    pub fn cell(&self, column: u32, row: u32) -> Option<Cell<P>> {
+        self.index(column, row).map(|index| self.cells[index])
+    }
+
+    /// Replaces the cell at the particular column and row. Does nothing if the position is
+    /// outside of the grid.
+    pub fn set_cell(&mut self, column: u32, row: u32, cell: Cell<P>) {
+        if let Some(index) = self.index(column, row) {
+            if self.cells[index] != cell {
+                self.cells[index] = cell;
+                self.dirty[index] = true;
+            }
+        }
+    }
+
+    /// Writes `text` into a row, starting at `column`.
+    ///
+    /// Text that doesn't fit is cut off at the end of the row; newlines aren't interpreted.
+    pub fn print(&mut self, column: u32, row: u32, text: &str, fg: P, bg: P) {
+        for (offset, ch) in text.chars().enumerate() {
+            match column.checked_add(offset as u32) {
+                Some(column) if column < self.columns => {
+                    self.set_cell(column, row, Cell { ch, fg, bg })
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Blanks every cell with the given colors.
+    pub fn clear(&mut self, fg: P, bg: P) {
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                self.set_cell(column, row, Cell { ch: ' ', fg, bg });
+            }
+        }
+    }
+
+    /// Marks every cell as needing to be redrawn, e.g. after the target buffer has been drawn over.
+    pub fn mark_all_dirty(&mut self) {
+        for dirty in &mut self.dirty {
+            *dirty = true;
+        }
+    }
+
+    /// Draws every dirty cell into `target`, with the grid's top-left corner at the target's
+    /// top-left corner.
+    ///
+    /// Returns the bounding box of the pixels that were drawn, which is what needs to be blitted to
+    /// show the changes. Returns `None` if no cells were dirty. Cells that don't fit entirely
+    /// inside `target` are clipped.
+    pub fn render<'a>(&mut self, target: impl Into<RegionMut<'a, P>>) -> Option<Rect> {
+        let mut target = target.into();
+        let mut drawn: Option<Rect> = None;
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let index = row as usize * self.columns as usize + column as usize;
+                if !self.dirty[index] {
+                    continue;
+                }
+                self.dirty[index] = false;
+
+                let (x, y) = (column * Self::CELL_WIDTH, row * Self::CELL_HEIGHT);
+                let width = Self::CELL_WIDTH.min(target.width().saturating_sub(x));
+                let height = Self::CELL_HEIGHT.min(target.height().saturating_sub(y));
+                if width == 0 || height == 0 {
+                    continue;
+                }
+                let cell = self.cells[index];
+                let glyph = glyph(cell.ch);
+                for (dy, bits) in (0..height).zip(glyph.iter()) {
+                    let pixels = &mut target.row_mut(y + dy).unwrap()[x as usize..];
+                    for (dx, pixel) in pixels[..width as usize].iter_mut().enumerate() {
+                        *pixel = match bits >> dx & 1 {
+                            1 => cell.fg,
+                            _ => cell.bg,
+                        };
+                    }
+                }
+                let cell = Rect::new((x, y), (width, height));
+                drawn = Some(drawn.unwrap_or_default().union(cell));
+            }
+        }
+        drawn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PixelBufferTyped, BGRA};
+
+    const FG: BGRA = BGRA::from_rgb(255, 255, 255);
+    const BG: BGRA = BGRA::from_rgb(0, 0, 0);
+
+    #[test]
+    /// The purpose of this test is to verify that rendering draws each dirty cell's glyph, and
+    /// that later renders only redraw the cells that changed.
+    fn render_redraws_only_dirty_cells() {
+        let mut grid = TextGrid::new(3, 2, FG, BG);
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(24, 16).unwrap();
+        assert_eq!(grid.render(&mut pb), Some(Rect::new((0, 0), (24, 16))));
+        assert!(pb.rows().flatten().all(|&pixel| pixel == BG));
+        assert_eq!(grid.render(&mut pb), None);
+
+        grid.print(1, 1, "A", FG, BG);
+        // Writing the same cell again doesn't make it dirty.
+        grid.print(1, 1, "A", FG, BG);
+        assert_eq!(grid.render(&mut pb), Some(Rect::new((8, 8), (8, 8))));
+        for (dy, bits) in glyph('A').iter().enumerate() {
+            let row = &pb.row(8 + dy as u32).unwrap()[8..16];
+            for (dx, &pixel) in row.iter().enumerate() {
+                let expected = if bits >> dx & 1 == 1 { FG } else { BG };
+                assert_eq!(pixel, expected);
+            }
+        }
+        assert_eq!(grid.render(&mut pb), None);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that text running past the end of a row is cut off,
+    /// and that cells which don't fit inside the target are clipped.
+    fn print_and_render_clip_at_the_edges() {
+        let mut grid = TextGrid::new(2, 1, FG, BG);
+        grid.print(1, 0, "xyz", FG, BG);
+        assert_eq!(grid.cell(1, 0).map(|cell| cell.ch), Some('x'));
+        assert_eq!(grid.cell(2, 0), None);
+
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(12, 4).unwrap();
+        assert_eq!(grid.render(&mut pb), Some(Rect::new((0, 0), (12, 4))));
+    }
+}
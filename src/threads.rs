@@ -0,0 +1,60 @@
+use rayon::ThreadPool;
+use std::sync::{Arc, RwLock};
+
+static POOL: RwLock<Option<Arc<ThreadPool>>> = RwLock::new(None);
+
+/// The thread pool set with [`set_thread_pool`], if any.
+pub fn thread_pool() -> Option<Arc<ThreadPool>> {
+    POOL.read().unwrap().clone()
+}
+
+/// Sets the rayon thread pool the crate's parallel operations, such as
+/// [`par_generate`](crate::PixelBufferTyped::par_generate) and the `par_` [filters](crate::filters),
+/// run on. Pass `None` to use rayon's global pool again, which is the default.
+///
+/// Applications that already keep every core busy with their own worker pool can pass that pool,
+/// or one built with fewer threads by [`ThreadPoolBuilder::num_threads`], so that drawing doesn't
+/// oversubscribe the machine. Operations called from inside a pool's
+/// [`install`](ThreadPool::install) run there anyway. Parallel iterators, such as
+/// [`par_rows_mut`](crate::PixelBuffer::par_rows_mut), run wherever they're driven.
+///
+/// [`ThreadPoolBuilder::num_threads`]: rayon::ThreadPoolBuilder::num_threads
+pub fn set_thread_pool(pool: Option<Arc<ThreadPool>>) {
+    *POOL.write().unwrap() = pool;
+}
+
+/// Runs `op` in the configured thread pool, or in the current one if there's none.
+pub(crate) fn install<R: Send>(op: impl FnOnce() -> R + Send) -> R {
+    match thread_pool() {
+        Some(pool) => pool.install(op),
+        None => op(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PixelBufferTyped, BGRA};
+
+    #[test]
+    /// The purpose of this test is to verify that parallel operations run on the thread pool set
+    /// with `set_thread_pool`.
+    fn thread_pool_runs_parallel_operations() {
+        let pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .unwrap(),
+        );
+        set_thread_pool(Some(pool.clone()));
+        assert!(thread_pool().is_some());
+
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(5, 3).unwrap();
+        pb.par_generate(|x, y| {
+            assert_eq!(pool.current_thread_index(), Some(0));
+            BGRA::from_rgb(x as u8, y as u8, 0)
+        });
+        set_thread_pool(None);
+        assert_eq!(pb.row(2).unwrap()[4], BGRA::from_rgb(4, 2, 0));
+    }
+}
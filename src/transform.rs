@@ -0,0 +1,210 @@
+use crate::PixelBufferFormat;
+use std::ops::Mul;
+
+/// A 2D affine transformation.
+///
+/// A point `(x, y)` is mapped to `(xx * x + xy * y + x0, yx * x + yy * y + y0)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine2 {
+    pub xx: f32,
+    pub yx: f32,
+    pub xy: f32,
+    pub yy: f32,
+    pub x0: f32,
+    pub y0: f32,
+}
+
+/// How pixels are sampled when an image is resampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Filter {
+    /// Use the nearest pixel. Fast, and keeps pixel art crisp.
+    Nearest,
+    /// Linearly interpolate between the four nearest pixels.
+    Bilinear,
+}
+
+impl Affine2 {
+    /// The transformation that leaves every point where it is.
+    pub const IDENTITY: Affine2 = Affine2 {
+        xx: 1.0,
+        yx: 0.0,
+        xy: 0.0,
+        yy: 1.0,
+        x0: 0.0,
+        y0: 0.0,
+    };
+
+    /// Moves every point by `(x, y)`.
+    pub fn translation(x: f32, y: f32) -> Affine2 {
+        Affine2 {
+            x0: x,
+            y0: y,
+            ..Affine2::IDENTITY
+        }
+    }
+
+    /// Scales every point by `(x, y)`, relative to the origin.
+    pub fn scale(x: f32, y: f32) -> Affine2 {
+        Affine2 {
+            xx: x,
+            yy: y,
+            ..Affine2::IDENTITY
+        }
+    }
+
+    /// Rotates every point by `radians` around the origin.
+    ///
+    /// Since the y axis points down, positive angles rotate clockwise on screen.
+    pub fn rotation(radians: f32) -> Affine2 {
+        let (sin, cos) = radians.sin_cos();
+        Affine2 {
+            xx: cos,
+            yx: sin,
+            xy: -sin,
+            yy: cos,
+            ..Affine2::IDENTITY
+        }
+    }
+
+    /// Returns the transformation that applies `self`, followed by `next`.
+    pub fn then(self, next: Affine2) -> Affine2 {
+        next * self
+    }
+
+    /// The inverse transformation, or `None` if the transformation can't be inverted.
+    pub fn inverse(&self) -> Option<Affine2> {
+        let det = self.xx * self.yy - self.xy * self.yx;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let (xx, yx, xy, yy) = (self.yy / det, -self.yx / det, -self.xy / det, self.xx / det);
+        Some(Affine2 {
+            xx,
+            yx,
+            xy,
+            yy,
+            x0: -(xx * self.x0 + xy * self.y0),
+            y0: -(yx * self.x0 + yy * self.y0),
+        })
+    }
+
+    /// Transforms the point `(x, y)`.
+    pub fn apply(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            self.xx * x + self.xy * y + self.x0,
+            self.yx * x + self.yy * y + self.y0,
+        )
+    }
+}
+
+impl Default for Affine2 {
+    fn default() -> Self {
+        Affine2::IDENTITY
+    }
+}
+
+/// Composes two transformations. `a * b` applies `b` first, then `a`.
+impl Mul for Affine2 {
+    type Output = Affine2;
+    fn mul(self, rhs: Affine2) -> Affine2 {
+        Affine2 {
+            xx: self.xx * rhs.xx + self.xy * rhs.yx,
+            yx: self.yx * rhs.xx + self.yy * rhs.yx,
+            xy: self.xx * rhs.xy + self.xy * rhs.yy,
+            yy: self.yx * rhs.xy + self.yy * rhs.yy,
+            x0: self.xx * rhs.x0 + self.xy * rhs.y0 + self.x0,
+            y0: self.yx * rhs.x0 + self.yy * rhs.y0 + self.y0,
+        }
+    }
+}
+
+/// An image to sample from: `width` by `height` pixels, starting at `pixels[offset]` with rows
+/// `stride` pixels apart.
+pub(crate) struct Source<'a, P> {
+    pub pixels: &'a [P],
+    pub offset: usize,
+    pub stride: usize,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl<P: PixelBufferFormat> Source<'_, P> {
+    fn get(&self, x: u32, y: u32) -> P {
+        self.pixels[self.offset + y as usize * self.stride + x as usize]
+    }
+
+    /// Samples the image at `(x, y)`, measured in pixels from its top-left corner. Returns `None`
+    /// if the point lies outside the image.
+    pub fn sample(&self, (x, y): (f32, f32), filter: Filter) -> Option<P> {
+        if !(x >= 0.0 && y >= 0.0 && x < self.width as f32 && y < self.height as f32) {
+            return None;
+        }
+        match filter {
+            Filter::Nearest => Some(self.get(x as u32, y as u32)),
+            Filter::Bilinear => {
+                // Interpolate between the centers of the surrounding pixels, clamping at the
+                // image's edges.
+                let (x, y) = ((x - 0.5).max(0.0), (y - 0.5).max(0.0));
+                let (x0, y0) = (x as u32, y as u32);
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                let fx = ((x - x0 as f32) * 256.0) as u32;
+                let fy = ((y - y0 as f32) * 256.0) as u32;
+                Some(bilinear(
+                    [
+                        self.get(x0, y0),
+                        self.get(x1, y0),
+                        self.get(x0, y1),
+                        self.get(x1, y1),
+                    ],
+                    fx,
+                    fy,
+                ))
+            }
+        }
+    }
+}
+
+/// Interpolates between the corners `[top_left, top_right, bottom_left, bottom_right]`, where
+/// `fx` and `fy` are fractions out of `256`.
+pub(crate) fn bilinear<P: PixelBufferFormat>(corners: [P; 4], fx: u32, fy: u32) -> P {
+    let weights = [
+        (256 - fx) * (256 - fy),
+        fx * (256 - fy),
+        (256 - fx) * fy,
+        fx * fy,
+    ];
+    let mut out = corners[0];
+    let out_bytes: &mut [u8] = AsMut::<P::Array>::as_mut(&mut out).as_mut();
+    for (i, out) in out_bytes.iter_mut().enumerate() {
+        let sum: u32 = corners
+            .iter()
+            .zip(&weights)
+            .map(|(corner, weight)| {
+                let bytes: &[u8] = AsRef::<P::Array>::as_ref(corner).as_ref();
+                bytes[i] as u32 * weight
+            })
+            .sum();
+        *out = ((sum + (1 << 15)) >> 16) as u8;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// The purpose of this test is to verify that transformations compose in the documented
+    /// order, and that inverting them undoes them unless they're singular.
+    fn affine2_composes_and_inverts() {
+        let transform = Affine2::scale(2.0, 4.0).then(Affine2::translation(1.0, -1.0));
+        assert_eq!(transform.apply((3.0, 1.0)), (7.0, 3.0));
+        assert_eq!(Affine2::IDENTITY.apply((3.0, 1.0)), (3.0, 1.0));
+        let inverse = transform.inverse().unwrap();
+        assert_eq!(inverse.apply((7.0, 3.0)), (3.0, 1.0));
+        assert_eq!((transform * inverse).apply((-5.0, 2.0)), (-5.0, 2.0));
+
+        assert_eq!(Affine2::scale(0.0, 1.0).inverse(), None);
+        assert_eq!(Affine2::scale(f32::NAN, 1.0).inverse(), None);
+    }
+}
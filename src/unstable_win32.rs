@@ -0,0 +1,57 @@
+//! Direct access to the GDI objects behind pixel buffers, for expert users with unusual setups.
+//!
+//! **This module is exempt from semver.** It exposes implementation details that may change in
+//! any release, including which `winapi` version its types come from. Enable it with the
+//! `unstable-win32` feature.
+
+use crate::{PixelBuffer, PixelBufferFormat, PixelBufferTyped};
+pub use winapi::{shared::windef::HBITMAP, um::wingdi::BITMAP};
+
+/// Access to the DIB section a pixel buffer stores its pixels in.
+pub trait PixelBufferExtWin32 {
+    /// The buffer's DIB section, which can be selected into a device context for custom GDI
+    /// drawing. Null for buffers whose memory comes from a
+    /// [`BufferAllocator`](crate::BufferAllocator).
+    ///
+    /// The bitmap stays owned by the pixel buffer, and must not be deleted.
+    fn hbitmap(&self) -> HBITMAP;
+
+    /// The description of the buffer's pixels that blits are made from.
+    fn bitmap(&self) -> &BITMAP;
+
+    /// The description of the buffer's pixels that blits are made from, allowing modification,
+    /// e.g. of `bmWidthBytes` for memory with an unusual row pitch.
+    ///
+    /// # Safety
+    /// The buffer trusts the description when reading and writing its pixels, so it must keep
+    /// describing memory that the buffer owns.
+    unsafe fn bitmap_mut(&mut self) -> &mut BITMAP;
+}
+
+impl PixelBufferExtWin32 for PixelBuffer {
+    fn hbitmap(&self) -> HBITMAP {
+        self.p.hbitmap()
+    }
+
+    fn bitmap(&self) -> &BITMAP {
+        self.p.bitmap()
+    }
+
+    unsafe fn bitmap_mut(&mut self) -> &mut BITMAP {
+        self.p.bitmap_mut()
+    }
+}
+
+impl<P: PixelBufferFormat> PixelBufferExtWin32 for PixelBufferTyped<P> {
+    fn hbitmap(&self) -> HBITMAP {
+        self.p.hbitmap()
+    }
+
+    fn bitmap(&self) -> &BITMAP {
+        self.p.bitmap()
+    }
+
+    unsafe fn bitmap_mut(&mut self) -> &mut BITMAP {
+        self.p.bitmap_mut()
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{PixelBufferFormat, PixelBufferFormatType, PixelBufferTyped};
+use std::{
+    ffi::OsStr,
+    io::{self, BufWriter, Write},
+    marker::PhantomData,
+    mem,
+    process::{Child, ChildStdin, Command, Stdio},
+};
+
+/// Writes presented frames as a raw video stream, e.g. into `ffmpeg`'s standard input, to
+/// screen-record an application.
+///
+/// Every [`present`](Self::present)ed buffer becomes one frame of tightly-packed, top-down pixels
+/// in the buffer's own format, so no conversion happens while recording. The stream has no
+/// header; readers have to be told the frame size, frame rate and [`pix_fmt`](Self::pix_fmt).
+/// [`ffmpeg`](VideoSink::ffmpeg) does that for you.
+pub struct VideoSink<P: PixelBufferFormat, W: Write = BufWriter<ChildStdin>> {
+    /// Taken once the stream is finished, to close the pipe before waiting for the process.
+    out: Option<W>,
+    /// The process reading the stream, if it was spawned by the sink.
+    child: Option<Child>,
+    width: u32,
+    height: u32,
+    /// The padded frame, kept between presents to avoid reallocating it.
+    frame: Vec<u8>,
+    frame_count: u64,
+    _format: PhantomData<P>,
+}
+
+impl<P: PixelBufferFormat> VideoSink<P> {
+    /// Spawns `ffmpeg` reading `width` by `height` frames at `fps` frames per second from its
+    /// standard input.
+    ///
+    /// `args` are passed after the input, and usually name the output, e.g.
+    /// `["-y", "recording.mp4"]`. `ffmpeg` has to be on the `PATH`. Its errors are printed to
+    /// standard error.
+    pub fn ffmpeg<I, S>(width: u32, height: u32, fps: u32, args: I) -> io::Result<VideoSink<P>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        if width == 0 || height == 0 || fps == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frames must be at least 1x1, at a frame rate of at least 1",
+            ));
+        }
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-f", "rawvideo", "-pix_fmt"])
+            .arg(Self::pix_fmt())
+            .arg("-video_size")
+            .arg(format!("{}x{}", width, height))
+            .arg("-framerate")
+            .arg(fps.to_string())
+            .args(["-i", "-"])
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+        let stdin = BufWriter::new(child.stdin.take().unwrap());
+        let mut sink = VideoSink::new(stdin, width, height);
+        sink.child = Some(child);
+        Ok(sink)
+    }
+}
+
+impl<P: PixelBufferFormat, W: Write> VideoSink<P, W> {
+    /// Starts writing `width` by `height` frames to `out`.
+    pub fn new(out: W, width: u32, height: u32) -> VideoSink<P, W> {
+        VideoSink {
+            out: Some(out),
+            child: None,
+            width,
+            height,
+            frame: vec![0; width as usize * height as usize * mem::size_of::<P>()],
+            frame_count: 0,
+            _format: PhantomData,
+        }
+    }
+
+    /// The name `ffmpeg` uses for the format of the stream's pixels, e.g. `bgra`.
+    pub fn pix_fmt() -> &'static str {
+        match P::FORMAT_TYPE {
+            PixelBufferFormatType::BGR => "bgr24",
+            PixelBufferFormatType::BGRA => "bgra",
+            PixelBufferFormatType::RGB => "rgb24",
+            PixelBufferFormatType::RGBA => "rgba",
+        }
+    }
+
+    /// The width, in pixels, of every frame.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height, in pixels, of every frame.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The number of frames written so far.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Writes `buffer` to the stream as the next frame, starting at its top-left corner.
+    ///
+    /// Pixels that don't fit are cut off, and any part of the frame that `buffer` doesn't cover
+    /// is zeroed.
+    pub fn present(&mut self, buffer: &PixelBufferTyped<P>) -> io::Result<()> {
+        let row_len = self.width as usize * mem::size_of::<P>();
+        let mut src_rows = buffer.rows();
+        for dst in self.frame.chunks_exact_mut(row_len.max(1)) {
+            let src = P::to_raw_slice(src_rows.next().unwrap_or(&[]));
+            let len = src.len().min(row_len);
+            dst[..len].copy_from_slice(&src[..len]);
+            dst[len..].fill(0);
+        }
+        self.out.as_mut().unwrap().write_all(&self.frame)?;
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    /// Ends the stream, and waits for the process reading it to exit if the sink spawned it.
+    ///
+    /// Returns an error if flushing the stream fails, or if the process exits unsuccessfully.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.finish_inner()
+    }
+
+    fn finish_inner(&mut self) -> io::Result<()> {
+        let flushed = match self.out.take() {
+            Some(mut out) => out.flush(),
+            None => Ok(()),
+        };
+        if let Some(mut child) = self.child.take() {
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(io::Error::other(format!("ffmpeg exited with {}", status)));
+            }
+        }
+        flushed
+    }
+}
+
+impl<P: PixelBufferFormat, W: Write> Drop for VideoSink<P, W> {
+    fn drop(&mut self) {
+        let _ = self.finish_inner();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BGRA;
+
+    #[test]
+    /// The purpose of this test is to verify that `VideoSink` writes frames of the requested size
+    /// in the buffer's format, cropping and padding buffers of a different size.
+    fn video_sink_writes_padded_frames() {
+        let mut pb = PixelBufferTyped::<BGRA>::new_offscreen(3, 1).unwrap();
+        pb.row_mut(0).unwrap()[0] = BGRA::from_rgb(1, 2, 3);
+        let mut out = Vec::new();
+        let mut sink = VideoSink::new(&mut out, 2, 2);
+        sink.present(&pb).unwrap();
+        assert_eq!(sink.frame_count(), 1);
+        sink.finish().unwrap();
+
+        assert_eq!(VideoSink::<BGRA, Vec<u8>>::pix_fmt(), "bgra");
+        let mut expected = vec![0; 16];
+        expected[..8].copy_from_slice(BGRA::to_raw_slice(&pb.row(0).unwrap()[..2]));
+        assert_eq!(&expected[..3], &[3, 2, 1]);
+        assert_eq!(out, expected);
+    }
+}
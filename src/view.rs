@@ -0,0 +1,140 @@
+use crate::{PixelBufferFormat, PixelBufferTyped};
+use std::{marker::PhantomData, mem};
+
+/// A pixel buffer viewed as if it stored pixels of format `Q`.
+///
+/// Returned by [`PixelBufferTyped::view_as`]. Nothing is copied; pixels are converted from the
+/// buffer's format `P` as they're read.
+pub struct FormatView<'a, P: PixelBufferFormat, Q: PixelBufferFormat> {
+    buffer: &'a PixelBufferTyped<P>,
+    _format: PhantomData<Q>,
+}
+
+/// A pixel buffer viewed as if it stored pixels of format `Q`, allowing modification.
+///
+/// Returned by [`PixelBufferTyped::view_as_mut`]. Nothing is copied; pixels are converted from
+/// and to the buffer's format `P` as they're read and written.
+pub struct FormatViewMut<'a, P: PixelBufferFormat, Q: PixelBufferFormat> {
+    buffer: &'a mut PixelBufferTyped<P>,
+    _format: PhantomData<Q>,
+}
+
+/// Fails to compile when `P` and `Q` differ in size, since a view of one as the other couldn't
+/// be a drop-in replacement for the real thing.
+struct SameSize<P, Q>(PhantomData<(P, Q)>);
+
+impl<P, Q> SameSize<P, Q> {
+    const CHECK: () = assert!(
+        mem::size_of::<P>() == mem::size_of::<Q>(),
+        "pixel formats must have the same size"
+    );
+}
+
+fn convert<P: PixelBufferFormat, Q: PixelBufferFormat>(pixel: P) -> Q {
+    let [r, g, b, a] = pixel.to_rgba();
+    Q::from_rgba(r, g, b, a)
+}
+
+impl<P: PixelBufferFormat> PixelBufferTyped<P> {
+    /// Views the pixel buffer as if it stored pixels of format `Q`, e.g. a [`BGRA`](crate::BGRA)
+    /// buffer as [`RGBA`](crate::RGBA), for code that insists on a particular channel order.
+    ///
+    /// `P` and `Q` must be the same size, which is checked at compile time.
+    pub fn view_as<Q: PixelBufferFormat>(&self) -> FormatView<'_, P, Q> {
+        let () = SameSize::<P, Q>::CHECK;
+        FormatView {
+            buffer: self,
+            _format: PhantomData,
+        }
+    }
+
+    /// Views the pixel buffer as if it stored pixels of format `Q`, allowing modification.
+    ///
+    /// `P` and `Q` must be the same size, which is checked at compile time.
+    pub fn view_as_mut<Q: PixelBufferFormat>(&mut self) -> FormatViewMut<'_, P, Q> {
+        let () = SameSize::<P, Q>::CHECK;
+        FormatViewMut {
+            buffer: self,
+            _format: PhantomData,
+        }
+    }
+}
+
+impl<'a, P: PixelBufferFormat, Q: PixelBufferFormat> FormatView<'a, P, Q> {
+    /// The width, in pixels, of the buffer.
+    pub fn width(&self) -> u32 {
+        self.buffer.width()
+    }
+
+    /// The height, in pixels, of the buffer.
+    pub fn height(&self) -> u32 {
+        self.buffer.height()
+    }
+
+    /// Gets the pixel at the particular column and row.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Q> {
+        let pixel = *self.buffer.row(y)?.get(x as usize)?;
+        Some(convert(pixel))
+    }
+
+    /// Iterate through the pixels of the row at the particular height.
+    pub fn row(&self, row: u32) -> Option<impl ExactSizeIterator<Item = Q> + 'a> {
+        let buffer: &'a PixelBufferTyped<P> = self.buffer;
+        Some(buffer.row(row)?.iter().map(|&pixel| convert(pixel)))
+    }
+
+    /// Iterate through all rows in the buffer.
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = impl ExactSizeIterator<Item = Q> + 'a> {
+        let buffer: &'a PixelBufferTyped<P> = self.buffer;
+        buffer
+            .rows()
+            .map(|row| row.iter().map(|&pixel| convert(pixel)))
+    }
+}
+
+impl<P: PixelBufferFormat, Q: PixelBufferFormat> FormatViewMut<'_, P, Q> {
+    /// The width, in pixels, of the buffer.
+    pub fn width(&self) -> u32 {
+        self.buffer.width()
+    }
+
+    /// The height, in pixels, of the buffer.
+    pub fn height(&self) -> u32 {
+        self.buffer.height()
+    }
+
+    /// Gets the pixel at the particular column and row.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Q> {
+        let pixel = *self.buffer.row(y)?.get(x as usize)?;
+        Some(convert(pixel))
+    }
+
+    /// Replaces the pixel at the particular column and row. Does nothing if the position is
+    /// outside of the buffer.
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: Q) {
+        if let Some(dst) = self
+            .buffer
+            .row_mut(y)
+            .and_then(|row| row.get_mut(x as usize))
+        {
+            *dst = convert(pixel);
+        }
+    }
+
+    /// Calls `f` with every pixel and its position, replacing the pixel with the result.
+    pub fn map_pixels(&mut self, mut f: impl FnMut(u32, u32, Q) -> Q) {
+        for (y, row) in self.buffer.rows_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = convert(f(x as u32, y as u32, convert(*pixel)));
+            }
+        }
+    }
+
+    /// Borrows the view as an immutable one.
+    pub fn as_view(&self) -> FormatView<'_, P, Q> {
+        FormatView {
+            buffer: self.buffer,
+            _format: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,500 @@
+use crate::{PixelBufferFormat, PixelBufferTyped};
+use std::{
+    convert::TryFrom,
+    io::{self, BufWriter, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    thread,
+    time::Duration,
+};
+
+/// How long the listening thread sleeps between checks for new connections.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// An embedded VNC server that makes pixel buffers viewable over the network.
+///
+/// Instead of (or in addition to) blitting onto a local window, every frame is
+/// [`present`](Self::present)ed to the server, which serves it over the RFB protocol to any
+/// number of VNC viewers. This makes applications remotely viewable on devices without a display
+/// of their own.
+///
+/// Viewers are served from background threads, using the raw encoding and without any
+/// authentication, so only bind to addresses on trusted networks. Input from viewers is ignored.
+/// Dropping the server disconnects every viewer.
+pub struct VncServer {
+    shared: Arc<Shared>,
+    local_addr: SocketAddr,
+}
+
+struct Shared {
+    frame: Mutex<Frame>,
+    frame_changed: Condvar,
+    /// The connection to every viewer, along with an ID, so they can be shut down along with the
+    /// server.
+    clients: Mutex<Vec<(u64, TcpStream)>>,
+}
+
+struct Frame {
+    width: u16,
+    height: u16,
+    /// The frame's pixels as `[r, g, b]`, row by row.
+    pixels: Vec<[u8; 3]>,
+    /// Incremented by every `present`.
+    generation: u64,
+    closed: bool,
+}
+
+impl VncServer {
+    /// Starts serving a `width` by `height` frame on `addr`. Until the first
+    /// [`present`](Self::present), viewers are shown a black frame.
+    ///
+    /// Returns an error if the frame is empty, or larger than 65535 pixels in either direction,
+    /// which RFB can't describe, or if `addr` can't be bound.
+    pub fn bind<A: ToSocketAddrs>(addr: A, width: u32, height: u32) -> io::Result<VncServer> {
+        if width == 0 || height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame is empty",
+            ));
+        }
+        let too_large = |_| io::Error::new(io::ErrorKind::InvalidInput, "frame is too large");
+        let (width, height) = (
+            u16::try_from(width).map_err(too_large)?,
+            u16::try_from(height).map_err(too_large)?,
+        );
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        // Polling lets the listening thread notice when the server has been dropped.
+        listener.set_nonblocking(true)?;
+
+        let shared = Arc::new(Shared {
+            frame: Mutex::new(Frame {
+                width,
+                height,
+                pixels: vec![[0; 3]; width as usize * height as usize],
+                generation: 0,
+                closed: false,
+            }),
+            frame_changed: Condvar::new(),
+            clients: Mutex::new(Vec::new()),
+        });
+        let accept_shared = shared.clone();
+        thread::spawn(move || accept(listener, accept_shared));
+        Ok(VncServer { shared, local_addr })
+    }
+
+    /// The address the server is listening on, e.g. to find out which port was picked when
+    /// binding to port `0`.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// The number of viewers currently connected.
+    pub fn viewer_count(&self) -> usize {
+        lock(&self.shared.clients).len()
+    }
+
+    /// Copies `buffer` into the frame served to viewers, starting at its top-left corner.
+    /// Pixels that don't fit are cut off.
+    pub fn present<P: PixelBufferFormat>(&self, buffer: &PixelBufferTyped<P>) {
+        let mut frame = lock(&self.shared.frame);
+        let width = frame.width as usize;
+        for (dst, src) in frame.pixels.chunks_mut(width).zip(buffer.rows()) {
+            for (dst, src) in dst.iter_mut().zip(src) {
+                let [r, g, b, _] = src.to_rgba();
+                *dst = [r, g, b];
+            }
+        }
+        frame.generation += 1;
+        self.shared.frame_changed.notify_all();
+    }
+}
+
+impl Drop for VncServer {
+    fn drop(&mut self) {
+        lock(&self.shared.frame).closed = true;
+        self.shared.frame_changed.notify_all();
+        for (_, client) in lock(&self.shared.clients).drain(..) {
+            let _ = client.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Locks `mutex`, even if a thread panicked while holding it. At worst, that leaves a partially
+/// presented frame behind, which is better than taking down every other viewer and the
+/// application along with it.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn accept(listener: TcpListener, shared: Arc<Shared>) {
+    let mut next_id = 0;
+    while !lock(&shared.frame).closed {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(_) => {
+                thread::sleep(ACCEPT_INTERVAL);
+                continue;
+            }
+        };
+        let registered = stream
+            .set_nonblocking(false)
+            .and_then(|()| stream.try_clone());
+        if let Ok(clone) = registered {
+            let id = next_id;
+            next_id += 1;
+            lock(&shared.clients).push((id, clone));
+            let shared = shared.clone();
+            thread::spawn(move || {
+                // Errors just mean the viewer went away, or spoke something other than RFB.
+                let _ = serve(stream, &shared);
+                lock(&shared.clients).retain(|&(client, _)| client != id);
+            });
+        }
+    }
+}
+
+impl Frame {
+    /// Encodes a FramebufferUpdate message with the part of the frame in the requested rectangle
+    /// into `out`. The rectangle is clipped to the frame, and if nothing of it is left, the
+    /// update holds no rectangles at all.
+    fn encode_update(
+        &self,
+        (x, y): (u16, u16),
+        (w, h): (u16, u16),
+        format: PixelFormat,
+        out: &mut Vec<u8>,
+    ) {
+        out.extend_from_slice(&[0, 0]);
+        if x >= self.width || y >= self.height || w == 0 || h == 0 {
+            out.extend_from_slice(&0u16.to_be_bytes());
+            return;
+        }
+        let w = w.min(self.width - x);
+        let h = h.min(self.height - y);
+        out.extend_from_slice(&1u16.to_be_bytes());
+        for value in [x, y, w, h] {
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+        // The raw encoding.
+        out.extend_from_slice(&0i32.to_be_bytes());
+        let width = self.width as usize;
+        for row in self
+            .pixels
+            .chunks_exact(width)
+            .skip(y as usize)
+            .take(h as usize)
+        {
+            for &pixel in &row[x as usize..][..w as usize] {
+                format.encode(pixel, out);
+            }
+        }
+    }
+}
+
+/// The format a viewer wants pixels to be sent in. Only true-color formats are supported.
+#[derive(Clone, Copy)]
+struct PixelFormat {
+    bytes_per_pixel: usize,
+    big_endian: bool,
+    max: [u16; 3],
+    shift: [u8; 3],
+}
+
+impl PixelFormat {
+    /// The format announced to viewers: 32-bit, little-endian `0x00RRGGBB`.
+    const DEFAULT: PixelFormat = PixelFormat {
+        bytes_per_pixel: 4,
+        big_endian: false,
+        max: [255, 255, 255],
+        shift: [16, 8, 0],
+    };
+
+    fn to_bytes(self) -> [u8; 16] {
+        let [r_max, g_max, b_max] = self.max.map(u16::to_be_bytes);
+        let [r_shift, g_shift, b_shift] = self.shift;
+        let bits = self.bytes_per_pixel as u8 * 8;
+        #[rustfmt::skip]
+        let bytes = [
+            bits, 24, self.big_endian as u8, 1,
+            r_max[0], r_max[1], g_max[0], g_max[1], b_max[0], b_max[1],
+            r_shift, g_shift, b_shift,
+            0, 0, 0,
+        ];
+        bytes
+    }
+
+    fn from_bytes(bytes: [u8; 16]) -> io::Result<PixelFormat> {
+        let true_color = bytes[3] != 0;
+        if !true_color || ![8, 16, 32].contains(&bytes[0]) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only 8, 16 and 32-bit true-color formats are supported",
+            ));
+        }
+        let max = |i: usize| u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+        Ok(PixelFormat {
+            bytes_per_pixel: bytes[0] as usize / 8,
+            big_endian: bytes[2] != 0,
+            max: [max(4), max(6), max(8)],
+            shift: [bytes[10] % 32, bytes[11] % 32, bytes[12] % 32],
+        })
+    }
+
+    fn encode(self, rgb: [u8; 3], out: &mut Vec<u8>) {
+        let mut value = 0u32;
+        for ((&channel, &max), &shift) in rgb.iter().zip(&self.max).zip(&self.shift) {
+            value |= ((channel as u32 * max as u32 + 127) / 255) << shift;
+        }
+        let bytes = match self.big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        };
+        match self.big_endian {
+            true => out.extend_from_slice(&bytes[4 - self.bytes_per_pixel..]),
+            false => out.extend_from_slice(&bytes[..self.bytes_per_pixel]),
+        }
+    }
+}
+
+fn read_array<const N: usize>(stream: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    stream.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u16(stream: &mut impl Read) -> io::Result<u16> {
+    read_array(stream).map(u16::from_be_bytes)
+}
+
+fn read_u32(stream: &mut impl Read) -> io::Result<u32> {
+    read_array(stream).map(u32::from_be_bytes)
+}
+
+/// Speaks RFB with a single viewer until it disconnects.
+fn serve(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    let mut reader = stream.try_clone()?;
+    let mut writer = BufWriter::new(stream);
+
+    // Versions 3.3, 3.7 and 3.8 only differ in how security types are negotiated.
+    writer.write_all(b"RFB 003.008\n")?;
+    writer.flush()?;
+    let version: [u8; 12] = read_array(&mut reader)?;
+    let minor = match &version {
+        b"RFB 003.003\n" => 3,
+        b"RFB 003.007\n" => 7,
+        _ if version.starts_with(b"RFB 003.") => 8,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an RFB viewer",
+            ))
+        }
+    };
+    const SECURITY_NONE: u8 = 1;
+    if minor == 3 {
+        writer.write_all(&(SECURITY_NONE as u32).to_be_bytes())?;
+    } else {
+        writer.write_all(&[1, SECURITY_NONE])?;
+        writer.flush()?;
+        if read_array::<1>(&mut reader)? != [SECURITY_NONE] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unknown security type",
+            ));
+        }
+        if minor == 8 {
+            writer.write_all(&0u32.to_be_bytes())?;
+        }
+    }
+    writer.flush()?;
+
+    // Whether the desktop is shared makes no difference, since input is ignored anyway.
+    let _shared_flag: [u8; 1] = read_array(&mut reader)?;
+    let (width, height) = {
+        let frame = lock(&shared.frame);
+        (frame.width, frame.height)
+    };
+    let name = b"winit-blit";
+    writer.write_all(&width.to_be_bytes())?;
+    writer.write_all(&height.to_be_bytes())?;
+    writer.write_all(&PixelFormat::DEFAULT.to_bytes())?;
+    writer.write_all(&(name.len() as u32).to_be_bytes())?;
+    writer.write_all(name)?;
+    writer.flush()?;
+
+    let mut format = PixelFormat::DEFAULT;
+    let mut seen_generation = None;
+    let mut update = Vec::new();
+    loop {
+        match read_array::<1>(&mut reader)?[0] {
+            // SetPixelFormat
+            0 => {
+                let _padding: [u8; 3] = read_array(&mut reader)?;
+                format = PixelFormat::from_bytes(read_array(&mut reader)?)?;
+            }
+            // SetEncodings. Raw is always supported, so the list doesn't matter.
+            2 => {
+                let _padding: [u8; 1] = read_array(&mut reader)?;
+                let count = read_u16(&mut reader)?;
+                for _ in 0..count {
+                    read_u32(&mut reader)?;
+                }
+            }
+            // FramebufferUpdateRequest
+            3 => {
+                let incremental = read_array::<1>(&mut reader)?[0] != 0;
+                let (x, y) = (read_u16(&mut reader)?, read_u16(&mut reader)?);
+                let (w, h) = (read_u16(&mut reader)?, read_u16(&mut reader)?);
+
+                let mut frame = lock(&shared.frame);
+                // Incremental updates are only sent once there's something new to show.
+                while incremental && seen_generation == Some(frame.generation) && !frame.closed {
+                    frame = shared
+                        .frame_changed
+                        .wait(frame)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+                if frame.closed {
+                    return Ok(());
+                }
+                seen_generation = Some(frame.generation);
+                update.clear();
+                frame.encode_update((x, y), (w, h), format, &mut update);
+                drop(frame);
+                writer.write_all(&update)?;
+                writer.flush()?;
+            }
+            // KeyEvent
+            4 => {
+                let _event: [u8; 7] = read_array(&mut reader)?;
+            }
+            // PointerEvent
+            5 => {
+                let _event: [u8; 5] = read_array(&mut reader)?;
+            }
+            // ClientCutText
+            6 => {
+                let _padding: [u8; 3] = read_array(&mut reader)?;
+                let len = read_u32(&mut reader)?;
+                io::copy(&mut (&mut reader).take(len as u64), &mut io::sink())?;
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unknown message",
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: u16, height: u16) -> Frame {
+        let pixels = (0..width as usize * height as usize)
+            .map(|i| [i as u8, 0, 0])
+            .collect();
+        Frame {
+            width,
+            height,
+            pixels,
+            generation: 0,
+            closed: false,
+        }
+    }
+
+    /// Encodes pixels like `PixelFormat::DEFAULT` does, as little-endian `0x00RRGGBB`.
+    fn encoded(reds: &[u8]) -> Vec<u8> {
+        reds.iter().flat_map(|&r| [0, 0, r, 0]).collect()
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that updates reaching past the frame only hold the
+    /// part of it that exists.
+    fn update_is_clipped_to_the_frame() {
+        let mut update = Vec::new();
+        frame(3, 2).encode_update((1, 1), (10, 10), PixelFormat::DEFAULT, &mut update);
+        let mut expected = vec![0, 0, 0, 1, 0, 1, 0, 1, 0, 2, 0, 1, 0, 0, 0, 0];
+        expected.extend(encoded(&[4, 5]));
+        assert_eq!(update, expected);
+
+        let mut update = Vec::new();
+        frame(3, 2).encode_update((0, 0), (2, 2), PixelFormat::DEFAULT, &mut update);
+        assert_eq!(update[16..], encoded(&[0, 1, 3, 4])[..]);
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that requests for empty rectangles, or ones outside
+    /// of the frame, are answered with an update without any rectangles instead of panicking.
+    fn update_outside_the_frame_is_empty() {
+        let requests = [
+            ((3, 0), (1, 1)),
+            ((0, 2), (1, 1)),
+            ((u16::MAX, u16::MAX), (u16::MAX, u16::MAX)),
+            ((0, 0), (0, 1)),
+            ((0, 0), (1, 0)),
+        ];
+        for (pos, size) in requests {
+            let mut update = Vec::new();
+            frame(3, 2).encode_update(pos, size, PixelFormat::DEFAULT, &mut update);
+            assert_eq!(update, [0, 0, 0, 0], "{:?} {:?}", pos, size);
+        }
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that frames RFB can't describe are rejected up front.
+    fn bind_rejects_empty_and_oversized_frames() {
+        for (width, height) in [(0, 1), (1, 0), (65536, 1)] {
+            let error = VncServer::bind("127.0.0.1:0", width, height).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    /// The purpose of this test is to verify that a viewer can connect and receive updates, and
+    /// stays connected after requesting an update outside of the frame.
+    fn serves_updates_to_viewers() {
+        let server = VncServer::bind("127.0.0.1:0", 3, 2).unwrap();
+        let mut viewer = TcpStream::connect(server.local_addr()).unwrap();
+        viewer
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+
+        assert_eq!(&read_array::<12>(&mut viewer).unwrap(), b"RFB 003.008\n");
+        viewer.write_all(b"RFB 003.008\n").unwrap();
+        assert_eq!(read_array::<2>(&mut viewer).unwrap(), [1, 1]);
+        viewer.write_all(&[1]).unwrap();
+        assert_eq!(read_u32(&mut viewer).unwrap(), 0);
+        viewer.write_all(&[1]).unwrap();
+        assert_eq!(
+            (
+                read_u16(&mut viewer).unwrap(),
+                read_u16(&mut viewer).unwrap()
+            ),
+            (3, 2)
+        );
+        assert_eq!(
+            read_array::<16>(&mut viewer).unwrap(),
+            PixelFormat::DEFAULT.to_bytes()
+        );
+        let name_len = read_u32(&mut viewer).unwrap();
+        let mut name = vec![0; name_len as usize];
+        viewer.read_exact(&mut name).unwrap();
+        assert_eq!(name, b"winit-blit");
+
+        // A request entirely outside the frame gets an empty update, and the viewer stays
+        // connected.
+        viewer.write_all(&[3, 0, 0, 5, 0, 5, 0, 1, 0, 1]).unwrap();
+        assert_eq!(read_array::<4>(&mut viewer).unwrap(), [0, 0, 0, 0]);
+
+        viewer.write_all(&[3, 0, 0, 0, 0, 0, 0, 3, 0, 2]).unwrap();
+        let header = read_array::<16>(&mut viewer).unwrap();
+        assert_eq!(header, [0, 0, 0, 1, 0, 0, 0, 0, 0, 3, 0, 2, 0, 0, 0, 0]);
+        let pixels = read_array::<24>(&mut viewer).unwrap();
+        assert_eq!(pixels, [0; 24]);
+        assert_eq!(server.viewer_count(), 1);
+    }
+}
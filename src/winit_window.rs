@@ -0,0 +1,104 @@
+use crate::{
+    BlitError, Color, PixelBuffer, PixelBufferCreationError, PixelBufferFormat,
+    PixelBufferFormatSupported, PixelBufferFormatType, PixelBufferTyped,
+};
+use winit::window::Window;
+
+impl PixelBuffer {
+    /// Initialize a new pixel buffer in the native format, covering `window`'s client area.
+    ///
+    /// The size is taken from [`Window::inner_size`], which is in physical pixels, so the buffer
+    /// maps one-to-one onto the screen's pixels at any scale factor.
+    pub fn for_winit_window(window: &Window) -> Result<PixelBuffer, PixelBufferCreationError> {
+        let size = window.inner_size();
+        PixelBuffer::new(
+            size.width,
+            size.height,
+            PixelBufferFormatType::NATIVE,
+            window,
+        )
+    }
+
+    /// Blits the pixel buffer's contents onto `window`, clearing any part of the window the
+    /// buffer doesn't cover to black.
+    ///
+    /// Uncovered areas show up when the window has been resized but the buffer hasn't been
+    /// recreated yet; clearing them avoids leaving stale contents behind.
+    ///
+    /// # Panics
+    /// `window` must be the window passed to `new`, or one added with `add_window`. Failing to do
+    /// so will result in a panic.
+    pub fn blit_to_winit(&self, window: &Window) -> Result<(), BlitError> {
+        let size = window.inner_size();
+        if (size.width, size.height) == (self.width(), self.height()) {
+            self.blit(window)
+        } else {
+            let blit_size = (self.width(), self.height());
+            self.blit_rect_with_background((0, 0), (0, 0), blit_size, Color::BLACK, window)
+        }
+    }
+}
+
+impl<P: PixelBufferFormat> PixelBufferTyped<P> {
+    /// Initialize a new pixel buffer covering `window`'s client area.
+    ///
+    /// See [`PixelBuffer::for_winit_window`].
+    pub fn for_winit_window(
+        window: &Window,
+    ) -> Result<PixelBufferTyped<P>, PixelBufferCreationError>
+    where
+        P: PixelBufferFormatSupported,
+    {
+        let size = window.inner_size();
+        PixelBufferTyped::new(size.width, size.height, window)
+    }
+
+    /// Blits the pixel buffer's contents onto `window`, clearing any part of the window the
+    /// buffer doesn't cover to black.
+    ///
+    /// See [`PixelBuffer::blit_to_winit`].
+    pub fn blit_to_winit(&self, window: &Window) -> Result<(), BlitError> {
+        self.p.blit_to_winit(window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BGRA;
+    use winit::{
+        dpi::PhysicalSize, event_loop::EventLoopBuilder,
+        platform::windows::EventLoopBuilderExtWindows, window::WindowBuilder,
+    };
+
+    #[test]
+    /// The purpose of this test is to verify that buffers created for a winit window cover its
+    /// client area in physical pixels, and that buffers of any size can be blitted onto it.
+    ///
+    /// winit only allows one event loop per process, so every check that needs a window lives in
+    /// this test.
+    fn for_winit_window_covers_client_area() {
+        let event_loop = EventLoopBuilder::new()
+            .with_any_thread(true)
+            .build()
+            .unwrap();
+        let window = WindowBuilder::new()
+            .with_visible(false)
+            .with_inner_size(PhysicalSize::new(320, 240))
+            .build(&event_loop)
+            .unwrap();
+        let size = window.inner_size();
+
+        let pb = PixelBuffer::for_winit_window(&window).unwrap();
+        assert_eq!((pb.width(), pb.height()), (size.width, size.height));
+        assert_eq!(pb.backend_format(), PixelBufferFormatType::NATIVE);
+        pb.blit_to_winit(&window).unwrap();
+
+        let typed = PixelBufferTyped::<BGRA>::for_winit_window(&window).unwrap();
+        assert_eq!((typed.width(), typed.height()), (size.width, size.height));
+
+        // A buffer that hasn't caught up with a resize yet.
+        let smaller = PixelBufferTyped::<BGRA>::new(size.width / 2, size.height, &window).unwrap();
+        smaller.blit_to_winit(&window).unwrap();
+    }
+}